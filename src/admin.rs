@@ -0,0 +1,205 @@
+use anyhow::{Context, Result};
+use std::fmt::Write as _;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::thread;
+
+use crate::{
+    coordinator::{consumer_lag, PartitionLag},
+    metadata::read_cluster_metadata,
+};
+
+/// Plain HTTP endpoint for operators, next to the Kafka listener:
+/// `GET /consumer-lag[?group=<id>]` returns the lag of committed offsets as
+/// JSON and `GET /metrics` the same figures in the Prometheus text format.
+pub struct AdminServer {
+    pub address: String,
+}
+
+impl AdminServer {
+    /// Serves requests on a background thread.
+    pub fn spawn(self) -> Result<()> {
+        let listener = TcpListener::bind(&self.address)
+            .with_context(|| format!("Failed to bind admin listener {}", self.address))?;
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                thread::spawn(|| {
+                    let _ = handle_connection(stream);
+                });
+            }
+        });
+        Ok(())
+    }
+}
+
+fn handle_connection(mut stream: TcpStream) -> Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    // The headers are not used, but must be read before answering.
+    let mut line = String::new();
+    while reader.read_line(&mut line)? > 2 {
+        line.clear();
+    }
+    let mut parts = request_line.split_whitespace();
+    let (method, target) = (parts.next().unwrap_or(""), parts.next().unwrap_or(""));
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let group_id = query
+        .split('&')
+        .filter_map(|x| x.split_once('='))
+        .find(|(k, _)| *k == "group")
+        .map(|(_, v)| percent_decode(v));
+    let (status, content_type, body) = match (method, path) {
+        ("GET", "/consumer-lag") => match lag(group_id.as_deref()) {
+            Ok(lag) => ("200 OK", "application/json", lag_json(&lag)),
+            Err(e) => error_response(e),
+        },
+        ("GET", "/metrics") => match lag(None) {
+            Ok(lag) => ("200 OK", "text/plain; version=0.0.4", lag_metrics(&lag)),
+            Err(e) => error_response(e),
+        },
+        ("GET", _) => ("404 Not Found", "text/plain", "Not found\n".to_owned()),
+        _ => (
+            "405 Method Not Allowed",
+            "text/plain",
+            "Method not allowed\n".to_owned(),
+        ),
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        content_type,
+        body.len(),
+        body
+    )?;
+    stream.flush()?;
+    Ok(())
+}
+
+fn lag(group_id: Option<&str>) -> Result<Vec<PartitionLag>> {
+    consumer_lag(group_id, &read_cluster_metadata()?)
+}
+
+fn error_response(e: anyhow::Error) -> (&'static str, &'static str, String) {
+    (
+        "500 Internal Server Error",
+        "text/plain",
+        format!("{:#}\n", e),
+    )
+}
+
+fn lag_json(lag: &[PartitionLag]) -> String {
+    let partitions: Vec<String> = lag
+        .iter()
+        .map(|x| {
+            format!(
+                "{{\"group\":{},\"topic\":{},\"partition\":{},\"committed_offset\":{},\
+                 \"log_end_offset\":{},\"lag\":{},\"time_lag_ms\":{}}}",
+                json_string(&x.group_id),
+                json_string(&x.topic),
+                x.partition,
+                x.committed_offset,
+                x.log_end_offset,
+                x.lag,
+                x.time_lag_ms
+            )
+        })
+        .collect();
+    format!("{{\"partitions\":[{}]}}\n", partitions.join(","))
+}
+
+/// Name, help text and value of a per-partition gauge.
+type Gauge = (&'static str, &'static str, fn(&PartitionLag) -> i64);
+
+fn lag_metrics(lag: &[PartitionLag]) -> String {
+    let metrics: [Gauge; 4] = [
+        (
+            "kafka_consumer_group_committed_offset",
+            "Offset committed by the group.",
+            |x| x.committed_offset,
+        ),
+        (
+            "kafka_consumer_group_log_end_offset",
+            "High watermark of the partition.",
+            |x| x.log_end_offset,
+        ),
+        (
+            "kafka_consumer_group_lag",
+            "Records the group has not consumed yet.",
+            |x| x.lag,
+        ),
+        (
+            "kafka_consumer_group_time_lag_ms",
+            "Age in milliseconds of the oldest record the group has not consumed yet.",
+            |x| x.time_lag_ms,
+        ),
+    ];
+    let mut out = String::new();
+    for (name, help, value) in metrics {
+        let _ = writeln!(out, "# HELP {} {}", name, help);
+        let _ = writeln!(out, "# TYPE {} gauge", name);
+        for x in lag {
+            let _ = writeln!(
+                out,
+                "{}{{group={},topic={},partition=\"{}\"}} {}",
+                name,
+                label_value(&x.group_id),
+                label_value(&x.topic),
+                x.partition,
+                value(x)
+            );
+        }
+    }
+    out
+}
+
+fn label_value(value: &str) -> String {
+    let escaped = value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n");
+    format!("\"{}\"", escaped)
+}
+
+fn json_string(value: &str) -> String {
+    let mut out = String::from("\"");
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            c if c.is_control() => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut out = vec![];
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'%' if i + 2 < bytes.len() => {
+                let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).unwrap_or("");
+                match u8::from_str_radix(hex, 16) {
+                    Ok(x) => {
+                        out.push(x);
+                        i += 3;
+                        continue;
+                    }
+                    Err(_) => out.push(b'%'),
+                }
+            }
+            b'+' => out.push(b' '),
+            x => out.push(x),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
@@ -0,0 +1,459 @@
+use crate::storage::LOG_DIR;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[repr(i8)]
+pub enum ConfigType {
+    Boolean = 1,
+    String = 2,
+    Int = 3,
+    Short = 4,
+    Long = 5,
+    List = 7,
+}
+
+#[derive(Debug)]
+pub struct ConfigDef {
+    /// Name of the broker config.
+    pub name: &'static str,
+    /// Name of the per-topic override, if the config can be set on topics.
+    pub topic_name: Option<&'static str>,
+    pub config_type: ConfigType,
+    pub default: &'static str,
+    /// Whether the broker config can be changed without a restart.
+    pub dynamic: bool,
+    /// Accepted values for string and list configs, empty when unrestricted.
+    pub valid_values: &'static [&'static str],
+    /// Lowest accepted value of numeric configs.
+    pub at_least: Option<i64>,
+    pub documentation: &'static str,
+}
+
+impl ConfigDef {
+    pub fn is_valid(&self, value: &str) -> bool {
+        let valid = match self.config_type {
+            ConfigType::Boolean => value.parse::<bool>().is_ok(),
+            ConfigType::Int => value.parse::<i32>().is_ok(),
+            ConfigType::Short => value.parse::<i16>().is_ok(),
+            ConfigType::Long => value.parse::<i64>().is_ok(),
+            ConfigType::String | ConfigType::List => true,
+        };
+        valid
+            && self
+                .at_least
+                .map_or(true, |min| value.parse::<i64>().is_ok_and(|x| x >= min))
+            && (self.valid_values.is_empty()
+                || split_list(value).all(|x| self.valid_values.contains(&x)))
+    }
+}
+
+pub fn split_list(value: &str) -> impl Iterator<Item = &str> {
+    value.split(',').map(str::trim).filter(|x| !x.is_empty())
+}
+
+pub const CONFIG_DEFS: &[ConfigDef] = &[
+    ConfigDef {
+        name: "log.retention.ms",
+        topic_name: Some("retention.ms"),
+        config_type: ConfigType::Long,
+        default: "604800000",
+        dynamic: true,
+        valid_values: &[],
+        at_least: None,
+        documentation: "The number of milliseconds to keep a log file before deleting it.",
+    },
+    ConfigDef {
+        name: "log.retention.bytes",
+        topic_name: Some("retention.bytes"),
+        config_type: ConfigType::Long,
+        default: "-1",
+        dynamic: true,
+        valid_values: &[],
+        at_least: None,
+        documentation: "The maximum size of the log before deleting it.",
+    },
+    ConfigDef {
+        name: "log.cleanup.policy",
+        topic_name: Some("cleanup.policy"),
+        config_type: ConfigType::List,
+        default: "delete",
+        dynamic: true,
+        valid_values: &["delete", "compact"],
+        at_least: None,
+        documentation: "The default cleanup policy for segments beyond the retention window.",
+    },
+    ConfigDef {
+        name: "message.max.bytes",
+        topic_name: Some("max.message.bytes"),
+        config_type: ConfigType::Int,
+        default: "1048588",
+        dynamic: true,
+        valid_values: &[],
+        at_least: None,
+        documentation: "The largest record batch size allowed.",
+    },
+    ConfigDef {
+        name: "log.segment.bytes",
+        topic_name: Some("segment.bytes"),
+        config_type: ConfigType::Int,
+        default: "1073741824",
+        dynamic: true,
+        valid_values: &[],
+        at_least: None,
+        documentation: "The maximum size of a single log file.",
+    },
+    ConfigDef {
+        name: "compression.type",
+        topic_name: Some("compression.type"),
+        config_type: ConfigType::String,
+        default: "producer",
+        dynamic: true,
+        valid_values: &["uncompressed", "zstd", "lz4", "snappy", "gzip", "producer"],
+        at_least: None,
+        documentation: "The final compression type for a given topic.",
+    },
+    ConfigDef {
+        name: "min.insync.replicas",
+        topic_name: Some("min.insync.replicas"),
+        config_type: ConfigType::Int,
+        default: "1",
+        dynamic: true,
+        valid_values: &[],
+        at_least: None,
+        documentation: "The minimum number of replicas that must acknowledge a write.",
+    },
+    ConfigDef {
+        name: "log.message.timestamp.type",
+        topic_name: Some("message.timestamp.type"),
+        config_type: ConfigType::String,
+        default: "CreateTime",
+        dynamic: true,
+        valid_values: &["CreateTime", "LogAppendTime"],
+        at_least: None,
+        documentation: "Whether the timestamp in the message is create time or log append time.",
+    },
+    ConfigDef {
+        name: "producer.id.expiration.ms",
+        topic_name: None,
+        config_type: ConfigType::Int,
+        default: "86400000",
+        dynamic: true,
+        valid_values: &[],
+        at_least: None,
+        documentation: "The time a producer id is kept after its last write.",
+    },
+    ConfigDef {
+        name: "num.partitions",
+        topic_name: None,
+        config_type: ConfigType::Int,
+        default: "1",
+        dynamic: false,
+        valid_values: &[],
+        at_least: Some(1),
+        documentation: "The default number of log partitions per topic.",
+    },
+    ConfigDef {
+        name: "default.replication.factor",
+        topic_name: None,
+        config_type: ConfigType::Int,
+        default: "1",
+        dynamic: false,
+        valid_values: &[],
+        at_least: None,
+        documentation: "The default replication factor for automatically created topics.",
+    },
+    ConfigDef {
+        name: "offsets.topic.num.partitions",
+        topic_name: None,
+        config_type: ConfigType::Int,
+        default: "50",
+        dynamic: false,
+        valid_values: &[],
+        at_least: Some(1),
+        documentation: "The number of partitions for the offset commit topic.",
+    },
+    ConfigDef {
+        name: "offsets.topic.replication.factor",
+        topic_name: None,
+        config_type: ConfigType::Short,
+        default: "1",
+        dynamic: false,
+        valid_values: &[],
+        at_least: None,
+        documentation: "The replication factor for the offsets topic.",
+    },
+    ConfigDef {
+        name: "offsets.retention.minutes",
+        topic_name: None,
+        config_type: ConfigType::Int,
+        default: "10080",
+        dynamic: false,
+        valid_values: &[],
+        at_least: None,
+        documentation: "How long committed offsets are kept after a group becomes empty.",
+    },
+    ConfigDef {
+        name: "offsets.retention.check.interval.ms",
+        topic_name: None,
+        config_type: ConfigType::Long,
+        default: "600000",
+        dynamic: false,
+        valid_values: &[],
+        at_least: None,
+        documentation: "Frequency at which to check for stale offsets.",
+    },
+    ConfigDef {
+        name: "offset.metadata.max.bytes",
+        topic_name: None,
+        config_type: ConfigType::Int,
+        default: "4096",
+        dynamic: false,
+        valid_values: &[],
+        at_least: None,
+        documentation: "The maximum size for a metadata entry associated with an offset commit.",
+    },
+    ConfigDef {
+        name: "transaction.state.log.num.partitions",
+        topic_name: None,
+        config_type: ConfigType::Int,
+        default: "50",
+        dynamic: false,
+        valid_values: &[],
+        at_least: Some(1),
+        documentation: "The number of partitions for the transaction topic.",
+    },
+    ConfigDef {
+        name: "transaction.state.log.replication.factor",
+        topic_name: None,
+        config_type: ConfigType::Short,
+        default: "1",
+        dynamic: false,
+        valid_values: &[],
+        at_least: None,
+        documentation: "The replication factor for the transaction topic.",
+    },
+    ConfigDef {
+        name: "transaction.max.timeout.ms",
+        topic_name: None,
+        config_type: ConfigType::Int,
+        default: "900000",
+        dynamic: false,
+        valid_values: &[],
+        at_least: None,
+        documentation: "The maximum allowed timeout for transactions.",
+    },
+    ConfigDef {
+        name: "transaction.abort.timed.out.transaction.cleanup.interval.ms",
+        topic_name: None,
+        config_type: ConfigType::Int,
+        default: "10000",
+        dynamic: false,
+        valid_values: &[],
+        at_least: None,
+        documentation: "The interval at which to roll back transactions that have timed out.",
+    },
+    ConfigDef {
+        name: "max.incremental.fetch.session.cache.slots",
+        topic_name: None,
+        config_type: ConfigType::Int,
+        default: "1000",
+        dynamic: false,
+        valid_values: &[],
+        at_least: None,
+        documentation: "The maximum number of incremental fetch sessions that we will maintain.",
+    },
+    ConfigDef {
+        name: "share.coordinator.state.topic.num.partitions",
+        topic_name: None,
+        config_type: ConfigType::Int,
+        default: "50",
+        dynamic: false,
+        valid_values: &[],
+        at_least: Some(1),
+        documentation: "The number of partitions for the share-group state topic.",
+    },
+    ConfigDef {
+        name: "share.coordinator.state.topic.replication.factor",
+        topic_name: None,
+        config_type: ConfigType::Short,
+        default: "1",
+        dynamic: false,
+        valid_values: &[],
+        at_least: None,
+        documentation: "The replication factor for the share-group state topic.",
+    },
+    ConfigDef {
+        name: "group.initial.rebalance.delay.ms",
+        topic_name: None,
+        config_type: ConfigType::Int,
+        default: "3000",
+        dynamic: false,
+        valid_values: &[],
+        at_least: None,
+        documentation:
+            "How long the coordinator waits for more members before the first rebalance.",
+    },
+    ConfigDef {
+        name: "group.min.session.timeout.ms",
+        topic_name: None,
+        config_type: ConfigType::Int,
+        default: "6000",
+        dynamic: false,
+        valid_values: &[],
+        at_least: None,
+        documentation: "The minimum allowed session timeout for registered consumers.",
+    },
+    ConfigDef {
+        name: "group.max.session.timeout.ms",
+        topic_name: None,
+        config_type: ConfigType::Int,
+        default: "1800000",
+        dynamic: false,
+        valid_values: &[],
+        at_least: None,
+        documentation: "The maximum allowed session timeout for registered consumers.",
+    },
+    ConfigDef {
+        name: "group.max.size",
+        topic_name: None,
+        config_type: ConfigType::Int,
+        default: "2147483647",
+        dynamic: false,
+        valid_values: &[],
+        at_least: None,
+        documentation:
+            "The maximum number of consumers that a single consumer group can accommodate.",
+    },
+    ConfigDef {
+        name: "group.consumer.session.timeout.ms",
+        topic_name: None,
+        config_type: ConfigType::Int,
+        default: "45000",
+        dynamic: false,
+        valid_values: &[],
+        at_least: None,
+        documentation:
+            "The timeout to detect client failures when using the consumer group protocol.",
+    },
+    ConfigDef {
+        name: "group.consumer.heartbeat.interval.ms",
+        topic_name: None,
+        config_type: ConfigType::Int,
+        default: "5000",
+        dynamic: false,
+        valid_values: &[],
+        at_least: None,
+        documentation: "The heartbeat interval given to the members of a consumer group.",
+    },
+    ConfigDef {
+        name: "group.consumer.max.size",
+        topic_name: None,
+        config_type: ConfigType::Int,
+        default: "2147483647",
+        dynamic: false,
+        valid_values: &[],
+        at_least: None,
+        documentation:
+            "The maximum number of consumers that a single consumer group can accommodate.",
+    },
+    ConfigDef {
+        name: "group.consumer.assignors",
+        topic_name: None,
+        config_type: ConfigType::List,
+        default: "uniform,range",
+        dynamic: false,
+        valid_values: &["uniform", "range"],
+        at_least: None,
+        documentation:
+            "The server side assignors, in order of preference; the first is the default.",
+    },
+    ConfigDef {
+        name: "group.share.session.timeout.ms",
+        topic_name: None,
+        config_type: ConfigType::Int,
+        default: "45000",
+        dynamic: false,
+        valid_values: &[],
+        at_least: None,
+        documentation: "The timeout to detect client failures when using the share group protocol.",
+    },
+    ConfigDef {
+        name: "group.share.heartbeat.interval.ms",
+        topic_name: None,
+        config_type: ConfigType::Int,
+        default: "5000",
+        dynamic: false,
+        valid_values: &[],
+        at_least: None,
+        documentation: "The heartbeat interval given to the members of a share group.",
+    },
+    ConfigDef {
+        name: "group.share.max.size",
+        topic_name: None,
+        config_type: ConfigType::Short,
+        default: "200",
+        dynamic: false,
+        valid_values: &[],
+        at_least: None,
+        documentation: "The maximum number of members that a single share group can accommodate.",
+    },
+    ConfigDef {
+        name: "group.share.record.lock.duration.ms",
+        topic_name: None,
+        config_type: ConfigType::Int,
+        default: "30000",
+        dynamic: false,
+        valid_values: &[],
+        at_least: None,
+        documentation:
+            "How long a share group member holds the records it fetched before they are released.",
+    },
+    ConfigDef {
+        name: "group.share.delivery.count.limit",
+        topic_name: None,
+        config_type: ConfigType::Int,
+        default: "5",
+        dynamic: false,
+        valid_values: &[],
+        at_least: None,
+        documentation:
+            "The number of delivery attempts of a record before it is archived as undeliverable.",
+    },
+    ConfigDef {
+        name: "group.share.partition.max.record.locks",
+        topic_name: None,
+        config_type: ConfigType::Int,
+        default: "2000",
+        dynamic: false,
+        valid_values: &[],
+        at_least: None,
+        documentation: "The maximum number of records a share-partition can have acquired at once.",
+    },
+    ConfigDef {
+        name: "node.id",
+        topic_name: None,
+        config_type: ConfigType::Int,
+        default: "1",
+        dynamic: false,
+        valid_values: &[],
+        at_least: None,
+        documentation: "The node id of this broker.",
+    },
+    ConfigDef {
+        name: "log.dirs",
+        topic_name: None,
+        config_type: ConfigType::String,
+        default: LOG_DIR,
+        dynamic: false,
+        valid_values: &[],
+        at_least: None,
+        documentation: "The directories in which the log data is kept.",
+    },
+];
+
+pub fn broker_config_def(name: &str) -> Option<&'static ConfigDef> {
+    CONFIG_DEFS.iter().find(|x| x.name == name)
+}
+
+pub fn topic_config_def(name: &str) -> Option<&'static ConfigDef> {
+    CONFIG_DEFS.iter().find(|x| x.topic_name == Some(name))
+}
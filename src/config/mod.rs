@@ -0,0 +1,6 @@
+mod definitions;
+pub use definitions::*;
+mod store;
+pub use store::*;
+mod properties;
+pub use properties::*;
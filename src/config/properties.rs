@@ -0,0 +1,75 @@
+use anyhow::{bail, Context, Result};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::sync::OnceLock;
+
+use crate::storage::LOG_DIR;
+
+use super::CONFIG_DEFS;
+
+static SERVER_PROPERTIES: OnceLock<HashMap<String, String>> = OnceLock::new();
+
+/// Loads the static broker config passed on the command line. Must be called
+/// once before the server starts accepting connections. Invalid values of
+/// known configs keep the broker from starting.
+pub fn load_server_properties(path: &str) -> Result<()> {
+    let content = fs::read_to_string(path).with_context(|| format!("Failed to read {}", path))?;
+    let properties = parse_properties(&content);
+    for def in CONFIG_DEFS {
+        if let Some(value) = properties.get(def.name) {
+            if !def.is_valid(value) {
+                bail!("Invalid value {} for configuration {}", value, def.name);
+            }
+        }
+    }
+    let _ = SERVER_PROPERTIES.set(properties);
+    Ok(())
+}
+
+pub fn server_properties() -> &'static HashMap<String, String> {
+    SERVER_PROPERTIES.get_or_init(HashMap::new)
+}
+
+/// The cluster id written to meta.properties when the log dir was formatted.
+pub fn cluster_id() -> String {
+    fs::read_to_string(Path::new(LOG_DIR).join("meta.properties"))
+        .ok()
+        .and_then(|x| parse_properties(&x).remove("cluster.id"))
+        .unwrap_or_default()
+}
+
+/// Host and port clients should use to reach this broker.
+pub fn advertised_endpoint() -> (String, i32) {
+    let properties = server_properties();
+    properties
+        .get("advertised.listeners")
+        .or_else(|| properties.get("listeners"))
+        .and_then(|x| x.split(',').next())
+        .and_then(|x| x.split_once("://"))
+        .and_then(|(_, address)| address.rsplit_once(':'))
+        .map(|(host, port)| {
+            let host = if host.is_empty() { "localhost" } else { host };
+            (host.to_owned(), port.parse().unwrap_or(9092))
+        })
+        .unwrap_or(("localhost".to_owned(), 9092))
+}
+
+/// Address of the HTTP admin endpoint, `admin.listener` in server.properties.
+/// The endpoint is disabled when unset.
+pub fn admin_listener() -> Option<String> {
+    server_properties()
+        .get("admin.listener")
+        .map(|x| x.trim_start_matches("http://").to_owned())
+        .filter(|x| !x.is_empty())
+}
+
+fn parse_properties(content: &str) -> HashMap<String, String> {
+    content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#') && !line.starts_with('!'))
+        .filter_map(|line| line.split_once(['=', ':']))
+        .map(|(key, value)| (key.trim().to_owned(), value.trim().to_owned()))
+        .collect()
+}
@@ -0,0 +1,212 @@
+use std::collections::HashMap;
+
+use crate::metadata::MetadataFile;
+
+use super::{server_properties, ConfigDef, CONFIG_DEFS};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[repr(i8)]
+pub enum ConfigResourceType {
+    Topic = 2,
+    Broker = 4,
+}
+
+impl TryFrom<i8> for ConfigResourceType {
+    type Error = i8;
+
+    fn try_from(value: i8) -> Result<Self, Self::Error> {
+        match value {
+            2 => Ok(Self::Topic),
+            4 => Ok(Self::Broker),
+            _ => Err(value),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[repr(i8)]
+pub enum ConfigSource {
+    DynamicTopic = 1,
+    DynamicBroker = 2,
+    DynamicDefaultBroker = 3,
+    StaticBroker = 4,
+    Default = 5,
+}
+
+#[derive(Debug, Clone)]
+pub struct ConfigSynonym {
+    pub name: String,
+    pub value: String,
+    pub source: ConfigSource,
+}
+
+#[derive(Debug)]
+pub struct ConfigEntry {
+    pub name: String,
+    pub value: String,
+    pub source: ConfigSource,
+    pub read_only: bool,
+    /// Every layer that sets the config, highest precedence first.
+    pub synonyms: Vec<ConfigSynonym>,
+    pub def: &'static ConfigDef,
+}
+
+/// Effective configs, layering topic overrides on dynamic broker configs,
+/// the static server.properties and built-in defaults.
+#[derive(Debug, Default)]
+pub struct ConfigStore {
+    broker_defaults: HashMap<String, String>,
+    brokers: HashMap<String, HashMap<String, String>>,
+    topics: HashMap<String, HashMap<String, String>>,
+}
+
+pub fn node_id() -> i32 {
+    server_properties()
+        .get("node.id")
+        .and_then(|x| x.parse().ok())
+        .unwrap_or(1)
+}
+
+impl ConfigStore {
+    /// Replays the ConfigRecords of the cluster metadata log.
+    pub fn load(metadata: &MetadataFile) -> Self {
+        let mut store = Self::default();
+        for record in metadata.get_configs() {
+            let configs = match ConfigResourceType::try_from(record.resource_type) {
+                Ok(ConfigResourceType::Topic) => store
+                    .topics
+                    .entry(record.resource_name.clone())
+                    .or_default(),
+                Ok(ConfigResourceType::Broker) if record.resource_name.is_empty() => {
+                    &mut store.broker_defaults
+                }
+                Ok(ConfigResourceType::Broker) => store
+                    .brokers
+                    .entry(record.resource_name.clone())
+                    .or_default(),
+                Err(_) => continue,
+            };
+            match &record.value {
+                Some(value) => configs.insert(record.name.clone(), value.clone()),
+                None => configs.remove(&record.name),
+            };
+        }
+        store
+    }
+
+    /// Configs set through the admin APIs on exactly this resource.
+    pub fn dynamic_configs(
+        &self,
+        resource_type: ConfigResourceType,
+        resource_name: &str,
+    ) -> HashMap<String, String> {
+        let configs = match resource_type {
+            ConfigResourceType::Topic => self.topics.get(resource_name),
+            ConfigResourceType::Broker if resource_name.is_empty() => Some(&self.broker_defaults),
+            ConfigResourceType::Broker => self.brokers.get(resource_name),
+        };
+        configs.cloned().unwrap_or_default()
+    }
+
+    fn broker_synonyms(&self, def: &'static ConfigDef) -> Vec<ConfigSynonym> {
+        let layers = [
+            (
+                self.brokers.get(&node_id().to_string()),
+                ConfigSource::DynamicBroker,
+            ),
+            (
+                Some(&self.broker_defaults),
+                ConfigSource::DynamicDefaultBroker,
+            ),
+            (Some(server_properties()), ConfigSource::StaticBroker),
+        ];
+        let mut synonyms: Vec<ConfigSynonym> = layers
+            .into_iter()
+            .filter_map(|(configs, source)| {
+                configs
+                    .and_then(|x| x.get(def.name))
+                    .map(|value| ConfigSynonym {
+                        name: def.name.to_owned(),
+                        value: value.clone(),
+                        source,
+                    })
+            })
+            .collect();
+        synonyms.push(ConfigSynonym {
+            name: def.name.to_owned(),
+            value: def.default.to_owned(),
+            source: ConfigSource::Default,
+        });
+        synonyms
+    }
+
+    fn topic_synonyms(&self, topic: &str, def: &'static ConfigDef) -> Vec<ConfigSynonym> {
+        let topic_name = def.topic_name.unwrap_or(def.name);
+        let mut synonyms = vec![];
+        if let Some(value) = self.topics.get(topic).and_then(|x| x.get(topic_name)) {
+            synonyms.push(ConfigSynonym {
+                name: topic_name.to_owned(),
+                value: value.clone(),
+                source: ConfigSource::DynamicTopic,
+            });
+        }
+        synonyms.extend(self.broker_synonyms(def));
+        synonyms
+    }
+
+    pub fn describe(
+        &self,
+        resource_type: ConfigResourceType,
+        resource_name: &str,
+    ) -> Vec<ConfigEntry> {
+        CONFIG_DEFS
+            .iter()
+            .filter_map(|def| {
+                let (name, synonyms, read_only) = match resource_type {
+                    ConfigResourceType::Topic => (
+                        def.topic_name?,
+                        self.topic_synonyms(resource_name, def),
+                        false,
+                    ),
+                    // The cluster-wide default resource only lists what was set on it.
+                    ConfigResourceType::Broker if resource_name.is_empty() => (
+                        def.name,
+                        vec![ConfigSynonym {
+                            name: def.name.to_owned(),
+                            value: self.broker_defaults.get(def.name)?.clone(),
+                            source: ConfigSource::DynamicDefaultBroker,
+                        }],
+                        !def.dynamic,
+                    ),
+                    ConfigResourceType::Broker => {
+                        (def.name, self.broker_synonyms(def), !def.dynamic)
+                    }
+                };
+                Some(ConfigEntry {
+                    name: name.to_owned(),
+                    value: synonyms[0].value.clone(),
+                    source: synonyms[0].source,
+                    read_only,
+                    synonyms,
+                    def,
+                })
+            })
+            .collect()
+    }
+
+    pub fn topic_config(&self, topic: &str, name: &str) -> Option<String> {
+        let def = CONFIG_DEFS.iter().find(|x| x.topic_name == Some(name))?;
+        Some(self.topic_synonyms(topic, def).remove(0).value)
+    }
+
+    pub fn broker_config(&self, name: &str) -> Option<String> {
+        let def = CONFIG_DEFS.iter().find(|x| x.name == name)?;
+        Some(self.broker_synonyms(def).remove(0).value)
+    }
+
+    pub fn broker_config_i64(&self, name: &str) -> i64 {
+        self.broker_config(name)
+            .and_then(|x| x.parse().ok())
+            .unwrap_or_default()
+    }
+}
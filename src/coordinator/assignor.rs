@@ -0,0 +1,227 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Partitions by topic id.
+pub type Assignment = BTreeMap<i128, BTreeSet<i32>>;
+
+/// What an assignor sees of a member.
+#[derive(Debug, Clone)]
+pub struct MemberSpec {
+    pub subscribed_topic_ids: BTreeSet<i128>,
+    /// The member's current target, which sticky assignors try to keep.
+    pub current: Assignment,
+}
+
+/// Input of a target assignment computation.
+#[derive(Debug, Clone)]
+pub struct AssignmentSpec {
+    pub members: BTreeMap<String, MemberSpec>,
+    /// Partition count of every subscribed topic.
+    pub topics: BTreeMap<i128, i32>,
+}
+
+/// A server-side assignor computing the target assignment of a consumer
+/// group.
+pub trait PartitionAssignor: Sync {
+    fn name(&self) -> &'static str;
+
+    fn assign(&self, spec: &AssignmentSpec) -> BTreeMap<String, Assignment>;
+}
+
+const ASSIGNORS: [&dyn PartitionAssignor; 3] = [&UniformAssignor, &RangeAssignor, &SimpleAssignor];
+
+pub fn assignor(name: &str) -> Option<&'static dyn PartitionAssignor> {
+    ASSIGNORS.into_iter().find(|x| x.name() == name)
+}
+
+fn count(assignment: &Assignment) -> usize {
+    assignment.values().map(BTreeSet::len).sum()
+}
+
+/// Spreads all partitions evenly over the members subscribing to them,
+/// keeping as much of the current assignment as balance allows.
+#[derive(Debug)]
+pub struct UniformAssignor;
+
+impl PartitionAssignor for UniformAssignor {
+    fn name(&self) -> &'static str {
+        "uniform"
+    }
+
+    fn assign(&self, spec: &AssignmentSpec) -> BTreeMap<String, Assignment> {
+        let mut result: BTreeMap<String, Assignment> = spec
+            .members
+            .keys()
+            .map(|x| (x.clone(), Assignment::new()))
+            .collect();
+        let subscribers = |topic_id: &i128| -> Vec<&String> {
+            spec.members
+                .iter()
+                .filter(|(_, x)| x.subscribed_topic_ids.contains(topic_id))
+                .map(|(member_id, _)| member_id)
+                .collect()
+        };
+
+        // Keep every current partition that is still valid and unclaimed.
+        let mut claimed: BTreeSet<(i128, i32)> = BTreeSet::new();
+        for (member_id, member) in &spec.members {
+            for (topic_id, partitions) in &member.current {
+                let Some(&count) = spec.topics.get(topic_id) else {
+                    continue;
+                };
+                if !member.subscribed_topic_ids.contains(topic_id) {
+                    continue;
+                }
+                for &partition in partitions {
+                    if partition < count && claimed.insert((*topic_id, partition)) {
+                        result
+                            .get_mut(member_id)
+                            .unwrap()
+                            .entry(*topic_id)
+                            .or_default()
+                            .insert(partition);
+                    }
+                }
+            }
+        }
+
+        // Hand out the rest to the least loaded subscriber.
+        for (topic_id, &partitions) in &spec.topics {
+            let candidates = subscribers(topic_id);
+            for partition in 0..partitions {
+                if claimed.contains(&(*topic_id, partition)) {
+                    continue;
+                }
+                let Some(member_id) = candidates.iter().min_by_key(|x| count(&result[**x])) else {
+                    continue;
+                };
+                result
+                    .get_mut(*member_id)
+                    .unwrap()
+                    .entry(*topic_id)
+                    .or_default()
+                    .insert(partition);
+            }
+        }
+
+        // Move partitions from the most to the least loaded members that
+        // can take them until no member has two more than another.
+        loop {
+            let mut moved = false;
+            let mut by_load: Vec<&String> = spec.members.keys().collect();
+            by_load.sort_by_key(|x| std::cmp::Reverse(count(&result[*x])));
+            'search: for from in &by_load {
+                for to in by_load.iter().rev() {
+                    if count(&result[*from]) <= count(&result[*to]) + 1 {
+                        break;
+                    }
+                    let movable = result[*from].iter().find_map(|(topic_id, partitions)| {
+                        let subscribed = spec.members[*to].subscribed_topic_ids.contains(topic_id);
+                        subscribed
+                            .then(|| partitions.last().map(|x| (*topic_id, *x)))
+                            .flatten()
+                    });
+                    if let Some((topic_id, partition)) = movable {
+                        let source = result.get_mut(*from).unwrap();
+                        source.get_mut(&topic_id).unwrap().remove(&partition);
+                        source.retain(|_, x| !x.is_empty());
+                        result
+                            .get_mut(*to)
+                            .unwrap()
+                            .entry(topic_id)
+                            .or_default()
+                            .insert(partition);
+                        moved = true;
+                        break 'search;
+                    }
+                }
+            }
+            if !moved {
+                return result;
+            }
+        }
+    }
+}
+
+/// Splits each topic into contiguous ranges over its subscribers in member
+/// id order.
+#[derive(Debug)]
+pub struct RangeAssignor;
+
+impl PartitionAssignor for RangeAssignor {
+    fn name(&self) -> &'static str {
+        "range"
+    }
+
+    fn assign(&self, spec: &AssignmentSpec) -> BTreeMap<String, Assignment> {
+        let mut result: BTreeMap<String, Assignment> = spec
+            .members
+            .keys()
+            .map(|x| (x.clone(), Assignment::new()))
+            .collect();
+        for (topic_id, &partitions) in &spec.topics {
+            let subscribers: Vec<&String> = spec
+                .members
+                .iter()
+                .filter(|(_, x)| x.subscribed_topic_ids.contains(topic_id))
+                .map(|(member_id, _)| member_id)
+                .collect();
+            if subscribers.is_empty() {
+                continue;
+            }
+            let quota = partitions as usize / subscribers.len();
+            let extra = partitions as usize % subscribers.len();
+            let mut start = 0;
+            for (idx, member_id) in subscribers.into_iter().enumerate() {
+                let length = quota + usize::from(idx < extra);
+                if length > 0 {
+                    let range = (start..start + length).map(|x| x as i32).collect();
+                    result.get_mut(member_id).unwrap().insert(*topic_id, range);
+                }
+                start += length;
+            }
+        }
+        result
+    }
+}
+
+/// The share group assignor. Members of a share group may share partitions,
+/// so every subscriber gets at least one partition of each topic and every
+/// partition at least one member.
+#[derive(Debug)]
+pub struct SimpleAssignor;
+
+impl PartitionAssignor for SimpleAssignor {
+    fn name(&self) -> &'static str {
+        "simple"
+    }
+
+    fn assign(&self, spec: &AssignmentSpec) -> BTreeMap<String, Assignment> {
+        let mut result: BTreeMap<String, Assignment> = spec
+            .members
+            .keys()
+            .map(|x| (x.clone(), Assignment::new()))
+            .collect();
+        for (topic_id, &partitions) in &spec.topics {
+            let subscribers: Vec<&String> = spec
+                .members
+                .iter()
+                .filter(|(_, x)| x.subscribed_topic_ids.contains(topic_id))
+                .map(|(member_id, _)| member_id)
+                .collect();
+            if subscribers.is_empty() || partitions <= 0 {
+                continue;
+            }
+            let slots = subscribers.len().max(partitions as usize);
+            for slot in 0..slots {
+                let member_id = subscribers[slot % subscribers.len()];
+                result
+                    .get_mut(member_id)
+                    .unwrap()
+                    .entry(*topic_id)
+                    .or_default()
+                    .insert((slot % partitions as usize) as i32);
+            }
+        }
+        result
+    }
+}
@@ -0,0 +1,284 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::time::{Duration, Instant};
+
+use super::Assignment;
+
+/// Member epoch of a member leaving the group for good.
+pub const LEAVE_GROUP_MEMBER_EPOCH: i32 = -1;
+/// Member epoch of a static member leaving temporarily; it keeps its
+/// partitions until its session times out.
+pub const LEAVE_GROUP_STATIC_MEMBER_EPOCH: i32 = -2;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ConsumerGroupState {
+    Empty,
+    /// The group epoch is ahead of the target assignment.
+    Assigning,
+    /// Members are still converging to the target assignment.
+    Reconciling,
+    Stable,
+}
+
+impl ConsumerGroupState {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Empty => "Empty",
+            Self::Assigning => "Assigning",
+            Self::Reconciling => "Reconciling",
+            Self::Stable => "Stable",
+        }
+    }
+}
+
+/// Where a member is in reconciling its target assignment. The values are
+/// the ones persisted in the current member assignment record.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[repr(i8)]
+pub enum MemberState {
+    Stable = 1,
+    /// The member must revoke partitions before it gets the new epoch.
+    UnrevokedPartitions = 2,
+    /// The member has the new epoch but waits for partitions other members
+    /// still own.
+    UnreleasedPartitions = 3,
+}
+
+impl From<i8> for MemberState {
+    fn from(value: i8) -> Self {
+        match value {
+            2 => Self::UnrevokedPartitions,
+            3 => Self::UnreleasedPartitions,
+            _ => Self::Stable,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ConsumerMember {
+    pub member_id: String,
+    pub instance_id: Option<String>,
+    pub rack_id: Option<String>,
+    pub client_id: String,
+    pub client_host: String,
+    pub subscribed_topic_names: Vec<String>,
+    pub server_assignor: Option<String>,
+    pub rebalance_timeout_ms: i32,
+    pub member_epoch: i32,
+    pub previous_member_epoch: i32,
+    pub state: MemberState,
+    pub assigned: Assignment,
+    pub pending_revocation: Assignment,
+    pub last_heartbeat: Instant,
+    pub session_timeout: Duration,
+    /// Set while the member has partitions to revoke.
+    pub revocation_deadline: Option<Instant>,
+}
+
+impl ConsumerMember {
+    pub fn new(member_id: &str, now: Instant) -> Self {
+        Self {
+            member_id: member_id.to_owned(),
+            instance_id: None,
+            rack_id: None,
+            client_id: String::new(),
+            client_host: String::new(),
+            subscribed_topic_names: vec![],
+            server_assignor: None,
+            rebalance_timeout_ms: -1,
+            member_epoch: 0,
+            previous_member_epoch: 0,
+            state: MemberState::Stable,
+            assigned: Assignment::new(),
+            pending_revocation: Assignment::new(),
+            last_heartbeat: now,
+            session_timeout: Duration::ZERO,
+            revocation_deadline: None,
+        }
+    }
+
+    /// When the member is fenced for not heartbeating or not revoking.
+    pub fn deadline(&self) -> Instant {
+        let session = self.last_heartbeat + self.session_timeout;
+        self.revocation_deadline.map_or(session, |x| x.min(session))
+    }
+
+    /// Whether the member owns the partition or has yet to revoke it.
+    fn holds(&self, topic_id: &i128, partition: i32) -> bool {
+        [&self.assigned, &self.pending_revocation]
+            .iter()
+            .any(|x| x.get(topic_id).is_some_and(|x| x.contains(&partition)))
+    }
+}
+
+/// A consumer group using the KIP-848 protocol. The coordinator computes a
+/// target assignment per group epoch and each member converges to it one
+/// heartbeat at a time.
+#[derive(Debug, Clone)]
+pub struct ConsumerGroup {
+    pub group_id: String,
+    pub group_epoch: i32,
+    pub assignment_epoch: i32,
+    pub members: BTreeMap<String, ConsumerMember>,
+    pub target: BTreeMap<String, Assignment>,
+    /// Name and partition count of every subscribed topic, by topic id.
+    pub subscription_metadata: BTreeMap<i128, (String, i32)>,
+    /// Member id currently holding each static instance id.
+    pub static_members: HashMap<String, String>,
+}
+
+impl ConsumerGroup {
+    pub fn new(group_id: &str) -> Self {
+        Self {
+            group_id: group_id.to_owned(),
+            group_epoch: 0,
+            assignment_epoch: 0,
+            members: BTreeMap::new(),
+            target: BTreeMap::new(),
+            subscription_metadata: BTreeMap::new(),
+            static_members: HashMap::new(),
+        }
+    }
+
+    pub fn state(&self) -> ConsumerGroupState {
+        if self.members.is_empty() {
+            ConsumerGroupState::Empty
+        } else if self.group_epoch > self.assignment_epoch {
+            ConsumerGroupState::Assigning
+        } else if self
+            .members
+            .values()
+            .any(|x| x.member_epoch != self.assignment_epoch || x.state != MemberState::Stable)
+        {
+            ConsumerGroupState::Reconciling
+        } else {
+            ConsumerGroupState::Stable
+        }
+    }
+
+    /// Topics subscribed by any member.
+    pub fn subscribed_topic_names(&self) -> BTreeSet<String> {
+        self.members
+            .values()
+            .flat_map(|x| x.subscribed_topic_names.iter().cloned())
+            .collect()
+    }
+
+    /// The assignor most members asked for, or `default` if none did.
+    pub fn preferred_assignor(&self, default: &str) -> String {
+        let mut votes: BTreeMap<&str, usize> = BTreeMap::new();
+        for assignor in self
+            .members
+            .values()
+            .filter_map(|x| x.server_assignor.as_deref())
+        {
+            *votes.entry(assignor).or_default() += 1;
+        }
+        votes
+            .into_iter()
+            .max_by_key(|(_, count)| *count)
+            .map_or(default.to_owned(), |(name, _)| name.to_owned())
+    }
+
+    pub fn add_member(&mut self, member: ConsumerMember) {
+        if let Some(instance_id) = &member.instance_id {
+            self.static_members
+                .insert(instance_id.clone(), member.member_id.clone());
+        }
+        self.members.insert(member.member_id.clone(), member);
+    }
+
+    pub fn remove_member(&mut self, member_id: &str) -> Option<ConsumerMember> {
+        self.static_members.retain(|_, x| x != member_id);
+        self.target.remove(member_id);
+        self.members.remove(member_id)
+    }
+
+    /// Whether another member than `member_id` still holds the partition.
+    fn held_by_other(&self, member_id: &str, topic_id: &i128, partition: i32) -> bool {
+        self.members
+            .values()
+            .any(|x| x.member_id != member_id && x.holds(topic_id, partition))
+    }
+
+    /// Moves a member one step towards its target assignment. A member first
+    /// revokes what it must give up while keeping its epoch, then gets the
+    /// assignment epoch along with the partitions no one else holds. `owned`
+    /// is what the member reported owning, `None` if it did not say.
+    pub fn reconcile(&mut self, member_id: &str, owned: Option<&Assignment>, now: Instant) {
+        let Some(member) = self.members.get(member_id) else {
+            return;
+        };
+        let target_epoch = self.assignment_epoch;
+        let target = self.target.get(member_id).cloned().unwrap_or_default();
+        let mut next = member.clone();
+        match member.state {
+            MemberState::Stable if member.member_epoch == target_epoch => return,
+            MemberState::UnrevokedPartitions => {
+                let revoked = owned.is_some_and(|owned| {
+                    !member
+                        .pending_revocation
+                        .iter()
+                        .any(|(topic_id, partitions)| {
+                            owned
+                                .get(topic_id)
+                                .is_some_and(|x| !x.is_disjoint(partitions))
+                        })
+                });
+                if !revoked {
+                    return;
+                }
+                next.pending_revocation.clear();
+                next.revocation_deadline = None;
+            }
+            _ => {}
+        }
+
+        let mut keep = Assignment::new();
+        let mut revoke = Assignment::new();
+        for (topic_id, partitions) in &next.assigned {
+            let wanted = target.get(topic_id);
+            for &partition in partitions {
+                let set = if wanted.is_some_and(|x| x.contains(&partition)) {
+                    &mut keep
+                } else {
+                    &mut revoke
+                };
+                set.entry(*topic_id).or_default().insert(partition);
+            }
+        }
+        if !revoke.is_empty() {
+            next.assigned = keep;
+            next.pending_revocation = revoke;
+            next.state = MemberState::UnrevokedPartitions;
+            next.revocation_deadline =
+                Some(now + Duration::from_millis(next.rebalance_timeout_ms.max(0) as u64));
+        } else {
+            let mut unreleased = false;
+            for (topic_id, partitions) in &target {
+                for &partition in partitions {
+                    if keep.get(topic_id).is_some_and(|x| x.contains(&partition)) {
+                        continue;
+                    }
+                    if self.held_by_other(member_id, topic_id, partition) {
+                        unreleased = true;
+                    } else {
+                        keep.entry(*topic_id).or_default().insert(partition);
+                    }
+                }
+            }
+            next.assigned = keep;
+            next.pending_revocation.clear();
+            next.revocation_deadline = None;
+            if next.member_epoch != target_epoch {
+                next.previous_member_epoch = next.member_epoch;
+                next.member_epoch = target_epoch;
+            }
+            next.state = if unreleased {
+                MemberState::UnreleasedPartitions
+            } else {
+                MemberState::Stable
+            };
+        }
+        self.members.insert(member_id.to_owned(), next);
+    }
+}
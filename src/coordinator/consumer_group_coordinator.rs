@@ -0,0 +1,738 @@
+use anyhow::Result;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::sync::{Mutex, MutexGuard, OnceLock};
+use std::time::{Duration, Instant};
+
+use crate::{
+    config::{split_list, ConfigStore},
+    metadata::{random_uuid, read_cluster_metadata, MetadataFile},
+    protocol::ErrorCode,
+};
+
+use super::{
+    assignor, replay_group_metadata, write_records, Assignment, AssignmentSpec, ConsumerGroup,
+    ConsumerGroupRecord, ConsumerMember, MemberAssignmentValue, MemberMetadataValue, MemberSpec,
+    MemberState, LEAVE_GROUP_MEMBER_EPOCH, LEAVE_GROUP_STATIC_MEMBER_EPOCH,
+};
+
+/// Broker configs of consumer groups.
+#[derive(Debug, Clone)]
+pub struct ConsumerGroupConfig {
+    pub session_timeout: Duration,
+    pub heartbeat_interval_ms: i32,
+    pub max_size: i64,
+    /// Server assignors members may pick, the first being the default.
+    pub assignors: Vec<String>,
+}
+
+impl ConsumerGroupConfig {
+    pub fn load(store: &ConfigStore) -> Self {
+        let assignors = store
+            .broker_config("group.consumer.assignors")
+            .unwrap_or_default();
+        Self {
+            session_timeout: Duration::from_millis(
+                store
+                    .broker_config_i64("group.consumer.session.timeout.ms")
+                    .max(0) as u64,
+            ),
+            heartbeat_interval_ms: store.broker_config_i64("group.consumer.heartbeat.interval.ms")
+                as i32,
+            max_size: store.broker_config_i64("group.consumer.max.size"),
+            assignors: split_list(&assignors).map(str::to_owned).collect(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ConsumerGroupHeartbeatParams {
+    pub group_id: String,
+    /// Empty for a member joining for the first time.
+    pub member_id: String,
+    pub member_epoch: i32,
+    pub instance_id: Option<String>,
+    pub rack_id: Option<String>,
+    /// -1 if unchanged.
+    pub rebalance_timeout_ms: i32,
+    /// `None` if unchanged.
+    pub subscribed_topic_names: Option<Vec<String>>,
+    pub server_assignor: Option<String>,
+    /// The partitions the member owns, `None` if unchanged.
+    pub owned: Option<Assignment>,
+    pub client_id: String,
+    pub client_host: String,
+}
+
+#[derive(Debug)]
+pub struct ConsumerGroupHeartbeatResult {
+    pub member_id: String,
+    pub member_epoch: i32,
+    /// Only set when the member has to learn its assignment.
+    pub assignment: Option<Assignment>,
+}
+
+type HeartbeatError = (ErrorCode, String);
+
+/// Consumer groups using the KIP-848 protocol, cached in memory and persisted
+/// as records in `__consumer_offsets`. Unlike classic groups nothing blocks:
+/// every heartbeat moves the member as far as it can and returns.
+#[derive(Debug, Default)]
+pub struct ConsumerGroupCoordinator {
+    groups: Mutex<HashMap<String, ConsumerGroup>>,
+}
+
+/// The consumer group coordinator, rebuilt from `__consumer_offsets` on
+/// first use.
+pub fn consumer_group_coordinator() -> &'static ConsumerGroupCoordinator {
+    static COORDINATOR: OnceLock<ConsumerGroupCoordinator> = OnceLock::new();
+    COORDINATOR.get_or_init(|| ConsumerGroupCoordinator::load().unwrap_or_default())
+}
+
+fn metadata_value(member: &ConsumerMember) -> MemberMetadataValue {
+    MemberMetadataValue {
+        instance_id: member.instance_id.clone(),
+        rack_id: member.rack_id.clone(),
+        client_id: member.client_id.clone(),
+        client_host: member.client_host.clone(),
+        subscribed_topic_names: member.subscribed_topic_names.clone(),
+        server_assignor: member.server_assignor.clone(),
+        rebalance_timeout_ms: member.rebalance_timeout_ms,
+    }
+}
+
+fn assignment_value(member: &ConsumerMember) -> MemberAssignmentValue {
+    MemberAssignmentValue {
+        member_epoch: member.member_epoch,
+        previous_member_epoch: member.previous_member_epoch,
+        state: member.state as i8,
+        assigned: member.assigned.clone(),
+        pending_revocation: member.pending_revocation.clone(),
+    }
+}
+
+fn member_record(group_id: &str, member: &ConsumerMember) -> ConsumerGroupRecord {
+    ConsumerGroupRecord::MemberMetadata {
+        group_id: group_id.to_owned(),
+        member_id: member.member_id.clone(),
+        value: Some(metadata_value(member)),
+    }
+}
+
+fn assignment_record(group_id: &str, member: &ConsumerMember) -> ConsumerGroupRecord {
+    ConsumerGroupRecord::CurrentMemberAssignment {
+        group_id: group_id.to_owned(),
+        member_id: member.member_id.clone(),
+        value: Some(assignment_value(member)),
+    }
+}
+
+fn member_tombstones(group_id: &str, member_id: &str) -> [ConsumerGroupRecord; 3] {
+    [
+        ConsumerGroupRecord::CurrentMemberAssignment {
+            group_id: group_id.to_owned(),
+            member_id: member_id.to_owned(),
+            value: None,
+        },
+        ConsumerGroupRecord::TargetAssignmentMember {
+            group_id: group_id.to_owned(),
+            member_id: member_id.to_owned(),
+            partitions: None,
+        },
+        ConsumerGroupRecord::MemberMetadata {
+            group_id: group_id.to_owned(),
+            member_id: member_id.to_owned(),
+            value: None,
+        },
+    ]
+}
+
+/// Name and partition count of the existing topics among `topic_names`, by
+/// topic id.
+pub fn subscription_metadata(
+    topic_names: BTreeSet<String>,
+    metadata: &MetadataFile,
+) -> BTreeMap<i128, (String, i32)> {
+    topic_names
+        .into_iter()
+        .filter_map(|name| {
+            let topic = metadata.get_topic_by_name(&name)?;
+            let partitions: BTreeSet<i32> = metadata
+                .get_topic_partitions(&topic.uuid)
+                .map(|x| x.partition_id)
+                .collect();
+            Some((topic.uuid, (name, partitions.len() as i32)))
+        })
+        .collect()
+}
+
+/// Starts a new group epoch, refreshing the subscription metadata.
+fn bump_group_epoch(
+    group: &mut ConsumerGroup,
+    metadata: &MetadataFile,
+    records: &mut Vec<ConsumerGroupRecord>,
+) {
+    let subscription = subscription_metadata(group.subscribed_topic_names(), metadata);
+    if subscription != group.subscription_metadata {
+        group.subscription_metadata = subscription.clone();
+        records.push(ConsumerGroupRecord::PartitionMetadata {
+            group_id: group.group_id.clone(),
+            topics: Some(subscription),
+        });
+    }
+    group.group_epoch += 1;
+    records.push(ConsumerGroupRecord::GroupMetadata {
+        group_id: group.group_id.clone(),
+        epoch: Some(group.group_epoch),
+    });
+}
+
+/// Computes the target assignment of the current group epoch.
+fn compute_target(
+    group: &mut ConsumerGroup,
+    config: &ConsumerGroupConfig,
+    records: &mut Vec<ConsumerGroupRecord>,
+) -> Result<(), HeartbeatError> {
+    let default = config.assignors.first().map_or("uniform", String::as_str);
+    let name = group.preferred_assignor(default);
+    let Some(assignor) = assignor(&name) else {
+        return Err((
+            ErrorCode::UnsupportedAssignor,
+            format!("Assignor {} is not supported.", name),
+        ));
+    };
+    let topic_ids: HashMap<&str, i128> = group
+        .subscription_metadata
+        .iter()
+        .map(|(topic_id, (name, _))| (name.as_str(), *topic_id))
+        .collect();
+    let spec = AssignmentSpec {
+        members: group
+            .members
+            .values()
+            .map(|member| {
+                let spec = MemberSpec {
+                    subscribed_topic_ids: member
+                        .subscribed_topic_names
+                        .iter()
+                        .filter_map(|x| topic_ids.get(x.as_str()).copied())
+                        .collect(),
+                    current: group
+                        .target
+                        .get(&member.member_id)
+                        .cloned()
+                        .unwrap_or_default(),
+                };
+                (member.member_id.clone(), spec)
+            })
+            .collect(),
+        topics: group
+            .subscription_metadata
+            .iter()
+            .map(|(topic_id, (_, count))| (*topic_id, *count))
+            .collect(),
+    };
+    let target = assignor.assign(&spec);
+    for (member_id, partitions) in &target {
+        if group.target.get(member_id) != Some(partitions) {
+            records.push(ConsumerGroupRecord::TargetAssignmentMember {
+                group_id: group.group_id.clone(),
+                member_id: member_id.clone(),
+                partitions: Some(partitions.clone()),
+            });
+        }
+    }
+    group.target = target;
+    group.assignment_epoch = group.group_epoch;
+    records.push(ConsumerGroupRecord::TargetAssignmentMetadata {
+        group_id: group.group_id.clone(),
+        assignment_epoch: Some(group.assignment_epoch),
+    });
+    Ok(())
+}
+
+fn write(group_id: &str, records: &[ConsumerGroupRecord]) -> Result<()> {
+    write_records(
+        group_id,
+        records.iter().map(ConsumerGroupRecord::serialize).collect(),
+    )
+}
+
+fn new_member_id() -> String {
+    let uuid = format!("{:032x}", random_uuid() as u128);
+    format!(
+        "{}-{}-{}-{}-{}",
+        &uuid[..8],
+        &uuid[8..12],
+        &uuid[12..16],
+        &uuid[16..20],
+        &uuid[20..]
+    )
+}
+
+impl ConsumerGroupCoordinator {
+    fn load() -> Result<Self> {
+        let store = ConfigStore::load(&read_cluster_metadata()?);
+        let session_timeout = ConsumerGroupConfig::load(&store).session_timeout;
+        let now = Instant::now();
+        let mut groups: HashMap<String, ConsumerGroup> = HashMap::new();
+        replay_group_metadata(|key, value| {
+            if let Some(record) = ConsumerGroupRecord::deserialize(key, value) {
+                Self::apply(&mut groups, record, now, session_timeout);
+            }
+        })?;
+        Ok(Self {
+            groups: Mutex::new(groups),
+        })
+    }
+
+    fn apply(
+        groups: &mut HashMap<String, ConsumerGroup>,
+        record: ConsumerGroupRecord,
+        now: Instant,
+        session_timeout: Duration,
+    ) {
+        let group_id = record.group_id().to_owned();
+        if let ConsumerGroupRecord::GroupMetadata { epoch: None, .. } = record {
+            groups.remove(&group_id);
+            return;
+        }
+        let group = groups
+            .entry(group_id.clone())
+            .or_insert_with(|| ConsumerGroup::new(&group_id));
+        match record {
+            ConsumerGroupRecord::GroupMetadata { epoch, .. } => {
+                group.group_epoch = epoch.unwrap_or_default();
+            }
+            ConsumerGroupRecord::PartitionMetadata { topics, .. } => {
+                group.subscription_metadata = topics.unwrap_or_default();
+            }
+            ConsumerGroupRecord::MemberMetadata {
+                member_id, value, ..
+            } => match value {
+                None => {
+                    group.remove_member(&member_id);
+                }
+                Some(value) => {
+                    let mut member = group
+                        .members
+                        .get(&member_id)
+                        .cloned()
+                        .unwrap_or_else(|| ConsumerMember::new(&member_id, now));
+                    member.instance_id = value.instance_id;
+                    member.rack_id = value.rack_id;
+                    member.client_id = value.client_id;
+                    member.client_host = value.client_host;
+                    member.subscribed_topic_names = value.subscribed_topic_names;
+                    member.server_assignor = value.server_assignor;
+                    member.rebalance_timeout_ms = value.rebalance_timeout_ms;
+                    member.session_timeout = session_timeout;
+                    group.add_member(member);
+                }
+            },
+            ConsumerGroupRecord::TargetAssignmentMetadata {
+                assignment_epoch, ..
+            } => {
+                group.assignment_epoch = assignment_epoch.unwrap_or_default();
+            }
+            ConsumerGroupRecord::TargetAssignmentMember {
+                member_id,
+                partitions,
+                ..
+            } => match partitions {
+                Some(partitions) => {
+                    group.target.insert(member_id, partitions);
+                }
+                None => {
+                    group.target.remove(&member_id);
+                }
+            },
+            ConsumerGroupRecord::CurrentMemberAssignment {
+                member_id, value, ..
+            } => {
+                if let (Some(member), Some(value)) = (group.members.get_mut(&member_id), value) {
+                    member.member_epoch = value.member_epoch;
+                    member.previous_member_epoch = value.previous_member_epoch;
+                    member.state = MemberState::from(value.state);
+                    member.assigned = value.assigned;
+                    member.pending_revocation = value.pending_revocation;
+                }
+            }
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<String, ConsumerGroup>> {
+        self.groups.lock().unwrap()
+    }
+
+    /// Fences the members that missed their session or revocation timeout.
+    fn expire(
+        &self,
+        groups: &mut HashMap<String, ConsumerGroup>,
+        group_id: &str,
+        metadata: &MetadataFile,
+    ) {
+        let Some(group) = groups.get(group_id) else {
+            return;
+        };
+        let now = Instant::now();
+        let expired: Vec<String> = group
+            .members
+            .values()
+            .filter(|x| now >= x.deadline())
+            .map(|x| x.member_id.clone())
+            .collect();
+        if expired.is_empty() {
+            return;
+        }
+        let mut group = group.clone();
+        let mut records = vec![];
+        for member_id in expired {
+            group.remove_member(&member_id);
+            records.extend(member_tombstones(group_id, &member_id));
+        }
+        bump_group_epoch(&mut group, metadata, &mut records);
+        if write(group_id, &records).is_ok() {
+            groups.insert(group_id.to_owned(), group);
+        }
+    }
+
+    pub fn heartbeat(
+        &self,
+        params: ConsumerGroupHeartbeatParams,
+        config: &ConsumerGroupConfig,
+        metadata: &MetadataFile,
+    ) -> Result<ConsumerGroupHeartbeatResult, HeartbeatError> {
+        let group_id = params.group_id.as_str();
+        let mut groups = self.lock();
+        self.expire(&mut groups, group_id, metadata);
+        let mut group = match groups.get(group_id) {
+            Some(group) => group.clone(),
+            None if params.member_epoch == 0 => ConsumerGroup::new(group_id),
+            None => {
+                return Err((
+                    ErrorCode::GroupIdNotFound,
+                    format!("Group {} does not exist.", group_id),
+                ))
+            }
+        };
+        let mut records = vec![];
+        let leaving = matches!(
+            params.member_epoch,
+            LEAVE_GROUP_MEMBER_EPOCH | LEAVE_GROUP_STATIC_MEMBER_EPOCH
+        );
+        let result = if leaving {
+            Self::leave(&mut group, &params, metadata, &mut records)?
+        } else {
+            Self::update_member(&mut group, &params, config, metadata, &mut records)?
+        };
+        write(group_id, &records).map_err(|_| {
+            (
+                ErrorCode::CoordinatorNotAvailable,
+                "Failed to persist the group.".to_owned(),
+            )
+        })?;
+        groups.insert(group_id.to_owned(), group);
+        Ok(result)
+    }
+
+    /// Looks up the member a heartbeat comes from, fencing members that use
+    /// the id of a replaced static instance.
+    fn find_member<'a>(
+        group: &'a ConsumerGroup,
+        params: &ConsumerGroupHeartbeatParams,
+    ) -> Result<&'a ConsumerMember, HeartbeatError> {
+        if let Some(instance_id) = &params.instance_id {
+            if let Some(member_id) = group.static_members.get(instance_id) {
+                if *member_id != params.member_id {
+                    return Err((
+                        ErrorCode::FencedInstanceId,
+                        format!(
+                            "Static member {} with instance id {} was replaced.",
+                            params.member_id, instance_id
+                        ),
+                    ));
+                }
+            }
+        }
+        group.members.get(&params.member_id).ok_or_else(|| {
+            (
+                ErrorCode::UnknownMemberId,
+                format!(
+                    "Member {} is not a member of group {}.",
+                    params.member_id, group.group_id
+                ),
+            )
+        })
+    }
+
+    fn leave(
+        group: &mut ConsumerGroup,
+        params: &ConsumerGroupHeartbeatParams,
+        metadata: &MetadataFile,
+        records: &mut Vec<ConsumerGroupRecord>,
+    ) -> Result<ConsumerGroupHeartbeatResult, HeartbeatError> {
+        let member = Self::find_member(group, params)?;
+        if params.member_epoch == LEAVE_GROUP_STATIC_MEMBER_EPOCH {
+            // The instance keeps its partitions until it comes back or its
+            // session times out.
+            let mut member = member.clone();
+            member.member_epoch = LEAVE_GROUP_STATIC_MEMBER_EPOCH;
+            records.push(assignment_record(&group.group_id, &member));
+            group.members.insert(member.member_id.clone(), member);
+        } else {
+            let member_id = member.member_id.clone();
+            group.remove_member(&member_id);
+            records.extend(member_tombstones(&group.group_id, &member_id));
+            bump_group_epoch(group, metadata, records);
+        }
+        Ok(ConsumerGroupHeartbeatResult {
+            member_id: params.member_id.clone(),
+            member_epoch: params.member_epoch,
+            assignment: None,
+        })
+    }
+
+    /// The member a joining heartbeat registers: a new one, the one whose
+    /// id it rejoins with, or the static member whose instance it takes over.
+    /// Also returns what the group knew of the member, `None` if it is new.
+    fn joining_member(
+        group: &mut ConsumerGroup,
+        params: &ConsumerGroupHeartbeatParams,
+        config: &ConsumerGroupConfig,
+        records: &mut Vec<ConsumerGroupRecord>,
+    ) -> Result<(ConsumerMember, Option<ConsumerMember>), HeartbeatError> {
+        let now = Instant::now();
+        if let Some(instance_id) = &params.instance_id {
+            if let Some(old_member_id) = group.static_members.get(instance_id).cloned() {
+                let old = &group.members[&old_member_id];
+                if old.member_epoch != LEAVE_GROUP_STATIC_MEMBER_EPOCH {
+                    return Err((
+                        ErrorCode::UnreleasedInstanceId,
+                        format!(
+                            "Static member {} with instance id {} is not released yet.",
+                            old_member_id, instance_id
+                        ),
+                    ));
+                }
+                let target = group.target.get(&old_member_id).cloned();
+                let mut member = group.remove_member(&old_member_id).unwrap();
+                records.extend(member_tombstones(&group.group_id, &old_member_id));
+                member.member_id = new_member_id();
+                member.member_epoch = 0;
+                member.previous_member_epoch = 0;
+                member.state = MemberState::Stable;
+                if let Some(target) = target {
+                    records.push(ConsumerGroupRecord::TargetAssignmentMember {
+                        group_id: group.group_id.clone(),
+                        member_id: member.member_id.clone(),
+                        partitions: Some(target.clone()),
+                    });
+                    group.target.insert(member.member_id.clone(), target);
+                }
+                records.push(member_record(&group.group_id, &member));
+                return Ok((member.clone(), Some(member)));
+            }
+        }
+        if let Some(member) = group.members.get(&params.member_id) {
+            return Ok((member.clone(), Some(member.clone())));
+        }
+        if group.members.len() as i64 >= config.max_size {
+            return Err((
+                ErrorCode::GroupMaxSizeReached,
+                format!("Group {} is full.", group.group_id),
+            ));
+        }
+        let member_id = if params.member_id.is_empty() {
+            new_member_id()
+        } else {
+            params.member_id.clone()
+        };
+        let mut member = ConsumerMember::new(&member_id, now);
+        member.instance_id = params.instance_id.clone();
+        Ok((member, None))
+    }
+
+    fn update_member(
+        group: &mut ConsumerGroup,
+        params: &ConsumerGroupHeartbeatParams,
+        config: &ConsumerGroupConfig,
+        metadata: &MetadataFile,
+        records: &mut Vec<ConsumerGroupRecord>,
+    ) -> Result<ConsumerGroupHeartbeatResult, HeartbeatError> {
+        let joining = params.member_epoch == 0;
+        let (old, known) = if joining {
+            Self::joining_member(group, params, config, records)?
+        } else {
+            let member = Self::find_member(group, params)?;
+            let epoch = params.member_epoch;
+            // A member that missed the response carrying its new epoch may
+            // still use the previous one with the same partitions.
+            let stale_ok = epoch == member.previous_member_epoch
+                && params
+                    .owned
+                    .as_ref()
+                    .map_or(true, |x| *x == member.assigned);
+            if epoch > member.member_epoch || (epoch < member.member_epoch && !stale_ok) {
+                return Err((
+                    ErrorCode::FencedMemberEpoch,
+                    format!(
+                        "The member epoch {} of member {} is fenced, the current one is {}.",
+                        epoch, member.member_id, member.member_epoch
+                    ),
+                ));
+            }
+            (member.clone(), Some(member.clone()))
+        };
+
+        let now = Instant::now();
+        let mut member = old.clone();
+        member.client_id = params.client_id.clone();
+        member.client_host = params.client_host.clone();
+        member.last_heartbeat = now;
+        member.session_timeout = config.session_timeout;
+        if params.rack_id.is_some() {
+            member.rack_id = params.rack_id.clone();
+        }
+        if params.rebalance_timeout_ms != -1 {
+            member.rebalance_timeout_ms = params.rebalance_timeout_ms;
+        }
+        if let Some(topics) = &params.subscribed_topic_names {
+            member.subscribed_topic_names = topics.clone();
+        }
+        if params.server_assignor.is_some() {
+            member.server_assignor = params.server_assignor.clone();
+        }
+        let subscription_changed = known.as_ref().map_or(true, |x| {
+            x.subscribed_topic_names != member.subscribed_topic_names
+                || x.server_assignor != member.server_assignor
+        });
+        if known
+            .as_ref()
+            .map_or(true, |x| metadata_value(x) != metadata_value(&member))
+        {
+            records.push(member_record(&group.group_id, &member));
+        }
+        let member_id = member.member_id.clone();
+        group.add_member(member);
+
+        if subscription_changed
+            || subscription_metadata(group.subscribed_topic_names(), metadata)
+                != group.subscription_metadata
+        {
+            bump_group_epoch(group, metadata, records);
+        }
+        if group.group_epoch > group.assignment_epoch {
+            compute_target(group, config, records)?;
+        }
+        group.reconcile(&member_id, params.owned.as_ref(), now);
+        let member = &group.members[&member_id];
+        let reconciled = known
+            .as_ref()
+            .map_or(true, |x| assignment_value(x) != assignment_value(member));
+        if joining || reconciled {
+            records.push(assignment_record(&group.group_id, member));
+        }
+        let send_assignment = joining
+            || known
+                .as_ref()
+                .map_or(true, |x| x.assigned != member.assigned)
+            || params.owned.as_ref().is_some_and(|x| *x != member.assigned);
+        Ok(ConsumerGroupHeartbeatResult {
+            member_id,
+            member_epoch: member.member_epoch,
+            assignment: send_assignment.then(|| member.assigned.clone()),
+        })
+    }
+
+    pub fn describe_group(&self, group_id: &str, metadata: &MetadataFile) -> Option<ConsumerGroup> {
+        let mut groups = self.lock();
+        self.expire(&mut groups, group_id, metadata);
+        groups.get(group_id).cloned()
+    }
+
+    pub fn list_groups(&self, metadata: &MetadataFile) -> Vec<ConsumerGroup> {
+        let mut groups = self.lock();
+        let group_ids: Vec<String> = groups.keys().cloned().collect();
+        for group_id in &group_ids {
+            self.expire(&mut groups, group_id, metadata);
+        }
+        groups.values().cloned().collect()
+    }
+
+    pub fn contains(&self, group_id: &str) -> bool {
+        self.lock().contains_key(group_id)
+    }
+
+    /// Whether the group has members, which keeps its offsets from expiring.
+    pub fn has_members(&self, group_id: &str) -> bool {
+        self.lock()
+            .get(group_id)
+            .is_some_and(|x| !x.members.is_empty())
+    }
+
+    /// Topics the group's members subscribe to, `None` for unknown groups.
+    pub fn subscribed_topics(&self, group_id: &str) -> Option<HashSet<String>> {
+        let groups = self.lock();
+        let group = groups.get(group_id)?;
+        Some(group.subscribed_topic_names().into_iter().collect())
+    }
+
+    /// Checks that a member may commit offsets for the group; the generation
+    /// is the member epoch. Commits from outside the group are only allowed
+    /// while it is empty.
+    pub fn validate_offset_commit(
+        &self,
+        group_id: &str,
+        generation_id: i32,
+        member_id: &str,
+    ) -> Result<(), ErrorCode> {
+        let groups = self.lock();
+        let Some(group) = groups.get(group_id) else {
+            return Err(ErrorCode::GroupIdNotFound);
+        };
+        if generation_id < 0 && member_id.is_empty() && group.members.is_empty() {
+            return Ok(());
+        }
+        let Some(member) = group.members.get(member_id) else {
+            return Err(ErrorCode::UnknownMemberId);
+        };
+        if generation_id != member.member_epoch {
+            return Err(ErrorCode::StaleMemberEpoch);
+        }
+        Ok(())
+    }
+
+    /// Deletes an empty group, running `delete_offsets` while the group is
+    /// still locked so no member can join in between.
+    pub fn delete_group(
+        &self,
+        group_id: &str,
+        delete_offsets: impl FnOnce() -> Result<()>,
+    ) -> Result<(), ErrorCode> {
+        let mut groups = self.lock();
+        let Some(group) = groups.get(group_id) else {
+            return Err(ErrorCode::GroupIdNotFound);
+        };
+        if !group.members.is_empty() {
+            return Err(ErrorCode::NonEmptyGroup);
+        }
+        let records = [
+            ConsumerGroupRecord::TargetAssignmentMetadata {
+                group_id: group_id.to_owned(),
+                assignment_epoch: None,
+            },
+            ConsumerGroupRecord::PartitionMetadata {
+                group_id: group_id.to_owned(),
+                topics: None,
+            },
+            ConsumerGroupRecord::GroupMetadata {
+                group_id: group_id.to_owned(),
+                epoch: None,
+            },
+        ];
+        write(group_id, &records).map_err(|_| ErrorCode::CoordinatorNotAvailable)?;
+        delete_offsets().map_err(|_| ErrorCode::CoordinatorNotAvailable)?;
+        groups.remove(group_id);
+        Ok(())
+    }
+}
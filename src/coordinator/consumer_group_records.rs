@@ -0,0 +1,267 @@
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use std::collections::BTreeMap;
+
+use crate::protocol::{
+    get_array, get_nullable_string, get_string, get_tag_buffer, put_array, put_nullable_string,
+    put_string, put_tag_buffer,
+};
+
+use super::Assignment;
+
+/// Fields of the member metadata record.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MemberMetadataValue {
+    pub instance_id: Option<String>,
+    pub rack_id: Option<String>,
+    pub client_id: String,
+    pub client_host: String,
+    pub subscribed_topic_names: Vec<String>,
+    pub server_assignor: Option<String>,
+    pub rebalance_timeout_ms: i32,
+}
+
+/// Fields of the current member assignment record.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MemberAssignmentValue {
+    pub member_epoch: i32,
+    pub previous_member_epoch: i32,
+    pub state: i8,
+    pub assigned: Assignment,
+    pub pending_revocation: Assignment,
+}
+
+/// The records a consumer group is persisted as in `__consumer_offsets`,
+/// keyed by versions 3 to 8. Keys use the classic encoding and values the
+/// flexible one; a `None` value is a tombstone.
+#[derive(Debug, Clone)]
+pub enum ConsumerGroupRecord {
+    GroupMetadata {
+        group_id: String,
+        epoch: Option<i32>,
+    },
+    PartitionMetadata {
+        group_id: String,
+        topics: Option<BTreeMap<i128, (String, i32)>>,
+    },
+    MemberMetadata {
+        group_id: String,
+        member_id: String,
+        value: Option<MemberMetadataValue>,
+    },
+    TargetAssignmentMetadata {
+        group_id: String,
+        assignment_epoch: Option<i32>,
+    },
+    TargetAssignmentMember {
+        group_id: String,
+        member_id: String,
+        partitions: Option<Assignment>,
+    },
+    CurrentMemberAssignment {
+        group_id: String,
+        member_id: String,
+        value: Option<MemberAssignmentValue>,
+    },
+}
+
+const VALUE_VERSION: i16 = 0;
+
+fn put_topic_partitions(bytes: &mut BytesMut, assignment: &Assignment) {
+    let topics: Vec<_> = assignment.iter().collect();
+    put_array(bytes, &topics, true, |(topic_id, partitions), bytes| {
+        bytes.put_i128(**topic_id);
+        let partitions: Vec<i32> = partitions.iter().copied().collect();
+        put_array(bytes, &partitions, true, |x, bytes| bytes.put_i32(*x));
+        put_tag_buffer(bytes, true);
+    });
+}
+
+fn get_topic_partitions(bytes: &mut Bytes) -> Assignment {
+    get_array(bytes, true, |bytes| {
+        let topic_id = bytes.get_i128();
+        let partitions = get_array(bytes, true, |bytes| bytes.get_i32());
+        get_tag_buffer(bytes, true);
+        (topic_id, partitions.into_iter().collect())
+    })
+    .into_iter()
+    .collect()
+}
+
+impl ConsumerGroupRecord {
+    pub fn group_id(&self) -> &str {
+        match self {
+            Self::GroupMetadata { group_id, .. }
+            | Self::PartitionMetadata { group_id, .. }
+            | Self::MemberMetadata { group_id, .. }
+            | Self::TargetAssignmentMetadata { group_id, .. }
+            | Self::TargetAssignmentMember { group_id, .. }
+            | Self::CurrentMemberAssignment { group_id, .. } => group_id,
+        }
+    }
+
+    fn key_version(&self) -> i16 {
+        match self {
+            Self::GroupMetadata { .. } => 3,
+            Self::PartitionMetadata { .. } => 4,
+            Self::MemberMetadata { .. } => 5,
+            Self::TargetAssignmentMetadata { .. } => 6,
+            Self::TargetAssignmentMember { .. } => 7,
+            Self::CurrentMemberAssignment { .. } => 8,
+        }
+    }
+
+    pub fn serialize(&self) -> (Bytes, Option<Bytes>) {
+        let mut key = BytesMut::new();
+        key.put_i16(self.key_version());
+        put_string(&mut key, self.group_id(), false);
+        if let Self::MemberMetadata { member_id, .. }
+        | Self::TargetAssignmentMember { member_id, .. }
+        | Self::CurrentMemberAssignment { member_id, .. } = self
+        {
+            put_string(&mut key, member_id, false);
+        }
+
+        let mut value = BytesMut::new();
+        value.put_i16(VALUE_VERSION);
+        match self {
+            Self::GroupMetadata { epoch: Some(x), .. }
+            | Self::TargetAssignmentMetadata {
+                assignment_epoch: Some(x),
+                ..
+            } => value.put_i32(*x),
+            Self::PartitionMetadata {
+                topics: Some(topics),
+                ..
+            } => {
+                let topics: Vec<_> = topics.iter().collect();
+                put_array(
+                    &mut value,
+                    &topics,
+                    true,
+                    |(topic_id, (name, count)), bytes| {
+                        bytes.put_i128(**topic_id);
+                        put_string(bytes, name, true);
+                        bytes.put_i32(*count);
+                        put_array::<i32>(bytes, &[], true, |_, _| {}); // partition racks
+                        put_tag_buffer(bytes, true);
+                    },
+                );
+            }
+            Self::MemberMetadata { value: Some(x), .. } => {
+                put_nullable_string(&mut value, x.instance_id.as_deref(), true);
+                put_nullable_string(&mut value, x.rack_id.as_deref(), true);
+                put_string(&mut value, &x.client_id, true);
+                put_string(&mut value, &x.client_host, true);
+                put_array(&mut value, &x.subscribed_topic_names, true, |x, bytes| {
+                    put_string(bytes, x, true)
+                });
+                put_nullable_string(&mut value, None, true); // subscribed topic regex
+                put_nullable_string(&mut value, x.server_assignor.as_deref(), true);
+                value.put_i32(x.rebalance_timeout_ms);
+            }
+            Self::TargetAssignmentMember {
+                partitions: Some(x),
+                ..
+            } => put_topic_partitions(&mut value, x),
+            Self::CurrentMemberAssignment { value: Some(x), .. } => {
+                value.put_i32(x.member_epoch);
+                value.put_i32(x.previous_member_epoch);
+                value.put_i8(x.state);
+                put_topic_partitions(&mut value, &x.assigned);
+                put_topic_partitions(&mut value, &x.pending_revocation);
+            }
+            _ => return (key.freeze(), None),
+        }
+        put_tag_buffer(&mut value, true);
+        (key.freeze(), Some(value.freeze()))
+    }
+
+    /// `None` for keys of other record types.
+    pub fn deserialize(mut key: Bytes, value: Option<Bytes>) -> Option<Self> {
+        if key.len() < 2 {
+            return None;
+        }
+        let key_version = key.get_i16();
+        if !(3..=8).contains(&key_version) {
+            return None;
+        }
+        let group_id = get_string(&mut key, false);
+        let member_id = if matches!(key_version, 5 | 7 | 8) {
+            get_string(&mut key, false)
+        } else {
+            String::new()
+        };
+        let mut value = value.filter(|x| x.len() >= 2);
+        if let Some(value) = value.as_mut() {
+            value.get_i16();
+        }
+        let record = match key_version {
+            3 => Self::GroupMetadata {
+                group_id,
+                epoch: value.map(|mut x| x.get_i32()),
+            },
+            4 => Self::PartitionMetadata {
+                group_id,
+                topics: value.map(|mut bytes| {
+                    get_array(&mut bytes, true, |bytes| {
+                        let topic_id = bytes.get_i128();
+                        let name = get_string(bytes, true);
+                        let count = bytes.get_i32();
+                        get_array(bytes, true, |bytes| {
+                            bytes.get_i32();
+                            get_array(bytes, true, |bytes| get_string(bytes, true));
+                            get_tag_buffer(bytes, true);
+                        });
+                        get_tag_buffer(bytes, true);
+                        (topic_id, (name, count))
+                    })
+                    .into_iter()
+                    .collect()
+                }),
+            },
+            5 => Self::MemberMetadata {
+                group_id,
+                member_id,
+                value: value.map(|mut bytes| {
+                    let instance_id = get_nullable_string(&mut bytes, true);
+                    let rack_id = get_nullable_string(&mut bytes, true);
+                    let client_id = get_string(&mut bytes, true);
+                    let client_host = get_string(&mut bytes, true);
+                    let subscribed_topic_names =
+                        get_array(&mut bytes, true, |bytes| get_string(bytes, true));
+                    let _subscribed_topic_regex = get_nullable_string(&mut bytes, true);
+                    MemberMetadataValue {
+                        instance_id,
+                        rack_id,
+                        client_id,
+                        client_host,
+                        subscribed_topic_names,
+                        server_assignor: get_nullable_string(&mut bytes, true),
+                        rebalance_timeout_ms: bytes.get_i32(),
+                    }
+                }),
+            },
+            6 => Self::TargetAssignmentMetadata {
+                group_id,
+                assignment_epoch: value.map(|mut x| x.get_i32()),
+            },
+            7 => Self::TargetAssignmentMember {
+                group_id,
+                member_id,
+                partitions: value.map(|mut x| get_topic_partitions(&mut x)),
+            },
+            _ => Self::CurrentMemberAssignment {
+                group_id,
+                member_id,
+                value: value.map(|mut bytes| MemberAssignmentValue {
+                    member_epoch: bytes.get_i32(),
+                    previous_member_epoch: bytes.get_i32(),
+                    state: bytes.get_i8(),
+                    assigned: get_topic_partitions(&mut bytes),
+                    pending_revocation: get_topic_partitions(&mut bytes),
+                }),
+            },
+        };
+        Some(record)
+    }
+}
@@ -0,0 +1,83 @@
+use anyhow::Result;
+
+use crate::{
+    metadata::{now_ms, MetadataFile},
+    storage::PartitionLog,
+};
+
+use super::offset_store;
+
+/// How far a group's committed offset trails the end of a partition.
+#[derive(Debug, Clone)]
+pub struct PartitionLag {
+    pub group_id: String,
+    pub topic: String,
+    pub partition: i32,
+    pub committed_offset: i64,
+    pub log_end_offset: i64,
+    /// Records between the committed offset and the end of the log.
+    pub lag: i64,
+    /// Age of the oldest record the group has not consumed yet, 0 when it
+    /// is caught up.
+    pub time_lag_ms: i64,
+}
+
+/// Lag of every committed offset of the given group, or of all groups.
+/// Offsets of topics that no longer exist are skipped.
+pub fn consumer_lag(group_id: Option<&str>, metadata: &MetadataFile) -> Result<Vec<PartitionLag>> {
+    let mut group_ids = match group_id {
+        Some(group_id) => vec![group_id.to_owned()],
+        None => offset_store().group_ids(),
+    };
+    group_ids.sort();
+    let topics: Vec<String> = metadata
+        .get_topics()
+        .map(|x| x.topic_name.data.clone())
+        .collect();
+    let now = now_ms();
+    let mut result = vec![];
+    for group_id in group_ids {
+        for ((topic, partition), committed) in offset_store().fetch(&group_id, None) {
+            let Some(committed) = committed else {
+                continue;
+            };
+            if !topics.contains(&topic) {
+                continue;
+            }
+            let log = PartitionLog::open(&topic, partition)?;
+            let log_end_offset = log.high_watermark()?;
+            let lag = (log_end_offset - committed.offset).max(0);
+            let time_lag_ms = match lag {
+                0 => 0,
+                _ => next_record_timestamp(&log, committed.offset)?.map_or(0, |x| (now - x).max(0)),
+            };
+            result.push(PartitionLag {
+                group_id: group_id.clone(),
+                topic,
+                partition,
+                committed_offset: committed.offset,
+                log_end_offset,
+                lag,
+                time_lag_ms,
+            });
+        }
+    }
+    Ok(result)
+}
+
+/// Timestamp of the first data record at or after `offset`.
+fn next_record_timestamp(log: &PartitionLog, offset: i64) -> Result<Option<i64>> {
+    for (batch, bytes) in log.read_batches(offset)? {
+        if batch.is_control() {
+            continue;
+        }
+        if let Some((_, timestamp)) = batch
+            .record_timestamps(&bytes)
+            .into_iter()
+            .find(|(x, _)| *x >= offset)
+        {
+            return Ok(Some(timestamp));
+        }
+    }
+    Ok(None)
+}
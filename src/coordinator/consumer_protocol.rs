@@ -0,0 +1,119 @@
+use bytes::{Buf, Bytes};
+
+/// Protocol type of groups using the Java consumer's embedded protocol.
+pub const CONSUMER_PROTOCOL_TYPE: &str = "consumer";
+
+/// The member metadata a consumer sends in JoinGroup.
+#[derive(Debug, Clone)]
+pub struct ConsumerProtocolSubscription {
+    pub version: i16,
+    pub topics: Vec<String>,
+    pub user_data: Option<Bytes>,
+    pub owned_partitions: Vec<(String, Vec<i32>)>,
+    pub generation_id: i32,
+    pub rack_id: Option<String>,
+}
+
+/// The assignment the leader hands each consumer in SyncGroup.
+#[derive(Debug, Clone)]
+pub struct ConsumerProtocolAssignment {
+    pub version: i16,
+    pub assigned_partitions: Vec<(String, Vec<i32>)>,
+    pub user_data: Option<Bytes>,
+}
+
+impl ConsumerProtocolSubscription {
+    /// Decodes versions 0 to 3, `None` if the bytes do not match the schema.
+    pub fn decode(bytes: &Bytes) -> Option<Self> {
+        let mut reader = Reader(bytes.clone());
+        let version = reader.i16()?;
+        if version < 0 {
+            return None;
+        }
+        let topics = reader.array(|x| x.string())?;
+        let user_data = reader.nullable_bytes()?;
+        let owned_partitions = if version >= 1 {
+            reader.topic_partitions()?
+        } else {
+            vec![]
+        };
+        let generation_id = if version >= 2 { reader.i32()? } else { -1 };
+        let rack_id = if version >= 3 {
+            reader.nullable_string()?
+        } else {
+            None
+        };
+        Some(Self {
+            version,
+            topics,
+            user_data,
+            owned_partitions,
+            generation_id,
+            rack_id,
+        })
+    }
+}
+
+impl ConsumerProtocolAssignment {
+    /// Decodes versions 0 to 3, `None` if the bytes do not match the schema.
+    pub fn decode(bytes: &Bytes) -> Option<Self> {
+        let mut reader = Reader(bytes.clone());
+        let version = reader.i16()?;
+        if version < 0 {
+            return None;
+        }
+        Some(Self {
+            version,
+            assigned_partitions: reader.topic_partitions()?,
+            user_data: reader.nullable_bytes()?,
+        })
+    }
+}
+
+/// Bounds-checked reader for the classic encoding used by the embedded
+/// protocol, which comes straight from clients.
+struct Reader(Bytes);
+
+impl Reader {
+    fn i16(&mut self) -> Option<i16> {
+        (self.0.remaining() >= 2).then(|| self.0.get_i16())
+    }
+
+    fn i32(&mut self) -> Option<i32> {
+        (self.0.remaining() >= 4).then(|| self.0.get_i32())
+    }
+
+    fn take(&mut self, length: usize) -> Option<Bytes> {
+        (self.0.remaining() >= length).then(|| self.0.split_to(length))
+    }
+
+    fn nullable_string(&mut self) -> Option<Option<String>> {
+        let length = self.i16()?;
+        if length < 0 {
+            return Some(None);
+        }
+        let data = self.take(length as usize)?;
+        Some(Some(String::from_utf8_lossy(&data).to_string()))
+    }
+
+    fn string(&mut self) -> Option<String> {
+        self.nullable_string()?
+    }
+
+    fn nullable_bytes(&mut self) -> Option<Option<Bytes>> {
+        let length = self.i32()?;
+        if length < 0 {
+            return Some(None);
+        }
+        Some(Some(self.take(length as usize)?))
+    }
+
+    fn array<T>(&mut self, mut item: impl FnMut(&mut Self) -> Option<T>) -> Option<Vec<T>> {
+        let count = self.i32()?.max(0);
+        (0..count).map(|_| item(self)).collect()
+    }
+
+    fn topic_partitions(&mut self) -> Option<Vec<(String, Vec<i32>)>> {
+        self.array(|x| Some((x.string()?, x.array(|x| x.i32())?)))
+    }
+}
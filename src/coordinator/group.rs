@@ -0,0 +1,540 @@
+use bytes::Bytes;
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
+
+use crate::protocol::ErrorCode;
+
+use super::{ConsumerProtocolSubscription, CONSUMER_PROTOCOL_TYPE};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GroupState {
+    Empty,
+    PreparingRebalance,
+    CompletingRebalance,
+    Stable,
+}
+
+impl GroupState {
+    /// Name reported by the admin APIs.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Empty => "Empty",
+            Self::PreparingRebalance => "PreparingRebalance",
+            Self::CompletingRebalance => "CompletingRebalance",
+            Self::Stable => "Stable",
+        }
+    }
+}
+
+/// What a JoinGroup call returns once its rebalance completes.
+#[derive(Debug, Clone)]
+pub struct JoinResult {
+    pub error_code: ErrorCode,
+    pub generation_id: i32,
+    pub protocol_type: Option<String>,
+    pub protocol_name: Option<String>,
+    pub leader_id: String,
+    pub member_id: String,
+    /// The leader keeps the current assignment instead of computing one.
+    pub skip_assignment: bool,
+    /// Members and their metadata for the chosen protocol, only sent to the leader.
+    pub members: Vec<JoinedMember>,
+}
+
+#[derive(Debug, Clone)]
+pub struct JoinedMember {
+    pub member_id: String,
+    pub group_instance_id: Option<String>,
+    pub metadata: Bytes,
+}
+
+impl JoinResult {
+    pub fn error(error_code: ErrorCode, member_id: &str) -> Self {
+        Self {
+            error_code,
+            generation_id: -1,
+            protocol_type: None,
+            protocol_name: None,
+            leader_id: String::new(),
+            member_id: member_id.to_owned(),
+            skip_assignment: false,
+            members: vec![],
+        }
+    }
+}
+
+/// A member as reported by DescribeGroups.
+#[derive(Debug, Clone)]
+pub struct MemberSummary {
+    pub member_id: String,
+    pub group_instance_id: Option<String>,
+    pub client_id: String,
+    pub client_host: String,
+    pub metadata: Bytes,
+    pub assignment: Bytes,
+}
+
+/// A group as reported by DescribeGroups and ListGroups.
+#[derive(Debug, Clone)]
+pub struct GroupSummary {
+    pub group_id: String,
+    pub state: GroupState,
+    pub protocol_type: String,
+    pub protocol_name: String,
+    pub members: Vec<MemberSummary>,
+}
+
+#[derive(Debug)]
+pub struct Member {
+    pub member_id: String,
+    /// Set for static members, which keep their identity across restarts.
+    pub group_instance_id: Option<String>,
+    pub client_id: String,
+    pub client_host: String,
+    pub session_timeout: Duration,
+    pub rebalance_timeout: Duration,
+    pub protocol_type: String,
+    /// Supported protocols in order of preference, with their metadata.
+    pub protocols: Vec<(String, Bytes)>,
+    pub assignment: Bytes,
+    pub last_heartbeat: Instant,
+    /// A JoinGroup call is parked waiting for the rebalance to complete.
+    pub awaiting_join: bool,
+    /// A SyncGroup call is parked waiting for the leader's assignment.
+    pub awaiting_sync: bool,
+    pub join_result: Option<JoinResult>,
+}
+
+impl Member {
+    pub fn metadata(&self, protocol_name: &str) -> Bytes {
+        self.protocols
+            .iter()
+            .find(|(name, _)| name == protocol_name)
+            .map(|(_, metadata)| metadata.clone())
+            .unwrap_or_default()
+    }
+
+    fn session_deadline(&self) -> Option<Instant> {
+        if self.awaiting_join || self.awaiting_sync {
+            None
+        } else {
+            Some(self.last_heartbeat + self.session_timeout)
+        }
+    }
+}
+
+/// A classic consumer group and its rebalance state machine. Parked
+/// JoinGroup and SyncGroup calls poll it through the coordinator, which
+/// drives the timeouts with [`Group::expire`].
+#[derive(Debug)]
+pub struct Group {
+    pub group_id: String,
+    pub state: GroupState,
+    pub generation_id: i32,
+    pub protocol_type: Option<String>,
+    pub protocol_name: Option<String>,
+    pub leader_id: Option<String>,
+    /// Members in join order.
+    pub members: Vec<Member>,
+    /// Member ids handed out with MEMBER_ID_REQUIRED that have not joined yet,
+    /// and when they expire.
+    pub pending_members: HashMap<String, Instant>,
+    /// Member id currently holding each static instance id.
+    pub static_members: HashMap<String, String>,
+    /// Members that have not sent SyncGroup for the current generation.
+    pending_sync: HashSet<String>,
+    rebalance_started: Option<Instant>,
+    /// The first rebalance of an empty group waits this long for more members.
+    initial_delay_deadline: Option<Instant>,
+    sync_deadline: Option<Instant>,
+}
+
+impl Group {
+    pub fn new(group_id: &str) -> Self {
+        Self {
+            group_id: group_id.to_owned(),
+            state: GroupState::Empty,
+            generation_id: 0,
+            protocol_type: None,
+            protocol_name: None,
+            leader_id: None,
+            members: vec![],
+            pending_members: HashMap::new(),
+            static_members: HashMap::new(),
+            pending_sync: HashSet::new(),
+            rebalance_started: None,
+            initial_delay_deadline: None,
+            sync_deadline: None,
+        }
+    }
+
+    pub fn member(&self, member_id: &str) -> Option<&Member> {
+        self.members.iter().find(|x| x.member_id == member_id)
+    }
+
+    pub fn member_mut(&mut self, member_id: &str) -> Option<&mut Member> {
+        self.members.iter_mut().find(|x| x.member_id == member_id)
+    }
+
+    pub fn is_leader(&self, member_id: &str) -> bool {
+        self.leader_id.as_deref() == Some(member_id)
+    }
+
+    pub fn size(&self) -> usize {
+        self.members.len() + self.pending_members.len()
+    }
+
+    /// Checks that `member_id` holds the static instance id it claims. A
+    /// member still using the id of a replaced instance is fenced.
+    pub fn validate_instance(
+        &self,
+        member_id: &str,
+        group_instance_id: Option<&str>,
+    ) -> Result<(), ErrorCode> {
+        let Some(group_instance_id) = group_instance_id else {
+            return Ok(());
+        };
+        match self.static_members.get(group_instance_id) {
+            Some(x) if x == member_id => Ok(()),
+            Some(_) => Err(ErrorCode::FencedInstanceId),
+            None => Err(ErrorCode::UnknownMemberId),
+        }
+    }
+
+    /// Hands a static member's place in the group to a new member id, so a
+    /// restarted instance takes over without a rebalance.
+    pub fn replace_static_member(&mut self, old_member_id: &str, new_member_id: &str) {
+        if self.is_leader(old_member_id) {
+            self.leader_id = Some(new_member_id.to_owned());
+        }
+        if self.pending_sync.remove(old_member_id) {
+            self.pending_sync.insert(new_member_id.to_owned());
+        }
+        let Some(member) = self.member_mut(old_member_id) else {
+            return;
+        };
+        member.member_id = new_member_id.to_owned();
+        member.awaiting_join = false;
+        member.awaiting_sync = false;
+        member.join_result = None;
+        if let Some(group_instance_id) = member.group_instance_id.clone() {
+            self.static_members
+                .insert(group_instance_id, new_member_id.to_owned());
+        }
+    }
+
+    /// Topics the members subscribe to, decoded from their consumer protocol
+    /// metadata. `None` when the group does not use the consumer protocol or
+    /// a subscription cannot be decoded.
+    pub fn subscribed_topics(&self) -> Option<HashSet<String>> {
+        if self.members.is_empty() {
+            return Some(HashSet::new());
+        }
+        if self.protocol_type.as_deref() != Some(CONSUMER_PROTOCOL_TYPE) {
+            return None;
+        }
+        let protocol_name = self.protocol_name.as_deref()?;
+        let mut topics = HashSet::new();
+        for member in &self.members {
+            let subscription =
+                ConsumerProtocolSubscription::decode(&member.metadata(protocol_name))?;
+            topics.extend(subscription.topics);
+        }
+        Some(topics)
+    }
+
+    /// Member metadata and assignments are only meaningful once the group is
+    /// stable, so other states report the members without them.
+    pub fn summary(&self) -> GroupSummary {
+        let stable = self.state == GroupState::Stable;
+        let protocol_name = if stable {
+            self.protocol_name.clone().unwrap_or_default()
+        } else {
+            String::new()
+        };
+        let members = self
+            .members
+            .iter()
+            .map(|x| MemberSummary {
+                member_id: x.member_id.clone(),
+                group_instance_id: x.group_instance_id.clone(),
+                client_id: x.client_id.clone(),
+                client_host: x.client_host.clone(),
+                metadata: if stable {
+                    x.metadata(&protocol_name)
+                } else {
+                    Bytes::new()
+                },
+                assignment: if stable {
+                    x.assignment.clone()
+                } else {
+                    Bytes::new()
+                },
+            })
+            .collect();
+        GroupSummary {
+            group_id: self.group_id.clone(),
+            state: self.state,
+            protocol_type: self.protocol_type.clone().unwrap_or_default(),
+            protocol_name,
+            members,
+        }
+    }
+
+    /// Protocols every member supports.
+    fn candidate_protocols(&self) -> Vec<String> {
+        let Some(first) = self.members.first() else {
+            return vec![];
+        };
+        first
+            .protocols
+            .iter()
+            .map(|(name, _)| name.clone())
+            .filter(|name| {
+                self.members
+                    .iter()
+                    .all(|x| x.protocols.iter().any(|(other, _)| other == name))
+            })
+            .collect()
+    }
+
+    /// Whether a member with these protocols may join without breaking the
+    /// group's protocol agreement.
+    pub fn supports_protocols(&self, protocol_type: &str, protocols: &[(String, Bytes)]) -> bool {
+        if protocol_type.is_empty() || protocols.is_empty() {
+            return false;
+        }
+        if self.members.is_empty() {
+            return true;
+        }
+        let candidates = self.candidate_protocols();
+        self.protocol_type.as_deref() == Some(protocol_type)
+            && protocols.iter().any(|(name, _)| candidates.contains(name))
+    }
+
+    /// Each member votes for its most preferred candidate; the most voted wins.
+    pub fn select_protocol(&self) -> Option<String> {
+        let candidates = self.candidate_protocols();
+        let mut votes: Vec<(String, usize)> = candidates.iter().map(|x| (x.clone(), 0)).collect();
+        for member in &self.members {
+            let vote = member
+                .protocols
+                .iter()
+                .find_map(|(name, _)| votes.iter().position(|(x, _)| x == name));
+            if let Some(idx) = vote {
+                votes[idx].1 += 1;
+            }
+        }
+        votes
+            .into_iter()
+            .rev()
+            .max_by_key(|(_, count)| *count)
+            .map(|(name, _)| name)
+    }
+
+    fn rebalance_deadline(&self) -> Option<Instant> {
+        let started = self.rebalance_started?;
+        let timeout = self
+            .members
+            .iter()
+            .map(|x| x.rebalance_timeout)
+            .max()
+            .unwrap_or_default();
+        Some(started + timeout)
+    }
+
+    pub fn add_member(&mut self, member: Member, now: Instant, initial_delay: Duration) {
+        if self.members.is_empty() {
+            self.protocol_type = Some(member.protocol_type.clone());
+        }
+        if let Some(group_instance_id) = &member.group_instance_id {
+            self.static_members
+                .insert(group_instance_id.clone(), member.member_id.clone());
+        }
+        self.members.push(member);
+        match self.state {
+            GroupState::PreparingRebalance => {
+                // Every new member during the initial delay buys the others more time.
+                if self.initial_delay_deadline.is_some() {
+                    self.initial_delay_deadline = self
+                        .rebalance_deadline()
+                        .map(|x| x.min(now + initial_delay));
+                }
+            }
+            _ => self.prepare_rebalance(now, initial_delay),
+        }
+    }
+
+    pub fn prepare_rebalance(&mut self, now: Instant, initial_delay: Duration) {
+        let was_empty = self.state == GroupState::Empty;
+        self.state = GroupState::PreparingRebalance;
+        self.rebalance_started = Some(now);
+        self.pending_sync.clear();
+        self.sync_deadline = None;
+        self.initial_delay_deadline = if was_empty && !initial_delay.is_zero() {
+            self.rebalance_deadline()
+                .map(|x| x.min(now + initial_delay))
+        } else {
+            None
+        };
+    }
+
+    pub fn remove_member(&mut self, member_id: &str, now: Instant) {
+        self.members.retain(|x| x.member_id != member_id);
+        self.static_members.retain(|_, x| x != member_id);
+        self.pending_sync.remove(member_id);
+        if self.is_leader(member_id) {
+            self.leader_id = None;
+        }
+        if matches!(
+            self.state,
+            GroupState::Stable | GroupState::CompletingRebalance
+        ) {
+            self.prepare_rebalance(now, Duration::ZERO);
+        }
+    }
+
+    fn can_complete_join(&self, now: Instant) -> bool {
+        if self.state != GroupState::PreparingRebalance {
+            return false;
+        }
+        let all_joined =
+            self.members.iter().all(|x| x.awaiting_join) && self.pending_members.is_empty();
+        let delay_passed = self.initial_delay_deadline.map_or(true, |x| now >= x);
+        (all_joined && delay_passed) || self.rebalance_deadline().is_some_and(|x| now >= x)
+    }
+
+    /// Moves to the next generation with the members that rejoined.
+    fn complete_join(&mut self, now: Instant) {
+        let dropped: Vec<String> = self
+            .members
+            .iter()
+            .filter(|x| !x.awaiting_join)
+            .map(|x| x.member_id.clone())
+            .collect();
+        for member_id in dropped {
+            self.remove_member(&member_id, now);
+        }
+        self.generation_id += 1;
+        self.rebalance_started = None;
+        self.initial_delay_deadline = None;
+        if self.members.is_empty() {
+            self.state = GroupState::Empty;
+            self.protocol_name = None;
+            return;
+        }
+        self.state = GroupState::CompletingRebalance;
+        self.protocol_name = self.select_protocol();
+        if self.leader_id.is_none() {
+            self.leader_id = Some(self.members[0].member_id.clone());
+        }
+        let timeout = self
+            .members
+            .iter()
+            .map(|x| x.rebalance_timeout)
+            .max()
+            .unwrap_or_default();
+        self.sync_deadline = Some(now + timeout);
+        self.pending_sync = self.members.iter().map(|x| x.member_id.clone()).collect();
+        let results: Vec<JoinResult> = self
+            .members
+            .iter()
+            .map(|x| self.join_result(&x.member_id))
+            .collect();
+        for (member, result) in self.members.iter_mut().zip(results) {
+            member.awaiting_join = false;
+            member.last_heartbeat = now;
+            member.join_result = Some(result);
+        }
+    }
+
+    /// The JoinGroup response for `member_id` in the current generation.
+    pub fn join_result(&self, member_id: &str) -> JoinResult {
+        let protocol_name = self.protocol_name.clone().unwrap_or_default();
+        JoinResult {
+            error_code: ErrorCode::NoError,
+            generation_id: self.generation_id,
+            protocol_type: self.protocol_type.clone(),
+            protocol_name: self.protocol_name.clone(),
+            leader_id: self.leader_id.clone().unwrap_or_default(),
+            member_id: member_id.to_owned(),
+            skip_assignment: false,
+            members: if self.is_leader(member_id) {
+                self.members
+                    .iter()
+                    .map(|x| JoinedMember {
+                        member_id: x.member_id.clone(),
+                        group_instance_id: x.group_instance_id.clone(),
+                        metadata: x.metadata(&protocol_name),
+                    })
+                    .collect()
+            } else {
+                vec![]
+            },
+        }
+    }
+
+    /// Installs the leader's assignment and makes the group stable. Members
+    /// the leader left out get an empty assignment.
+    pub fn complete_sync(&mut self, assignments: Vec<(String, Bytes)>) {
+        let mut assignments: HashMap<String, Bytes> = assignments.into_iter().collect();
+        for member in &mut self.members {
+            member.assignment = assignments.remove(&member.member_id).unwrap_or_default();
+        }
+        self.state = GroupState::Stable;
+    }
+
+    pub fn mark_synced(&mut self, member_id: &str, now: Instant) {
+        self.pending_sync.remove(member_id);
+        if self.pending_sync.is_empty() {
+            self.sync_deadline = None;
+        }
+        if let Some(member) = self.member_mut(member_id) {
+            member.last_heartbeat = now;
+        }
+    }
+
+    /// Applies every timeout that has passed and completes the pending join
+    /// if it can. Returns whether anything changed.
+    pub fn expire(&mut self, now: Instant) -> bool {
+        let before = (self.state, self.generation_id, self.size());
+        self.pending_members.retain(|_, expiry| *expiry > now);
+        let expired: Vec<String> = self
+            .members
+            .iter()
+            .filter(|x| x.session_deadline().is_some_and(|x| now >= x))
+            .map(|x| x.member_id.clone())
+            .collect();
+        for member_id in expired {
+            self.remove_member(&member_id, now);
+        }
+        if self.sync_deadline.is_some_and(|x| now >= x) {
+            self.sync_deadline = None;
+            let unsynced: Vec<String> = self.pending_sync.drain().collect();
+            for member_id in unsynced {
+                self.remove_member(&member_id, now);
+            }
+        }
+        if self.can_complete_join(now) {
+            self.complete_join(now);
+        }
+        before != (self.state, self.generation_id, self.size())
+    }
+
+    /// The next time [`Group::expire`] may have something to do.
+    pub fn next_deadline(&self) -> Option<Instant> {
+        let (rebalance, initial_delay) = if self.state == GroupState::PreparingRebalance {
+            (self.rebalance_deadline(), self.initial_delay_deadline)
+        } else {
+            (None, None)
+        };
+        self.members
+            .iter()
+            .filter_map(Member::session_deadline)
+            .chain(self.pending_members.values().copied())
+            .chain(self.sync_deadline)
+            .chain(rebalance)
+            .chain(initial_delay)
+            .min()
+    }
+}
@@ -0,0 +1,580 @@
+use bytes::Bytes;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Condvar, Mutex, MutexGuard, OnceLock};
+use std::time::{Duration, Instant};
+
+use crate::{config::ConfigStore, metadata::random_uuid, protocol::ErrorCode};
+
+use super::{Group, GroupState, GroupSummary, JoinResult, Member};
+
+/// How long a parked call sleeps when no group timeout is pending.
+const MAX_WAIT: Duration = Duration::from_secs(1);
+
+/// Broker configs that bound group membership.
+#[derive(Debug, Clone)]
+pub struct GroupConfig {
+    pub min_session_timeout: i64,
+    pub max_session_timeout: i64,
+    pub max_size: i64,
+    pub initial_rebalance_delay: Duration,
+}
+
+impl GroupConfig {
+    pub fn load(store: &ConfigStore) -> Self {
+        Self {
+            min_session_timeout: store.broker_config_i64("group.min.session.timeout.ms"),
+            max_session_timeout: store.broker_config_i64("group.max.session.timeout.ms"),
+            max_size: store.broker_config_i64("group.max.size"),
+            initial_rebalance_delay: Duration::from_millis(
+                store
+                    .broker_config_i64("group.initial.rebalance.delay.ms")
+                    .max(0) as u64,
+            ),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct JoinGroupParams {
+    pub group_id: String,
+    /// Empty for a member joining for the first time.
+    pub member_id: String,
+    pub group_instance_id: Option<String>,
+    pub client_id: String,
+    pub client_host: String,
+    pub session_timeout_ms: i32,
+    pub rebalance_timeout_ms: i32,
+    pub protocol_type: String,
+    pub protocols: Vec<(String, Bytes)>,
+    /// Clients from JoinGroup v4 first get a member id with MEMBER_ID_REQUIRED.
+    pub require_known_member_id: bool,
+    /// JoinGroup v9 leaders can keep the current assignment after a static
+    /// member rejoins.
+    pub supports_skip_assignment: bool,
+}
+
+#[derive(Debug)]
+pub struct SyncGroupParams {
+    pub group_id: String,
+    pub generation_id: i32,
+    pub member_id: String,
+    pub group_instance_id: Option<String>,
+    pub protocol_type: Option<String>,
+    pub protocol_name: Option<String>,
+    /// Only sent by the leader.
+    pub assignments: Vec<(String, Bytes)>,
+}
+
+#[derive(Debug)]
+pub struct SyncResult {
+    pub error_code: ErrorCode,
+    pub protocol_type: Option<String>,
+    pub protocol_name: Option<String>,
+    pub assignment: Bytes,
+}
+
+impl SyncResult {
+    fn error(error_code: ErrorCode) -> Self {
+        Self {
+            error_code,
+            protocol_type: None,
+            protocol_name: None,
+            assignment: Bytes::new(),
+        }
+    }
+}
+
+/// Outcome of registering a JoinGroup member.
+enum Joined {
+    /// The member waits for the rebalance to complete.
+    Parked(String),
+    /// The response to send right away.
+    Done(JoinResult),
+}
+
+/// In-memory state of the classic groups this broker coordinates. JoinGroup
+/// and SyncGroup block their connection thread until the rebalance step they
+/// wait for completes.
+#[derive(Debug, Default)]
+pub struct GroupCoordinator {
+    groups: Mutex<HashMap<String, Group>>,
+    changed: Condvar,
+}
+
+pub fn group_coordinator() -> &'static GroupCoordinator {
+    static COORDINATOR: OnceLock<GroupCoordinator> = OnceLock::new();
+    COORDINATOR.get_or_init(GroupCoordinator::default)
+}
+
+fn new_member_id(client_id: &str) -> String {
+    let uuid = format!("{:032x}", random_uuid() as u128);
+    format!(
+        "{}-{}-{}-{}-{}-{}",
+        client_id,
+        &uuid[..8],
+        &uuid[8..12],
+        &uuid[12..16],
+        &uuid[16..20],
+        &uuid[20..]
+    )
+}
+
+impl GroupCoordinator {
+    fn lock(&self) -> MutexGuard<'_, HashMap<String, Group>> {
+        self.groups.lock().unwrap()
+    }
+
+    /// Applies expired timeouts to `group_id` and wakes parked calls if that
+    /// changed anything.
+    fn expire(&self, groups: &mut HashMap<String, Group>, group_id: &str) {
+        let changed = groups
+            .get_mut(group_id)
+            .is_some_and(|group| group.expire(Instant::now()));
+        if changed {
+            self.changed.notify_all();
+        }
+    }
+
+    /// Parks the calling thread until the group changes or its next timeout.
+    fn wait<'a>(
+        &self,
+        groups: MutexGuard<'a, HashMap<String, Group>>,
+        group_id: &str,
+    ) -> MutexGuard<'a, HashMap<String, Group>> {
+        let timeout = groups
+            .get(group_id)
+            .and_then(Group::next_deadline)
+            .map_or(MAX_WAIT, |x| x.saturating_duration_since(Instant::now()))
+            .min(MAX_WAIT);
+        let mut groups = self.changed.wait_timeout(groups, timeout).unwrap().0;
+        self.expire(&mut groups, group_id);
+        groups
+    }
+
+    pub fn join_group(&self, params: JoinGroupParams, config: &GroupConfig) -> JoinResult {
+        let mut groups = self.lock();
+        let member_id = match self.add_to_group(&mut groups, &params, config) {
+            Joined::Parked(member_id) => member_id,
+            Joined::Done(result) => return result,
+        };
+        self.expire(&mut groups, &params.group_id);
+        self.changed.notify_all();
+        loop {
+            let Some(group) = groups.get_mut(&params.group_id) else {
+                return JoinResult::error(ErrorCode::UnknownMemberId, &member_id);
+            };
+            // A restarted static instance may have taken over meanwhile.
+            if let Err(error_code) =
+                group.validate_instance(&member_id, params.group_instance_id.as_deref())
+            {
+                return JoinResult::error(error_code, &member_id);
+            }
+            let Some(member) = group.member_mut(&member_id) else {
+                return JoinResult::error(ErrorCode::UnknownMemberId, &member_id);
+            };
+            if let Some(result) = member.join_result.take() {
+                return result;
+            }
+            groups = self.wait(groups, &params.group_id);
+        }
+    }
+
+    /// Registers the joining member.
+    fn add_to_group(
+        &self,
+        groups: &mut HashMap<String, Group>,
+        params: &JoinGroupParams,
+        config: &GroupConfig,
+    ) -> Joined {
+        let error = |error_code| Joined::Done(JoinResult::error(error_code, &params.member_id));
+        if params.group_id.is_empty() {
+            return error(ErrorCode::InvalidGroupId);
+        }
+        let session_timeout = params.session_timeout_ms as i64;
+        if session_timeout < config.min_session_timeout
+            || session_timeout > config.max_session_timeout
+        {
+            return error(ErrorCode::InvalidSessionTimeout);
+        }
+        if !params.member_id.is_empty() && !groups.contains_key(&params.group_id) {
+            return error(ErrorCode::UnknownMemberId);
+        }
+        self.expire(groups, &params.group_id);
+        let now = Instant::now();
+        let group = groups
+            .entry(params.group_id.clone())
+            .or_insert_with(|| Group::new(&params.group_id));
+        if !group.supports_protocols(&params.protocol_type, &params.protocols) {
+            return error(ErrorCode::InconsistentGroupProtocol);
+        }
+        let new_member = |member_id: String| Member {
+            member_id,
+            group_instance_id: params.group_instance_id.clone(),
+            client_id: params.client_id.clone(),
+            client_host: params.client_host.clone(),
+            session_timeout: Duration::from_millis(params.session_timeout_ms as u64),
+            rebalance_timeout: Duration::from_millis(params.rebalance_timeout_ms.max(0) as u64),
+            protocol_type: params.protocol_type.clone(),
+            protocols: params.protocols.clone(),
+            assignment: Bytes::new(),
+            last_heartbeat: now,
+            awaiting_join: true,
+            awaiting_sync: false,
+            join_result: None,
+        };
+
+        if params.member_id.is_empty() {
+            if let Some(group_instance_id) = &params.group_instance_id {
+                if let Some(old_member_id) = group.static_members.get(group_instance_id).cloned() {
+                    return self.rejoin_static_member(group, params, &old_member_id, now);
+                }
+            }
+            if group.size() as i64 >= config.max_size {
+                return error(ErrorCode::GroupMaxSizeReached);
+            }
+            let member_id = new_member_id(
+                params
+                    .group_instance_id
+                    .as_ref()
+                    .unwrap_or(&params.client_id),
+            );
+            // The instance id already identifies a static member, so it skips
+            // the MEMBER_ID_REQUIRED round trip.
+            if params.require_known_member_id && params.group_instance_id.is_none() {
+                let expiry = now + Duration::from_millis(params.session_timeout_ms as u64);
+                group.pending_members.insert(member_id.clone(), expiry);
+                return Joined::Done(JoinResult::error(ErrorCode::MemberIdRequired, &member_id));
+            }
+            group.add_member(
+                new_member(member_id.clone()),
+                now,
+                config.initial_rebalance_delay,
+            );
+            return Joined::Parked(member_id);
+        }
+
+        let member_id = params.member_id.clone();
+        if group.pending_members.remove(&member_id).is_some() {
+            group.add_member(
+                new_member(member_id.clone()),
+                now,
+                config.initial_rebalance_delay,
+            );
+            return Joined::Parked(member_id);
+        }
+        if let Err(error_code) =
+            group.validate_instance(&member_id, params.group_instance_id.as_deref())
+        {
+            return error(error_code);
+        }
+        let is_leader = group.is_leader(&member_id);
+        let state = group.state;
+        let Some(member) = group.member_mut(&member_id) else {
+            return error(ErrorCode::UnknownMemberId);
+        };
+        // A member that missed its JoinGroup response gets the current
+        // generation back instead of forcing another rebalance.
+        let resend = matches!(state, GroupState::CompletingRebalance | GroupState::Stable)
+            && member.protocols == params.protocols
+            && !(state == GroupState::Stable && is_leader);
+        member.client_host = params.client_host.clone();
+        member.session_timeout = Duration::from_millis(params.session_timeout_ms as u64);
+        member.rebalance_timeout = Duration::from_millis(params.rebalance_timeout_ms.max(0) as u64);
+        member.protocols = params.protocols.clone();
+        member.last_heartbeat = now;
+        if resend {
+            return Joined::Done(group.join_result(&member_id));
+        }
+        member.awaiting_join = true;
+        if state != GroupState::PreparingRebalance {
+            group.prepare_rebalance(now, Duration::ZERO);
+        }
+        Joined::Parked(member_id)
+    }
+
+    /// A static member rejoining without its member id replaces its previous
+    /// incarnation, which is fenced from then on. A stable group keeps its
+    /// assignment unless the new metadata changes the selected protocol.
+    fn rejoin_static_member(
+        &self,
+        group: &mut Group,
+        params: &JoinGroupParams,
+        old_member_id: &str,
+        now: Instant,
+    ) -> Joined {
+        let group_instance_id = params.group_instance_id.as_deref().unwrap_or_default();
+        let member_id = new_member_id(group_instance_id);
+        group.replace_static_member(old_member_id, &member_id);
+        // Wake the fenced member's parked calls.
+        self.changed.notify_all();
+        let Some(member) = group.member_mut(&member_id) else {
+            return Joined::Done(JoinResult::error(ErrorCode::UnknownMemberId, &member_id));
+        };
+        member.client_id = params.client_id.clone();
+        member.client_host = params.client_host.clone();
+        member.session_timeout = Duration::from_millis(params.session_timeout_ms as u64);
+        member.rebalance_timeout = Duration::from_millis(params.rebalance_timeout_ms.max(0) as u64);
+        member.protocols = params.protocols.clone();
+        member.last_heartbeat = now;
+        if group.state == GroupState::Stable && group.select_protocol() == group.protocol_name {
+            let mut result = group.join_result(&member_id);
+            if group.is_leader(&member_id) {
+                result.skip_assignment = params.supports_skip_assignment;
+                if !params.supports_skip_assignment {
+                    result.members.clear();
+                }
+            }
+            return Joined::Done(result);
+        }
+        if let Some(member) = group.member_mut(&member_id) {
+            member.awaiting_join = true;
+        }
+        if group.state != GroupState::PreparingRebalance {
+            group.prepare_rebalance(now, Duration::ZERO);
+        }
+        Joined::Parked(member_id)
+    }
+
+    pub fn sync_group(&self, params: SyncGroupParams) -> SyncResult {
+        let SyncGroupParams {
+            group_id,
+            generation_id,
+            member_id,
+            group_instance_id,
+            protocol_type,
+            protocol_name,
+            assignments,
+        } = params;
+        let (group_id, member_id) = (group_id.as_str(), member_id.as_str());
+        let group_instance_id = group_instance_id.as_deref();
+        let mut groups = self.lock();
+        self.expire(&mut groups, group_id);
+        let Some(group) = groups.get_mut(group_id) else {
+            return SyncResult::error(ErrorCode::UnknownMemberId);
+        };
+        if let Err(error_code) = group.validate_instance(member_id, group_instance_id) {
+            return SyncResult::error(error_code);
+        }
+        if group.member(member_id).is_none() {
+            return SyncResult::error(ErrorCode::UnknownMemberId);
+        }
+        if generation_id != group.generation_id {
+            return SyncResult::error(ErrorCode::IllegalGeneration);
+        }
+        if protocol_type.is_some_and(|x| group.protocol_type.as_ref() != Some(&x))
+            || protocol_name.is_some_and(|x| group.protocol_name.as_ref() != Some(&x))
+        {
+            return SyncResult::error(ErrorCode::InconsistentGroupProtocol);
+        }
+        match group.state {
+            GroupState::Empty => return SyncResult::error(ErrorCode::UnknownMemberId),
+            GroupState::PreparingRebalance => {
+                return SyncResult::error(ErrorCode::RebalanceInProgress)
+            }
+            GroupState::CompletingRebalance | GroupState::Stable => {
+                group.mark_synced(member_id, Instant::now());
+            }
+        }
+        if group.state == GroupState::CompletingRebalance {
+            if group.is_leader(member_id) {
+                group.complete_sync(assignments);
+                self.changed.notify_all();
+            } else if let Some(member) = group.member_mut(member_id) {
+                member.awaiting_sync = true;
+            }
+        }
+        loop {
+            let Some(group) = groups.get_mut(group_id) else {
+                return SyncResult::error(ErrorCode::UnknownMemberId);
+            };
+            if let Err(error_code) = group.validate_instance(member_id, group_instance_id) {
+                return SyncResult::error(error_code);
+            }
+            let (state, generation) = (group.state, group.generation_id);
+            let (protocol_type, protocol_name) =
+                (group.protocol_type.clone(), group.protocol_name.clone());
+            let Some(member) = group.member_mut(member_id) else {
+                return SyncResult::error(ErrorCode::UnknownMemberId);
+            };
+            if generation != generation_id || state != GroupState::CompletingRebalance {
+                member.awaiting_sync = false;
+                if generation != generation_id || state != GroupState::Stable {
+                    return SyncResult::error(ErrorCode::RebalanceInProgress);
+                }
+                return SyncResult {
+                    error_code: ErrorCode::NoError,
+                    protocol_type,
+                    protocol_name,
+                    assignment: member.assignment.clone(),
+                };
+            }
+            groups = self.wait(groups, group_id);
+        }
+    }
+
+    pub fn heartbeat(
+        &self,
+        group_id: &str,
+        generation_id: i32,
+        member_id: &str,
+        group_instance_id: Option<&str>,
+    ) -> ErrorCode {
+        let mut groups = self.lock();
+        self.expire(&mut groups, group_id);
+        let Some(group) = groups.get_mut(group_id) else {
+            return ErrorCode::UnknownMemberId;
+        };
+        if let Err(error_code) = group.validate_instance(member_id, group_instance_id) {
+            return error_code;
+        }
+        let state = group.state;
+        let current_generation = group.generation_id;
+        let Some(member) = group.member_mut(member_id) else {
+            return ErrorCode::UnknownMemberId;
+        };
+        if generation_id != current_generation {
+            return ErrorCode::IllegalGeneration;
+        }
+        member.last_heartbeat = Instant::now();
+        match state {
+            GroupState::PreparingRebalance => ErrorCode::RebalanceInProgress,
+            GroupState::Empty => ErrorCode::UnknownMemberId,
+            GroupState::CompletingRebalance | GroupState::Stable => ErrorCode::NoError,
+        }
+    }
+
+    /// Checks that a member may commit offsets for the group. Commits with a
+    /// negative generation come from consumers outside any rebalance and
+    /// create the group when it does not exist.
+    pub fn validate_offset_commit(
+        &self,
+        group_id: &str,
+        generation_id: i32,
+        member_id: &str,
+        group_instance_id: Option<&str>,
+    ) -> Result<(), ErrorCode> {
+        let mut groups = self.lock();
+        self.expire(&mut groups, group_id);
+        let Some(group) = groups.get_mut(group_id) else {
+            if generation_id < 0 {
+                groups.insert(group_id.to_owned(), Group::new(group_id));
+                return Ok(());
+            }
+            return Err(ErrorCode::IllegalGeneration);
+        };
+        if generation_id < 0 && group.state == GroupState::Empty {
+            return Ok(());
+        }
+        group.validate_instance(member_id, group_instance_id)?;
+        if group.state == GroupState::CompletingRebalance {
+            return Err(ErrorCode::RebalanceInProgress);
+        }
+        let current_generation = group.generation_id;
+        let Some(member) = group.member_mut(member_id) else {
+            return Err(ErrorCode::UnknownMemberId);
+        };
+        if generation_id != current_generation {
+            return Err(ErrorCode::IllegalGeneration);
+        }
+        member.last_heartbeat = Instant::now();
+        Ok(())
+    }
+
+    /// Whether the group has members, which keeps its offsets from expiring.
+    pub fn has_members(&self, group_id: &str) -> bool {
+        self.lock()
+            .get(group_id)
+            .is_some_and(|x| !x.members.is_empty())
+    }
+
+    /// Removes the given members, returning an error per member. Static
+    /// members may be named by their instance id alone.
+    pub fn leave_group(
+        &self,
+        group_id: &str,
+        members: &[(String, Option<String>)],
+    ) -> Result<Vec<ErrorCode>, ErrorCode> {
+        let mut groups = self.lock();
+        self.expire(&mut groups, group_id);
+        let Some(group) = groups.get_mut(group_id) else {
+            return Ok(members.iter().map(|_| ErrorCode::UnknownMemberId).collect());
+        };
+        let now = Instant::now();
+        let errors = members
+            .iter()
+            .map(|(member_id, group_instance_id)| {
+                let member_id = match group_instance_id {
+                    Some(group_instance_id) => match group.static_members.get(group_instance_id) {
+                        None => return ErrorCode::UnknownMemberId,
+                        Some(x) if !member_id.is_empty() && x != member_id => {
+                            return ErrorCode::FencedInstanceId
+                        }
+                        Some(x) => x.clone(),
+                    },
+                    None => member_id.clone(),
+                };
+                let member_id = &member_id;
+                if group.member(member_id).is_some() {
+                    group.remove_member(member_id, now);
+                    ErrorCode::NoError
+                } else if group.pending_members.remove(member_id).is_some() {
+                    ErrorCode::NoError
+                } else {
+                    ErrorCode::UnknownMemberId
+                }
+            })
+            .collect();
+        self.expire(&mut groups, group_id);
+        self.changed.notify_all();
+        Ok(errors)
+    }
+
+    pub fn describe_group(&self, group_id: &str) -> Option<GroupSummary> {
+        let mut groups = self.lock();
+        self.expire(&mut groups, group_id);
+        groups.get(group_id).map(Group::summary)
+    }
+
+    pub fn list_groups(&self) -> Vec<GroupSummary> {
+        let mut groups = self.lock();
+        let group_ids: Vec<String> = groups.keys().cloned().collect();
+        for group_id in &group_ids {
+            self.expire(&mut groups, group_id);
+        }
+        groups.values().map(Group::summary).collect()
+    }
+
+    pub fn contains(&self, group_id: &str) -> bool {
+        self.lock().contains_key(group_id)
+    }
+
+    /// Deletes an empty group, running `delete_offsets` while the group is
+    /// still locked so no member can join in between.
+    pub fn delete_group(
+        &self,
+        group_id: &str,
+        delete_offsets: impl FnOnce() -> anyhow::Result<()>,
+    ) -> Result<(), ErrorCode> {
+        let mut groups = self.lock();
+        self.expire(&mut groups, group_id);
+        if let Some(group) = groups.get(group_id) {
+            if group.state != GroupState::Empty || !group.pending_members.is_empty() {
+                return Err(ErrorCode::NonEmptyGroup);
+            }
+        }
+        delete_offsets().map_err(|_| ErrorCode::CoordinatorNotAvailable)?;
+        groups.remove(group_id);
+        Ok(())
+    }
+
+    /// Topics the group's members subscribe to. Offsets of these topics may
+    /// not be deleted.
+    pub fn subscribed_topics(&self, group_id: &str) -> Result<HashSet<String>, ErrorCode> {
+        let mut groups = self.lock();
+        self.expire(&mut groups, group_id);
+        match groups.get(group_id) {
+            None => Ok(HashSet::new()),
+            Some(group) => group.subscribed_topics().ok_or(ErrorCode::NonEmptyGroup),
+        }
+    }
+}
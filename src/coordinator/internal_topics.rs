@@ -0,0 +1,240 @@
+use anyhow::{bail, Context, Result};
+use bytes::Bytes;
+use std::collections::BTreeSet;
+use std::sync::Mutex;
+
+use crate::{
+    config::{node_id, ConfigStore},
+    metadata::{create_topic, read_cluster_metadata, MetadataFile, Record, RecordBatch},
+    storage::{PartitionLog, TxnMarker},
+};
+
+pub const GROUP_METADATA_TOPIC: &str = "__consumer_offsets";
+pub const TRANSACTION_STATE_TOPIC: &str = "__transaction_state";
+pub const SHARE_GROUP_STATE_TOPIC: &str = "__share_group_state";
+
+pub fn is_internal_topic(topic_name: &str) -> bool {
+    [
+        GROUP_METADATA_TOPIC,
+        TRANSACTION_STATE_TOPIC,
+        SHARE_GROUP_STATE_TOPIC,
+    ]
+    .contains(&topic_name)
+}
+
+static CREATE_LOCK: Mutex<()> = Mutex::new(());
+
+/// Java's `String.hashCode`, which Kafka uses to spread coordinator keys.
+pub fn java_string_hash(value: &str) -> i32 {
+    value
+        .encode_utf16()
+        .fold(0i32, |hash, x| hash.wrapping_mul(31).wrapping_add(x as i32))
+}
+
+/// The partition of an internal topic owning `key`, as Kafka's `Utils.abs`
+/// of the key's hash modulo the partition count.
+pub fn partition_for(key: &str, partition_count: i32) -> i32 {
+    let hash = java_string_hash(key);
+    let hash = if hash == i32::MIN { 0 } else { hash.abs() };
+    hash % partition_count
+}
+
+fn topic_settings(topic_name: &str, store: &ConfigStore) -> (i64, i64) {
+    let prefix = match topic_name {
+        GROUP_METADATA_TOPIC => "offsets.topic",
+        SHARE_GROUP_STATE_TOPIC => "share.coordinator.state.topic",
+        _ => "transaction.state.log",
+    };
+    (
+        store.broker_config_i64(&format!("{}.num.partitions", prefix)),
+        store.broker_config_i64(&format!("{}.replication.factor", prefix)),
+    )
+}
+
+/// Number of partitions of an internal topic, creating the topic with the
+/// configured partition count and replication factor if it does not exist.
+pub fn ensure_internal_topic(topic_name: &str) -> Result<(MetadataFile, i32)> {
+    let _guard = CREATE_LOCK.lock().unwrap();
+    let metadata = read_cluster_metadata()?;
+    if let Some(topic) = metadata.get_topic_by_name(topic_name) {
+        // A partition has a record for every change of its leader.
+        let partitions = metadata
+            .get_topic_partitions(&topic.uuid)
+            .map(|x| x.partition_id)
+            .collect::<BTreeSet<i32>>()
+            .len() as i32;
+        // Keys cannot be spread over a topic without partitions.
+        if partitions == 0 {
+            bail!("{} has no partitions", topic_name);
+        }
+        return Ok((metadata, partitions));
+    }
+    let store = ConfigStore::load(&metadata);
+    let (partitions, replication_factor) = topic_settings(topic_name, &store);
+    if partitions <= 0 || partitions > i32::MAX as i64 {
+        bail!(
+            "Cannot create {} with {} partitions",
+            topic_name,
+            partitions
+        );
+    }
+    // This broker is the only one we can place replicas on.
+    if replication_factor != 1 {
+        bail!(
+            "Cannot create {} with replication factor {} on a single broker",
+            topic_name,
+            replication_factor
+        );
+    }
+    create_topic(topic_name, partitions as i32, vec![node_id()])?;
+    Ok((read_cluster_metadata()?, partitions as i32))
+}
+
+/// A record of an internal topic as seen by a replay.
+#[derive(Debug)]
+pub enum InternalRecord {
+    /// A record, with the producer id of the transaction it was written in.
+    Data {
+        producer_id: Option<i64>,
+        key: Bytes,
+        value: Option<Bytes>,
+    },
+    /// The end of a producer's transaction.
+    Marker { producer_id: i64, commit: bool },
+}
+
+/// Calls `f` with the key and value of every non-transactional record of an
+/// internal topic, in log order within each partition.
+pub fn replay_internal_topic(
+    topic_name: &str,
+    mut f: impl FnMut(Bytes, Option<Bytes>),
+) -> Result<()> {
+    replay_internal_records(topic_name, |record| {
+        if let InternalRecord::Data {
+            producer_id: None,
+            key,
+            value,
+        } = record
+        {
+            f(key, value);
+        }
+    })
+}
+
+/// Calls `f` with every record and transaction marker of an internal topic,
+/// in log order within each partition.
+pub fn replay_internal_records(topic_name: &str, mut f: impl FnMut(InternalRecord)) -> Result<()> {
+    let metadata = read_cluster_metadata()?;
+    let Some(topic) = metadata.get_topic_by_name(topic_name) else {
+        return Ok(());
+    };
+    let partitions: BTreeSet<i32> = metadata
+        .get_topic_partitions(&topic.uuid)
+        .map(|x| x.partition_id)
+        .collect();
+    for partition in partitions {
+        let log = PartitionLog::open(topic_name, partition)?;
+        for (batch, data) in log.read_batches(log.log_start_offset())? {
+            let producer_id = Some(batch.producer_id).filter(|_| batch.is_transactional());
+            // Malformed batches are skipped rather than failing the replay.
+            for record in batch.records(&data).unwrap_or_default() {
+                let Some(key) = record.key else {
+                    continue;
+                };
+                if batch.is_control() {
+                    f(InternalRecord::Marker {
+                        producer_id: batch.producer_id,
+                        commit: TxnMarker::is_commit(&key),
+                    });
+                } else {
+                    f(InternalRecord::Data {
+                        producer_id,
+                        key,
+                        value: record.value,
+                    });
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Appends the records as one batch to the partition of an internal topic
+/// that `key` hashes to, creating the topic if needed.
+pub fn append_internal_records(
+    topic_name: &str,
+    key: &str,
+    records: Vec<(Bytes, Option<Bytes>)>,
+) -> Result<()> {
+    append_internal_batch(topic_name, key, None, records)
+}
+
+/// Like [`append_internal_records`], writing the batch in the transaction of
+/// the given producer id and epoch.
+pub fn append_internal_batch(
+    topic_name: &str,
+    key: &str,
+    producer: Option<(i64, i16)>,
+    records: Vec<(Bytes, Option<Bytes>)>,
+) -> Result<()> {
+    if records.is_empty() {
+        return Ok(());
+    }
+    let (metadata, partitions) = ensure_internal_topic(topic_name)?;
+    let partition = partition_for(key, partitions);
+    let leader_epoch = metadata
+        .get_topic_by_name(topic_name)
+        .and_then(|x| metadata.get_partition(&x.uuid, partition))
+        .map(|x| x.leader_epoch)
+        .with_context(|| {
+            format!(
+                "{} partition is missing from the cluster metadata",
+                topic_name
+            )
+        })?;
+    let records = records
+        .into_iter()
+        .enumerate()
+        .map(|(idx, (key, value))| Record::new_keyed(idx as i64, key, value))
+        .collect();
+    let mut log = PartitionLog::open(topic_name, partition)?;
+    let mut batch = match producer {
+        Some((producer_id, producer_epoch)) => {
+            RecordBatch::new_transactional(leader_epoch, producer_id, producer_epoch, records)
+        }
+        None => RecordBatch::new(leader_epoch, records),
+    };
+    log.append(&mut batch)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn java_string_hash_matches_java() {
+        assert_eq!(java_string_hash(""), 0);
+        assert_eq!(java_string_hash("hello"), 99162322);
+        assert_eq!(java_string_hash("my-group"), -1906497762);
+    }
+
+    #[test]
+    fn partition_for_positive_hash() {
+        assert_eq!(partition_for("hello", 50), 22);
+        assert_eq!(partition_for("console-consumer-1", 50), 24);
+    }
+
+    #[test]
+    fn partition_for_negative_hash() {
+        assert_eq!(partition_for("my-group", 50), 12);
+    }
+
+    #[test]
+    fn partition_for_min_hash() {
+        // Utils.abs maps i32::MIN to 0, where Math.abs would stay negative.
+        assert_eq!(java_string_hash("polygenelubricants"), i32::MIN);
+        assert_eq!(partition_for("polygenelubricants", 50), 0);
+        assert_eq!(partition_for("polygenelubricants", 7), 0);
+    }
+}
@@ -0,0 +1,34 @@
+mod internal_topics;
+pub use internal_topics::*;
+mod group;
+pub use group::*;
+mod group_coordinator;
+pub use group_coordinator::*;
+mod offsets;
+pub use offsets::*;
+mod consumer_protocol;
+pub use consumer_protocol::*;
+mod assignor;
+pub use assignor::*;
+mod consumer_group;
+pub use consumer_group::*;
+mod consumer_group_records;
+pub use consumer_group_records::*;
+mod consumer_group_coordinator;
+pub use consumer_group_coordinator::*;
+mod share_group;
+pub use share_group::*;
+mod share_group_coordinator;
+pub use share_group_coordinator::*;
+mod share_partition;
+pub use share_partition::*;
+mod share_partition_manager;
+pub use share_partition_manager::*;
+mod consumer_lag;
+pub use consumer_lag::*;
+mod producer_ids;
+pub use producer_ids::*;
+mod transaction_state;
+pub use transaction_state::*;
+mod transaction_coordinator;
+pub use transaction_coordinator::*;
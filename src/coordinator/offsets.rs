@@ -0,0 +1,448 @@
+use anyhow::Result;
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use std::collections::{HashMap, HashSet};
+use std::sync::{Mutex, MutexGuard, OnceLock};
+use std::thread;
+use std::time::Duration;
+
+use crate::{
+    config::ConfigStore,
+    metadata::{now_ms, read_cluster_metadata},
+    protocol::{get_string, put_string},
+};
+
+use super::{
+    append_internal_batch, append_internal_records, consumer_group_coordinator, group_coordinator,
+    replay_internal_records, replay_internal_topic, InternalRecord, GROUP_METADATA_TOPIC,
+};
+
+/// Key of an offset commit in `__consumer_offsets`. Versions 0 and 1 are
+/// offset commits; later versions are group metadata.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct OffsetKey {
+    pub group_id: String,
+    pub topic: String,
+    pub partition: i32,
+}
+
+impl OffsetKey {
+    const VERSION: i16 = 1;
+
+    pub fn serialize(&self) -> Bytes {
+        let mut bytes = BytesMut::new();
+        bytes.put_i16(Self::VERSION);
+        put_string(&mut bytes, &self.group_id, false);
+        put_string(&mut bytes, &self.topic, false);
+        bytes.put_i32(self.partition);
+        bytes.freeze()
+    }
+
+    pub fn deserialize(mut bytes: Bytes) -> Option<Self> {
+        if bytes.len() < 2 || !(0..=1).contains(&bytes.get_i16()) {
+            return None;
+        }
+        Some(Self {
+            group_id: get_string(&mut bytes, false),
+            topic: get_string(&mut bytes, false),
+            partition: bytes.get_i32(),
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CommittedOffset {
+    pub offset: i64,
+    pub leader_epoch: i32,
+    pub metadata: String,
+    pub commit_timestamp: i64,
+    /// Set by OffsetCommit v2-v4 requests carrying their own retention time.
+    pub expire_timestamp: Option<i64>,
+}
+
+impl CommittedOffset {
+    /// Value v1 is the only one carrying an expire timestamp, v3 the only
+    /// one carrying a leader epoch.
+    pub fn serialize(&self) -> Bytes {
+        let mut bytes = BytesMut::new();
+        match self.expire_timestamp {
+            Some(expire_timestamp) => {
+                bytes.put_i16(1);
+                bytes.put_i64(self.offset);
+                put_string(&mut bytes, &self.metadata, false);
+                bytes.put_i64(self.commit_timestamp);
+                bytes.put_i64(expire_timestamp);
+            }
+            None => {
+                bytes.put_i16(3);
+                bytes.put_i64(self.offset);
+                bytes.put_i32(self.leader_epoch);
+                put_string(&mut bytes, &self.metadata, false);
+                bytes.put_i64(self.commit_timestamp);
+            }
+        }
+        bytes.freeze()
+    }
+
+    pub fn deserialize(mut bytes: Bytes) -> Option<Self> {
+        if bytes.len() < 2 {
+            return None;
+        }
+        let version = bytes.get_i16();
+        if !(0..=3).contains(&version) {
+            return None;
+        }
+        let offset = bytes.get_i64();
+        let leader_epoch = if version >= 3 { bytes.get_i32() } else { -1 };
+        let metadata = get_string(&mut bytes, false);
+        let commit_timestamp = bytes.get_i64();
+        let expire_timestamp = if version == 1 {
+            Some(bytes.get_i64())
+        } else {
+            None
+        };
+        Some(Self {
+            offset,
+            leader_epoch,
+            metadata,
+            commit_timestamp,
+            expire_timestamp,
+        })
+    }
+
+    fn is_expired(&self, now: i64, retention_ms: i64, group_active: bool) -> bool {
+        match self.expire_timestamp {
+            Some(expire_timestamp) => now >= expire_timestamp,
+            None => !group_active && now >= self.commit_timestamp.saturating_add(retention_ms),
+        }
+    }
+}
+
+type GroupOffsets = HashMap<(String, i32), CommittedOffset>;
+
+#[derive(Debug, Default)]
+struct OffsetCache {
+    groups: HashMap<String, GroupOffsets>,
+    /// Offsets committed in ongoing transactions, by producer id then group.
+    pending: HashMap<i64, HashMap<String, GroupOffsets>>,
+}
+
+/// Expires committed offsets every `offsets.retention.check.interval.ms`
+/// from a background thread, so that offsets expire whether or not groups
+/// keep committing or fetching them.
+pub fn spawn_offset_expiration() {
+    thread::spawn(|| loop {
+        let interval = match read_cluster_metadata() {
+            Ok(metadata) => {
+                let store = ConfigStore::load(&metadata);
+                offset_store().expire_offsets(&store, |x| {
+                    group_coordinator().has_members(x)
+                        || consumer_group_coordinator().has_members(x)
+                });
+                store.broker_config_i64("offsets.retention.check.interval.ms")
+            }
+            // Retried at the default interval.
+            Err(_) => 600_000,
+        };
+        thread::sleep(Duration::from_millis(interval.max(1) as u64));
+    });
+}
+
+/// Committed offsets of every group, cached in memory and persisted in the
+/// `__consumer_offsets` partition the group hashes to.
+#[derive(Debug, Default)]
+pub struct OffsetStore {
+    cache: Mutex<OffsetCache>,
+}
+
+/// The offset store, rebuilt from `__consumer_offsets` on first use.
+pub fn offset_store() -> &'static OffsetStore {
+    static STORE: OnceLock<OffsetStore> = OnceLock::new();
+    STORE.get_or_init(|| OffsetStore::load().unwrap_or_default())
+}
+
+impl OffsetStore {
+    /// Replays every `__consumer_offsets` partition. Offsets committed in a
+    /// transaction stay pending until its marker.
+    pub fn load() -> Result<Self> {
+        let mut cache = OffsetCache::default();
+        replay_internal_records(GROUP_METADATA_TOPIC, |record| match record {
+            InternalRecord::Data {
+                producer_id,
+                key,
+                value,
+            } => {
+                let Some(key) = OffsetKey::deserialize(key) else {
+                    return;
+                };
+                let value = value.and_then(CommittedOffset::deserialize);
+                match (producer_id, value) {
+                    (None, value) => cache.apply(key, value),
+                    (Some(producer_id), Some(value)) => cache.add_pending(producer_id, key, value),
+                    (Some(_), None) => {}
+                }
+            }
+            InternalRecord::Marker {
+                producer_id,
+                commit,
+            } => cache.complete_transaction(producer_id, commit),
+        })?;
+        Ok(Self {
+            cache: Mutex::new(cache),
+        })
+    }
+
+    fn lock(&self) -> MutexGuard<'_, OffsetCache> {
+        self.cache.lock().unwrap()
+    }
+
+    /// Persists the commits, `None` deleting an offset, then updates the cache.
+    pub fn write(
+        &self,
+        group_id: &str,
+        entries: Vec<((String, i32), Option<CommittedOffset>)>,
+    ) -> Result<()> {
+        let mut cache = self.lock();
+        write_records(group_id, offset_records(group_id, &entries))?;
+        for ((topic, partition), value) in entries {
+            let key = OffsetKey {
+                group_id: group_id.to_owned(),
+                topic,
+                partition,
+            };
+            cache.apply(key, value);
+        }
+        Ok(())
+    }
+
+    /// Persists offsets committed in a producer's transaction. They stay
+    /// pending until the transaction's marker reaches the group's partition.
+    pub fn write_pending(
+        &self,
+        (producer_id, producer_epoch): (i64, i16),
+        group_id: &str,
+        entries: Vec<((String, i32), CommittedOffset)>,
+    ) -> Result<()> {
+        let mut cache = self.lock();
+        let entries: Vec<_> = entries.into_iter().map(|(k, v)| (k, Some(v))).collect();
+        append_internal_batch(
+            GROUP_METADATA_TOPIC,
+            group_id,
+            Some((producer_id, producer_epoch)),
+            offset_records(group_id, &entries),
+        )?;
+        for ((topic, partition), value) in entries {
+            let key = OffsetKey {
+                group_id: group_id.to_owned(),
+                topic,
+                partition,
+            };
+            if let Some(value) = value {
+                cache.add_pending(producer_id, key, value);
+            }
+        }
+        Ok(())
+    }
+
+    /// Applies or drops the pending offsets of a producer once the marker
+    /// ending its transaction is written.
+    pub fn complete_transaction(&self, producer_id: i64, commit: bool) {
+        self.lock().complete_transaction(producer_id, commit);
+    }
+
+    /// Committed offsets of a group for the given partitions, or all of them.
+    pub fn fetch(
+        &self,
+        group_id: &str,
+        partitions: Option<&[(String, i32)]>,
+    ) -> Vec<((String, i32), Option<CommittedOffset>)> {
+        let cache = self.lock();
+        let offsets = cache.groups.get(group_id);
+        match partitions {
+            Some(partitions) => partitions
+                .iter()
+                .map(|x| (x.clone(), offsets.and_then(|o| o.get(x)).cloned()))
+                .collect(),
+            None => {
+                let mut all: Vec<_> = offsets
+                    .into_iter()
+                    .flatten()
+                    .map(|(k, v)| (k.clone(), Some(v.clone())))
+                    .collect();
+                all.sort_by(|a, b| a.0.cmp(&b.0));
+                all
+            }
+        }
+    }
+
+    pub fn has_group(&self, group_id: &str) -> bool {
+        self.lock().groups.contains_key(group_id)
+    }
+
+    pub fn group_ids(&self) -> Vec<String> {
+        self.lock().groups.keys().cloned().collect()
+    }
+
+    /// Deletes the given offsets of a group, skipping those never committed.
+    pub fn delete_offsets(&self, group_id: &str, partitions: &[(String, i32)]) -> Result<()> {
+        let committed: Vec<(String, i32)> = {
+            let cache = self.lock();
+            let offsets = cache.groups.get(group_id);
+            partitions
+                .iter()
+                .filter(|x| offsets.is_some_and(|o| o.contains_key(*x)))
+                .cloned()
+                .collect()
+        };
+        self.write(group_id, committed.into_iter().map(|x| (x, None)).collect())
+    }
+
+    /// Writes tombstones for every offset of the group and for its group
+    /// metadata.
+    pub fn delete_group(&self, group_id: &str) -> Result<()> {
+        let mut cache = self.lock();
+        let entries: Vec<_> = cache
+            .groups
+            .get(group_id)
+            .into_iter()
+            .flat_map(|x| x.keys())
+            .map(|x| (x.clone(), None))
+            .collect();
+        let mut records = offset_records(group_id, &entries);
+        records.push((group_metadata_key(group_id), None));
+        write_records(group_id, records)?;
+        cache.groups.remove(group_id);
+        Ok(())
+    }
+
+    /// Deletes offsets past `offsets.retention.minutes`. Offsets of groups
+    /// with live members only expire when they carry an explicit expire
+    /// timestamp.
+    pub fn expire_offsets(&self, store: &ConfigStore, is_active: impl Fn(&str) -> bool) {
+        let retention_ms = store
+            .broker_config_i64("offsets.retention.minutes")
+            .saturating_mul(60_000);
+        let group_ids: Vec<String> = self.lock().groups.keys().cloned().collect();
+        // Asked without holding the cache, the group coordinator may be
+        // waiting on it.
+        let active: HashSet<String> = group_ids.into_iter().filter(|x| is_active(x)).collect();
+        let now_ms = now_ms();
+        let mut cache = self.lock();
+        let expired: Vec<(String, Vec<(String, i32)>)> = cache
+            .groups
+            .iter()
+            .map(|(group_id, offsets)| {
+                let active = active.contains(group_id);
+                let keys: Vec<(String, i32)> = offsets
+                    .iter()
+                    .filter(|(_, x)| x.is_expired(now_ms, retention_ms, active))
+                    .map(|(k, _)| k.clone())
+                    .collect();
+                (group_id.clone(), keys)
+            })
+            .filter(|(_, keys)| !keys.is_empty())
+            .collect();
+        for (group_id, keys) in expired {
+            let entries: Vec<_> = keys.into_iter().map(|x| (x, None)).collect();
+            if write_records(&group_id, offset_records(&group_id, &entries)).is_err() {
+                continue;
+            }
+            for ((topic, partition), _) in entries {
+                let key = OffsetKey {
+                    group_id: group_id.clone(),
+                    topic,
+                    partition,
+                };
+                cache.apply(key, None);
+            }
+        }
+    }
+}
+
+impl OffsetCache {
+    fn add_pending(&mut self, producer_id: i64, key: OffsetKey, value: CommittedOffset) {
+        self.pending
+            .entry(producer_id)
+            .or_default()
+            .entry(key.group_id)
+            .or_default()
+            .insert((key.topic, key.partition), value);
+    }
+
+    fn complete_transaction(&mut self, producer_id: i64, commit: bool) {
+        let Some(groups) = self.pending.remove(&producer_id) else {
+            return;
+        };
+        if !commit {
+            return;
+        }
+        for (group_id, offsets) in groups {
+            for ((topic, partition), value) in offsets {
+                let key = OffsetKey {
+                    group_id: group_id.clone(),
+                    topic,
+                    partition,
+                };
+                self.apply(key, Some(value));
+            }
+        }
+    }
+
+    fn apply(&mut self, key: OffsetKey, value: Option<CommittedOffset>) {
+        let partition = (key.topic, key.partition);
+        match value {
+            Some(value) => {
+                self.groups
+                    .entry(key.group_id)
+                    .or_default()
+                    .insert(partition, value);
+            }
+            None => {
+                if let Some(offsets) = self.groups.get_mut(&key.group_id) {
+                    offsets.remove(&partition);
+                    if offsets.is_empty() {
+                        self.groups.remove(&key.group_id);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Calls `f` with the key and value of every record in `__consumer_offsets`,
+/// in log order within each partition.
+pub fn replay_group_metadata(f: impl FnMut(Bytes, Option<Bytes>)) -> Result<()> {
+    replay_internal_topic(GROUP_METADATA_TOPIC, f)
+}
+
+/// Key of a group's metadata record in `__consumer_offsets`.
+fn group_metadata_key(group_id: &str) -> Bytes {
+    let mut bytes = BytesMut::new();
+    bytes.put_i16(2);
+    put_string(&mut bytes, group_id, false);
+    bytes.freeze()
+}
+
+fn offset_records(
+    group_id: &str,
+    entries: &[((String, i32), Option<CommittedOffset>)],
+) -> Vec<(Bytes, Option<Bytes>)> {
+    entries
+        .iter()
+        .map(|((topic, partition), value)| {
+            let key = OffsetKey {
+                group_id: group_id.to_owned(),
+                topic: topic.clone(),
+                partition: *partition,
+            };
+            (
+                key.serialize(),
+                value.as_ref().map(CommittedOffset::serialize),
+            )
+        })
+        .collect()
+}
+
+/// Appends the records as one batch to the group's offsets partition.
+pub fn write_records(group_id: &str, records: Vec<(Bytes, Option<Bytes>)>) -> Result<()> {
+    append_internal_records(GROUP_METADATA_TOPIC, group_id, records)
+}
@@ -0,0 +1,49 @@
+use anyhow::{bail, Result};
+use std::ops::Range;
+use std::sync::{Mutex, OnceLock};
+
+use crate::{
+    config::node_id,
+    metadata::{append_cluster_metadata, read_cluster_metadata, ProducerIdsRecord, RecordType},
+};
+
+/// Number of producer ids reserved at once in the cluster metadata.
+pub const PRODUCER_ID_BLOCK_SIZE: i64 = 1000;
+
+/// Hands out producer ids from blocks reserved through ProducerIdsRecords,
+/// so ids are never reused, even after a restart. Ids left in a block when
+/// the broker stops are skipped.
+#[derive(Debug, Default)]
+pub struct ProducerIdManager {
+    block: Mutex<Range<i64>>,
+}
+
+pub fn producer_id_manager() -> &'static ProducerIdManager {
+    static MANAGER: OnceLock<ProducerIdManager> = OnceLock::new();
+    MANAGER.get_or_init(ProducerIdManager::default)
+}
+
+impl ProducerIdManager {
+    pub fn generate(&self) -> Result<i64> {
+        let mut block = self.block.lock().unwrap();
+        if block.is_empty() {
+            *block = Self::reserve_block()?;
+        }
+        let producer_id = block.start;
+        block.start += 1;
+        Ok(producer_id)
+    }
+
+    fn reserve_block() -> Result<Range<i64>> {
+        let start = read_cluster_metadata()?.next_producer_id();
+        let Some(end) = start.checked_add(PRODUCER_ID_BLOCK_SIZE) else {
+            bail!("Producer ids are exhausted");
+        };
+        append_cluster_metadata(vec![RecordType::ProducerIds(ProducerIdsRecord::new(
+            node_id(),
+            0,
+            end,
+        ))])?;
+        Ok(start..end)
+    }
+}
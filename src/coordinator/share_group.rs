@@ -0,0 +1,80 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::time::{Duration, Instant};
+
+use super::Assignment;
+
+#[derive(Debug, Clone)]
+pub struct ShareMember {
+    pub member_id: String,
+    pub rack_id: Option<String>,
+    pub client_id: String,
+    pub client_host: String,
+    pub subscribed_topic_names: Vec<String>,
+    pub member_epoch: i32,
+    pub assigned: Assignment,
+    pub last_heartbeat: Instant,
+    pub session_timeout: Duration,
+}
+
+impl ShareMember {
+    pub fn new(member_id: &str, now: Instant) -> Self {
+        Self {
+            member_id: member_id.to_owned(),
+            rack_id: None,
+            client_id: String::new(),
+            client_host: String::new(),
+            subscribed_topic_names: vec![],
+            member_epoch: 0,
+            assigned: Assignment::new(),
+            last_heartbeat: now,
+            session_timeout: Duration::ZERO,
+        }
+    }
+
+    pub fn deadline(&self) -> Instant {
+        self.last_heartbeat + self.session_timeout
+    }
+}
+
+/// A share group (KIP-932). Members may share partitions, so they get their
+/// target assignment on their next heartbeat instead of waiting for others
+/// to revoke it.
+#[derive(Debug, Clone)]
+pub struct ShareGroup {
+    pub group_id: String,
+    pub group_epoch: i32,
+    pub assignment_epoch: i32,
+    pub members: BTreeMap<String, ShareMember>,
+    pub target: BTreeMap<String, Assignment>,
+    /// Name and partition count of every subscribed topic, by topic id.
+    pub subscription_metadata: BTreeMap<i128, (String, i32)>,
+}
+
+impl ShareGroup {
+    pub fn new(group_id: &str) -> Self {
+        Self {
+            group_id: group_id.to_owned(),
+            group_epoch: 0,
+            assignment_epoch: 0,
+            members: BTreeMap::new(),
+            target: BTreeMap::new(),
+            subscription_metadata: BTreeMap::new(),
+        }
+    }
+
+    pub fn state(&self) -> &'static str {
+        if self.members.is_empty() {
+            "Empty"
+        } else {
+            "Stable"
+        }
+    }
+
+    /// Topics subscribed by any member.
+    pub fn subscribed_topic_names(&self) -> BTreeSet<String> {
+        self.members
+            .values()
+            .flat_map(|x| x.subscribed_topic_names.iter().cloned())
+            .collect()
+    }
+}
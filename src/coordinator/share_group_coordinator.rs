@@ -0,0 +1,270 @@
+use std::collections::HashMap;
+use std::sync::{Mutex, MutexGuard, OnceLock};
+use std::time::{Duration, Instant};
+
+use crate::{config::ConfigStore, metadata::MetadataFile, protocol::ErrorCode};
+
+use super::{
+    share_partition_manager, subscription_metadata, Assignment, AssignmentSpec, MemberSpec,
+    PartitionAssignor, ShareGroup, ShareMember, SharePartitionLimits, SimpleAssignor,
+    LEAVE_GROUP_MEMBER_EPOCH,
+};
+
+/// Broker configs of share groups.
+#[derive(Debug, Clone)]
+pub struct ShareGroupConfig {
+    pub session_timeout: Duration,
+    pub heartbeat_interval_ms: i32,
+    pub max_size: i64,
+    pub limits: SharePartitionLimits,
+}
+
+impl ShareGroupConfig {
+    pub fn load(store: &ConfigStore) -> Self {
+        let millis =
+            |name: &str| Duration::from_millis(store.broker_config_i64(name).max(0) as u64);
+        Self {
+            session_timeout: millis("group.share.session.timeout.ms"),
+            heartbeat_interval_ms: store.broker_config_i64("group.share.heartbeat.interval.ms")
+                as i32,
+            max_size: store.broker_config_i64("group.share.max.size"),
+            limits: SharePartitionLimits {
+                record_lock_duration: millis("group.share.record.lock.duration.ms"),
+                delivery_count_limit: store
+                    .broker_config_i64("group.share.delivery.count.limit")
+                    .clamp(1, i16::MAX as i64) as i16,
+                max_record_locks: store.broker_config_i64("group.share.partition.max.record.locks"),
+            },
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ShareGroupHeartbeatParams {
+    pub group_id: String,
+    pub member_id: String,
+    pub member_epoch: i32,
+    pub rack_id: Option<String>,
+    /// `None` if unchanged.
+    pub subscribed_topic_names: Option<Vec<String>>,
+    pub client_id: String,
+    pub client_host: String,
+}
+
+#[derive(Debug)]
+pub struct ShareGroupHeartbeatResult {
+    pub member_epoch: i32,
+    /// Only set when the member has to learn its assignment.
+    pub assignment: Option<Assignment>,
+}
+
+type HeartbeatError = (ErrorCode, String);
+
+/// Share groups, kept in memory only: members rejoin after a restart while
+/// the delivery state of their partitions is persisted separately by the
+/// share partition manager.
+#[derive(Debug, Default)]
+pub struct ShareGroupCoordinator {
+    groups: Mutex<HashMap<String, ShareGroup>>,
+}
+
+pub fn share_group_coordinator() -> &'static ShareGroupCoordinator {
+    static COORDINATOR: OnceLock<ShareGroupCoordinator> = OnceLock::new();
+    COORDINATOR.get_or_init(ShareGroupCoordinator::default)
+}
+
+/// Starts a new group epoch and computes its target assignment.
+fn rebalance(group: &mut ShareGroup, metadata: &MetadataFile) {
+    group.subscription_metadata = subscription_metadata(group.subscribed_topic_names(), metadata);
+    group.group_epoch += 1;
+    let topic_ids: HashMap<&str, i128> = group
+        .subscription_metadata
+        .iter()
+        .map(|(topic_id, (name, _))| (name.as_str(), *topic_id))
+        .collect();
+    let spec = AssignmentSpec {
+        members: group
+            .members
+            .values()
+            .map(|member| {
+                let spec = MemberSpec {
+                    subscribed_topic_ids: member
+                        .subscribed_topic_names
+                        .iter()
+                        .filter_map(|x| topic_ids.get(x.as_str()).copied())
+                        .collect(),
+                    current: group
+                        .target
+                        .get(&member.member_id)
+                        .cloned()
+                        .unwrap_or_default(),
+                };
+                (member.member_id.clone(), spec)
+            })
+            .collect(),
+        topics: group
+            .subscription_metadata
+            .iter()
+            .map(|(topic_id, (_, count))| (*topic_id, *count))
+            .collect(),
+    };
+    group.target = SimpleAssignor.assign(&spec);
+    group.assignment_epoch = group.group_epoch;
+}
+
+impl ShareGroupCoordinator {
+    fn lock(&self) -> MutexGuard<'_, HashMap<String, ShareGroup>> {
+        self.groups.lock().unwrap()
+    }
+
+    /// Removes the members that missed their session timeout, releasing the
+    /// records they hold.
+    fn expire(
+        groups: &mut HashMap<String, ShareGroup>,
+        group_id: &str,
+        config: &ShareGroupConfig,
+        metadata: &MetadataFile,
+    ) {
+        let Some(group) = groups.get_mut(group_id) else {
+            return;
+        };
+        let now = Instant::now();
+        let expired: Vec<String> = group
+            .members
+            .values()
+            .filter(|x| now >= x.deadline())
+            .map(|x| x.member_id.clone())
+            .collect();
+        if expired.is_empty() {
+            return;
+        }
+        for member_id in expired {
+            group.members.remove(&member_id);
+            group.target.remove(&member_id);
+            share_partition_manager().release_member(group_id, &member_id, &config.limits);
+        }
+        rebalance(group, metadata);
+    }
+
+    pub fn heartbeat(
+        &self,
+        params: ShareGroupHeartbeatParams,
+        config: &ShareGroupConfig,
+        metadata: &MetadataFile,
+    ) -> Result<ShareGroupHeartbeatResult, HeartbeatError> {
+        let group_id = params.group_id.as_str();
+        let mut groups = self.lock();
+        Self::expire(&mut groups, group_id, config, metadata);
+        if params.member_epoch != 0 && !groups.contains_key(group_id) {
+            return Err((
+                ErrorCode::GroupIdNotFound,
+                format!("Group {} does not exist.", group_id),
+            ));
+        }
+        let group = groups
+            .entry(group_id.to_owned())
+            .or_insert_with(|| ShareGroup::new(group_id));
+        let known = group.members.get(&params.member_id).cloned();
+        if params.member_epoch == LEAVE_GROUP_MEMBER_EPOCH {
+            if known.is_none() {
+                return Err(Self::unknown_member(group, &params.member_id));
+            }
+            group.members.remove(&params.member_id);
+            group.target.remove(&params.member_id);
+            share_partition_manager().release_member(group_id, &params.member_id, &config.limits);
+            rebalance(group, metadata);
+            return Ok(ShareGroupHeartbeatResult {
+                member_epoch: LEAVE_GROUP_MEMBER_EPOCH,
+                assignment: None,
+            });
+        }
+
+        let joining = params.member_epoch == 0;
+        let now = Instant::now();
+        let mut member = match &known {
+            Some(member) if !joining && params.member_epoch != member.member_epoch => {
+                return Err((
+                    ErrorCode::FencedMemberEpoch,
+                    format!(
+                        "The member epoch {} of member {} is fenced, the current one is {}.",
+                        params.member_epoch, member.member_id, member.member_epoch
+                    ),
+                ))
+            }
+            Some(member) => member.clone(),
+            None if !joining => return Err(Self::unknown_member(group, &params.member_id)),
+            None if group.members.len() as i64 >= config.max_size => {
+                return Err((
+                    ErrorCode::GroupMaxSizeReached,
+                    format!("Group {} is full.", group_id),
+                ))
+            }
+            None => ShareMember::new(&params.member_id, now),
+        };
+        member.client_id = params.client_id;
+        member.client_host = params.client_host;
+        member.last_heartbeat = now;
+        member.session_timeout = config.session_timeout;
+        if params.rack_id.is_some() {
+            member.rack_id = params.rack_id;
+        }
+        if let Some(topics) = params.subscribed_topic_names {
+            member.subscribed_topic_names = topics;
+        }
+        let subscription_changed = known.as_ref().map_or(true, |x| {
+            x.subscribed_topic_names != member.subscribed_topic_names
+        });
+        let member_id = member.member_id.clone();
+        group.members.insert(member_id.clone(), member);
+        if subscription_changed
+            || subscription_metadata(group.subscribed_topic_names(), metadata)
+                != group.subscription_metadata
+        {
+            rebalance(group, metadata);
+        }
+        let target_epoch = group.assignment_epoch;
+        let target = group.target.get(&member_id).cloned().unwrap_or_default();
+        let member = group.members.get_mut(&member_id).unwrap();
+        member.member_epoch = target_epoch;
+        member.assigned = target;
+        let send_assignment = joining || known.map_or(true, |x| x.assigned != member.assigned);
+        Ok(ShareGroupHeartbeatResult {
+            member_epoch: member.member_epoch,
+            assignment: send_assignment.then(|| member.assigned.clone()),
+        })
+    }
+
+    fn unknown_member(group: &ShareGroup, member_id: &str) -> HeartbeatError {
+        (
+            ErrorCode::UnknownMemberId,
+            format!(
+                "Member {} is not a member of group {}.",
+                member_id, group.group_id
+            ),
+        )
+    }
+
+    /// Whether the member belongs to the group and may fetch its records.
+    pub fn is_member(&self, group_id: &str, member_id: &str) -> bool {
+        self.lock()
+            .get(group_id)
+            .is_some_and(|x| x.members.contains_key(member_id))
+    }
+
+    pub fn list_groups(
+        &self,
+        config: &ShareGroupConfig,
+        metadata: &MetadataFile,
+    ) -> Vec<ShareGroup> {
+        let mut groups = self.lock();
+        let group_ids: Vec<String> = groups.keys().cloned().collect();
+        for group_id in &group_ids {
+            Self::expire(&mut groups, group_id, config, metadata);
+        }
+        groups.values().cloned().collect()
+    }
+
+    pub fn contains(&self, group_id: &str) -> bool {
+        self.lock().contains_key(group_id)
+    }
+}
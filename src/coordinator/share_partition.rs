@@ -0,0 +1,673 @@
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+use crate::{
+    protocol::{put_array, put_string, put_tag_buffer, ErrorCode},
+    storage::PartitionLog,
+};
+
+/// Delivery state of an in-flight record. Acquired records are persisted as
+/// available, so they are delivered again after a restart.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[repr(i8)]
+pub enum RecordState {
+    Available = 0,
+    Acquired = 1,
+    Acknowledged = 2,
+    Archived = 4,
+}
+
+impl From<i8> for RecordState {
+    fn from(value: i8) -> Self {
+        match value {
+            2 => Self::Acknowledged,
+            4 => Self::Archived,
+            _ => Self::Available,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[repr(i8)]
+pub enum AcknowledgeType {
+    /// The offset holds no record, e.g. it was compacted away.
+    Gap = 0,
+    Accept = 1,
+    Release = 2,
+    Reject = 3,
+}
+
+impl TryFrom<i8> for AcknowledgeType {
+    type Error = i8;
+
+    fn try_from(value: i8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(Self::Gap),
+            1 => Ok(Self::Accept),
+            2 => Ok(Self::Release),
+            3 => Ok(Self::Reject),
+            _ => Err(value),
+        }
+    }
+}
+
+/// Acknowledgement of the offsets from `first_offset` to `last_offset`,
+/// either one type for all of them or one per offset.
+#[derive(Debug, Clone)]
+pub struct AcknowledgementBatch {
+    pub first_offset: i64,
+    pub last_offset: i64,
+    pub acknowledge_types: Vec<i8>,
+}
+
+/// A run of records handed to a member with the same delivery count.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AcquiredRecords {
+    pub first_offset: i64,
+    pub last_offset: i64,
+    pub delivery_count: i16,
+}
+
+/// Records acquired from a share-partition and the batches holding them.
+#[derive(Debug, Default)]
+pub struct ShareFetchData {
+    pub acquired: Vec<AcquiredRecords>,
+    pub records: Vec<Bytes>,
+}
+
+/// Limits applied to a share-partition, from the share group configs.
+#[derive(Debug, Clone)]
+pub struct SharePartitionLimits {
+    pub record_lock_duration: Duration,
+    pub delivery_count_limit: i16,
+    pub max_record_locks: i64,
+}
+
+#[derive(Debug, Clone)]
+struct InFlightRecord {
+    state: RecordState,
+    delivery_count: i16,
+    /// Member holding the acquisition lock.
+    owner: Option<String>,
+    lock_deadline: Option<Instant>,
+}
+
+/// Delivery state of one topic partition for one share group: records below
+/// `start_offset` are done with, records from `end_offset` on were never
+/// acquired, and those in between are tracked one by one.
+#[derive(Debug, Clone)]
+pub struct SharePartition {
+    pub start_offset: i64,
+    pub end_offset: i64,
+    pub state_epoch: i32,
+    pub snapshot_epoch: i32,
+    records: BTreeMap<i64, InFlightRecord>,
+}
+
+impl SharePartition {
+    /// A share-partition starting at `start_offset`.
+    pub fn new(start_offset: i64) -> Self {
+        Self {
+            start_offset,
+            end_offset: start_offset,
+            state_epoch: 0,
+            snapshot_epoch: 0,
+            records: BTreeMap::new(),
+        }
+    }
+
+    fn acquired_count(&self) -> i64 {
+        self.records
+            .values()
+            .filter(|x| x.state == RecordState::Acquired)
+            .count() as i64
+    }
+
+    /// Makes a record available again, or archives it once it used up its
+    /// delivery attempts.
+    fn release(record: &mut InFlightRecord, delivery_count_limit: i16) {
+        record.state = if record.delivery_count >= delivery_count_limit {
+            RecordState::Archived
+        } else {
+            RecordState::Available
+        };
+        record.owner = None;
+        record.lock_deadline = None;
+    }
+
+    /// Moves the start offset past the records that are done with.
+    fn advance_start(&mut self) {
+        while self.start_offset < self.end_offset {
+            let done = self.records.get(&self.start_offset).map_or(true, |x| {
+                matches!(x.state, RecordState::Acknowledged | RecordState::Archived)
+            });
+            if !done {
+                break;
+            }
+            self.records.remove(&self.start_offset);
+            self.start_offset += 1;
+        }
+    }
+
+    /// Skips the records deleted from the log.
+    fn skip_to(&mut self, log_start_offset: i64) {
+        if self.start_offset >= log_start_offset {
+            return;
+        }
+        self.records = self.records.split_off(&log_start_offset);
+        self.start_offset = log_start_offset;
+        self.end_offset = self.end_offset.max(log_start_offset);
+    }
+
+    /// Releases the records whose acquisition lock timed out. Returns
+    /// whether any was released.
+    pub fn expire_locks(&mut self, now: Instant, delivery_count_limit: i16) -> bool {
+        let mut changed = false;
+        for record in self.records.values_mut() {
+            if record.state == RecordState::Acquired
+                && record.lock_deadline.is_some_and(|x| now >= x)
+            {
+                Self::release(record, delivery_count_limit);
+                changed = true;
+            }
+        }
+        self.advance_start();
+        changed
+    }
+
+    /// Acquires up to `max_records` available records of the log for
+    /// `member_id`, redelivering released records before new ones.
+    pub fn acquire(
+        &mut self,
+        member_id: &str,
+        log: &PartitionLog,
+        max_records: i64,
+        limits: &SharePartitionLimits,
+        now: Instant,
+    ) -> anyhow::Result<ShareFetchData> {
+        self.skip_to(log.log_start_offset());
+        let high_watermark = log.high_watermark()?;
+        let mut budget = max_records.min(limits.max_record_locks - self.acquired_count());
+        let mut data = ShareFetchData::default();
+        for (batch, bytes) in log.read_batches(self.start_offset)? {
+            if budget <= 0 {
+                break;
+            }
+            let control = batch.is_control();
+            let mut taken = false;
+            let first = batch.base_offset.max(self.start_offset);
+            let last = batch.last_offset.min(high_watermark - 1);
+            for offset in first..=last {
+                if budget <= 0 {
+                    break;
+                }
+                let record = if offset < self.end_offset {
+                    match self.records.get_mut(&offset) {
+                        Some(x) if x.state == RecordState::Available => x,
+                        _ => continue,
+                    }
+                } else {
+                    self.end_offset = offset + 1;
+                    let state = if control {
+                        RecordState::Archived
+                    } else {
+                        RecordState::Available
+                    };
+                    let record = InFlightRecord {
+                        state,
+                        delivery_count: 0,
+                        owner: None,
+                        lock_deadline: None,
+                    };
+                    let record = self.records.entry(offset).or_insert(record);
+                    if control {
+                        continue;
+                    }
+                    record
+                };
+                record.state = RecordState::Acquired;
+                record.delivery_count = record.delivery_count.saturating_add(1);
+                record.owner = Some(member_id.to_owned());
+                record.lock_deadline = Some(now + limits.record_lock_duration);
+                budget -= 1;
+                taken = true;
+                match data.acquired.last_mut() {
+                    Some(x)
+                        if x.last_offset + 1 == offset
+                            && x.delivery_count == record.delivery_count =>
+                    {
+                        x.last_offset = offset
+                    }
+                    _ => data.acquired.push(AcquiredRecords {
+                        first_offset: offset,
+                        last_offset: offset,
+                        delivery_count: record.delivery_count,
+                    }),
+                }
+            }
+            if taken {
+                data.records.push(bytes);
+            }
+        }
+        self.advance_start();
+        Ok(data)
+    }
+
+    /// Applies a member's acknowledgements. Nothing changes unless every
+    /// acknowledged record is acquired by the member.
+    pub fn acknowledge(
+        &mut self,
+        member_id: &str,
+        batches: &[AcknowledgementBatch],
+        delivery_count_limit: i16,
+    ) -> Result<(), ErrorCode> {
+        let mut updates = vec![];
+        for batch in batches {
+            let length = batch.last_offset - batch.first_offset + 1;
+            if length <= 0
+                || (batch.acknowledge_types.len() != 1
+                    && batch.acknowledge_types.len() as i64 != length)
+            {
+                return Err(ErrorCode::InvalidRequest);
+            }
+            for offset in batch.first_offset..=batch.last_offset {
+                let idx = if batch.acknowledge_types.len() == 1 {
+                    0
+                } else {
+                    (offset - batch.first_offset) as usize
+                };
+                let ack = AcknowledgeType::try_from(batch.acknowledge_types[idx])
+                    .map_err(|_| ErrorCode::InvalidRequest)?;
+                let owned = self.records.get(&offset).is_some_and(|x| {
+                    x.state == RecordState::Acquired && x.owner.as_deref() == Some(member_id)
+                });
+                if !owned {
+                    // Gaps may cover offsets that were never records.
+                    if ack == AcknowledgeType::Gap && !self.records.contains_key(&offset) {
+                        continue;
+                    }
+                    return Err(ErrorCode::InvalidRecordState);
+                }
+                updates.push((offset, ack));
+            }
+        }
+        for (offset, ack) in updates {
+            let record = self.records.get_mut(&offset).unwrap();
+            match ack {
+                AcknowledgeType::Accept => record.state = RecordState::Acknowledged,
+                AcknowledgeType::Gap | AcknowledgeType::Reject => {
+                    record.state = RecordState::Archived
+                }
+                AcknowledgeType::Release => Self::release(record, delivery_count_limit),
+            }
+            record.owner = None;
+            record.lock_deadline = None;
+        }
+        self.advance_start();
+        Ok(())
+    }
+
+    /// Releases every record the member holds. Returns whether any was held.
+    pub fn release_member(&mut self, member_id: &str, delivery_count_limit: i16) -> bool {
+        let mut changed = false;
+        for record in self.records.values_mut() {
+            if record.state == RecordState::Acquired && record.owner.as_deref() == Some(member_id) {
+                Self::release(record, delivery_count_limit);
+                changed = true;
+            }
+        }
+        self.advance_start();
+        changed
+    }
+
+    /// Runs of in-flight records with the same persisted state and delivery
+    /// count.
+    fn state_batches(&self) -> Vec<StateBatch> {
+        let mut batches: Vec<StateBatch> = vec![];
+        for (offset, record) in &self.records {
+            let state = match record.state {
+                RecordState::Acquired => RecordState::Available,
+                x => x,
+            };
+            match batches.last_mut() {
+                Some(x)
+                    if x.last_offset + 1 == *offset
+                        && x.state == state
+                        && x.delivery_count == record.delivery_count =>
+                {
+                    x.last_offset = *offset
+                }
+                _ => batches.push(StateBatch {
+                    first_offset: *offset,
+                    last_offset: *offset,
+                    state,
+                    delivery_count: record.delivery_count,
+                }),
+            }
+        }
+        batches
+    }
+
+    fn from_state_batches(start_offset: i64, state_epoch: i32, batches: Vec<StateBatch>) -> Self {
+        let mut partition = Self::new(start_offset);
+        partition.state_epoch = state_epoch;
+        for batch in batches {
+            for offset in batch.first_offset.max(start_offset)..=batch.last_offset {
+                let record = InFlightRecord {
+                    state: batch.state,
+                    delivery_count: batch.delivery_count,
+                    owner: None,
+                    lock_deadline: None,
+                };
+                partition.records.insert(offset, record);
+                partition.end_offset = partition.end_offset.max(offset + 1);
+            }
+        }
+        partition.advance_start();
+        partition
+    }
+}
+
+#[derive(Debug, Clone)]
+struct StateBatch {
+    first_offset: i64,
+    last_offset: i64,
+    state: RecordState,
+    delivery_count: i16,
+}
+
+/// Identifies a share-partition: a topic partition consumed by a share group.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct SharePartitionKey {
+    pub group_id: String,
+    pub topic_id: i128,
+    pub partition: i32,
+}
+
+impl SharePartitionKey {
+    const VERSION: i16 = 0;
+    const VALUE_VERSION: i16 = 0;
+
+    /// The key the share-group state partition is chosen by.
+    pub fn coordinator_key(&self) -> String {
+        format!(
+            "{}:{:032x}:{}",
+            self.group_id, self.topic_id as u128, self.partition
+        )
+    }
+
+    /// The snapshot record of the share-partition in `__share_group_state`,
+    /// `None` writing a tombstone.
+    pub fn snapshot_record(
+        &self,
+        partition: Option<&SharePartition>,
+        leader_epoch: i32,
+    ) -> (Bytes, Option<Bytes>) {
+        let mut key = BytesMut::new();
+        key.put_i16(Self::VERSION);
+        put_string(&mut key, &self.group_id, false);
+        key.put_i128(self.topic_id);
+        key.put_i32(self.partition);
+        let value = partition.map(|partition| {
+            let mut value = BytesMut::new();
+            value.put_i16(Self::VALUE_VERSION);
+            value.put_i32(partition.snapshot_epoch);
+            value.put_i32(partition.state_epoch);
+            value.put_i32(leader_epoch);
+            value.put_i64(partition.start_offset);
+            put_array(&mut value, &partition.state_batches(), true, |x, bytes| {
+                bytes.put_i64(x.first_offset);
+                bytes.put_i64(x.last_offset);
+                bytes.put_i8(x.state as i8);
+                bytes.put_i16(x.delivery_count);
+                put_tag_buffer(bytes, true);
+            });
+            put_tag_buffer(&mut value, true);
+            value.freeze()
+        });
+        (key.freeze(), value)
+    }
+
+    /// Reads a snapshot record; `Some((key, None))` for tombstones, and
+    /// `None` for other keys and records too short for their fields.
+    pub fn parse_snapshot(
+        key: Bytes,
+        value: Option<Bytes>,
+    ) -> Option<(Self, Option<SharePartition>)> {
+        let mut key = Reader(key);
+        if key.i16()? != Self::VERSION {
+            return None;
+        }
+        let share_key = Self {
+            group_id: key.string()?,
+            topic_id: key.i128()?,
+            partition: key.i32()?,
+        };
+        let partition = match value {
+            Some(value) => Some(Self::parse_partition(Reader(value))?),
+            None => None,
+        };
+        Some((share_key, partition))
+    }
+
+    fn parse_partition(mut value: Reader) -> Option<SharePartition> {
+        value.i16()?;
+        let snapshot_epoch = value.i32()?;
+        let state_epoch = value.i32()?;
+        let _leader_epoch = value.i32()?;
+        let start_offset = value.i64()?;
+        let batches = value.compact_array(|x| {
+            let batch = StateBatch {
+                first_offset: x.i64()?,
+                last_offset: x.i64()?,
+                state: RecordState::from(x.i8()?),
+                delivery_count: x.i16()?,
+            };
+            x.tag_buffer()?;
+            Some(batch)
+        })?;
+        value.tag_buffer()?;
+        let mut partition = SharePartition::from_state_batches(start_offset, state_epoch, batches);
+        partition.snapshot_epoch = snapshot_epoch;
+        Some(partition)
+    }
+}
+
+/// Bounds-checked reader for `__share_group_state` records, which may be
+/// cut short on disk.
+struct Reader(Bytes);
+
+impl Reader {
+    fn take(&mut self, length: usize) -> Option<Bytes> {
+        (self.0.remaining() >= length).then(|| self.0.split_to(length))
+    }
+
+    fn i8(&mut self) -> Option<i8> {
+        (self.0.remaining() >= 1).then(|| self.0.get_i8())
+    }
+
+    fn i16(&mut self) -> Option<i16> {
+        (self.0.remaining() >= 2).then(|| self.0.get_i16())
+    }
+
+    fn i32(&mut self) -> Option<i32> {
+        (self.0.remaining() >= 4).then(|| self.0.get_i32())
+    }
+
+    fn i64(&mut self) -> Option<i64> {
+        (self.0.remaining() >= 8).then(|| self.0.get_i64())
+    }
+
+    fn i128(&mut self) -> Option<i128> {
+        (self.0.remaining() >= 16).then(|| self.0.get_i128())
+    }
+
+    fn unsigned_varint(&mut self) -> Option<u64> {
+        let mut value = 0;
+        for shift in (0..64).step_by(7) {
+            let byte = self.i8()? as u8;
+            value |= ((byte & 0x7F) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Some(value);
+            }
+        }
+        None
+    }
+
+    fn string(&mut self) -> Option<String> {
+        let length = usize::try_from(self.i16()?).ok()?;
+        let data = self.take(length)?;
+        Some(String::from_utf8_lossy(&data).to_string())
+    }
+
+    fn compact_array<T>(&mut self, mut item: impl FnMut(&mut Self) -> Option<T>) -> Option<Vec<T>> {
+        let count = self.unsigned_varint()?.saturating_sub(1);
+        (0..count).map(|_| item(self)).collect()
+    }
+
+    fn tag_buffer(&mut self) -> Option<()> {
+        for _ in 0..self.unsigned_varint()? {
+            self.unsigned_varint()?;
+            let size = self.unsigned_varint()?;
+            self.take(usize::try_from(size).ok()?)?;
+        }
+        Some(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metadata::{Record, RecordBatch};
+    use std::fs;
+    use std::path::PathBuf;
+
+    const LIMITS: SharePartitionLimits = SharePartitionLimits {
+        record_lock_duration: Duration::from_secs(30),
+        delivery_count_limit: 2,
+        max_record_locks: 200,
+    };
+
+    /// A log of `count` records in one batch, in a directory of its own.
+    fn log(name: &str, count: i64) -> (PathBuf, PartitionLog) {
+        let dir =
+            std::env::temp_dir().join(format!("share-partition-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let mut log = PartitionLog::open_in(&dir, "foo", 0).unwrap();
+        let records = (0..count)
+            .map(|x| Record::new_keyed(x, Bytes::new(), Some(Bytes::from_static(b"v"))))
+            .collect();
+        log.append(&mut RecordBatch::new(0, records)).unwrap();
+        (dir, log)
+    }
+
+    fn acquired(first_offset: i64, last_offset: i64, delivery_count: i16) -> AcquiredRecords {
+        AcquiredRecords {
+            first_offset,
+            last_offset,
+            delivery_count,
+        }
+    }
+
+    fn ack(
+        first_offset: i64,
+        last_offset: i64,
+        acknowledge_type: AcknowledgeType,
+    ) -> AcknowledgementBatch {
+        AcknowledgementBatch {
+            first_offset,
+            last_offset,
+            acknowledge_types: vec![acknowledge_type as i8],
+        }
+    }
+
+    #[test]
+    fn acquire_locks_records_for_one_member() {
+        let (dir, log) = log("acquire", 3);
+        let mut partition = SharePartition::new(0);
+        let now = Instant::now();
+        let data = partition.acquire("m1", &log, 10, &LIMITS, now).unwrap();
+        assert_eq!(data.acquired, [acquired(0, 2, 1)]);
+        assert_eq!(data.records.len(), 1);
+        let data = partition.acquire("m2", &log, 10, &LIMITS, now).unwrap();
+        assert!(data.acquired.is_empty());
+        assert!(data.records.is_empty());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn acquire_stops_at_max_records() {
+        let (dir, log) = log("max-records", 3);
+        let mut partition = SharePartition::new(0);
+        let now = Instant::now();
+        let data = partition.acquire("m1", &log, 2, &LIMITS, now).unwrap();
+        assert_eq!(data.acquired, [acquired(0, 1, 1)]);
+        let data = partition.acquire("m2", &log, 2, &LIMITS, now).unwrap();
+        assert_eq!(data.acquired, [acquired(2, 2, 1)]);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn acknowledge_accepts_and_releases() {
+        let (dir, log) = log("acknowledge", 3);
+        let mut partition = SharePartition::new(0);
+        let now = Instant::now();
+        partition.acquire("m1", &log, 10, &LIMITS, now).unwrap();
+        let batches = [
+            ack(0, 0, AcknowledgeType::Accept),
+            ack(1, 2, AcknowledgeType::Release),
+        ];
+        partition
+            .acknowledge("m1", &batches, LIMITS.delivery_count_limit)
+            .unwrap();
+        assert_eq!(partition.start_offset, 1);
+        // Released records are delivered again, to any member.
+        let data = partition.acquire("m2", &log, 10, &LIMITS, now).unwrap();
+        assert_eq!(data.acquired, [acquired(1, 2, 2)]);
+        // Records out of delivery attempts are archived when released.
+        partition
+            .acknowledge(
+                "m2",
+                &[ack(1, 2, AcknowledgeType::Release)],
+                LIMITS.delivery_count_limit,
+            )
+            .unwrap();
+        assert_eq!(partition.start_offset, 3);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn acknowledge_requires_records_held_by_the_member() {
+        let (dir, log) = log("not-held", 2);
+        let mut partition = SharePartition::new(0);
+        partition
+            .acquire("m1", &log, 10, &LIMITS, Instant::now())
+            .unwrap();
+        let limit = LIMITS.delivery_count_limit;
+        assert!(matches!(
+            partition.acknowledge("m2", &[ack(0, 1, AcknowledgeType::Accept)], limit),
+            Err(ErrorCode::InvalidRecordState)
+        ));
+        // Nothing changes when any record of the request is not held.
+        let batches = [
+            ack(0, 0, AcknowledgeType::Accept),
+            ack(5, 5, AcknowledgeType::Accept),
+        ];
+        assert!(matches!(
+            partition.acknowledge("m1", &batches, limit),
+            Err(ErrorCode::InvalidRecordState)
+        ));
+        assert_eq!(partition.start_offset, 0);
+        let invalid = AcknowledgementBatch {
+            first_offset: 0,
+            last_offset: 1,
+            acknowledge_types: vec![1, 1, 1],
+        };
+        assert!(matches!(
+            partition.acknowledge("m1", &[invalid], limit),
+            Err(ErrorCode::InvalidRequest)
+        ));
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
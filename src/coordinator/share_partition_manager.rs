@@ -0,0 +1,259 @@
+use anyhow::Result;
+use std::collections::{BTreeSet, HashMap};
+use std::sync::{Mutex, MutexGuard, OnceLock};
+use std::time::Instant;
+
+use crate::{metadata::MetadataFile, protocol::ErrorCode, storage::PartitionLog};
+
+use super::{
+    append_internal_records, replay_internal_topic, AcknowledgementBatch, ShareFetchData,
+    SharePartition, SharePartitionKey, SharePartitionLimits, SHARE_GROUP_STATE_TOPIC,
+};
+
+/// Share session epoch opening a new session.
+pub const INITIAL_SHARE_SESSION_EPOCH: i32 = 0;
+/// Share session epoch of the request closing the session.
+pub const FINAL_SHARE_SESSION_EPOCH: i32 = -1;
+
+/// The partitions a member fetches from, sent in full only when the session
+/// opens and incrementally afterwards.
+#[derive(Debug, Clone)]
+struct ShareSession {
+    epoch: i32,
+    partitions: BTreeSet<(i128, i32)>,
+}
+
+#[derive(Debug, Default)]
+struct ShareState {
+    partitions: HashMap<SharePartitionKey, SharePartition>,
+    sessions: HashMap<(String, String), ShareSession>,
+}
+
+/// Delivery state of every share-partition, cached in memory and persisted
+/// as snapshots in `__share_group_state`, along with the share sessions.
+#[derive(Debug, Default)]
+pub struct SharePartitionManager {
+    state: Mutex<ShareState>,
+}
+
+/// The share partition manager, rebuilt from `__share_group_state` on first
+/// use.
+pub fn share_partition_manager() -> &'static SharePartitionManager {
+    static MANAGER: OnceLock<SharePartitionManager> = OnceLock::new();
+    MANAGER.get_or_init(|| SharePartitionManager::load().unwrap_or_default())
+}
+
+/// Writes the snapshot of a share-partition, bumping its snapshot epoch.
+fn persist(
+    key: &SharePartitionKey,
+    partition: &mut SharePartition,
+    leader_epoch: i32,
+) -> Result<()> {
+    partition.snapshot_epoch += 1;
+    let record = key.snapshot_record(Some(partition), leader_epoch);
+    append_internal_records(
+        SHARE_GROUP_STATE_TOPIC,
+        &key.coordinator_key(),
+        vec![record],
+    )
+}
+
+fn storage_error(_: anyhow::Error) -> ErrorCode {
+    ErrorCode::KafkaStorageError
+}
+
+impl SharePartitionManager {
+    fn load() -> Result<Self> {
+        let mut state = ShareState::default();
+        replay_internal_topic(SHARE_GROUP_STATE_TOPIC, |key, value| {
+            if let Some((key, partition)) = SharePartitionKey::parse_snapshot(key, value) {
+                match partition {
+                    Some(partition) => state.partitions.insert(key, partition),
+                    None => state.partitions.remove(&key),
+                };
+            }
+        })?;
+        Ok(Self {
+            state: Mutex::new(state),
+        })
+    }
+
+    fn lock(&self) -> MutexGuard<'_, ShareState> {
+        self.state.lock().unwrap()
+    }
+
+    /// Checks the session epoch of a request and applies its partition
+    /// changes. Returns the partitions of the session; opening a new one
+    /// releases the records the member held through the old one.
+    pub fn update_session(
+        &self,
+        group_id: &str,
+        member_id: &str,
+        epoch: i32,
+        added: &[(i128, i32)],
+        forgotten: &[(i128, i32)],
+        limits: &SharePartitionLimits,
+    ) -> Result<Vec<(i128, i32)>, (ErrorCode, String)> {
+        let mut state = self.lock();
+        let session_key = (group_id.to_owned(), member_id.to_owned());
+        let session = match epoch {
+            INITIAL_SHARE_SESSION_EPOCH => {
+                if let Some(old) = state.sessions.remove(&session_key) {
+                    Self::release(&mut state, group_id, member_id, &old.partitions, limits);
+                }
+                state.sessions.entry(session_key).or_insert(ShareSession {
+                    epoch: 0,
+                    partitions: BTreeSet::new(),
+                })
+            }
+            _ => {
+                let Some(session) = state.sessions.get_mut(&session_key) else {
+                    return Err((
+                        ErrorCode::ShareSessionNotFound,
+                        format!("Share session of member {} not found.", member_id),
+                    ));
+                };
+                if epoch != FINAL_SHARE_SESSION_EPOCH && epoch != session.epoch {
+                    return Err((
+                        ErrorCode::InvalidShareSessionEpoch,
+                        format!(
+                            "Share session epoch {} is invalid, expected {}.",
+                            epoch, session.epoch
+                        ),
+                    ));
+                }
+                session
+            }
+        };
+        session.partitions.extend(added.iter().copied());
+        for x in forgotten {
+            session.partitions.remove(x);
+        }
+        session.epoch = session.epoch.checked_add(1).unwrap_or(1);
+        let partitions: Vec<(i128, i32)> = session.partitions.iter().copied().collect();
+        Ok(partitions)
+    }
+
+    /// Closes the member's session once a final request is processed,
+    /// releasing the records it still holds.
+    pub fn close_session(&self, group_id: &str, member_id: &str, limits: &SharePartitionLimits) {
+        let mut state = self.lock();
+        let session_key = (group_id.to_owned(), member_id.to_owned());
+        if let Some(session) = state.sessions.remove(&session_key) {
+            Self::release(&mut state, group_id, member_id, &session.partitions, limits);
+        }
+    }
+
+    fn release(
+        state: &mut ShareState,
+        group_id: &str,
+        member_id: &str,
+        partitions: &BTreeSet<(i128, i32)>,
+        limits: &SharePartitionLimits,
+    ) {
+        for (topic_id, partition) in partitions {
+            let key = SharePartitionKey {
+                group_id: group_id.to_owned(),
+                topic_id: *topic_id,
+                partition: *partition,
+            };
+            if let Some(share_partition) = state.partitions.get_mut(&key) {
+                if share_partition.release_member(member_id, limits.delivery_count_limit) {
+                    // Acquired records are persisted as available, so a failed
+                    // write only loses what a restart would undo anyway.
+                    let _ = persist(&key, share_partition, -1);
+                }
+            }
+        }
+    }
+
+    /// Releases the records a member holds in every partition of its group,
+    /// once it left or was fenced, and drops its session.
+    pub fn release_member(&self, group_id: &str, member_id: &str, limits: &SharePartitionLimits) {
+        let mut state = self.lock();
+        state
+            .sessions
+            .remove(&(group_id.to_owned(), member_id.to_owned()));
+        let keys: Vec<SharePartitionKey> = state
+            .partitions
+            .keys()
+            .filter(|x| x.group_id == group_id)
+            .cloned()
+            .collect();
+        for key in keys {
+            let share_partition = state.partitions.get_mut(&key).unwrap();
+            if share_partition.release_member(member_id, limits.delivery_count_limit) {
+                let _ = persist(&key, share_partition, -1);
+            }
+        }
+    }
+
+    /// The share-partition of a group, starting at the log end offset for
+    /// partitions the group never consumed.
+    fn partition<'a>(
+        state: &'a mut ShareState,
+        key: &SharePartitionKey,
+        log: &PartitionLog,
+    ) -> Result<&'a mut SharePartition> {
+        if !state.partitions.contains_key(key) {
+            let partition = SharePartition::new(log.high_watermark()?);
+            state.partitions.insert(key.clone(), partition);
+        }
+        Ok(state.partitions.get_mut(key).unwrap())
+    }
+
+    /// Acquires records of a partition for the member.
+    pub fn acquire(
+        &self,
+        key: &SharePartitionKey,
+        topic_name: &str,
+        member_id: &str,
+        max_records: i64,
+        limits: &SharePartitionLimits,
+        metadata: &MetadataFile,
+    ) -> Result<ShareFetchData, ErrorCode> {
+        let leader_epoch = metadata
+            .get_partition(&key.topic_id, key.partition)
+            .map_or(-1, |x| x.leader_epoch);
+        let log = PartitionLog::open(topic_name, key.partition).map_err(storage_error)?;
+        let mut state = self.lock();
+        let share_partition = Self::partition(&mut state, key, &log).map_err(storage_error)?;
+        let mut updated = share_partition.clone();
+        let now = Instant::now();
+        updated.expire_locks(now, limits.delivery_count_limit);
+        let data = updated
+            .acquire(member_id, &log, max_records, limits, now)
+            .map_err(storage_error)?;
+        if data.acquired.is_empty() && updated.start_offset == share_partition.start_offset {
+            *share_partition = updated;
+            return Ok(data);
+        }
+        persist(key, &mut updated, leader_epoch).map_err(storage_error)?;
+        *share_partition = updated;
+        Ok(data)
+    }
+
+    /// Applies a member's acknowledgements to a partition.
+    pub fn acknowledge(
+        &self,
+        key: &SharePartitionKey,
+        member_id: &str,
+        batches: &[AcknowledgementBatch],
+        limits: &SharePartitionLimits,
+        metadata: &MetadataFile,
+    ) -> Result<(), ErrorCode> {
+        let leader_epoch = metadata
+            .get_partition(&key.topic_id, key.partition)
+            .map_or(-1, |x| x.leader_epoch);
+        let mut state = self.lock();
+        let Some(share_partition) = state.partitions.get_mut(key) else {
+            return Err(ErrorCode::InvalidRecordState);
+        };
+        let mut updated = share_partition.clone();
+        updated.expire_locks(Instant::now(), limits.delivery_count_limit);
+        updated.acknowledge(member_id, batches, limits.delivery_count_limit)?;
+        persist(key, &mut updated, leader_epoch).map_err(storage_error)?;
+        *share_partition = updated;
+        Ok(())
+    }
+}
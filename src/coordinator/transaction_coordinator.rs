@@ -0,0 +1,479 @@
+use anyhow::Result;
+use std::collections::HashMap;
+use std::sync::{Mutex, MutexGuard, OnceLock};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::{
+    config::ConfigStore,
+    metadata::{now_ms, read_cluster_metadata},
+    protocol::ErrorCode,
+    storage::{append_txn_marker, TxnMarker},
+};
+
+use super::{
+    append_internal_records, ensure_internal_topic, offset_store, partition_for,
+    producer_id_manager, replay_internal_topic, CommittedOffset, TransactionMetadata,
+    TransactionState, GROUP_METADATA_TOPIC, NO_PRODUCER_EPOCH, NO_PRODUCER_ID,
+    TRANSACTION_STATE_TOPIC,
+};
+
+/// Epochs from this one on cannot be bumped any more, the producer gets a
+/// new producer id instead.
+const EXHAUSTED_PRODUCER_EPOCH: i16 = i16::MAX - 1;
+
+/// State of every transactional id, cached in memory and persisted in the
+/// `__transaction_state` partition the id hashes to.
+#[derive(Debug, Default)]
+pub struct TransactionCoordinator {
+    transactions: Mutex<HashMap<String, TransactionMetadata>>,
+    last_timeout_check: Mutex<Option<Instant>>,
+}
+
+/// Aborts timed out transactions every
+/// `transaction.abort.timed.out.transaction.cleanup.interval.ms` from a
+/// background thread, so that transactions of producers that stopped calling
+/// in do not hold back the last stable offset of their partitions.
+pub fn spawn_transaction_expiration() {
+    thread::spawn(|| loop {
+        let interval = match read_cluster_metadata() {
+            Ok(metadata) => {
+                let store = ConfigStore::load(&metadata);
+                transaction_coordinator().abort_timed_out_transactions(&store);
+                store.broker_config_i64(
+                    "transaction.abort.timed.out.transaction.cleanup.interval.ms",
+                )
+            }
+            // Retried at the default interval.
+            Err(_) => 10_000,
+        };
+        thread::sleep(Duration::from_millis(interval.max(1) as u64));
+    });
+}
+
+/// The transaction coordinator, rebuilt from `__transaction_state` on first
+/// use.
+pub fn transaction_coordinator() -> &'static TransactionCoordinator {
+    static COORDINATOR: OnceLock<TransactionCoordinator> = OnceLock::new();
+    COORDINATOR.get_or_init(|| TransactionCoordinator::load().unwrap_or_default())
+}
+
+fn generate_producer_id() -> Result<i64, ErrorCode> {
+    producer_id_manager()
+        .generate()
+        .map_err(|_| ErrorCode::CoordinatorNotAvailable)
+}
+
+fn persist(metadata: &TransactionMetadata) -> Result<(), ErrorCode> {
+    append_internal_records(
+        TRANSACTION_STATE_TOPIC,
+        &metadata.transactional_id,
+        vec![metadata.serialize()],
+    )
+    .map_err(|_| ErrorCode::CoordinatorNotAvailable)
+}
+
+/// Whether a producer presenting `expected` may take over the transactional
+/// id: a brand new id, its current producer id, or a retry of a request that
+/// rotated an exhausted producer id.
+fn is_valid_producer_id(metadata: &TransactionMetadata, expected: (i64, i16)) -> bool {
+    metadata.producer_epoch == NO_PRODUCER_EPOCH
+        || expected.0 == metadata.producer_id
+        || (expected.0 == metadata.last_producer_id && expected.1 >= EXHAUSTED_PRODUCER_EPOCH)
+}
+
+/// The transaction of `transactional_id` if the producer still owns it.
+fn check_producer<'a>(
+    transactions: &'a HashMap<String, TransactionMetadata>,
+    transactional_id: &str,
+    producer_id: i64,
+    producer_epoch: i16,
+) -> Result<&'a TransactionMetadata, ErrorCode> {
+    let Some(metadata) = transactions.get(transactional_id) else {
+        return Err(ErrorCode::InvalidProducerIdMapping);
+    };
+    if metadata.producer_id != producer_id {
+        return Err(ErrorCode::InvalidProducerIdMapping);
+    }
+    if metadata.producer_epoch != producer_epoch {
+        return Err(ErrorCode::ProducerFenced);
+    }
+    Ok(metadata)
+}
+
+/// Writes a transaction marker to a partition, completing the offsets the
+/// producer committed in the transaction when it is a `__consumer_offsets`
+/// partition.
+pub fn write_txn_marker(topic: &str, partition: i32, marker: &TxnMarker) -> Result<(), ErrorCode> {
+    let metadata = read_cluster_metadata().map_err(|_| ErrorCode::KafkaStorageError)?;
+    let Some(leader) = metadata
+        .get_topic_by_name(topic)
+        .and_then(|x| metadata.get_partition(&x.uuid, partition))
+    else {
+        return Err(ErrorCode::UnknownTopicOrPartition);
+    };
+    append_txn_marker(topic, partition, leader.leader_epoch, marker)?;
+    if topic == GROUP_METADATA_TOPIC {
+        offset_store().complete_transaction(marker.producer_id, marker.commit);
+    }
+    Ok(())
+}
+
+/// Epoch of the coordinator of a transactional id: the leader epoch of its
+/// `__transaction_state` partition.
+fn coordinator_epoch(transactional_id: &str) -> i32 {
+    let Ok((metadata, partitions)) = ensure_internal_topic(TRANSACTION_STATE_TOPIC) else {
+        return 0;
+    };
+    metadata
+        .get_topic_by_name(TRANSACTION_STATE_TOPIC)
+        .and_then(|x| metadata.get_partition(&x.uuid, partition_for(transactional_id, partitions)))
+        .map_or(0, |x| x.leader_epoch)
+}
+
+/// Takes a transaction in PrepareCommit or PrepareAbort to its complete
+/// state once its markers are written to every partition it touched.
+/// Partitions already done are dropped, so a failed attempt can resume.
+fn complete_transaction(metadata: &mut TransactionMetadata, now: i64) -> Result<(), ErrorCode> {
+    let commit = match metadata.state {
+        TransactionState::PrepareCommit => true,
+        TransactionState::PrepareAbort => false,
+        _ => return Err(ErrorCode::InvalidTxnState),
+    };
+    let marker = TxnMarker {
+        producer_id: metadata.producer_id,
+        producer_epoch: metadata.producer_epoch,
+        commit,
+        coordinator_epoch: coordinator_epoch(&metadata.transactional_id),
+    };
+    for (topic, partition) in metadata.partitions.clone() {
+        match write_txn_marker(&topic, partition, &marker) {
+            // Partitions deleted since they were added need no marker.
+            Ok(()) | Err(ErrorCode::UnknownTopicOrPartition) => {
+                metadata.partitions.remove(&(topic, partition));
+            }
+            Err(_) => return Err(ErrorCode::CoordinatorNotAvailable),
+        }
+    }
+    metadata.state = if commit {
+        TransactionState::CompleteCommit
+    } else {
+        TransactionState::CompleteAbort
+    };
+    metadata.partitions.clear();
+    metadata.last_update_timestamp = now;
+    persist(metadata)
+}
+
+/// Aborts an ongoing transaction, bumping the epoch first so that the
+/// producer which opened it is fenced.
+fn fence_and_abort(metadata: &mut TransactionMetadata, now: i64) -> Result<(), ErrorCode> {
+    if metadata.producer_epoch < EXHAUSTED_PRODUCER_EPOCH {
+        metadata.producer_epoch += 1;
+    }
+    metadata.state = TransactionState::PrepareAbort;
+    metadata.last_update_timestamp = now;
+    persist(metadata)?;
+    complete_transaction(metadata, now)
+}
+
+impl TransactionCoordinator {
+    fn load() -> Result<Self> {
+        let mut transactions = HashMap::new();
+        replay_internal_topic(TRANSACTION_STATE_TOPIC, |key, value| {
+            if let Some((transactional_id, metadata)) = TransactionMetadata::deserialize(key, value)
+            {
+                match metadata {
+                    Some(metadata) => transactions.insert(transactional_id, metadata),
+                    None => transactions.remove(&transactional_id),
+                };
+            }
+        })?;
+        Ok(Self {
+            transactions: Mutex::new(transactions),
+            last_timeout_check: Mutex::default(),
+        })
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<String, TransactionMetadata>> {
+        self.transactions.lock().unwrap()
+    }
+
+    pub fn describe(&self, transactional_id: &str) -> Option<TransactionMetadata> {
+        self.lock().get(transactional_id).cloned()
+    }
+
+    /// Every transactional id the coordinator knows, sorted by id.
+    pub fn transactions(&self) -> Vec<TransactionMetadata> {
+        let mut transactions: Vec<_> = self.lock().values().cloned().collect();
+        transactions.sort_by(|a, b| a.transactional_id.cmp(&b.transactional_id));
+        transactions
+    }
+
+    /// Gives an idempotent producer a new producer id, or a transactional
+    /// one its producer id with a bumped epoch, fencing older instances.
+    /// `expected` is the id and epoch the producer already had, if any.
+    pub fn init_producer_id(
+        &self,
+        transactional_id: Option<&str>,
+        timeout_ms: i32,
+        expected: Option<(i64, i16)>,
+        store: &ConfigStore,
+    ) -> Result<(i64, i16), ErrorCode> {
+        let Some(transactional_id) = transactional_id else {
+            return Ok((generate_producer_id()?, 0));
+        };
+        if transactional_id.is_empty() {
+            return Err(ErrorCode::InvalidRequest);
+        }
+        let max_timeout_ms = store.broker_config_i64("transaction.max.timeout.ms");
+        if timeout_ms <= 0 || timeout_ms as i64 > max_timeout_ms {
+            return Err(ErrorCode::InvalidTransactionTimeout);
+        }
+        self.abort_timed_out_transactions(store);
+        let expected = expected.filter(|x| x.0 != NO_PRODUCER_ID);
+        let now = now_ms();
+        let mut transactions = self.lock();
+        let mut metadata = match transactions.get(transactional_id) {
+            Some(metadata) => metadata.clone(),
+            None => {
+                TransactionMetadata::new(transactional_id, generate_producer_id()?, timeout_ms, now)
+            }
+        };
+        match metadata.state {
+            TransactionState::Empty
+            | TransactionState::CompleteCommit
+            | TransactionState::CompleteAbort => {}
+            TransactionState::Ongoing => {
+                // Abort the transaction of the previous instance; the
+                // producer retries once it is complete.
+                let result = fence_and_abort(&mut metadata, now);
+                transactions.insert(transactional_id.to_owned(), metadata);
+                result?;
+                return Err(ErrorCode::ConcurrentTransactions);
+            }
+            _ => return Err(ErrorCode::ConcurrentTransactions),
+        }
+        if let Some((producer_id, epoch)) = expected {
+            if !is_valid_producer_id(&metadata, (producer_id, epoch)) {
+                return Err(ErrorCode::ProducerFenced);
+            }
+            if metadata.producer_epoch != NO_PRODUCER_EPOCH
+                && (producer_id, epoch) != (metadata.producer_id, metadata.producer_epoch)
+            {
+                // A retry of a bump whose response the producer never got.
+                let is_retry = producer_id == metadata.last_producer_id
+                    || epoch == metadata.last_producer_epoch;
+                if !is_retry {
+                    return Err(ErrorCode::ProducerFenced);
+                }
+                return Ok((metadata.producer_id, metadata.producer_epoch));
+            }
+        }
+        if metadata.producer_epoch >= EXHAUSTED_PRODUCER_EPOCH {
+            metadata.last_producer_id = metadata.producer_id;
+            metadata.producer_id = generate_producer_id()?;
+            metadata.last_producer_epoch = NO_PRODUCER_EPOCH;
+            metadata.producer_epoch = 0;
+        } else {
+            metadata.last_producer_epoch = metadata.producer_epoch;
+            metadata.producer_epoch += 1;
+        }
+        metadata.timeout_ms = timeout_ms;
+        metadata.state = TransactionState::Empty;
+        metadata.partitions.clear();
+        metadata.last_update_timestamp = now;
+        metadata.start_timestamp = -1;
+        persist(&metadata)?;
+        let result = (metadata.producer_id, metadata.producer_epoch);
+        transactions.insert(transactional_id.to_owned(), metadata);
+        Ok(result)
+    }
+
+    /// Adds partitions to the producer's transaction, starting it if needed.
+    pub fn add_partitions(
+        &self,
+        transactional_id: &str,
+        producer_id: i64,
+        producer_epoch: i16,
+        partitions: &[(String, i32)],
+        store: &ConfigStore,
+    ) -> Result<(), ErrorCode> {
+        self.abort_timed_out_transactions(store);
+        let mut transactions = self.lock();
+        let metadata =
+            check_producer(&transactions, transactional_id, producer_id, producer_epoch)?;
+        match metadata.state {
+            TransactionState::PrepareCommit
+            | TransactionState::PrepareAbort
+            | TransactionState::PrepareEpochFence => return Err(ErrorCode::ConcurrentTransactions),
+            TransactionState::Dead => return Err(ErrorCode::InvalidTxnState),
+            TransactionState::Ongoing
+                if partitions.iter().all(|x| metadata.partitions.contains(x)) =>
+            {
+                return Ok(())
+            }
+            _ => {}
+        }
+        let now = now_ms();
+        let mut metadata = metadata.clone();
+        if metadata.state != TransactionState::Ongoing {
+            metadata.start_timestamp = now;
+        }
+        metadata.state = TransactionState::Ongoing;
+        metadata.partitions.extend(partitions.iter().cloned());
+        metadata.last_update_timestamp = now;
+        persist(&metadata)?;
+        transactions.insert(transactional_id.to_owned(), metadata);
+        Ok(())
+    }
+
+    /// Adds the `__consumer_offsets` partition of the group to the
+    /// transaction, so the offsets committed in it complete with it.
+    pub fn add_offsets(
+        &self,
+        transactional_id: &str,
+        producer_id: i64,
+        producer_epoch: i16,
+        group_id: &str,
+        store: &ConfigStore,
+    ) -> Result<(), ErrorCode> {
+        let (_, partitions) = ensure_internal_topic(GROUP_METADATA_TOPIC)
+            .map_err(|_| ErrorCode::CoordinatorNotAvailable)?;
+        let partition = (
+            GROUP_METADATA_TOPIC.to_owned(),
+            partition_for(group_id, partitions),
+        );
+        self.add_partitions(
+            transactional_id,
+            producer_id,
+            producer_epoch,
+            &[partition],
+            store,
+        )
+    }
+
+    /// Whether the partition is part of the producer's ongoing transaction.
+    pub fn verify_partition(
+        &self,
+        transactional_id: &str,
+        producer_id: i64,
+        producer_epoch: i16,
+        partition: &(String, i32),
+    ) -> Result<(), ErrorCode> {
+        let transactions = self.lock();
+        let metadata =
+            check_producer(&transactions, transactional_id, producer_id, producer_epoch)?;
+        if metadata.state != TransactionState::Ongoing || !metadata.partitions.contains(partition) {
+            return Err(ErrorCode::InvalidTxnState);
+        }
+        Ok(())
+    }
+
+    /// Commits or aborts the producer's transaction. Retries of an end that
+    /// already completed succeed.
+    pub fn end_txn(
+        &self,
+        transactional_id: &str,
+        producer_id: i64,
+        producer_epoch: i16,
+        commit: bool,
+        store: &ConfigStore,
+    ) -> Result<(), ErrorCode> {
+        self.abort_timed_out_transactions(store);
+        let mut transactions = self.lock();
+        let metadata =
+            check_producer(&transactions, transactional_id, producer_id, producer_epoch)?;
+        let (prepare, complete) = if commit {
+            (
+                TransactionState::PrepareCommit,
+                TransactionState::CompleteCommit,
+            )
+        } else {
+            (
+                TransactionState::PrepareAbort,
+                TransactionState::CompleteAbort,
+            )
+        };
+        match metadata.state {
+            TransactionState::Ongoing => {}
+            x if x == complete => return Ok(()),
+            x if x == prepare => return Err(ErrorCode::ConcurrentTransactions),
+            TransactionState::PrepareEpochFence => return Err(ErrorCode::ConcurrentTransactions),
+            _ => return Err(ErrorCode::InvalidTxnState),
+        }
+        let now = now_ms();
+        let mut metadata = metadata.clone();
+        metadata.state = prepare;
+        metadata.last_update_timestamp = now;
+        persist(&metadata)?;
+        let result = complete_transaction(&mut metadata, now);
+        transactions.insert(transactional_id.to_owned(), metadata);
+        result
+    }
+
+    /// Aborts transactions open for longer than their timeout, fencing their
+    /// producers, and finishes those left in a prepare state. Runs at most
+    /// once per `transaction.abort.timed.out.transaction.cleanup.interval.ms`.
+    pub fn abort_timed_out_transactions(&self, store: &ConfigStore) {
+        let interval = store
+            .broker_config_i64("transaction.abort.timed.out.transaction.cleanup.interval.ms")
+            .max(0) as u64;
+        {
+            let mut last_check = self.last_timeout_check.lock().unwrap();
+            let now = Instant::now();
+            if last_check.is_some_and(|x| now < x + Duration::from_millis(interval)) {
+                return;
+            }
+            *last_check = Some(now);
+        }
+        let now = now_ms();
+        let mut transactions = self.lock();
+        for metadata in transactions.values_mut() {
+            let mut updated = metadata.clone();
+            let result = match metadata.state {
+                TransactionState::Ongoing
+                    if now > metadata.start_timestamp + metadata.timeout_ms as i64 =>
+                {
+                    fence_and_abort(&mut updated, now)
+                }
+                TransactionState::PrepareCommit | TransactionState::PrepareAbort => {
+                    complete_transaction(&mut updated, now)
+                }
+                _ => continue,
+            };
+            // Whatever was persisted is kept; failures are retried next time.
+            if result.is_ok() || updated.state != metadata.state {
+                *metadata = updated;
+            }
+        }
+    }
+
+    /// Holds offsets committed in the producer's transaction until it ends.
+    /// The group's offsets partition must have been added to the transaction.
+    pub fn txn_offset_commit(
+        &self,
+        transactional_id: &str,
+        producer_id: i64,
+        producer_epoch: i16,
+        group_id: &str,
+        entries: Vec<((String, i32), CommittedOffset)>,
+    ) -> Result<(), ErrorCode> {
+        let (_, partitions) = ensure_internal_topic(GROUP_METADATA_TOPIC)
+            .map_err(|_| ErrorCode::CoordinatorNotAvailable)?;
+        let partition = (
+            GROUP_METADATA_TOPIC.to_owned(),
+            partition_for(group_id, partitions),
+        );
+        let transactions = self.lock();
+        let metadata =
+            check_producer(&transactions, transactional_id, producer_id, producer_epoch)?;
+        if metadata.state != TransactionState::Ongoing || !metadata.partitions.contains(&partition)
+        {
+            return Err(ErrorCode::InvalidTxnState);
+        }
+        offset_store()
+            .write_pending((producer_id, producer_epoch), group_id, entries)
+            .map_err(|_| ErrorCode::CoordinatorNotAvailable)
+    }
+}
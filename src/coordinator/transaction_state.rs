@@ -0,0 +1,162 @@
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::protocol::{get_nullable_array, get_string, put_array, put_string};
+
+/// Producer id and epoch of a producer that has none yet.
+pub const NO_PRODUCER_ID: i64 = -1;
+pub const NO_PRODUCER_EPOCH: i16 = -1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(i8)]
+pub enum TransactionState {
+    Empty = 0,
+    Ongoing = 1,
+    PrepareCommit = 2,
+    PrepareAbort = 3,
+    CompleteCommit = 4,
+    CompleteAbort = 5,
+    Dead = 6,
+    PrepareEpochFence = 7,
+}
+
+impl TryFrom<i8> for TransactionState {
+    type Error = i8;
+
+    fn try_from(value: i8) -> Result<Self, Self::Error> {
+        Ok(match value {
+            0 => Self::Empty,
+            1 => Self::Ongoing,
+            2 => Self::PrepareCommit,
+            3 => Self::PrepareAbort,
+            4 => Self::CompleteCommit,
+            5 => Self::CompleteAbort,
+            6 => Self::Dead,
+            7 => Self::PrepareEpochFence,
+            x => return Err(x),
+        })
+    }
+}
+
+impl TransactionState {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Empty => "Empty",
+            Self::Ongoing => "Ongoing",
+            Self::PrepareCommit => "PrepareCommit",
+            Self::PrepareAbort => "PrepareAbort",
+            Self::CompleteCommit => "CompleteCommit",
+            Self::CompleteAbort => "CompleteAbort",
+            Self::Dead => "Dead",
+            Self::PrepareEpochFence => "PrepareEpochFence",
+        }
+    }
+}
+
+/// State of a transactional id, persisted in `__transaction_state` under
+/// key version 0 with value version 0.
+#[derive(Debug, Clone)]
+pub struct TransactionMetadata {
+    pub transactional_id: String,
+    pub producer_id: i64,
+    /// Producer id before the last one was exhausted, not persisted.
+    pub last_producer_id: i64,
+    pub producer_epoch: i16,
+    /// Epoch before the last bump, not persisted.
+    pub last_producer_epoch: i16,
+    pub timeout_ms: i32,
+    pub state: TransactionState,
+    pub partitions: BTreeSet<(String, i32)>,
+    pub last_update_timestamp: i64,
+    pub start_timestamp: i64,
+}
+
+impl TransactionMetadata {
+    pub fn new(transactional_id: &str, producer_id: i64, timeout_ms: i32, now: i64) -> Self {
+        Self {
+            transactional_id: transactional_id.to_owned(),
+            producer_id,
+            last_producer_id: NO_PRODUCER_ID,
+            producer_epoch: NO_PRODUCER_EPOCH,
+            last_producer_epoch: NO_PRODUCER_EPOCH,
+            timeout_ms,
+            state: TransactionState::Empty,
+            partitions: BTreeSet::new(),
+            last_update_timestamp: now,
+            start_timestamp: -1,
+        }
+    }
+
+    pub fn key(transactional_id: &str) -> Bytes {
+        let mut bytes = BytesMut::new();
+        bytes.put_i16(0);
+        put_string(&mut bytes, transactional_id, false);
+        bytes.freeze()
+    }
+
+    pub fn serialize(&self) -> (Bytes, Option<Bytes>) {
+        let mut value = BytesMut::new();
+        value.put_i16(0);
+        value.put_i64(self.producer_id);
+        value.put_i16(self.producer_epoch);
+        value.put_i32(self.timeout_ms);
+        value.put_i8(self.state as i8);
+        if self.partitions.is_empty() {
+            value.put_i32(-1);
+        } else {
+            let mut topics: BTreeMap<&str, Vec<i32>> = BTreeMap::new();
+            for (topic, partition) in &self.partitions {
+                topics.entry(topic).or_default().push(*partition);
+            }
+            let topics: Vec<_> = topics.into_iter().collect();
+            put_array(&mut value, &topics, false, |(topic, partitions), bytes| {
+                put_string(bytes, topic, false);
+                put_array(bytes, partitions, false, |x, bytes| bytes.put_i32(*x));
+            });
+        }
+        value.put_i64(self.last_update_timestamp);
+        value.put_i64(self.start_timestamp);
+        (Self::key(&self.transactional_id), Some(value.freeze()))
+    }
+
+    /// The transactional id of a record, with its metadata unless the
+    /// record is a tombstone. `None` for records that cannot be read.
+    pub fn deserialize(mut key: Bytes, value: Option<Bytes>) -> Option<(String, Option<Self>)> {
+        if key.len() < 2 || key.get_i16() != 0 {
+            return None;
+        }
+        let transactional_id = get_string(&mut key, false);
+        let Some(mut value) = value else {
+            return Some((transactional_id, None));
+        };
+        if value.len() < 2 || value.get_i16() != 0 {
+            return None;
+        }
+        let producer_id = value.get_i64();
+        let producer_epoch = value.get_i16();
+        let timeout_ms = value.get_i32();
+        let state = TransactionState::try_from(value.get_i8()).ok()?;
+        let partitions = get_nullable_array(&mut value, false, |bytes| {
+            let topic = get_string(bytes, false);
+            let partitions = get_nullable_array(bytes, false, |bytes| bytes.get_i32());
+            (topic, partitions.unwrap_or_default())
+        })
+        .unwrap_or_default()
+        .into_iter()
+        .flat_map(|(topic, partitions)| partitions.into_iter().map(move |x| (topic.clone(), x)))
+        .collect();
+        let metadata = Self {
+            transactional_id: transactional_id.clone(),
+            producer_id,
+            last_producer_id: NO_PRODUCER_ID,
+            producer_epoch,
+            last_producer_epoch: NO_PRODUCER_EPOCH,
+            timeout_ms,
+            state,
+            partitions,
+            last_update_timestamp: value.get_i64(),
+            start_timestamp: value.get_i64(),
+        };
+        Some((transactional_id, Some(metadata)))
+    }
+}
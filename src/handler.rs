@@ -2,22 +2,57 @@ use bytes::Bytes;
 
 use crate::protocol::*;
 
-pub fn process_request(mut bytes: Bytes) -> anyhow::Result<Bytes> {
+pub fn process_request(mut bytes: Bytes) -> anyhow::Result<Reply> {
     let header: RequestHeader = RequestHeader::from(&mut bytes);
     let mut api = Api::from(header.request_api_key);
     let (min_ver, max_ver) = api.versions();
     if header.request_api_version < min_ver || header.request_api_version > max_ver {
         api = Api::Invalid;
     }
-    if api == Api::DescribeTopicPartitions {
-        Ok(describe_topic_partitions_handler(&mut bytes, header))
-    } else if api == Api::ApiVersions {
-        Ok(api_versions_handler(header))
-    } else if api == Api::Fetch {
-        fetch_handler(&mut bytes, header)
-    } else {
-        Ok(invalid_request_handler(header))
-    }
+    let response = match api {
+        Api::DescribeTopicPartitions => Ok(describe_topic_partitions_handler(&mut bytes, header)),
+        Api::ApiVersions => Ok(api_versions_handler(header)),
+        Api::Produce => produce_handler(&mut bytes, header),
+        // Fetch sends records straight from the segment files, and may
+        // answer once data arrives.
+        Api::Fetch => return fetch_handler(&mut bytes, header),
+        Api::ListOffsets => list_offsets_handler(&mut bytes, header),
+        Api::OffsetCommit => offset_commit_handler(&mut bytes, header),
+        Api::OffsetFetch => offset_fetch_handler(&mut bytes, header),
+        Api::FindCoordinator => find_coordinator_handler(&mut bytes, header),
+        Api::JoinGroup => join_group_handler(&mut bytes, header),
+        Api::Heartbeat => heartbeat_handler(&mut bytes, header),
+        Api::LeaveGroup => leave_group_handler(&mut bytes, header),
+        Api::SyncGroup => sync_group_handler(&mut bytes, header),
+        Api::DescribeGroups => describe_groups_handler(&mut bytes, header),
+        Api::ListGroups => list_groups_handler(&mut bytes, header),
+        Api::DeleteGroups => delete_groups_handler(&mut bytes, header),
+        Api::OffsetDelete => offset_delete_handler(&mut bytes, header),
+        Api::DeleteRecords => delete_records_handler(&mut bytes, header),
+        Api::InitProducerId => init_producer_id_handler(&mut bytes, header),
+        Api::OffsetForLeaderEpoch => offset_for_leader_epoch_handler(&mut bytes, header),
+        Api::AddPartitionsToTxn => add_partitions_to_txn_handler(&mut bytes, header),
+        Api::AddOffsetsToTxn => add_offsets_to_txn_handler(&mut bytes, header),
+        Api::EndTxn => end_txn_handler(&mut bytes, header),
+        Api::WriteTxnMarkers => write_txn_markers_handler(&mut bytes, header),
+        Api::TxnOffsetCommit => txn_offset_commit_handler(&mut bytes, header),
+        Api::DescribeConfigs => describe_configs_handler(&mut bytes, header),
+        Api::AlterConfigs => alter_configs_handler(&mut bytes, header),
+        Api::IncrementalAlterConfigs => incremental_alter_configs_handler(&mut bytes, header),
+        Api::DescribeLogDirs => describe_log_dirs_handler(&mut bytes, header),
+        Api::DescribeCluster => describe_cluster_handler(&mut bytes, header),
+        Api::DescribeProducers => describe_producers_handler(&mut bytes, header),
+        Api::DescribeTransactions => describe_transactions_handler(&mut bytes, header),
+        Api::ListTransactions => list_transactions_handler(&mut bytes, header),
+        Api::ConsumerGroupHeartbeat => consumer_group_heartbeat_handler(&mut bytes, header),
+        Api::ConsumerGroupDescribe => consumer_group_describe_handler(&mut bytes, header),
+        Api::ShareGroupHeartbeat => share_group_heartbeat_handler(&mut bytes, header),
+        // Share fetches may answer once records arrive, like fetches.
+        Api::ShareFetch => return share_fetch_handler(&mut bytes, header),
+        Api::ShareAcknowledge => share_acknowledge_handler(&mut bytes, header),
+        Api::Invalid => Ok(invalid_request_handler(header)),
+    };
+    response.map(|x| ResponseData::from(x).into())
 }
 
 fn invalid_request_handler(header: RequestHeader) -> Bytes {
@@ -1,4 +1,8 @@
+pub mod admin;
+pub mod config;
+pub mod coordinator;
 pub mod handler;
 pub mod metadata;
 pub mod protocol;
 pub mod server;
+pub mod storage;
@@ -2,9 +2,20 @@
 use std::net::TcpListener;
 
 use anyhow::{Context, Result};
-use codecrafters_kafka::server;
+use codecrafters_kafka::{admin, config, coordinator, server};
 
 fn main() -> Result<()> {
+    if let Some(path) = std::env::args().nth(1) {
+        config::load_server_properties(&path)?;
+    }
+    // Rebuild the committed offsets, consumer groups and share-partition
+    // state before serving requests.
+    coordinator::offset_store();
+    coordinator::consumer_group_coordinator();
+    coordinator::share_partition_manager();
+    if let Some(address) = config::admin_listener() {
+        admin::AdminServer { address }.spawn()?;
+    }
     let server = server::Server;
     server.run().context("Server error")?;
     Ok(())
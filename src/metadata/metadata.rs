@@ -1,11 +1,15 @@
 use crate::{
-    metadata::{PartitionRecord, RecordBatch, TopicRecord},
+    metadata::{ConfigRecord, PartitionRecord, Record, RecordBatch, RecordType, TopicRecord},
     protocol::{Deserialize, Serialize},
+    storage::{PartitionLog, LOG_DIR},
 };
 use anyhow::Result;
 use bytes::{Buf, Bytes, BytesMut};
-use std::fs::File;
+use std::collections::hash_map::RandomState;
+use std::fs::{self, File};
+use std::hash::{BuildHasher, Hasher};
 use std::io::Read;
+use std::path::Path;
 
 #[derive(Debug)]
 pub struct MetadataFile {
@@ -54,7 +58,70 @@ pub fn read_cluster_metadata() -> Result<MetadataFile> {
     Ok(MetadataFile::deserialize(&mut bytes))
 }
 
+pub const CLUSTER_METADATA_TOPIC: &str = "__cluster_metadata";
+
+/// Appends `records` to the cluster metadata log as a single batch.
+pub fn append_cluster_metadata(records: Vec<RecordType>) -> Result<i64> {
+    let mut log = PartitionLog::open(CLUSTER_METADATA_TOPIC, 0)?;
+    let leader_epoch = log
+        .batches()?
+        .last()
+        .map(|x| x.partition_leader_epoch)
+        .unwrap_or(0);
+    let records = records
+        .into_iter()
+        .enumerate()
+        .map(|(idx, value)| Record::new(idx as i64, value))
+        .collect();
+    log.append(&mut RecordBatch::new(leader_epoch, records))
+}
+
+pub fn random_uuid() -> i128 {
+    let state = RandomState::new();
+    let mut hasher = state.build_hasher();
+    let high = hasher.finish();
+    hasher.write_u64(high);
+    ((high as i128) << 64) | hasher.finish() as i128
+}
+
+/// Registers a new topic in the cluster metadata and creates its partition
+/// directories. Returns the topic id.
+pub fn create_topic(topic_name: &str, partitions: i32, replicas: Vec<i32>) -> Result<i128> {
+    let uuid = random_uuid();
+    let mut records = vec![RecordType::Topic(TopicRecord::new(topic_name, uuid))];
+    for partition_id in 0..partitions {
+        records.push(RecordType::Partition(PartitionRecord::new(
+            partition_id,
+            uuid,
+            replicas.clone(),
+        )));
+    }
+    append_cluster_metadata(records)?;
+    for partition_id in 0..partitions {
+        fs::create_dir_all(Path::new(LOG_DIR).join(format!("{}-{}", topic_name, partition_id)))?;
+    }
+    Ok(uuid)
+}
+
 impl MetadataFile {
+    pub fn get_configs(&self) -> impl Iterator<Item = &ConfigRecord> {
+        self.record_batches
+            .iter()
+            .flat_map(|x| &x.records)
+            .filter_map(|x| x.value.try_get_config())
+    }
+
+    /// First producer id not reserved by any broker yet.
+    pub fn next_producer_id(&self) -> i64 {
+        self.record_batches
+            .iter()
+            .flat_map(|x| &x.records)
+            .filter_map(|x| x.value.try_get_producer_ids())
+            .map(|x| x.next_producer_id)
+            .last()
+            .unwrap_or(0)
+    }
+
     pub fn get_topics(&self) -> impl Iterator<Item = &TopicRecord> {
         self.record_batches
             .iter()
@@ -62,6 +129,24 @@ impl MetadataFile {
             .filter_map(|x| x.value.try_get_topic())
     }
 
+    pub fn get_topic_by_name(&self, topic_name: &str) -> Option<&TopicRecord> {
+        self.get_topics().find(|x| x.topic_name.data == topic_name)
+    }
+
+    pub fn has_partition(&self, topic_uuid: &i128, partition_id: i32) -> bool {
+        self.get_partition(topic_uuid, partition_id).is_some()
+    }
+
+    /// The latest PartitionRecord written for the partition.
+    pub fn get_partition(&self, topic_uuid: &i128, partition_id: i32) -> Option<&PartitionRecord> {
+        self.record_batches
+            .iter()
+            .flat_map(|x| &x.records)
+            .filter_map(|x| x.value.try_get_topic_partition(topic_uuid))
+            .filter(|x| x.partition_id == partition_id)
+            .last()
+    }
+
     pub fn get_topic_partitions<'a>(
         &'a self,
         topic_uuid: &'a i128,
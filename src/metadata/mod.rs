@@ -1,5 +1,6 @@
 mod record_batch;
 pub use record_batch::*;
+#[allow(clippy::module_inception)]
 mod metadata;
 pub use metadata::*;
 mod record;
@@ -1,11 +1,16 @@
-use crate::protocol::{CompactArray, Deserialize, Partition, Serialize, TextData, VarIntUnsigned};
+use crate::protocol::{
+    get_nullable_string, get_string, put_nullable_string, put_string, CompactArray, Deserialize,
+    Partition, Serialize, TextData, VarIntUnsigned,
+};
 use bytes::{Buf, BufMut, Bytes};
 
 #[derive(Debug)]
 pub enum RecordType {
     Topic(TopicRecord),
     Partition(PartitionRecord),
+    Config(ConfigRecord),
     FeatureLevel(FeatureLevelRecord),
+    ProducerIds(ProducerIdsRecord),
     RawBytes(RawBytesRecord),
 }
 
@@ -15,7 +20,9 @@ impl RecordType {
         match record_type {
             2 => RecordType::Topic(TopicRecord::deserialize(bytes)),
             3 => RecordType::Partition(PartitionRecord::deserialize(bytes)),
+            4 => RecordType::Config(ConfigRecord::deserialize(bytes)),
             12 => RecordType::FeatureLevel(FeatureLevelRecord::deserialize(bytes)),
+            15 => RecordType::ProducerIds(ProducerIdsRecord::deserialize(bytes)),
             _ => RecordType::RawBytes(RawBytesRecord::new(bytes, length)),
         }
     }
@@ -24,11 +31,12 @@ impl RecordType {
 impl Serialize for RecordType {
     fn serialize(&self, bytes: &mut bytes::BytesMut) {
         match self {
-            // RecordType::Topic(topic) => topic.serialize(bytes),
-            // RecordType::Partition(partition) => partition.serialize(bytes),
+            RecordType::Topic(topic) => topic.serialize(bytes),
+            RecordType::Partition(partition) => partition.serialize(bytes),
+            RecordType::Config(config) => config.serialize(bytes),
             RecordType::FeatureLevel(feature_level) => feature_level.serialize(bytes),
+            RecordType::ProducerIds(producer_ids) => producer_ids.serialize(bytes),
             RecordType::RawBytes(raw_bytes) => raw_bytes.serialize(bytes),
-            _ => (),
         }
     }
 }
@@ -42,6 +50,22 @@ impl RecordType {
         }
     }
 
+    pub fn try_get_config(&self) -> Option<&ConfigRecord> {
+        if let Self::Config(config) = self {
+            Some(config)
+        } else {
+            None
+        }
+    }
+
+    pub fn try_get_producer_ids(&self) -> Option<&ProducerIdsRecord> {
+        if let Self::ProducerIds(producer_ids) = self {
+            Some(producer_ids)
+        } else {
+            None
+        }
+    }
+
     pub fn try_get_topic_partition(&self, topic_uuid: &i128) -> Option<&PartitionRecord> {
         if let Self::Partition(partition) = self {
             if partition.topic_uuid == *topic_uuid {
@@ -78,6 +102,32 @@ pub struct TopicRecord {
     _tagged_fields_count: u8,
 }
 
+impl TopicRecord {
+    pub fn new(topic_name: &str, uuid: i128) -> Self {
+        Self {
+            _frame_version: 1,
+            _record_type: 2,
+            _version: 0,
+            topic_name: TextData {
+                data: topic_name.to_owned(),
+            },
+            uuid,
+            _tagged_fields_count: 0,
+        }
+    }
+}
+
+impl Serialize for TopicRecord {
+    fn serialize(&self, bytes: &mut bytes::BytesMut) {
+        bytes.put_i8(self._frame_version);
+        bytes.put_i8(self._record_type);
+        bytes.put_i8(self._version);
+        put_string(bytes, &self.topic_name.data, true);
+        bytes.put_i128(self.uuid);
+        bytes.put_u8(self._tagged_fields_count);
+    }
+}
+
 impl Deserialize for TopicRecord {
     fn deserialize(bytes: &mut Bytes) -> Self {
         Self {
@@ -102,14 +152,34 @@ pub struct PartitionRecord {
     in_sync_replica_array: CompactArray<i32>,
     _removing_replica_array: CompactArray<i32>,
     _adding_replica_array: CompactArray<i32>,
-    leader_id: i32,
-    leader_epoch: i32,
+    pub leader_id: i32,
+    pub leader_epoch: i32,
     _partition_epoch: i32,
     _directories_array: CompactArray<i128>,
     _tagged_fields_count: u8,
 }
 
 impl PartitionRecord {
+    /// A freshly created partition led by `replicas[0]` with every replica in sync.
+    pub fn new(partition_id: i32, topic_uuid: i128, replicas: Vec<i32>) -> Self {
+        Self {
+            _frame_version: 1,
+            _record_type: 3,
+            _version: 1,
+            partition_id,
+            topic_uuid,
+            leader_id: replicas.first().copied().unwrap_or(-1),
+            leader_epoch: 0,
+            _partition_epoch: 0,
+            _directories_array: CompactArray::new(vec![0; replicas.len()]),
+            replica_array: CompactArray::new(replicas.clone()),
+            in_sync_replica_array: CompactArray::new(replicas),
+            _removing_replica_array: CompactArray::new(vec![]),
+            _adding_replica_array: CompactArray::new(vec![]),
+            _tagged_fields_count: 0,
+        }
+    }
+
     pub fn into_partition_response(&self, index: i32) -> Partition {
         Partition {
             error_code: crate::protocol::ErrorCode::NoError,
@@ -125,6 +195,25 @@ impl PartitionRecord {
     }
 }
 
+impl Serialize for PartitionRecord {
+    fn serialize(&self, bytes: &mut bytes::BytesMut) {
+        bytes.put_i8(self._frame_version);
+        bytes.put_i8(self._record_type);
+        bytes.put_i8(self._version);
+        bytes.put_i32(self.partition_id);
+        bytes.put_i128(self.topic_uuid);
+        self.replica_array.serialize(bytes);
+        self.in_sync_replica_array.serialize(bytes);
+        self._removing_replica_array.serialize(bytes);
+        self._adding_replica_array.serialize(bytes);
+        bytes.put_i32(self.leader_id);
+        bytes.put_i32(self.leader_epoch);
+        bytes.put_i32(self._partition_epoch);
+        self._directories_array.serialize(bytes);
+        bytes.put_u8(self._tagged_fields_count);
+    }
+}
+
 impl Deserialize for PartitionRecord {
     fn deserialize(bytes: &mut Bytes) -> Self {
         Self {
@@ -146,6 +235,62 @@ impl Deserialize for PartitionRecord {
     }
 }
 
+#[derive(Debug)]
+pub struct ConfigRecord {
+    frame_version: i8,
+    record_type: i8,
+    version: i8,
+    pub resource_type: i8,
+    pub resource_name: String,
+    pub name: String,
+    /// `None` removes the config from the resource.
+    pub value: Option<String>,
+    tagged_fields_count: u8,
+}
+
+impl ConfigRecord {
+    pub fn new(resource_type: i8, resource_name: &str, name: &str, value: Option<&str>) -> Self {
+        Self {
+            frame_version: 1,
+            record_type: 4,
+            version: 0,
+            resource_type,
+            resource_name: resource_name.to_owned(),
+            name: name.to_owned(),
+            value: value.map(str::to_owned),
+            tagged_fields_count: 0,
+        }
+    }
+}
+
+impl Deserialize for ConfigRecord {
+    fn deserialize(bytes: &mut Bytes) -> Self {
+        Self {
+            frame_version: bytes.get_i8(),
+            record_type: bytes.get_i8(),
+            version: bytes.get_i8(),
+            resource_type: bytes.get_i8(),
+            resource_name: get_string(bytes, true),
+            name: get_string(bytes, true),
+            value: get_nullable_string(bytes, true),
+            tagged_fields_count: bytes.get_u8(),
+        }
+    }
+}
+
+impl Serialize for ConfigRecord {
+    fn serialize(&self, bytes: &mut bytes::BytesMut) {
+        bytes.put_i8(self.frame_version);
+        bytes.put_i8(self.record_type);
+        bytes.put_i8(self.version);
+        bytes.put_i8(self.resource_type);
+        put_string(bytes, &self.resource_name, true);
+        put_string(bytes, &self.name, true);
+        put_nullable_string(bytes, self.value.as_deref(), true);
+        bytes.put_u8(self.tagged_fields_count);
+    }
+}
+
 #[derive(Debug)]
 pub struct FeatureLevelRecord {
     frame_version: i8,
@@ -191,6 +336,58 @@ impl Serialize for FeatureLevelRecord {
     }
 }
 
+/// Reserves the producer ids below `next_producer_id` for a broker.
+#[derive(Debug)]
+pub struct ProducerIdsRecord {
+    frame_version: i8,
+    record_type: i8,
+    version: i8,
+    pub broker_id: i32,
+    pub broker_epoch: i64,
+    pub next_producer_id: i64,
+    tagged_fields_count: u8,
+}
+
+impl ProducerIdsRecord {
+    pub fn new(broker_id: i32, broker_epoch: i64, next_producer_id: i64) -> Self {
+        Self {
+            frame_version: 1,
+            record_type: 15,
+            version: 0,
+            broker_id,
+            broker_epoch,
+            next_producer_id,
+            tagged_fields_count: 0,
+        }
+    }
+}
+
+impl Deserialize for ProducerIdsRecord {
+    fn deserialize(bytes: &mut Bytes) -> Self {
+        Self {
+            frame_version: bytes.get_i8(),
+            record_type: bytes.get_i8(),
+            version: bytes.get_i8(),
+            broker_id: bytes.get_i32(),
+            broker_epoch: bytes.get_i64(),
+            next_producer_id: bytes.get_i64(),
+            tagged_fields_count: bytes.get_u8(),
+        }
+    }
+}
+
+impl Serialize for ProducerIdsRecord {
+    fn serialize(&self, bytes: &mut bytes::BytesMut) {
+        bytes.put_i8(self.frame_version);
+        bytes.put_i8(self.record_type);
+        bytes.put_i8(self.version);
+        bytes.put_i32(self.broker_id);
+        bytes.put_i64(self.broker_epoch);
+        bytes.put_i64(self.next_producer_id);
+        bytes.put_u8(self.tagged_fields_count);
+    }
+}
+
 impl Deserialize for i32 {
     fn deserialize(bytes: &mut Bytes) -> Self {
         bytes.get_i32()
@@ -1,7 +1,18 @@
 use crate::protocol::{Deserialize, Serialize};
-use crate::{metadata::record::RecordType, protocol::VarIntSigned};
+use crate::{
+    metadata::record::{RawBytesRecord, RecordType},
+    protocol::VarIntSigned,
+};
 use bytes::{Buf, BufMut, Bytes, BytesMut};
 use crc32c::crc32c;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub fn now_ms() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|x| x.as_millis() as i64)
+        .unwrap_or(0)
+}
 
 #[derive(Debug)]
 pub struct RecordBatch {
@@ -19,6 +30,71 @@ pub struct RecordBatch {
     base_sequence: i32,
     pub records: Vec<Record>,
 }
+impl RecordBatch {
+    /// A new uncompressed, non-transactional batch. The base offset is
+    /// assigned by the log on append.
+    pub fn new(partition_leader_epoch: i32, records: Vec<Record>) -> Self {
+        let timestamp = now_ms();
+        Self {
+            base_offset: 0,
+            _batch_length: 0,
+            partition_leader_epoch,
+            magic_byte: 2,
+            _crc: 0,
+            attributes: 0,
+            last_offset_delta: records.len() as i32 - 1,
+            base_timestamp: timestamp,
+            max_timestamp: timestamp,
+            producer_id: -1,
+            producer_epoch: -1,
+            base_sequence: -1,
+            records,
+        }
+    }
+
+    /// A control batch holding a single transaction marker record.
+    pub fn new_control(
+        partition_leader_epoch: i32,
+        producer_id: i64,
+        producer_epoch: i16,
+        record: Record,
+    ) -> Self {
+        Self {
+            attributes: 0x30, // transactional control batch
+            producer_id,
+            producer_epoch,
+            ..Self::new(partition_leader_epoch, vec![record])
+        }
+    }
+
+    /// A transactional batch written by `producer_id`.
+    pub fn new_transactional(
+        partition_leader_epoch: i32,
+        producer_id: i64,
+        producer_epoch: i16,
+        records: Vec<Record>,
+    ) -> Self {
+        Self {
+            attributes: 0x10,
+            producer_id,
+            producer_epoch,
+            ..Self::new(partition_leader_epoch, records)
+        }
+    }
+
+    pub fn base_offset(&self) -> i64 {
+        self.base_offset
+    }
+
+    pub fn partition_leader_epoch(&self) -> i32 {
+        self.partition_leader_epoch
+    }
+
+    pub fn set_base_offset(&mut self, base_offset: i64) {
+        self.base_offset = base_offset;
+    }
+}
+
 impl Deserialize for RecordBatch {
     fn deserialize(bytes: &mut Bytes) -> Self {
         let base_offset = bytes.get_i64();
@@ -98,6 +174,52 @@ pub struct Record {
     pub value: RecordType,
     headers_length: i8,
 }
+impl Record {
+    pub fn new(offset_delta: i64, value: RecordType) -> Self {
+        let mut value_bytes = BytesMut::new();
+        value.serialize(&mut value_bytes);
+        let mut record = Self {
+            length: VarIntSigned(0),
+            attributes: 0,
+            timestamp_delta: VarIntSigned(0),
+            offset_delta: VarIntSigned(offset_delta),
+            key_length: VarIntSigned(-1),
+            key: vec![],
+            value_length: VarIntSigned(value_bytes.len() as i64),
+            value,
+            headers_length: 0,
+        };
+        let mut bytes = BytesMut::new();
+        record.serialize(&mut bytes);
+        record.length = VarIntSigned(bytes.len() as i64 - 1); // placeholder length is one byte
+        record
+    }
+}
+
+impl Record {
+    /// A data record with a raw key and value, `None` being a tombstone.
+    pub fn new_keyed(offset_delta: i64, key: Bytes, value: Option<Bytes>) -> Self {
+        let value_length = value.as_ref().map_or(-1, |x| x.len() as i64);
+        let mut record = Self {
+            length: VarIntSigned(0),
+            attributes: 0,
+            timestamp_delta: VarIntSigned(0),
+            offset_delta: VarIntSigned(offset_delta),
+            key_length: VarIntSigned(key.len() as i64),
+            key: key.to_vec(),
+            value_length: VarIntSigned(value_length),
+            value: RecordType::RawBytes(RawBytesRecord {
+                data: value.unwrap_or_default(),
+            }),
+            headers_length: 0,
+        };
+        let mut bytes = BytesMut::new();
+        record.serialize(&mut bytes);
+        record.length = VarIntSigned(bytes.len() as i64 - 1); // placeholder length is one byte
+        record
+    }
+}
+
 impl Deserialize for Record {
     fn deserialize(bytes: &mut Bytes) -> Self {
         let length = VarIntSigned::deserialize(bytes);
@@ -112,7 +234,11 @@ impl Deserialize for Record {
             }
         }
         let value_length = VarIntSigned::deserialize(bytes);
-        let value = RecordType::new(bytes, &value_length.0);
+        let value = if value_length.0 < 0 {
+            RecordType::RawBytes(RawBytesRecord { data: Bytes::new() })
+        } else {
+            RecordType::new(bytes, &value_length.0)
+        };
         let headers_length = bytes.get_i8();
         Self {
             length,
@@ -0,0 +1,225 @@
+use anyhow::Result;
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+
+use crate::{
+    config::ConfigStore,
+    coordinator::transaction_coordinator,
+    metadata::{read_cluster_metadata, MetadataFile},
+    protocol::{ErrorCode, Response},
+};
+
+use super::{
+    get_array, get_string, get_tag_buffer, put_array, put_string, put_tag_buffer, RequestHeader,
+    Serialize,
+};
+
+#[derive(Debug)]
+pub struct AddPartitionsToTxnTransaction {
+    transactional_id: String,
+    producer_id: i64,
+    producer_epoch: i16,
+    /// Set by brokers checking a partition was added before a produce.
+    verify_only: bool,
+    topics: Vec<(String, Vec<i32>)>,
+}
+
+/// Versions 0-3 carry a single transaction from a producer, version 4 a
+/// batch of them from a broker.
+#[derive(Debug)]
+pub struct AddPartitionsToTxnRequest {
+    transactions: Vec<AddPartitionsToTxnTransaction>,
+}
+
+fn get_topics(bytes: &mut Bytes, flexible: bool) -> Vec<(String, Vec<i32>)> {
+    get_array(bytes, flexible, |bytes| {
+        let name = get_string(bytes, flexible);
+        let partitions = get_array(bytes, flexible, |bytes| bytes.get_i32());
+        get_tag_buffer(bytes, flexible);
+        (name, partitions)
+    })
+}
+
+impl AddPartitionsToTxnRequest {
+    fn deserialize(bytes: &mut Bytes, version: i16, flexible: bool) -> Self {
+        let transactions = if version >= 4 {
+            get_array(bytes, flexible, |bytes| {
+                let transaction = AddPartitionsToTxnTransaction {
+                    transactional_id: get_string(bytes, flexible),
+                    producer_id: bytes.get_i64(),
+                    producer_epoch: bytes.get_i16(),
+                    verify_only: bytes.get_u8() != 0,
+                    topics: get_topics(bytes, flexible),
+                };
+                get_tag_buffer(bytes, flexible);
+                transaction
+            })
+        } else {
+            vec![AddPartitionsToTxnTransaction {
+                transactional_id: get_string(bytes, flexible),
+                producer_id: bytes.get_i64(),
+                producer_epoch: bytes.get_i16(),
+                verify_only: false,
+                topics: get_topics(bytes, flexible),
+            }]
+        };
+        get_tag_buffer(bytes, flexible);
+        Self { transactions }
+    }
+}
+
+type TopicResults = Vec<(String, Vec<(i32, ErrorCode)>)>;
+
+#[derive(Debug)]
+pub struct AddPartitionsToTxnResponse {
+    version: i16,
+    flexible: bool,
+    results: Vec<(String, TopicResults)>,
+}
+
+fn put_topic_results(bytes: &mut BytesMut, topics: &TopicResults, flexible: bool) {
+    put_array(bytes, topics, flexible, |(name, partitions), bytes| {
+        put_string(bytes, name, flexible);
+        put_array(
+            bytes,
+            partitions,
+            flexible,
+            |(partition_index, error_code), bytes| {
+                bytes.put_i32(*partition_index);
+                bytes.put_i16(error_code.clone() as i16);
+                put_tag_buffer(bytes, flexible);
+            },
+        );
+        put_tag_buffer(bytes, flexible);
+    });
+}
+
+impl Serialize for AddPartitionsToTxnResponse {
+    fn serialize(&self, bytes: &mut BytesMut) {
+        let flexible = self.flexible;
+        bytes.put_i32(0); // throttle time
+        if self.version >= 4 {
+            bytes.put_i16(ErrorCode::NoError as i16);
+            put_array(
+                bytes,
+                &self.results,
+                flexible,
+                |(transactional_id, topics), bytes| {
+                    put_string(bytes, transactional_id, flexible);
+                    put_topic_results(bytes, topics, flexible);
+                    put_tag_buffer(bytes, flexible);
+                },
+            );
+        } else if let Some((_, topics)) = self.results.first() {
+            put_topic_results(bytes, topics, flexible);
+        }
+        put_tag_buffer(bytes, flexible);
+    }
+}
+
+/// Every partition of the transaction gets the same error.
+fn with_error(topics: &[(String, Vec<i32>)], error_code: &ErrorCode) -> TopicResults {
+    topics
+        .iter()
+        .map(|(name, partitions)| {
+            let partitions = partitions
+                .iter()
+                .map(|x| (*x, error_code.clone()))
+                .collect();
+            (name.clone(), partitions)
+        })
+        .collect()
+}
+
+fn add_partitions(
+    transaction: &AddPartitionsToTxnTransaction,
+    version: i16,
+    metadata: &MetadataFile,
+    store: &ConfigStore,
+) -> TopicResults {
+    let coordinator = transaction_coordinator();
+    let partitions: Vec<(String, i32)> = transaction
+        .topics
+        .iter()
+        .flat_map(|(name, partitions)| partitions.iter().map(move |x| (name.clone(), *x)))
+        .collect();
+    if transaction.verify_only {
+        let mut results: TopicResults = vec![];
+        for (name, partition_indexes) in &transaction.topics {
+            let partitions = partition_indexes
+                .iter()
+                .map(|x| {
+                    let error_code = coordinator
+                        .verify_partition(
+                            &transaction.transactional_id,
+                            transaction.producer_id,
+                            transaction.producer_epoch,
+                            &(name.clone(), *x),
+                        )
+                        .err()
+                        .unwrap_or(ErrorCode::NoError);
+                    (*x, error_code)
+                })
+                .collect();
+            results.push((name.clone(), partitions));
+        }
+        return results;
+    }
+    // Unknown partitions fail the request; the others are not attempted.
+    let unknown = |name: &str, partition: i32| {
+        !metadata
+            .get_topic_by_name(name)
+            .is_some_and(|x| metadata.has_partition(&x.uuid, partition))
+    };
+    if partitions.iter().any(|(name, x)| unknown(name, *x)) {
+        return transaction
+            .topics
+            .iter()
+            .map(|(name, partitions)| {
+                let partitions = partitions
+                    .iter()
+                    .map(|x| match unknown(name, *x) {
+                        true => (*x, ErrorCode::UnknownTopicOrPartition),
+                        false => (*x, ErrorCode::OperationNotAttempted),
+                    })
+                    .collect();
+                (name.clone(), partitions)
+            })
+            .collect();
+    }
+    let result = coordinator.add_partitions(
+        &transaction.transactional_id,
+        transaction.producer_id,
+        transaction.producer_epoch,
+        &partitions,
+        store,
+    );
+    let error_code = match result {
+        Ok(()) => ErrorCode::NoError,
+        // PRODUCER_FENCED was introduced in v2.
+        Err(ErrorCode::ProducerFenced) if version < 2 => ErrorCode::InvalidProducerEpoch,
+        Err(x) => x,
+    };
+    with_error(&transaction.topics, &error_code)
+}
+
+pub fn add_partitions_to_txn_handler(bytes: &mut Bytes, header: RequestHeader) -> Result<Bytes> {
+    let version = header.request_api_version;
+    let flexible = header.is_flexible();
+    let req = AddPartitionsToTxnRequest::deserialize(bytes, version, flexible);
+    let metadata = read_cluster_metadata()?;
+    let store = ConfigStore::load(&metadata);
+    let results = req
+        .transactions
+        .iter()
+        .map(|x| {
+            let topics = add_partitions(x, version, &metadata, &store);
+            (x.transactional_id.clone(), topics)
+        })
+        .collect();
+    let body = AddPartitionsToTxnResponse {
+        version,
+        flexible,
+        results,
+    };
+    Ok(Response::for_request(&header, body).into())
+}
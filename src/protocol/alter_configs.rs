@@ -0,0 +1,292 @@
+use anyhow::Result;
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use std::collections::HashMap;
+
+use crate::{
+    config::{
+        broker_config_def, split_list, topic_config_def, ConfigDef, ConfigResourceType,
+        ConfigStore, ConfigType,
+    },
+    metadata::{append_cluster_metadata, read_cluster_metadata, ConfigRecord, RecordType},
+    protocol::{ErrorCode, Response},
+};
+
+use super::{
+    get_array, get_nullable_string, get_string, get_tag_buffer, put_array, put_nullable_string,
+    put_string, put_tag_buffer, validate_resource, RequestHeader, Serialize,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AlterConfigOp {
+    Set,
+    Delete,
+    Append,
+    Subtract,
+}
+
+impl TryFrom<i8> for AlterConfigOp {
+    type Error = i8;
+
+    fn try_from(value: i8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(Self::Set),
+            1 => Ok(Self::Delete),
+            2 => Ok(Self::Append),
+            3 => Ok(Self::Subtract),
+            _ => Err(value),
+        }
+    }
+}
+
+/// Shared request shape of AlterConfigs and IncrementalAlterConfigs. The
+/// legacy API always replaces the resource's configs with a SET of each entry.
+#[derive(Debug)]
+pub struct AlterConfigsRequest {
+    resources: Vec<AlterConfigsResource>,
+    validate_only: bool,
+}
+
+#[derive(Debug)]
+pub struct AlterConfigsResource {
+    resource_type: i8,
+    resource_name: String,
+    configs: Vec<AlterableConfig>,
+}
+
+#[derive(Debug)]
+pub struct AlterableConfig {
+    name: String,
+    operation: i8,
+    value: Option<String>,
+}
+
+impl AlterConfigsRequest {
+    fn deserialize(bytes: &mut Bytes, flexible: bool, incremental: bool) -> Self {
+        let resources = get_array(bytes, flexible, |bytes| {
+            let resource_type = bytes.get_i8();
+            let resource_name = get_string(bytes, flexible);
+            let configs = get_array(bytes, flexible, |bytes| {
+                let name = get_string(bytes, flexible);
+                let operation = if incremental { bytes.get_i8() } else { 0 };
+                let value = get_nullable_string(bytes, flexible);
+                get_tag_buffer(bytes, flexible);
+                AlterableConfig {
+                    name,
+                    operation,
+                    value,
+                }
+            });
+            get_tag_buffer(bytes, flexible);
+            AlterConfigsResource {
+                resource_type,
+                resource_name,
+                configs,
+            }
+        });
+        let validate_only = bytes.get_i8() != 0;
+        get_tag_buffer(bytes, flexible);
+        Self {
+            resources,
+            validate_only,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct AlterConfigsResponse {
+    flexible: bool,
+    responses: Vec<AlterConfigsResourceResponse>,
+}
+
+#[derive(Debug)]
+pub struct AlterConfigsResourceResponse {
+    error_code: ErrorCode,
+    error_message: Option<String>,
+    resource_type: i8,
+    resource_name: String,
+}
+
+impl Serialize for AlterConfigsResponse {
+    fn serialize(&self, bytes: &mut BytesMut) {
+        let flexible = self.flexible;
+        bytes.put_i32(0); // throttle time
+        put_array(bytes, &self.responses, flexible, |response, bytes| {
+            bytes.put_i16(response.error_code.clone() as i16);
+            put_nullable_string(bytes, response.error_message.as_deref(), flexible);
+            bytes.put_i8(response.resource_type);
+            put_string(bytes, &response.resource_name, flexible);
+            put_tag_buffer(bytes, flexible);
+        });
+        put_tag_buffer(bytes, flexible);
+    }
+}
+
+pub fn alter_configs_handler(bytes: &mut Bytes, header: RequestHeader) -> Result<Bytes> {
+    handle_alter_configs(bytes, header, false)
+}
+
+pub fn incremental_alter_configs_handler(
+    bytes: &mut Bytes,
+    header: RequestHeader,
+) -> Result<Bytes> {
+    handle_alter_configs(bytes, header, true)
+}
+
+fn handle_alter_configs(
+    bytes: &mut Bytes,
+    header: RequestHeader,
+    incremental: bool,
+) -> Result<Bytes> {
+    let metadata = read_cluster_metadata()?;
+    let store = ConfigStore::load(&metadata);
+    let flexible = header.is_flexible();
+    let req = AlterConfigsRequest::deserialize(bytes, flexible, incremental);
+    let mut records = vec![];
+    let mut responses = vec![];
+    for resource in req.resources.iter() {
+        let mut response = AlterConfigsResourceResponse {
+            error_code: ErrorCode::NoError,
+            error_message: None,
+            resource_type: resource.resource_type,
+            resource_name: resource.resource_name.clone(),
+        };
+        let result = validate_resource(resource.resource_type, &resource.resource_name, &metadata)
+            .and_then(|_| alter_resource(resource, &store, incremental));
+        match result {
+            Ok(changes) => records.extend(changes),
+            Err((error_code, error_message)) => {
+                response.error_code = error_code;
+                response.error_message = Some(error_message);
+            }
+        }
+        responses.push(response);
+    }
+    if !req.validate_only && !records.is_empty() {
+        append_cluster_metadata(records)?;
+    }
+    let body = AlterConfigsResponse {
+        flexible,
+        responses,
+    };
+    Ok(Response::for_request(&header, body).into())
+}
+
+/// Applies the requested operations to the resource's dynamic configs and
+/// returns the ConfigRecords describing the difference.
+fn alter_resource(
+    resource: &AlterConfigsResource,
+    store: &ConfigStore,
+    incremental: bool,
+) -> Result<Vec<RecordType>, (ErrorCode, String)> {
+    let resource_type = ConfigResourceType::try_from(resource.resource_type).unwrap();
+    let current = store.dynamic_configs(resource_type, &resource.resource_name);
+    let mut configs = if incremental {
+        current.clone()
+    } else {
+        HashMap::new()
+    };
+    for config in resource.configs.iter() {
+        let def = config_def(resource_type, &config.name)?;
+        let operation = AlterConfigOp::try_from(config.operation).map_err(|op| {
+            (
+                ErrorCode::InvalidRequest,
+                format!("Unknown config operation {}", op),
+            )
+        })?;
+        if matches!(operation, AlterConfigOp::Append | AlterConfigOp::Subtract)
+            && def.config_type != ConfigType::List
+        {
+            return Err((
+                ErrorCode::InvalidRequest,
+                format!("Config {} is not a list config", config.name),
+            ));
+        }
+        if operation == AlterConfigOp::Delete {
+            configs.remove(&config.name);
+            continue;
+        }
+        let Some(value) = config.value.as_deref() else {
+            return Err((
+                ErrorCode::InvalidRequest,
+                format!("Null value not supported for {}", config.name),
+            ));
+        };
+        let value = match operation {
+            AlterConfigOp::Append | AlterConfigOp::Subtract => {
+                let existing = match configs.get(&config.name) {
+                    Some(existing) => existing.clone(),
+                    None => effective_value(store, resource, &config.name),
+                };
+                let mut items: Vec<&str> = split_list(&existing).collect();
+                for item in split_list(value) {
+                    if operation == AlterConfigOp::Subtract {
+                        items.retain(|x| *x != item);
+                    } else if !items.contains(&item) {
+                        items.push(item);
+                    }
+                }
+                items.join(",")
+            }
+            _ => value.to_owned(),
+        };
+        if !def.is_valid(&value) {
+            return Err((
+                ErrorCode::InvalidConfig,
+                format!("Invalid value {} for configuration {}", value, config.name),
+            ));
+        }
+        configs.insert(config.name.clone(), value);
+    }
+
+    let mut records = vec![];
+    for name in current.keys().filter(|x| !configs.contains_key(*x)) {
+        records.push(RecordType::Config(ConfigRecord::new(
+            resource.resource_type,
+            &resource.resource_name,
+            name,
+            None,
+        )));
+    }
+    for (name, value) in configs.iter() {
+        if current.get(name) != Some(value) {
+            records.push(RecordType::Config(ConfigRecord::new(
+                resource.resource_type,
+                &resource.resource_name,
+                name,
+                Some(value),
+            )));
+        }
+    }
+    Ok(records)
+}
+
+fn config_def(
+    resource_type: ConfigResourceType,
+    name: &str,
+) -> Result<&'static ConfigDef, (ErrorCode, String)> {
+    let def = match resource_type {
+        ConfigResourceType::Topic => topic_config_def(name),
+        ConfigResourceType::Broker => broker_config_def(name),
+    };
+    let Some(def) = def else {
+        return Err((
+            ErrorCode::InvalidConfig,
+            format!("Unknown configuration {}", name),
+        ));
+    };
+    if resource_type == ConfigResourceType::Broker && !def.dynamic {
+        return Err((
+            ErrorCode::InvalidRequest,
+            format!("Cannot update {} dynamically", name),
+        ));
+    }
+    Ok(def)
+}
+
+fn effective_value(store: &ConfigStore, resource: &AlterConfigsResource, name: &str) -> String {
+    match ConfigResourceType::try_from(resource.resource_type) {
+        Ok(ConfigResourceType::Topic) => store.topic_config(&resource.resource_name, name),
+        _ => store.broker_config(name),
+    }
+    .unwrap_or_default()
+}
@@ -0,0 +1,134 @@
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+
+use super::{Deserialize, Serialize, VarIntUnsigned};
+
+// Helpers for APIs that span both the classic and the flexible (compact,
+// tagged) encodings. `flexible` is decided per request from `Api::is_flexible`.
+
+pub fn get_string(bytes: &mut Bytes, flexible: bool) -> String {
+    get_nullable_string(bytes, flexible).unwrap_or_default()
+}
+
+pub fn get_nullable_string(bytes: &mut Bytes, flexible: bool) -> Option<String> {
+    let length = if flexible {
+        VarIntUnsigned::deserialize(bytes).0 as i64 - 1
+    } else {
+        bytes.get_i16() as i64
+    };
+    if length < 0 {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&bytes.copy_to_bytes(length as usize)).to_string())
+}
+
+pub fn put_string(bytes: &mut BytesMut, value: &str, flexible: bool) {
+    put_nullable_string(bytes, Some(value), flexible);
+}
+
+pub fn put_nullable_string(bytes: &mut BytesMut, value: Option<&str>, flexible: bool) {
+    match (value, flexible) {
+        (Some(value), true) => {
+            VarIntUnsigned(value.len() as u64 + 1).serialize(bytes);
+            bytes.put_slice(value.as_bytes());
+        }
+        (Some(value), false) => {
+            bytes.put_i16(value.len() as i16);
+            bytes.put_slice(value.as_bytes());
+        }
+        (None, true) => VarIntUnsigned(0).serialize(bytes),
+        (None, false) => bytes.put_i16(-1),
+    }
+}
+
+pub fn get_bytes(bytes: &mut Bytes, flexible: bool) -> Bytes {
+    get_nullable_bytes(bytes, flexible).unwrap_or_default()
+}
+
+pub fn get_nullable_bytes(bytes: &mut Bytes, flexible: bool) -> Option<Bytes> {
+    let length = if flexible {
+        VarIntUnsigned::deserialize(bytes).0 as i64 - 1
+    } else {
+        bytes.get_i32() as i64
+    };
+    if length < 0 {
+        return None;
+    }
+    Some(bytes.copy_to_bytes(length as usize))
+}
+
+pub fn put_bytes(bytes: &mut BytesMut, value: &[u8], flexible: bool) {
+    put_nullable_bytes(bytes, Some(value), flexible);
+}
+
+pub fn put_nullable_bytes(bytes: &mut BytesMut, value: Option<&[u8]>, flexible: bool) {
+    match (value, flexible) {
+        (Some(value), true) => {
+            VarIntUnsigned(value.len() as u64 + 1).serialize(bytes);
+            bytes.put_slice(value);
+        }
+        (Some(value), false) => {
+            bytes.put_i32(value.len() as i32);
+            bytes.put_slice(value);
+        }
+        (None, true) => VarIntUnsigned(0).serialize(bytes),
+        (None, false) => bytes.put_i32(-1),
+    }
+}
+
+pub fn get_array<T>(
+    bytes: &mut Bytes,
+    flexible: bool,
+    item: impl FnMut(&mut Bytes) -> T,
+) -> Vec<T> {
+    get_nullable_array(bytes, flexible, item).unwrap_or_default()
+}
+
+pub fn get_nullable_array<T>(
+    bytes: &mut Bytes,
+    flexible: bool,
+    mut item: impl FnMut(&mut Bytes) -> T,
+) -> Option<Vec<T>> {
+    let length = if flexible {
+        VarIntUnsigned::deserialize(bytes).0 as i64 - 1
+    } else {
+        bytes.get_i32() as i64
+    };
+    if length < 0 {
+        return None;
+    }
+    Some((0..length).map(|_| item(bytes)).collect())
+}
+
+pub fn put_array<T>(
+    bytes: &mut BytesMut,
+    array: &[T],
+    flexible: bool,
+    mut item: impl FnMut(&T, &mut BytesMut),
+) {
+    if flexible {
+        VarIntUnsigned(array.len() as u64 + 1).serialize(bytes);
+    } else {
+        bytes.put_i32(array.len() as i32);
+    }
+    for value in array {
+        item(value, bytes);
+    }
+}
+
+pub fn get_tag_buffer(bytes: &mut Bytes, flexible: bool) {
+    if !flexible {
+        return;
+    }
+    let fields = VarIntUnsigned::deserialize(bytes).0;
+    for _ in 0..fields {
+        let _tag = VarIntUnsigned::deserialize(bytes);
+        let size = VarIntUnsigned::deserialize(bytes).0;
+        bytes.advance(size as usize);
+    }
+}
+
+pub fn put_tag_buffer(bytes: &mut BytesMut, flexible: bool) {
+    if flexible {
+        bytes.put_i8(0);
+    }
+}
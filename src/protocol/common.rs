@@ -7,27 +7,141 @@ use super::VarIntUnsigned;
 #[derive(EnumIter, PartialEq, Clone)]
 #[repr(i16)]
 pub enum Api {
-    Invalid,
+    Invalid = -1,
+    Produce = 0,
     Fetch = 1,
+    ListOffsets = 2,
+    OffsetCommit = 8,
+    OffsetFetch = 9,
+    FindCoordinator = 10,
+    JoinGroup = 11,
+    Heartbeat = 12,
+    LeaveGroup = 13,
+    SyncGroup = 14,
+    DescribeGroups = 15,
+    ListGroups = 16,
     ApiVersions = 18,
+    DeleteRecords = 21,
+    InitProducerId = 22,
+    OffsetForLeaderEpoch = 23,
+    AddPartitionsToTxn = 24,
+    AddOffsetsToTxn = 25,
+    EndTxn = 26,
+    WriteTxnMarkers = 27,
+    TxnOffsetCommit = 28,
+    DescribeConfigs = 32,
+    AlterConfigs = 33,
+    DescribeLogDirs = 35,
+    DeleteGroups = 42,
+    IncrementalAlterConfigs = 44,
+    OffsetDelete = 47,
+    DescribeCluster = 60,
+    DescribeProducers = 61,
+    DescribeTransactions = 65,
+    ListTransactions = 66,
+    ConsumerGroupHeartbeat = 68,
+    ConsumerGroupDescribe = 69,
     DescribeTopicPartitions = 75,
+    ShareGroupHeartbeat = 76,
+    ShareFetch = 78,
+    ShareAcknowledge = 79,
 }
 
 #[derive(Debug, Clone)]
 #[repr(i16)]
 pub enum ErrorCode {
+    UnknownServerError = -1,
     NoError = 0,
+    OffsetOutOfRange = 1,
+    CorruptMessage = 2,
     UnknownTopicOrPartition = 3,
+    OffsetMetadataTooLarge = 12,
+    InvalidTopicException = 17,
+    InvalidRequiredAcks = 21,
+    CoordinatorNotAvailable = 15,
+    IllegalGeneration = 22,
+    InconsistentGroupProtocol = 23,
+    InvalidGroupId = 24,
+    UnknownMemberId = 25,
+    InvalidSessionTimeout = 26,
+    RebalanceInProgress = 27,
     Unsupported = 35,
+    InvalidConfig = 40,
+    InvalidRequest = 42,
+    OutOfOrderSequenceNumber = 45,
+    InvalidProducerEpoch = 47,
+    InvalidTxnState = 48,
+    InvalidProducerIdMapping = 49,
+    InvalidTransactionTimeout = 50,
+    ConcurrentTransactions = 51,
+    OperationNotAttempted = 55,
+    KafkaStorageError = 56,
+    NonEmptyGroup = 68,
+    GroupIdNotFound = 69,
+    FetchSessionIdNotFound = 70,
+    InvalidFetchSessionEpoch = 71,
+    FencedLeaderEpoch = 74,
+    UnknownLeaderEpoch = 75,
+    UnsupportedCompressionType = 76,
+    MemberIdRequired = 79,
+    GroupMaxSizeReached = 81,
+    FencedInstanceId = 82,
+    GroupSubscribedToTopic = 86,
+    InvalidRecord = 87,
+    ProducerFenced = 90,
+    FencedMemberEpoch = 110,
+    UnreleasedInstanceId = 111,
+    UnsupportedAssignor = 112,
+    StaleMemberEpoch = 113,
     UnknownTopic = 100,
+    InconsistentTopicId = 103,
+    TransactionalIdNotFound = 105,
+    UnsupportedEndpointType = 120,
+    InvalidRecordState = 121,
+    ShareSessionNotFound = 122,
+    InvalidShareSessionEpoch = 123,
 }
 
 impl From<i16> for Api {
     fn from(value: i16) -> Self {
         match value {
+            0 => Self::Produce,
             1 => Self::Fetch,
+            2 => Self::ListOffsets,
+            8 => Self::OffsetCommit,
+            9 => Self::OffsetFetch,
+            10 => Self::FindCoordinator,
+            11 => Self::JoinGroup,
+            12 => Self::Heartbeat,
+            13 => Self::LeaveGroup,
+            14 => Self::SyncGroup,
+            15 => Self::DescribeGroups,
+            16 => Self::ListGroups,
             18 => Self::ApiVersions,
+            21 => Self::DeleteRecords,
+            22 => Self::InitProducerId,
+            23 => Self::OffsetForLeaderEpoch,
+            24 => Self::AddPartitionsToTxn,
+            25 => Self::AddOffsetsToTxn,
+            26 => Self::EndTxn,
+            27 => Self::WriteTxnMarkers,
+            28 => Self::TxnOffsetCommit,
+            32 => Self::DescribeConfigs,
+            33 => Self::AlterConfigs,
+            35 => Self::DescribeLogDirs,
+            42 => Self::DeleteGroups,
+            44 => Self::IncrementalAlterConfigs,
+            47 => Self::OffsetDelete,
+            60 => Self::DescribeCluster,
+            61 => Self::DescribeProducers,
+            65 => Self::DescribeTransactions,
+            66 => Self::ListTransactions,
+            68 => Self::ConsumerGroupHeartbeat,
+            69 => Self::ConsumerGroupDescribe,
             75 => Self::DescribeTopicPartitions,
+            76 => Self::ShareGroupHeartbeat,
+            78 => Self::ShareFetch,
+            79 => Self::ShareAcknowledge,
             _ => Self::Invalid,
         }
     }
@@ -40,22 +154,112 @@ impl Api {
 
     pub fn versions(&self) -> (i16, i16) {
         match self {
+            Self::Produce => (3, 11),
+            // Records are sent as stored from v4, and converted before.
             Self::Fetch => (0, 16),
+            Self::ListOffsets => (1, 7),
+            Self::OffsetCommit => (0, 8),
+            Self::OffsetFetch => (0, 8),
+            Self::FindCoordinator => (0, 6),
+            Self::JoinGroup => (0, 9),
+            Self::Heartbeat => (0, 4),
+            Self::LeaveGroup => (0, 5),
+            Self::SyncGroup => (0, 5),
+            Self::DescribeGroups => (0, 5),
+            Self::ListGroups => (0, 5),
             Self::ApiVersions => (0, 4),
+            Self::DeleteRecords => (0, 2),
+            Self::InitProducerId => (0, 5),
+            Self::OffsetForLeaderEpoch => (0, 4),
+            Self::AddPartitionsToTxn => (0, 4),
+            Self::AddOffsetsToTxn => (0, 4),
+            Self::EndTxn => (0, 4),
+            Self::WriteTxnMarkers => (0, 1),
+            Self::TxnOffsetCommit => (0, 4),
+            Self::DescribeConfigs => (0, 4),
+            Self::AlterConfigs => (0, 2),
+            Self::DescribeLogDirs => (0, 4),
+            Self::DeleteGroups => (0, 2),
+            Self::IncrementalAlterConfigs => (0, 1),
+            Self::OffsetDelete => (0, 0),
+            Self::DescribeCluster => (0, 1),
+            Self::DescribeProducers => (0, 0),
+            Self::DescribeTransactions => (0, 0),
+            Self::ListTransactions => (0, 1),
+            Self::ConsumerGroupHeartbeat => (0, 0),
+            Self::ConsumerGroupDescribe => (0, 0),
             Self::DescribeTopicPartitions => (0, 0),
+            Self::ShareGroupHeartbeat => (1, 1),
+            Self::ShareFetch => (1, 1),
+            Self::ShareAcknowledge => (1, 1),
             Self::Invalid => (0, 0),
         }
     }
+
+    /// First version using the compact encoding and tag buffers.
+    pub fn flexible_version(&self) -> i16 {
+        match self {
+            Self::Produce => 9,
+            Self::Fetch => 12,
+            Self::ListOffsets => 6,
+            Self::OffsetCommit => 8,
+            Self::OffsetFetch => 6,
+            Self::FindCoordinator => 3,
+            Self::JoinGroup => 6,
+            Self::Heartbeat => 4,
+            Self::LeaveGroup => 4,
+            Self::SyncGroup => 4,
+            Self::DescribeGroups => 5,
+            Self::ListGroups => 3,
+            Self::ApiVersions => 3,
+            Self::DeleteRecords => 2,
+            Self::InitProducerId => 2,
+            Self::OffsetForLeaderEpoch => 4,
+            Self::AddPartitionsToTxn => 3,
+            Self::AddOffsetsToTxn => 3,
+            Self::EndTxn => 3,
+            Self::WriteTxnMarkers => 1,
+            Self::TxnOffsetCommit => 3,
+            Self::DescribeConfigs => 4,
+            Self::AlterConfigs => 2,
+            Self::DescribeLogDirs => 2,
+            Self::DeleteGroups => 2,
+            Self::IncrementalAlterConfigs => 1,
+            Self::OffsetDelete => 1,
+            Self::DescribeCluster => 0,
+            Self::DescribeProducers => 0,
+            Self::DescribeTransactions => 0,
+            Self::ListTransactions => 0,
+            Self::ConsumerGroupHeartbeat => 0,
+            Self::ConsumerGroupDescribe => 0,
+            Self::DescribeTopicPartitions => 0,
+            Self::ShareGroupHeartbeat => 0,
+            Self::ShareFetch => 0,
+            Self::ShareAcknowledge => 0,
+            Self::Invalid => 0,
+        }
+    }
+
+    pub fn is_flexible(&self, version: i16) -> bool {
+        version >= self.flexible_version()
+    }
 }
 
 pub struct ClientId {
-    _id: String,
+    id: String,
+}
+
+impl ClientId {
+    pub fn id(&self) -> &str {
+        &self.id
+    }
 }
+
 impl Deserialize for ClientId {
     fn deserialize(bytes: &mut Bytes) -> Self {
-        let length = bytes.get_i16();
+        let length = bytes.get_i16().max(0);
         let id = String::from_utf8_lossy(&bytes.copy_to_bytes(length as usize)).to_string();
-        Self { _id: id }
+        Self { id }
     }
 }
 
@@ -114,6 +318,12 @@ impl Serialize for i32 {
     }
 }
 
+impl Serialize for i128 {
+    fn serialize(&self, bytes: &mut BytesMut) {
+        bytes.put_i128(*self);
+    }
+}
+
 pub trait Serialize {
     fn serialize(&self, bytes: &mut BytesMut);
 }
@@ -0,0 +1,147 @@
+use anyhow::Result;
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+
+use crate::{
+    config::ConfigStore,
+    coordinator::{consumer_group_coordinator, Assignment, ConsumerGroup, ConsumerGroupConfig},
+    metadata::read_cluster_metadata,
+    protocol::{ErrorCode, Response},
+};
+
+use super::{
+    get_array, get_string, get_tag_buffer, group_authorized_operations, put_array,
+    put_nullable_string, put_string, put_tag_buffer, RequestHeader, Serialize,
+    AUTHORIZED_OPERATIONS_OMITTED,
+};
+
+#[derive(Debug)]
+pub struct ConsumerGroupDescribeRequest {
+    group_ids: Vec<String>,
+    include_authorized_operations: bool,
+}
+
+impl ConsumerGroupDescribeRequest {
+    fn deserialize(bytes: &mut Bytes) -> Self {
+        let group_ids = get_array(bytes, true, |bytes| get_string(bytes, true));
+        let include_authorized_operations = bytes.get_i8() != 0;
+        get_tag_buffer(bytes, true);
+        Self {
+            group_ids,
+            include_authorized_operations,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ConsumerGroupDescribeResponse {
+    groups: Vec<DescribedConsumerGroup>,
+}
+
+#[derive(Debug)]
+pub struct DescribedConsumerGroup {
+    group_id: String,
+    error_code: ErrorCode,
+    error_message: Option<String>,
+    group: Option<ConsumerGroup>,
+    assignor_name: String,
+    authorized_operations: i32,
+}
+
+impl DescribedConsumerGroup {
+    fn put_assignment(&self, bytes: &mut BytesMut, assignment: &Assignment) {
+        let group = self.group.as_ref();
+        let topics: Vec<_> = assignment.iter().collect();
+        put_array(bytes, &topics, true, |(topic_id, partitions), bytes| {
+            let name = group
+                .and_then(|x| x.subscription_metadata.get(*topic_id))
+                .map_or("", |(name, _)| name.as_str());
+            bytes.put_i128(**topic_id);
+            put_string(bytes, name, true);
+            let partitions: Vec<i32> = partitions.iter().copied().collect();
+            put_array(bytes, &partitions, true, |x, bytes| bytes.put_i32(*x));
+            put_tag_buffer(bytes, true);
+        });
+        put_tag_buffer(bytes, true);
+    }
+}
+
+impl Serialize for ConsumerGroupDescribeResponse {
+    fn serialize(&self, bytes: &mut BytesMut) {
+        bytes.put_i32(0); // throttle time
+        put_array(bytes, &self.groups, true, |described, bytes| {
+            bytes.put_i16(described.error_code.clone() as i16);
+            put_nullable_string(bytes, described.error_message.as_deref(), true);
+            put_string(bytes, &described.group_id, true);
+            let group = described.group.as_ref();
+            put_string(bytes, group.map_or("", |x| x.state().name()), true);
+            bytes.put_i32(group.map_or(0, |x| x.group_epoch));
+            bytes.put_i32(group.map_or(0, |x| x.assignment_epoch));
+            put_string(bytes, &described.assignor_name, true);
+            let members: Vec<_> = group
+                .map(|x| x.members.values().collect())
+                .unwrap_or_default();
+            put_array(bytes, &members, true, |member, bytes| {
+                put_string(bytes, &member.member_id, true);
+                put_nullable_string(bytes, member.instance_id.as_deref(), true);
+                put_nullable_string(bytes, member.rack_id.as_deref(), true);
+                bytes.put_i32(member.member_epoch);
+                put_string(bytes, &member.client_id, true);
+                put_string(bytes, &member.client_host, true);
+                put_array(bytes, &member.subscribed_topic_names, true, |x, bytes| {
+                    put_string(bytes, x, true)
+                });
+                put_nullable_string(bytes, None, true); // subscribed topic regex
+                described.put_assignment(bytes, &member.assigned);
+                let target = group
+                    .and_then(|x| x.target.get(&member.member_id))
+                    .cloned()
+                    .unwrap_or_default();
+                described.put_assignment(bytes, &target);
+                put_tag_buffer(bytes, true);
+            });
+            bytes.put_i32(described.authorized_operations);
+            put_tag_buffer(bytes, true);
+        });
+        put_tag_buffer(bytes, true);
+    }
+}
+
+pub fn consumer_group_describe_handler(bytes: &mut Bytes, header: RequestHeader) -> Result<Bytes> {
+    let metadata = read_cluster_metadata()?;
+    let config = ConsumerGroupConfig::load(&ConfigStore::load(&metadata));
+    let default_assignor = config.assignors.first().cloned().unwrap_or_default();
+    let req = ConsumerGroupDescribeRequest::deserialize(bytes);
+    let authorized_operations = if req.include_authorized_operations {
+        group_authorized_operations()
+    } else {
+        AUTHORIZED_OPERATIONS_OMITTED
+    };
+    let groups = req
+        .group_ids
+        .into_iter()
+        .map(|group_id| {
+            let group = consumer_group_coordinator().describe_group(&group_id, &metadata);
+            let (error_code, error_message) = if group.is_some() {
+                (ErrorCode::NoError, None)
+            } else {
+                (
+                    ErrorCode::GroupIdNotFound,
+                    Some(format!("Group {} not found.", group_id)),
+                )
+            };
+            DescribedConsumerGroup {
+                assignor_name: group
+                    .as_ref()
+                    .map(|x| x.preferred_assignor(&default_assignor))
+                    .unwrap_or_default(),
+                group_id,
+                error_code,
+                error_message,
+                group,
+                authorized_operations,
+            }
+        })
+        .collect();
+    let body = ConsumerGroupDescribeResponse { groups };
+    Ok(Response::for_request(&header, body).into())
+}
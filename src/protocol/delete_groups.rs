@@ -0,0 +1,82 @@
+use anyhow::Result;
+use bytes::{BufMut, Bytes, BytesMut};
+
+use crate::{
+    coordinator::{consumer_group_coordinator, group_coordinator, offset_store},
+    protocol::{ErrorCode, Response},
+};
+
+use super::{
+    get_array, get_string, get_tag_buffer, put_array, put_string, put_tag_buffer, RequestHeader,
+    Serialize,
+};
+
+#[derive(Debug)]
+pub struct DeleteGroupsRequest {
+    groups_names: Vec<String>,
+}
+
+impl DeleteGroupsRequest {
+    fn deserialize(bytes: &mut Bytes, flexible: bool) -> Self {
+        let groups_names = get_array(bytes, flexible, |bytes| get_string(bytes, flexible));
+        get_tag_buffer(bytes, flexible);
+        Self { groups_names }
+    }
+}
+
+#[derive(Debug)]
+pub struct DeleteGroupsResponse {
+    flexible: bool,
+    results: Vec<(String, ErrorCode)>,
+}
+
+impl Serialize for DeleteGroupsResponse {
+    fn serialize(&self, bytes: &mut BytesMut) {
+        let flexible = self.flexible;
+        bytes.put_i32(0); // throttle time
+        put_array(
+            bytes,
+            &self.results,
+            flexible,
+            |(group_id, error_code), bytes| {
+                put_string(bytes, group_id, flexible);
+                bytes.put_i16(error_code.clone() as i16);
+                put_tag_buffer(bytes, flexible);
+            },
+        );
+        put_tag_buffer(bytes, flexible);
+    }
+}
+
+pub fn delete_groups_handler(bytes: &mut Bytes, header: RequestHeader) -> Result<Bytes> {
+    let flexible = header.is_flexible();
+    let req = DeleteGroupsRequest::deserialize(bytes, flexible);
+    let results = req
+        .groups_names
+        .into_iter()
+        .map(|group_id| {
+            let error_code = delete_group(&group_id);
+            (group_id, error_code)
+        })
+        .collect();
+    let body = DeleteGroupsResponse { flexible, results };
+    Ok(Response::for_request(&header, body).into())
+}
+
+fn delete_group(group_id: &str) -> ErrorCode {
+    if group_id.is_empty() {
+        return ErrorCode::InvalidGroupId;
+    }
+    if consumer_group_coordinator().contains(group_id) {
+        let result = consumer_group_coordinator()
+            .delete_group(group_id, || offset_store().delete_group(group_id));
+        return result.err().unwrap_or(ErrorCode::NoError);
+    }
+    if !group_coordinator().contains(group_id) && !offset_store().has_group(group_id) {
+        return ErrorCode::GroupIdNotFound;
+    }
+    match group_coordinator().delete_group(group_id, || offset_store().delete_group(group_id)) {
+        Ok(()) => ErrorCode::NoError,
+        Err(error_code) => error_code,
+    }
+}
@@ -0,0 +1,154 @@
+use anyhow::Result;
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+
+use crate::{
+    metadata::{read_cluster_metadata, MetadataFile},
+    protocol::{ErrorCode, Response},
+    storage::PartitionLog,
+};
+
+use super::{
+    get_array, get_string, get_tag_buffer, put_array, put_string, put_tag_buffer, RequestHeader,
+    Serialize,
+};
+
+/// Offset sentinel asking to delete everything up to the high watermark.
+const HIGH_WATERMARK: i64 = -1;
+
+#[derive(Debug)]
+pub struct DeleteRecordsRequest {
+    topics: Vec<DeleteRecordsTopic>,
+    _timeout_ms: i32,
+}
+
+#[derive(Debug)]
+pub struct DeleteRecordsTopic {
+    name: String,
+    partitions: Vec<DeleteRecordsPartition>,
+}
+
+#[derive(Debug)]
+pub struct DeleteRecordsPartition {
+    partition_index: i32,
+    offset: i64,
+}
+
+impl DeleteRecordsRequest {
+    fn deserialize(bytes: &mut Bytes, flexible: bool) -> Self {
+        let topics = get_array(bytes, flexible, |bytes| {
+            let name = get_string(bytes, flexible);
+            let partitions = get_array(bytes, flexible, |bytes| {
+                let partition = DeleteRecordsPartition {
+                    partition_index: bytes.get_i32(),
+                    offset: bytes.get_i64(),
+                };
+                get_tag_buffer(bytes, flexible);
+                partition
+            });
+            get_tag_buffer(bytes, flexible);
+            DeleteRecordsTopic { name, partitions }
+        });
+        let timeout_ms = bytes.get_i32();
+        get_tag_buffer(bytes, flexible);
+        Self {
+            topics,
+            _timeout_ms: timeout_ms,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct DeleteRecordsResponse {
+    flexible: bool,
+    topics: Vec<DeleteRecordsTopicResponse>,
+}
+
+#[derive(Debug)]
+pub struct DeleteRecordsTopicResponse {
+    name: String,
+    partitions: Vec<DeleteRecordsPartitionResponse>,
+}
+
+#[derive(Debug)]
+pub struct DeleteRecordsPartitionResponse {
+    partition_index: i32,
+    low_watermark: i64,
+    error_code: ErrorCode,
+}
+
+impl Serialize for DeleteRecordsResponse {
+    fn serialize(&self, bytes: &mut BytesMut) {
+        let flexible = self.flexible;
+        bytes.put_i32(0); // throttle time
+        put_array(bytes, &self.topics, flexible, |topic, bytes| {
+            put_string(bytes, &topic.name, flexible);
+            put_array(bytes, &topic.partitions, flexible, |partition, bytes| {
+                bytes.put_i32(partition.partition_index);
+                bytes.put_i64(partition.low_watermark);
+                bytes.put_i16(partition.error_code.clone() as i16);
+                put_tag_buffer(bytes, flexible);
+            });
+            put_tag_buffer(bytes, flexible);
+        });
+        put_tag_buffer(bytes, flexible);
+    }
+}
+
+pub fn delete_records_handler(bytes: &mut Bytes, header: RequestHeader) -> Result<Bytes> {
+    let metadata = read_cluster_metadata()?;
+    let flexible = header.is_flexible();
+    let req = DeleteRecordsRequest::deserialize(bytes, flexible);
+    let topics = req
+        .topics
+        .iter()
+        .map(|topic| DeleteRecordsTopicResponse {
+            name: topic.name.clone(),
+            partitions: topic
+                .partitions
+                .iter()
+                .map(|partition| partition_handler(&topic.name, partition, &metadata))
+                .collect(),
+        })
+        .collect();
+    let body = DeleteRecordsResponse { flexible, topics };
+    Ok(Response::for_request(&header, body).into())
+}
+
+fn partition_handler(
+    topic_name: &str,
+    partition: &DeleteRecordsPartition,
+    metadata: &MetadataFile,
+) -> DeleteRecordsPartitionResponse {
+    let mut response = DeleteRecordsPartitionResponse {
+        partition_index: partition.partition_index,
+        low_watermark: -1,
+        error_code: ErrorCode::NoError,
+    };
+    let known = metadata
+        .get_topic_by_name(topic_name)
+        .is_some_and(|x| metadata.has_partition(&x.uuid, partition.partition_index));
+    let mut log = match PartitionLog::open(topic_name, partition.partition_index) {
+        Ok(log) if known => log,
+        _ => {
+            response.error_code = ErrorCode::UnknownTopicOrPartition;
+            return response;
+        }
+    };
+    let Ok(high_watermark) = log.high_watermark() else {
+        response.error_code = ErrorCode::UnknownServerError;
+        return response;
+    };
+    let offset = match partition.offset {
+        HIGH_WATERMARK => high_watermark,
+        offset if offset < 0 || offset > high_watermark => {
+            response.error_code = ErrorCode::OffsetOutOfRange;
+            return response;
+        }
+        offset => offset,
+    };
+    match log.delete_records_before(offset) {
+        Ok(low_watermark) => response.low_watermark = low_watermark,
+        Err(_) => response.error_code = ErrorCode::UnknownServerError,
+    }
+    response
+}
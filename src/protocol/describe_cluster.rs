@@ -0,0 +1,127 @@
+use anyhow::Result;
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+
+use crate::{
+    config::{advertised_endpoint, cluster_id, node_id},
+    protocol::{ErrorCode, Response},
+};
+
+use super::{
+    get_tag_buffer, put_array, put_nullable_string, put_string, put_tag_buffer, RequestHeader,
+    Serialize,
+};
+
+/// Sentinel for "authorized operations were not requested".
+pub const AUTHORIZED_OPERATIONS_OMITTED: i32 = i32::MIN;
+
+/// Operations valid on the cluster resource, as AclOperation codes.
+const CLUSTER_OPERATIONS: [i32; 9] = [
+    5,  // CREATE
+    7,  // ALTER
+    8,  // DESCRIBE
+    9,  // CLUSTER_ACTION
+    10, // DESCRIBE_CONFIGS
+    11, // ALTER_CONFIGS
+    12, // IDEMPOTENT_WRITE
+    13, // CREATE_TOKENS
+    14, // DESCRIBE_TOKENS
+];
+
+const BROKER_ENDPOINT_TYPE: i8 = 1;
+
+#[derive(Debug)]
+pub struct DescribeClusterRequest {
+    include_cluster_authorized_operations: bool,
+    endpoint_type: i8,
+}
+
+impl DescribeClusterRequest {
+    fn deserialize(bytes: &mut Bytes, version: i16) -> Self {
+        let include_cluster_authorized_operations = bytes.get_i8() != 0;
+        let endpoint_type = if version >= 1 {
+            bytes.get_i8()
+        } else {
+            BROKER_ENDPOINT_TYPE
+        };
+        get_tag_buffer(bytes, true);
+        Self {
+            include_cluster_authorized_operations,
+            endpoint_type,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct DescribeClusterResponse {
+    version: i16,
+    error_code: ErrorCode,
+    error_message: Option<String>,
+    endpoint_type: i8,
+    cluster_id: String,
+    controller_id: i32,
+    brokers: Vec<DescribeClusterBroker>,
+    cluster_authorized_operations: i32,
+}
+
+#[derive(Debug)]
+pub struct DescribeClusterBroker {
+    broker_id: i32,
+    host: String,
+    port: i32,
+    rack: Option<String>,
+}
+
+impl Serialize for DescribeClusterResponse {
+    fn serialize(&self, bytes: &mut BytesMut) {
+        bytes.put_i32(0); // throttle time
+        bytes.put_i16(self.error_code.clone() as i16);
+        put_nullable_string(bytes, self.error_message.as_deref(), true);
+        if self.version >= 1 {
+            bytes.put_i8(self.endpoint_type);
+        }
+        put_string(bytes, &self.cluster_id, true);
+        bytes.put_i32(self.controller_id);
+        put_array(bytes, &self.brokers, true, |broker, bytes| {
+            bytes.put_i32(broker.broker_id);
+            put_string(bytes, &broker.host, true);
+            bytes.put_i32(broker.port);
+            put_nullable_string(bytes, broker.rack.as_deref(), true);
+            put_tag_buffer(bytes, true);
+        });
+        bytes.put_i32(self.cluster_authorized_operations);
+        put_tag_buffer(bytes, true);
+    }
+}
+
+pub fn cluster_authorized_operations() -> i32 {
+    CLUSTER_OPERATIONS.iter().fold(0, |acc, op| acc | (1 << op))
+}
+
+pub fn describe_cluster_handler(bytes: &mut Bytes, header: RequestHeader) -> Result<Bytes> {
+    let req = DescribeClusterRequest::deserialize(bytes, header.request_api_version);
+    let (host, port) = advertised_endpoint();
+    let mut body = DescribeClusterResponse {
+        version: header.request_api_version,
+        error_code: ErrorCode::NoError,
+        error_message: None,
+        endpoint_type: req.endpoint_type,
+        cluster_id: cluster_id(),
+        controller_id: node_id(),
+        brokers: vec![DescribeClusterBroker {
+            broker_id: node_id(),
+            host,
+            port,
+            rack: None,
+        }],
+        cluster_authorized_operations: AUTHORIZED_OPERATIONS_OMITTED,
+    };
+    if req.endpoint_type != BROKER_ENDPOINT_TYPE {
+        body.error_code = ErrorCode::UnsupportedEndpointType;
+        body.error_message = Some("Only broker endpoints are supported".to_owned());
+        body.brokers.clear();
+    }
+    if req.include_cluster_authorized_operations {
+        body.cluster_authorized_operations = cluster_authorized_operations();
+    }
+    Ok(Response::for_request(&header, body).into())
+}
@@ -0,0 +1,203 @@
+use anyhow::Result;
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+
+use crate::{
+    config::{node_id, ConfigEntry, ConfigResourceType, ConfigSource, ConfigStore},
+    metadata::{read_cluster_metadata, MetadataFile},
+    protocol::{ErrorCode, Response},
+};
+
+use super::{
+    get_array, get_nullable_array, get_string, get_tag_buffer, put_array, put_nullable_string,
+    put_string, put_tag_buffer, RequestHeader, Serialize,
+};
+
+#[derive(Debug)]
+pub struct DescribeConfigsRequest {
+    resources: Vec<DescribeConfigsResource>,
+    include_synonyms: bool,
+    include_documentation: bool,
+}
+
+#[derive(Debug)]
+pub struct DescribeConfigsResource {
+    resource_type: i8,
+    resource_name: String,
+    configuration_keys: Option<Vec<String>>,
+}
+
+impl DescribeConfigsRequest {
+    fn deserialize(bytes: &mut Bytes, version: i16, flexible: bool) -> Self {
+        let resources = get_array(bytes, flexible, |bytes| {
+            let resource_type = bytes.get_i8();
+            let resource_name = get_string(bytes, flexible);
+            // A null key array asks for every config of the resource.
+            let configuration_keys =
+                get_nullable_array(bytes, flexible, |bytes| get_string(bytes, flexible));
+            get_tag_buffer(bytes, flexible);
+            DescribeConfigsResource {
+                resource_type,
+                resource_name,
+                configuration_keys,
+            }
+        });
+        let include_synonyms = version >= 1 && bytes.get_i8() != 0;
+        let include_documentation = version >= 3 && bytes.get_i8() != 0;
+        get_tag_buffer(bytes, flexible);
+        Self {
+            resources,
+            include_synonyms,
+            include_documentation,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct DescribeConfigsResponse {
+    version: i16,
+    flexible: bool,
+    include_synonyms: bool,
+    include_documentation: bool,
+    results: Vec<DescribeConfigsResult>,
+}
+
+#[derive(Debug)]
+pub struct DescribeConfigsResult {
+    error_code: ErrorCode,
+    error_message: Option<String>,
+    resource_type: i8,
+    resource_name: String,
+    configs: Vec<ConfigEntry>,
+}
+
+impl Serialize for DescribeConfigsResponse {
+    fn serialize(&self, bytes: &mut BytesMut) {
+        let (version, flexible) = (self.version, self.flexible);
+        bytes.put_i32(0); // throttle time
+        put_array(bytes, &self.results, flexible, |result, bytes| {
+            bytes.put_i16(result.error_code.clone() as i16);
+            put_nullable_string(bytes, result.error_message.as_deref(), flexible);
+            bytes.put_i8(result.resource_type);
+            put_string(bytes, &result.resource_name, flexible);
+            put_array(bytes, &result.configs, flexible, |config, bytes| {
+                put_string(bytes, &config.name, flexible);
+                put_nullable_string(bytes, Some(&config.value), flexible);
+                bytes.put_i8(config.read_only as i8);
+                if version == 0 {
+                    bytes.put_i8((config.source == ConfigSource::Default) as i8);
+                } else {
+                    bytes.put_i8(config.source as i8);
+                }
+                bytes.put_i8(0); // is sensitive
+                if version >= 1 {
+                    let synonyms = if self.include_synonyms {
+                        &config.synonyms[..]
+                    } else {
+                        &[]
+                    };
+                    put_array(bytes, synonyms, flexible, |synonym, bytes| {
+                        put_string(bytes, &synonym.name, flexible);
+                        put_nullable_string(bytes, Some(&synonym.value), flexible);
+                        bytes.put_i8(synonym.source as i8);
+                        put_tag_buffer(bytes, flexible);
+                    });
+                }
+                if version >= 3 {
+                    bytes.put_i8(config.def.config_type as i8);
+                    let documentation =
+                        Some(config.def.documentation).filter(|_| self.include_documentation);
+                    put_nullable_string(bytes, documentation, flexible);
+                }
+                put_tag_buffer(bytes, flexible);
+            });
+            put_tag_buffer(bytes, flexible);
+        });
+        put_tag_buffer(bytes, flexible);
+    }
+}
+
+pub fn describe_configs_handler(bytes: &mut Bytes, header: RequestHeader) -> Result<Bytes> {
+    let metadata = read_cluster_metadata()?;
+    let store = ConfigStore::load(&metadata);
+    let flexible = header.is_flexible();
+    let req = DescribeConfigsRequest::deserialize(bytes, header.request_api_version, flexible);
+    let results = req
+        .resources
+        .iter()
+        .map(|resource| resource_handler(resource, &store, &metadata))
+        .collect();
+    let body = DescribeConfigsResponse {
+        version: header.request_api_version,
+        flexible,
+        include_synonyms: req.include_synonyms,
+        include_documentation: req.include_documentation,
+        results,
+    };
+    Ok(Response::for_request(&header, body).into())
+}
+
+fn resource_handler(
+    resource: &DescribeConfigsResource,
+    store: &ConfigStore,
+    metadata: &MetadataFile,
+) -> DescribeConfigsResult {
+    let mut result = DescribeConfigsResult {
+        error_code: ErrorCode::NoError,
+        error_message: None,
+        resource_type: resource.resource_type,
+        resource_name: resource.resource_name.clone(),
+        configs: vec![],
+    };
+    if let Err((error_code, error_message)) =
+        validate_resource(resource.resource_type, &resource.resource_name, metadata)
+    {
+        result.error_code = error_code;
+        result.error_message = Some(error_message);
+        return result;
+    }
+    let resource_type = ConfigResourceType::try_from(resource.resource_type).unwrap();
+    result.configs = store
+        .describe(resource_type, &resource.resource_name)
+        .into_iter()
+        .filter(|config| {
+            resource
+                .configuration_keys
+                .as_ref()
+                .map_or(true, |keys| keys.contains(&config.name))
+        })
+        .collect();
+    result
+}
+
+/// Checks that a config resource exists on this broker.
+pub fn validate_resource(
+    resource_type: i8,
+    resource_name: &str,
+    metadata: &MetadataFile,
+) -> Result<(), (ErrorCode, String)> {
+    match ConfigResourceType::try_from(resource_type) {
+        Ok(ConfigResourceType::Topic) => {
+            if metadata.get_topic_by_name(resource_name).is_none() {
+                return Err((
+                    ErrorCode::UnknownTopicOrPartition,
+                    format!("Topic {} does not exist", resource_name),
+                ));
+            }
+        }
+        Ok(ConfigResourceType::Broker) => {
+            if !resource_name.is_empty() && resource_name != node_id().to_string() {
+                return Err((
+                    ErrorCode::InvalidRequest,
+                    format!("Unexpected broker id {}", resource_name),
+                ));
+            }
+        }
+        Err(resource_type) => {
+            return Err((
+                ErrorCode::InvalidRequest,
+                format!("Unsupported resource type {}", resource_type),
+            ));
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,153 @@
+use anyhow::Result;
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+
+use crate::{
+    coordinator::{
+        group_coordinator, offset_store, ConsumerProtocolAssignment, ConsumerProtocolSubscription,
+        GroupState, GroupSummary, CONSUMER_PROTOCOL_TYPE,
+    },
+    protocol::{ErrorCode, Response},
+};
+
+use super::{
+    get_array, get_string, get_tag_buffer, put_array, put_bytes, put_nullable_string, put_string,
+    put_tag_buffer, RequestHeader, Serialize, AUTHORIZED_OPERATIONS_OMITTED,
+};
+
+/// Operations on a group resource: READ, DELETE and DESCRIBE.
+const GROUP_OPERATIONS: [i32; 3] = [3, 6, 8];
+/// State reported for groups that do not exist.
+const DEAD_GROUP_STATE: &str = "Dead";
+
+#[derive(Debug)]
+pub struct DescribeGroupsRequest {
+    groups: Vec<String>,
+    include_authorized_operations: bool,
+}
+
+impl DescribeGroupsRequest {
+    fn deserialize(bytes: &mut Bytes, version: i16, flexible: bool) -> Self {
+        let groups = get_array(bytes, flexible, |bytes| get_string(bytes, flexible));
+        let include_authorized_operations = version >= 3 && bytes.get_i8() != 0;
+        get_tag_buffer(bytes, flexible);
+        Self {
+            groups,
+            include_authorized_operations,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct DescribeGroupsResponse {
+    version: i16,
+    flexible: bool,
+    groups: Vec<DescribedGroup>,
+}
+
+#[derive(Debug)]
+pub struct DescribedGroup {
+    error_code: ErrorCode,
+    group_id: String,
+    /// `None` for groups that do not exist.
+    summary: Option<GroupSummary>,
+    authorized_operations: i32,
+}
+
+impl Serialize for DescribeGroupsResponse {
+    fn serialize(&self, bytes: &mut BytesMut) {
+        let (version, flexible) = (self.version, self.flexible);
+        if version >= 1 {
+            bytes.put_i32(0); // throttle time
+        }
+        put_array(bytes, &self.groups, flexible, |group, bytes| {
+            let summary = group.summary.as_ref();
+            bytes.put_i16(group.error_code.clone() as i16);
+            put_string(bytes, &group.group_id, flexible);
+            let state = summary.map_or(DEAD_GROUP_STATE, |x| x.state.name());
+            put_string(bytes, state, flexible);
+            put_string(bytes, summary.map_or("", |x| &x.protocol_type), flexible);
+            put_string(bytes, summary.map_or("", |x| &x.protocol_name), flexible);
+            let members = summary.map_or(&[][..], |x| &x.members);
+            put_array(bytes, members, flexible, |member, bytes| {
+                put_string(bytes, &member.member_id, flexible);
+                if version >= 4 {
+                    put_nullable_string(bytes, member.group_instance_id.as_deref(), flexible);
+                }
+                put_string(bytes, &member.client_id, flexible);
+                put_string(bytes, &member.client_host, flexible);
+                put_bytes(bytes, &member.metadata, flexible);
+                put_bytes(bytes, &member.assignment, flexible);
+                put_tag_buffer(bytes, flexible);
+            });
+            if version >= 3 {
+                bytes.put_i32(group.authorized_operations);
+            }
+            put_tag_buffer(bytes, flexible);
+        });
+        put_tag_buffer(bytes, flexible);
+    }
+}
+
+pub fn group_authorized_operations() -> i32 {
+    GROUP_OPERATIONS.iter().fold(0, |acc, op| acc | (1 << op))
+}
+
+pub fn describe_groups_handler(bytes: &mut Bytes, header: RequestHeader) -> Result<Bytes> {
+    let version = header.request_api_version;
+    let flexible = header.is_flexible();
+    let req = DescribeGroupsRequest::deserialize(bytes, version, flexible);
+    let authorized_operations = if req.include_authorized_operations {
+        group_authorized_operations()
+    } else {
+        AUTHORIZED_OPERATIONS_OMITTED
+    };
+    let groups = req
+        .groups
+        .iter()
+        .map(|group_id| DescribedGroup {
+            error_code: if group_id.is_empty() {
+                ErrorCode::InvalidGroupId
+            } else {
+                ErrorCode::NoError
+            },
+            group_id: group_id.clone(),
+            summary: describe_group(group_id),
+            authorized_operations,
+        })
+        .collect();
+    let body = DescribeGroupsResponse {
+        version,
+        flexible,
+        groups,
+    };
+    Ok(Response::for_request(&header, body).into())
+}
+
+fn describe_group(group_id: &str) -> Option<GroupSummary> {
+    // Groups that only have committed offsets are empty, the rest were never
+    // created.
+    let mut summary = match group_coordinator().describe_group(group_id) {
+        Some(summary) => summary,
+        None if offset_store().has_group(group_id) => GroupSummary {
+            group_id: group_id.to_owned(),
+            state: GroupState::Empty,
+            protocol_type: String::new(),
+            protocol_name: String::new(),
+            members: vec![],
+        },
+        None => return None,
+    };
+    // Consumer metadata that does not follow the consumer protocol is
+    // reported as missing rather than passed on for clients to choke on.
+    if summary.protocol_type == CONSUMER_PROTOCOL_TYPE {
+        for member in summary.members.iter_mut() {
+            if ConsumerProtocolSubscription::decode(&member.metadata).is_none() {
+                member.metadata = Bytes::new();
+            }
+            if ConsumerProtocolAssignment::decode(&member.assignment).is_none() {
+                member.assignment = Bytes::new();
+            }
+        }
+    }
+    Some(summary)
+}
@@ -0,0 +1,179 @@
+use anyhow::Result;
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use std::path::Path;
+
+use crate::{
+    metadata::{read_cluster_metadata, MetadataFile},
+    protocol::{ErrorCode, Response},
+    storage::{disk_usage, PartitionLog, LOG_DIR},
+};
+
+use super::{
+    get_array, get_nullable_array, get_string, get_tag_buffer, put_array, put_string,
+    put_tag_buffer, RequestHeader, Serialize,
+};
+
+#[derive(Debug)]
+pub struct DescribeLogDirsRequest {
+    /// `None` describes every partition.
+    topics: Option<Vec<DescribableLogDirTopic>>,
+}
+
+#[derive(Debug)]
+pub struct DescribableLogDirTopic {
+    topic: String,
+    partitions: Vec<i32>,
+}
+
+impl DescribeLogDirsRequest {
+    fn deserialize(bytes: &mut Bytes, flexible: bool) -> Self {
+        let topics = get_nullable_array(bytes, flexible, |bytes| {
+            let topic = get_string(bytes, flexible);
+            let partitions = get_array(bytes, flexible, |bytes| bytes.get_i32());
+            get_tag_buffer(bytes, flexible);
+            DescribableLogDirTopic { topic, partitions }
+        });
+        get_tag_buffer(bytes, flexible);
+        Self { topics }
+    }
+}
+
+#[derive(Debug)]
+pub struct DescribeLogDirsResponse {
+    version: i16,
+    flexible: bool,
+    results: Vec<DescribeLogDirsResult>,
+}
+
+#[derive(Debug)]
+pub struct DescribeLogDirsResult {
+    error_code: ErrorCode,
+    log_dir: String,
+    topics: Vec<DescribeLogDirsTopic>,
+    total_bytes: i64,
+    usable_bytes: i64,
+}
+
+#[derive(Debug)]
+pub struct DescribeLogDirsTopic {
+    name: String,
+    partitions: Vec<DescribeLogDirsPartition>,
+}
+
+#[derive(Debug)]
+pub struct DescribeLogDirsPartition {
+    partition_index: i32,
+    partition_size: i64,
+    offset_lag: i64,
+}
+
+impl Serialize for DescribeLogDirsResponse {
+    fn serialize(&self, bytes: &mut BytesMut) {
+        let (version, flexible) = (self.version, self.flexible);
+        bytes.put_i32(0); // throttle time
+        if version >= 3 {
+            bytes.put_i16(ErrorCode::NoError as i16);
+        }
+        put_array(bytes, &self.results, flexible, |result, bytes| {
+            bytes.put_i16(result.error_code.clone() as i16);
+            put_string(bytes, &result.log_dir, flexible);
+            put_array(bytes, &result.topics, flexible, |topic, bytes| {
+                put_string(bytes, &topic.name, flexible);
+                put_array(bytes, &topic.partitions, flexible, |partition, bytes| {
+                    bytes.put_i32(partition.partition_index);
+                    bytes.put_i64(partition.partition_size);
+                    bytes.put_i64(partition.offset_lag);
+                    bytes.put_i8(0); // is future key
+                    put_tag_buffer(bytes, flexible);
+                });
+                put_tag_buffer(bytes, flexible);
+            });
+            if version >= 4 {
+                bytes.put_i64(result.total_bytes);
+                bytes.put_i64(result.usable_bytes);
+            }
+            put_tag_buffer(bytes, flexible);
+        });
+        put_tag_buffer(bytes, flexible);
+    }
+}
+
+pub fn describe_log_dirs_handler(bytes: &mut Bytes, header: RequestHeader) -> Result<Bytes> {
+    let metadata = read_cluster_metadata()?;
+    let flexible = header.is_flexible();
+    let req = DescribeLogDirsRequest::deserialize(bytes, flexible);
+    let requested = requested_partitions(&req, &metadata);
+    // Partitions are only ever stored in LOG_DIR, whatever `log.dirs` says.
+    let results = vec![log_dir_handler(Path::new(LOG_DIR), &requested)];
+    let body = DescribeLogDirsResponse {
+        version: header.request_api_version,
+        flexible,
+        results,
+    };
+    Ok(Response::for_request(&header, body).into())
+}
+
+/// Partitions to describe, grouped by topic.
+fn requested_partitions(
+    req: &DescribeLogDirsRequest,
+    metadata: &MetadataFile,
+) -> Vec<(String, Vec<i32>)> {
+    match &req.topics {
+        Some(topics) => topics
+            .iter()
+            .map(|x| (x.topic.clone(), x.partitions.clone()))
+            .collect(),
+        None => metadata
+            .get_topics()
+            .map(|topic| {
+                let partitions = metadata
+                    .get_topic_partitions(&topic.uuid)
+                    .map(|x| x.partition_id)
+                    .collect();
+                (topic.topic_name.data.clone(), partitions)
+            })
+            .collect(),
+    }
+}
+
+fn log_dir_handler(log_dir: &Path, requested: &[(String, Vec<i32>)]) -> DescribeLogDirsResult {
+    let mut result = DescribeLogDirsResult {
+        error_code: ErrorCode::NoError,
+        log_dir: log_dir.display().to_string(),
+        topics: vec![],
+        total_bytes: -1,
+        usable_bytes: -1,
+    };
+    match disk_usage(log_dir) {
+        Ok((total_bytes, usable_bytes)) => {
+            result.total_bytes = total_bytes;
+            result.usable_bytes = usable_bytes;
+        }
+        Err(_) => {
+            result.error_code = ErrorCode::KafkaStorageError;
+            return result;
+        }
+    }
+    for (topic, partition_ids) in requested {
+        let partitions: Vec<DescribeLogDirsPartition> = partition_ids
+            .iter()
+            .filter_map(|partition| PartitionLog::open_in(log_dir, topic, *partition).ok())
+            .filter(|log| log.exists())
+            .map(|log| {
+                let state = log.state().unwrap_or_default();
+                DescribeLogDirsPartition {
+                    partition_index: log.partition,
+                    partition_size: log.size(),
+                    offset_lag: (state.high_watermark - state.log_end_offset).max(0),
+                }
+            })
+            .collect();
+        if !partitions.is_empty() {
+            result.topics.push(DescribeLogDirsTopic {
+                name: topic.clone(),
+                partitions,
+            });
+        }
+    }
+    result
+}
@@ -0,0 +1,126 @@
+use anyhow::Result;
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+
+use crate::{
+    metadata::{read_cluster_metadata, MetadataFile},
+    protocol::{ErrorCode, Response},
+    storage::{producer_state, PartitionLog, ProducerStateEntry},
+};
+
+use super::{
+    get_array, get_string, get_tag_buffer, put_array, put_nullable_string, put_string,
+    put_tag_buffer, RequestHeader, Serialize,
+};
+
+#[derive(Debug)]
+pub struct DescribeProducersRequest {
+    topics: Vec<(String, Vec<i32>)>,
+}
+
+impl DescribeProducersRequest {
+    fn deserialize(bytes: &mut Bytes, flexible: bool) -> Self {
+        let topics = get_array(bytes, flexible, |bytes| {
+            let name = get_string(bytes, flexible);
+            let partitions = get_array(bytes, flexible, |bytes| bytes.get_i32());
+            get_tag_buffer(bytes, flexible);
+            (name, partitions)
+        });
+        get_tag_buffer(bytes, flexible);
+        Self { topics }
+    }
+}
+
+#[derive(Debug)]
+pub struct DescribeProducersPartition {
+    partition_index: i32,
+    error_code: ErrorCode,
+    active_producers: Vec<ProducerStateEntry>,
+}
+
+#[derive(Debug)]
+pub struct DescribeProducersResponse {
+    flexible: bool,
+    topics: Vec<(String, Vec<DescribeProducersPartition>)>,
+}
+
+impl Serialize for DescribeProducersResponse {
+    fn serialize(&self, bytes: &mut BytesMut) {
+        let flexible = self.flexible;
+        bytes.put_i32(0); // throttle time
+        put_array(
+            bytes,
+            &self.topics,
+            flexible,
+            |(name, partitions), bytes| {
+                put_string(bytes, name, flexible);
+                put_array(bytes, partitions, flexible, |x, bytes| {
+                    bytes.put_i32(x.partition_index);
+                    bytes.put_i16(x.error_code.clone() as i16);
+                    put_nullable_string(bytes, None, flexible);
+                    put_array(bytes, &x.active_producers, flexible, |x, bytes| {
+                        bytes.put_i64(x.producer_id);
+                        bytes.put_i32(x.producer_epoch as i32);
+                        bytes.put_i32(x.last_seq());
+                        bytes.put_i64(x.last_timestamp);
+                        bytes.put_i32(x.coordinator_epoch);
+                        bytes.put_i64(x.current_txn_first_offset.unwrap_or(-1));
+                        put_tag_buffer(bytes, flexible);
+                    });
+                    put_tag_buffer(bytes, flexible);
+                });
+                put_tag_buffer(bytes, flexible);
+            },
+        );
+        put_tag_buffer(bytes, flexible);
+    }
+}
+
+/// The producers the partition's producer state still remembers.
+fn describe_partition(
+    topic: &str,
+    partition_index: i32,
+    metadata: &MetadataFile,
+) -> DescribeProducersPartition {
+    let mut response = DescribeProducersPartition {
+        partition_index,
+        error_code: ErrorCode::NoError,
+        active_producers: vec![],
+    };
+    let exists = metadata
+        .get_topic_by_name(topic)
+        .is_some_and(|x| metadata.has_partition(&x.uuid, partition_index));
+    if !exists {
+        response.error_code = ErrorCode::UnknownTopicOrPartition;
+        return response;
+    }
+    let state = producer_state(topic, partition_index);
+    let mut state = state.lock().unwrap();
+    let synced = PartitionLog::open(topic, partition_index).and_then(|log| state.sync(&log));
+    if synced.is_err() {
+        response.error_code = ErrorCode::KafkaStorageError;
+        return response;
+    }
+    let mut producers: Vec<_> = state.producers().values().cloned().collect();
+    producers.sort_by_key(|x| x.producer_id);
+    response.active_producers = producers;
+    response
+}
+
+pub fn describe_producers_handler(bytes: &mut Bytes, header: RequestHeader) -> Result<Bytes> {
+    let flexible = header.is_flexible();
+    let req = DescribeProducersRequest::deserialize(bytes, flexible);
+    let metadata = read_cluster_metadata()?;
+    let topics = req
+        .topics
+        .iter()
+        .map(|(name, partitions)| {
+            let partitions = partitions
+                .iter()
+                .map(|x| describe_partition(name, *x, &metadata))
+                .collect();
+            (name.clone(), partitions)
+        })
+        .collect();
+    let body = DescribeProducersResponse { flexible, topics };
+    Ok(Response::for_request(&header, body).into())
+}
@@ -1,6 +1,7 @@
 use bytes::{Buf, BufMut, Bytes, BytesMut};
 
 use crate::{
+    coordinator::is_internal_topic,
     metadata::{read_cluster_metadata, MetadataFile},
     protocol::{ErrorCode, Response},
 };
@@ -132,7 +133,7 @@ fn handle_topic(topic_name: &str, metadata: &MetadataFile) -> Topic {
             error_code: ErrorCode::NoError as i16,
             topic_name: topic.topic_name.clone(),
             topic_id: topic.uuid,
-            is_internal: false,
+            is_internal: is_internal_topic(topic_name),
             partitions: CompactArray::new(partitions),
             operations: 3576,
         }
@@ -0,0 +1,86 @@
+use anyhow::Result;
+use bytes::{BufMut, Bytes, BytesMut};
+use std::collections::BTreeMap;
+
+use crate::{
+    coordinator::{transaction_coordinator, TransactionMetadata},
+    protocol::{ErrorCode, Response},
+};
+
+use super::{
+    get_array, get_string, get_tag_buffer, put_array, put_string, put_tag_buffer, RequestHeader,
+    Serialize,
+};
+
+#[derive(Debug)]
+pub struct DescribeTransactionsResponse {
+    flexible: bool,
+    transaction_states: Vec<(String, Result<TransactionMetadata, ErrorCode>)>,
+}
+
+impl Serialize for DescribeTransactionsResponse {
+    fn serialize(&self, bytes: &mut BytesMut) {
+        let flexible = self.flexible;
+        bytes.put_i32(0); // throttle time
+        put_array(
+            bytes,
+            &self.transaction_states,
+            flexible,
+            |(transactional_id, result), bytes| {
+                match result {
+                    Ok(x) => {
+                        let mut topics: BTreeMap<&str, Vec<i32>> = BTreeMap::new();
+                        for (topic, partition) in &x.partitions {
+                            topics.entry(topic).or_default().push(*partition);
+                        }
+                        let topics: Vec<_> = topics.into_iter().collect();
+                        bytes.put_i16(ErrorCode::NoError as i16);
+                        put_string(bytes, transactional_id, flexible);
+                        put_string(bytes, x.state.name(), flexible);
+                        bytes.put_i32(x.timeout_ms);
+                        bytes.put_i64(x.start_timestamp);
+                        bytes.put_i64(x.producer_id);
+                        bytes.put_i16(x.producer_epoch);
+                        put_array(bytes, &topics, flexible, |(topic, partitions), bytes| {
+                            put_string(bytes, topic, flexible);
+                            put_array(bytes, partitions, flexible, |x, bytes| bytes.put_i32(*x));
+                            put_tag_buffer(bytes, flexible);
+                        });
+                    }
+                    Err(error_code) => {
+                        bytes.put_i16(error_code.clone() as i16);
+                        put_string(bytes, transactional_id, flexible);
+                        put_string(bytes, "", flexible);
+                        bytes.put_i32(0);
+                        bytes.put_i64(-1);
+                        bytes.put_i64(-1);
+                        bytes.put_i16(-1);
+                        put_array::<(String, Vec<i32>)>(bytes, &[], flexible, |_, _| {});
+                    }
+                }
+                put_tag_buffer(bytes, flexible);
+            },
+        );
+        put_tag_buffer(bytes, flexible);
+    }
+}
+
+pub fn describe_transactions_handler(bytes: &mut Bytes, header: RequestHeader) -> Result<Bytes> {
+    let flexible = header.is_flexible();
+    let transactional_ids = get_array(bytes, flexible, |bytes| get_string(bytes, flexible));
+    get_tag_buffer(bytes, flexible);
+    let transaction_states = transactional_ids
+        .into_iter()
+        .map(|id| {
+            let result = transaction_coordinator()
+                .describe(&id)
+                .ok_or(ErrorCode::TransactionalIdNotFound);
+            (id, result)
+        })
+        .collect();
+    let body = DescribeTransactionsResponse {
+        flexible,
+        transaction_states,
+    };
+    Ok(Response::for_request(&header, body).into())
+}
@@ -0,0 +1,71 @@
+use anyhow::Result;
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+
+use crate::{
+    config::ConfigStore,
+    coordinator::transaction_coordinator,
+    metadata::read_cluster_metadata,
+    protocol::{ErrorCode, Response},
+};
+
+use super::{get_string, get_tag_buffer, put_tag_buffer, RequestHeader, Serialize};
+
+#[derive(Debug)]
+pub struct EndTxnRequest {
+    transactional_id: String,
+    producer_id: i64,
+    producer_epoch: i16,
+    committed: bool,
+}
+
+impl EndTxnRequest {
+    fn deserialize(bytes: &mut Bytes, flexible: bool) -> Self {
+        let req = Self {
+            transactional_id: get_string(bytes, flexible),
+            producer_id: bytes.get_i64(),
+            producer_epoch: bytes.get_i16(),
+            committed: bytes.get_u8() != 0,
+        };
+        get_tag_buffer(bytes, flexible);
+        req
+    }
+}
+
+#[derive(Debug)]
+pub struct EndTxnResponse {
+    flexible: bool,
+    error_code: ErrorCode,
+}
+
+impl Serialize for EndTxnResponse {
+    fn serialize(&self, bytes: &mut BytesMut) {
+        bytes.put_i32(0); // throttle time
+        bytes.put_i16(self.error_code.clone() as i16);
+        put_tag_buffer(bytes, self.flexible);
+    }
+}
+
+pub fn end_txn_handler(bytes: &mut Bytes, header: RequestHeader) -> Result<Bytes> {
+    let version = header.request_api_version;
+    let flexible = header.is_flexible();
+    let req = EndTxnRequest::deserialize(bytes, flexible);
+    let store = ConfigStore::load(&read_cluster_metadata()?);
+    let result = transaction_coordinator().end_txn(
+        &req.transactional_id,
+        req.producer_id,
+        req.producer_epoch,
+        req.committed,
+        &store,
+    );
+    let error_code = match result {
+        Ok(()) => ErrorCode::NoError,
+        // PRODUCER_FENCED was introduced in v2.
+        Err(ErrorCode::ProducerFenced) if version < 2 => ErrorCode::InvalidProducerEpoch,
+        Err(x) => x,
+    };
+    let body = EndTxnResponse {
+        flexible,
+        error_code,
+    };
+    Ok(Response::for_request(&header, body).into())
+}
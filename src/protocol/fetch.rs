@@ -1,103 +1,232 @@
-use std::collections::HashSet;
+use std::cell::Cell;
+use std::time::{Duration, Instant};
 
 use crate::{
-    metadata::{
-        read_cluster_metadata, read_partition_metadata, MetadataFile, RecordBatch, TopicRecord,
-    },
-    protocol::{CompactArray, Response, TextData},
+    config::ConfigStore,
+    metadata::{read_cluster_metadata, MetadataFile, TopicRecord},
+    protocol::{Reply, ResponseBuilder, ResponseData},
+    storage::{parse_batches, FileRange, PartitionLog, UNDEFINED_EPOCH, UNDEFINED_EPOCH_OFFSET},
 };
 use anyhow::Result;
 use bytes::{Buf, BufMut, Bytes, BytesMut};
 
-use super::{Deserialize, ErrorCode, RequestHeader, Serialize, VarIntUnsigned};
+use super::{
+    check_leader_epoch, fetch_purgatory, fetch_session_cache, get_array, get_string,
+    get_tag_buffer, put_array, put_string, put_tag_buffer, CachedPartition, DelayedOperation,
+    ErrorCode, FetchContext, PartitionOffsets, RequestHeader, RequestedPartition, Serialize,
+    VarIntUnsigned, FINAL_FETCH_SESSION_EPOCH, NO_FETCH_SESSION_ID,
+};
+
+/// Isolation level of consumers that only read committed transactions.
+pub const READ_COMMITTED: i8 = 1;
+/// First version naming topics by id rather than by name.
+const TOPIC_ID_VERSION: i16 = 13;
+/// First version whose clients read v2 record batches. Older clients get
+/// the records converted to the messages they read.
+const RECORD_BATCH_VERSION: i16 = 4;
 
 #[derive(Debug)]
 pub struct FetchRequest {
-    _max_wait_ms: i32,
-    _min_bytes: i32,
-    _max_bytes: i32,
-    _isolation_level: i8,
+    max_wait_ms: i32,
+    min_bytes: i32,
+    max_bytes: i32,
+    isolation_level: i8,
     session_id: i32,
-    _session_epoch: i32,
-    topics: CompactArray<FetchTopic>,
+    session_epoch: i32,
+    topics: Vec<FetchTopic>,
+    forgotten_topics_data: Vec<ForgottenTopic>,
 }
-impl Deserialize for FetchRequest {
-    fn deserialize(bytes: &mut bytes::Bytes) -> Self {
+
+impl FetchRequest {
+    fn deserialize(bytes: &mut Bytes, version: i16, flexible: bool) -> Self {
+        if version < 15 {
+            let _replica_id = bytes.get_i32();
+        }
+        let max_wait_ms = bytes.get_i32();
+        let min_bytes = bytes.get_i32();
+        let max_bytes = if version >= 3 {
+            bytes.get_i32()
+        } else {
+            i32::MAX
+        };
+        let isolation_level = if version >= 4 { bytes.get_i8() } else { 0 };
+        let (session_id, session_epoch) = if version >= 7 {
+            (bytes.get_i32(), bytes.get_i32())
+        } else {
+            (NO_FETCH_SESSION_ID, FINAL_FETCH_SESSION_EPOCH)
+        };
+        let topics = get_array(bytes, flexible, |bytes| {
+            let (topic_id, topic_name) = get_topic(bytes, version, flexible);
+            let partitions = get_array(bytes, flexible, |bytes| {
+                let partition = FetchTopicRequestPartition {
+                    partition_index: bytes.get_i32(),
+                    current_leader_epoch: if version >= 9 { bytes.get_i32() } else { -1 },
+                    fetch_offset: bytes.get_i64(),
+                    last_fetched_epoch: if version >= 12 { bytes.get_i32() } else { -1 },
+                    _log_start_offset: if version >= 5 { bytes.get_i64() } else { -1 },
+                    partition_max_bytes: bytes.get_i32(),
+                };
+                get_tag_buffer(bytes, flexible);
+                partition
+            });
+            get_tag_buffer(bytes, flexible);
+            FetchTopic {
+                topic_id,
+                topic_name,
+                partitions,
+            }
+        });
+        let forgotten_topics_data = if version >= 7 {
+            get_array(bytes, flexible, |bytes| {
+                let (topic_id, topic_name) = get_topic(bytes, version, flexible);
+                let topic = ForgottenTopic {
+                    topic_id,
+                    topic_name,
+                    partitions: get_array(bytes, flexible, |bytes| bytes.get_i32()),
+                };
+                get_tag_buffer(bytes, flexible);
+                topic
+            })
+        } else {
+            vec![]
+        };
+        if version >= 11 {
+            let _rack_id = get_string(bytes, flexible);
+        }
+        get_tag_buffer(bytes, flexible);
         Self {
-            _max_wait_ms: bytes.get_i32(),
-            _min_bytes: bytes.get_i32(),
-            _max_bytes: bytes.get_i32(),
-            _isolation_level: bytes.get_i8(),
-            session_id: bytes.get_i32(),
-            _session_epoch: bytes.get_i32(),
-            topics: CompactArray::<FetchTopic>::deserialize(bytes),
+            max_wait_ms,
+            min_bytes,
+            max_bytes,
+            isolation_level,
+            session_id,
+            session_epoch,
+            topics,
+            forgotten_topics_data,
         }
     }
 }
 
+/// Topics are named by id from version 13, and by name before.
+fn get_topic(bytes: &mut Bytes, version: i16, flexible: bool) -> (i128, String) {
+    if version >= TOPIC_ID_VERSION {
+        (bytes.get_i128(), String::new())
+    } else {
+        (0, get_string(bytes, flexible))
+    }
+}
+
+/// Partitions an incremental request removes from its fetch session.
 #[derive(Debug)]
-pub struct FetchTopic {
+pub struct ForgottenTopic {
     topic_id: i128,
-    partitions: CompactArray<FetchTopicRequestPartition>,
-}
-impl Deserialize for FetchTopic {
-    fn deserialize(bytes: &mut bytes::Bytes) -> Self {
-        Self {
-            topic_id: bytes.get_i128(),
-            partitions: CompactArray::<FetchTopicRequestPartition>::deserialize(bytes),
-        }
-    }
+    topic_name: String,
+    partitions: Vec<i32>,
 }
 
 #[derive(Debug)]
+pub struct FetchTopic {
+    topic_id: i128,
+    topic_name: String,
+    partitions: Vec<FetchTopicRequestPartition>,
+}
+
+#[derive(Debug, Clone)]
 pub struct FetchTopicRequestPartition {
-    partition_index: i32,
-    _current_leader_epoch: i32,
-    _fetch_offset: i64,
+    pub partition_index: i32,
+    current_leader_epoch: i32,
+    fetch_offset: i64,
+    last_fetched_epoch: i32,
     _log_start_offset: i64,
-    _partition_max_bytes: i32,
+    partition_max_bytes: i32,
 }
-impl Deserialize for FetchTopicRequestPartition {
-    fn deserialize(bytes: &mut bytes::Bytes) -> Self {
+
+#[cfg(test)]
+impl FetchTopicRequestPartition {
+    pub fn new(partition_index: i32, fetch_offset: i64) -> Self {
         Self {
-            partition_index: bytes.get_i32(),
-            _current_leader_epoch: bytes.get_i32(),
-            _fetch_offset: bytes.get_i64(),
-            _log_start_offset: bytes.get_i64(),
-            _partition_max_bytes: bytes.get_i32(),
+            partition_index,
+            current_leader_epoch: -1,
+            fetch_offset,
+            last_fetched_epoch: -1,
+            _log_start_offset: -1,
+            partition_max_bytes: 1 << 20,
         }
     }
 }
 
 #[derive(Debug)]
 pub struct FetchResponseBody {
+    version: i16,
+    flexible: bool,
     error_code: ErrorCode,
     session_id: i32,
-    responses: CompactArray<FetchTopicResponse>,
+    responses: Vec<FetchTopicResponse>,
 }
-impl Serialize for FetchResponseBody {
-    fn serialize(&self, bytes: &mut BytesMut) {
-        bytes.put_i32(0); // throttle time
-        bytes.put_i16(self.error_code.clone() as i16);
-        bytes.put_i32(self.session_id);
-        self.responses.serialize(bytes);
-        bytes.put_i8(0); // tag buffer
+impl FetchResponseBody {
+    fn write(self, response: &mut ResponseBuilder) {
+        let (version, flexible) = (self.version, self.flexible);
+        let bytes = response.bytes();
+        if version >= 1 {
+            bytes.put_i32(0); // throttle time
+        }
+        if version >= 7 {
+            bytes.put_i16(self.error_code.clone() as i16);
+            bytes.put_i32(self.session_id);
+        }
+        put_length(bytes, self.responses.len(), flexible);
+        for topic in self.responses {
+            topic.write(response, version, flexible);
+        }
+        put_tag_buffer(response.bytes(), flexible);
+    }
+}
+
+/// The length of an array whose items are written one by one.
+fn put_length(bytes: &mut BytesMut, length: usize, flexible: bool) {
+    if flexible {
+        VarIntUnsigned(length as u64 + 1).serialize(bytes);
+    } else {
+        bytes.put_i32(length as i32);
     }
 }
 
 #[derive(Debug)]
 pub struct FetchTopicResponse {
     topic_id: i128,
-    partitions: CompactArray<FetchTopicPartition>,
+    topic_name: String,
+    partitions: Vec<FetchTopicPartition>,
 }
-impl Serialize for FetchTopicResponse {
-    fn serialize(&self, bytes: &mut BytesMut) {
-        bytes.put_i128(self.topic_id);
-        self.partitions.serialize(bytes);
-        bytes.put_i8(0); // tag buffer
+impl FetchTopicResponse {
+    fn write(self, response: &mut ResponseBuilder, version: i16, flexible: bool) {
+        let bytes = response.bytes();
+        if version >= TOPIC_ID_VERSION {
+            bytes.put_i128(self.topic_id);
+        } else {
+            put_string(bytes, &self.topic_name, flexible);
+        }
+        put_length(bytes, self.partitions.len(), flexible);
+        for partition in self.partitions {
+            partition.write(response, version, flexible);
+        }
+        put_tag_buffer(response.bytes(), flexible);
     }
 }
 
+#[derive(Debug)]
+pub struct AbortedTransaction {
+    producer_id: i64,
+    first_offset: i64,
+}
+
+/// The end of the fetcher's last fetched epoch in this log, when it is
+/// behind where the fetcher is, so that the fetcher truncates to it.
+#[derive(Debug)]
+pub struct DivergingEpoch {
+    epoch: i32,
+    end_offset: i64,
+}
+
 #[derive(Debug)]
 pub struct FetchTopicPartition {
     partition_index: i32,
@@ -105,106 +234,489 @@ pub struct FetchTopicPartition {
     high_watermark: i64,
     last_stable_offset: i64,
     log_start_offset: i64,
-    aborted_transactions: CompactArray<i32>,
+    aborted_transactions: Vec<AbortedTransaction>,
     preferred_read_replica: i32,
-    record_batches: Vec<RecordBatch>,
+    records: FetchedRecords,
+    diverging_epoch: Option<DivergingEpoch>,
 }
-impl Serialize for FetchTopicPartition {
-    fn serialize(&self, bytes: &mut BytesMut) {
+
+#[derive(Debug)]
+pub enum FetchedRecords {
+    /// The batches as stored in the segments, merged where contiguous.
+    Stored(Vec<FileRange>),
+    /// v0 or v1 messages converted from the batches.
+    Converted(Bytes),
+}
+
+impl FetchedRecords {
+    fn size(&self) -> u64 {
+        match self {
+            Self::Stored(ranges) => ranges.iter().map(|x| x.size).sum(),
+            Self::Converted(messages) => messages.len() as u64,
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.size() == 0
+    }
+}
+
+impl FetchTopicPartition {
+    fn write(self, response: &mut ResponseBuilder, version: i16, flexible: bool) {
+        let records_size = self.records.size();
+        let bytes = response.bytes();
         bytes.put_i32(self.partition_index);
         bytes.put_i16(self.error_code.clone() as i16);
         bytes.put_i64(self.high_watermark);
-        bytes.put_i64(self.last_stable_offset);
-        bytes.put_i64(self.log_start_offset);
-        self.aborted_transactions.serialize(bytes);
-        bytes.put_i32(self.preferred_read_replica);
-
-        let mut record_bytes = BytesMut::new();
-        for record in &self.record_batches {
-            record.serialize(&mut record_bytes);
+        if version >= 4 {
+            bytes.put_i64(self.last_stable_offset);
+        }
+        if version >= 5 {
+            bytes.put_i64(self.log_start_offset);
+        }
+        if version >= 4 {
+            put_array(bytes, &self.aborted_transactions, flexible, |txn, bytes| {
+                bytes.put_i64(txn.producer_id);
+                bytes.put_i64(txn.first_offset);
+                put_tag_buffer(bytes, flexible);
+            });
+        }
+        if version >= 11 {
+            bytes.put_i32(self.preferred_read_replica);
+        }
+        if flexible {
+            VarIntUnsigned(records_size + 1).serialize(bytes);
+        } else {
+            bytes.put_i32(records_size as i32);
+        }
+        match self.records {
+            FetchedRecords::Stored(ranges) => {
+                for range in ranges {
+                    response.put_file(range);
+                }
+            }
+            FetchedRecords::Converted(messages) => response.bytes().put_slice(&messages),
+        }
+        match self.diverging_epoch {
+            // Tagged field 0, a struct with its own empty tag buffer.
+            Some(diverging) if flexible => {
+                let bytes = response.bytes();
+                VarIntUnsigned(1).serialize(bytes); // tagged fields
+                VarIntUnsigned(0).serialize(bytes); // tag
+                VarIntUnsigned(13).serialize(bytes); // size
+                bytes.put_i32(diverging.epoch);
+                bytes.put_i64(diverging.end_offset);
+                bytes.put_i8(0); // tag buffer
+            }
+            _ => put_tag_buffer(response.bytes(), flexible),
         }
-        let length = VarIntUnsigned(record_bytes.len() as u64 + 1);
-        length.serialize(bytes);
-        bytes.put_slice(&record_bytes);
-        bytes.put_i8(0); // tag buffer
     }
 }
 
-pub fn fetch_handler(bytes: &mut bytes::Bytes, header: RequestHeader) -> Result<Bytes> {
+thread_local! {
+    /// Counts the sessionless fetches of the connection, whose rotation is
+    /// not kept in a fetch session.
+    static SESSIONLESS_FETCHES: Cell<usize> = const { Cell::new(0) };
+}
+
+/// Bytes a fetch may still return. Until some data is returned, the first
+/// batch is returned whatever its size so that consumers make progress.
+struct FetchBudget {
+    remaining: usize,
+    fetched: usize,
+}
+
+pub fn fetch_handler(bytes: &mut Bytes, header: RequestHeader) -> Result<Reply> {
     let metadata = read_cluster_metadata().unwrap();
-    let req = FetchRequest::deserialize(bytes);
-    let mut responses = vec![];
-    for topic in req.topics.array.iter() {
-        if let Some(topic_found) = metadata.get_topics().find(|x| x.uuid == topic.topic_id) {
-            let partition_ids = topic
+    let version = header.request_api_version;
+    let flexible = header.is_flexible();
+    let req = FetchRequest::deserialize(bytes, version, flexible);
+    let deadline = Instant::now() + Duration::from_millis(req.max_wait_ms.max(0) as u64);
+    let requested: Vec<RequestedPartition> = req
+        .topics
+        .iter()
+        .flat_map(|topic| {
+            let topic_id = resolve_topic_id(&metadata, topic.topic_id, &topic.topic_name);
+            topic
                 .partitions
-                .array
                 .iter()
-                .map(|x| x.partition_index)
-                .collect::<HashSet<_>>();
-            responses.push(topic_handler(topic_found, &metadata, partition_ids));
-        } else {
-            responses.push(topic_not_found_response(topic));
+                .map(move |x| (topic_id, topic.topic_name.clone(), x.clone()))
+        })
+        .collect();
+    let forgotten: Vec<(i128, String, i32)> = req
+        .forgotten_topics_data
+        .iter()
+        .flat_map(|topic| {
+            topic
+                .partitions
+                .iter()
+                .map(|x| (topic.topic_id, topic.topic_name.clone(), *x))
+        })
+        .collect();
+    let max_slots = ConfigStore::load(&metadata)
+        .broker_config_i64("max.incremental.fetch.session.cache.slots")
+        .max(0) as usize;
+    let context = match fetch_session_cache().begin(
+        req.session_id,
+        req.session_epoch,
+        requested,
+        &forgotten,
+        max_slots,
+    ) {
+        Ok(context) => context,
+        Err(error_code) => {
+            let mut response = ResponseBuilder::for_request(&header);
+            FetchResponseBody {
+                version,
+                flexible,
+                error_code,
+                session_id: NO_FETCH_SESSION_ID,
+                responses: vec![],
+            }
+            .write(&mut response);
+            return Ok(response.finish().into());
         }
-    }
-    let response_body = FetchResponseBody {
-        error_code: ErrorCode::NoError,
-        session_id: req.session_id,
-        responses: CompactArray::new(responses),
     };
-    let response = Response::new(header.correlation_id, response_body);
-    Ok(response.into())
+    // Each request of a session or connection starts handing out its byte
+    // budget one partition further, so that busy partitions early in every
+    // request do not starve the others.
+    let rotation = if context.session_id == NO_FETCH_SESSION_ID {
+        SESSIONLESS_FETCHES.with(|x| x.replace(x.get().wrapping_add(1)))
+    } else {
+        context.rotation
+    };
+    let mut fetch = PendingFetch {
+        header,
+        metadata,
+        max_bytes: req.max_bytes,
+        min_bytes: req.min_bytes,
+        isolation_level: req.isolation_level,
+        start: rotation % context.partitions.len().max(1),
+        context,
+        deadline,
+    };
+
+    // Fetches without min_bytes of data yet wait in the purgatory for more,
+    // until max_wait_ms passes, rather than on this thread.
+    let partitions = fetch.watched_partitions();
+    let seen = fetch_purgatory().appends_to(&partitions);
+    match fetch.try_complete() {
+        Some(response) => Ok(response.into()),
+        None if Instant::now() >= deadline => Ok(fetch.force_complete().into()),
+        None => Ok(Reply::Delayed(
+            fetch_purgatory().park(fetch, partitions, seen),
+        )),
+    }
 }
 
-fn topic_handler(
-    topic_record: &TopicRecord,
-    metadata: &MetadataFile,
-    partition_ids: HashSet<i32>,
-) -> FetchTopicResponse {
-    let mut partitions = vec![];
-    let partition_iter = metadata.get_topic_partitions(&topic_record.uuid);
-    for partition in partition_iter {
-        if !partition_ids.contains(&partition.partition_id) {
-            continue;
-        }
-        let mut records = vec![];
-        if let Ok(partition_metadata) =
-            read_partition_metadata(topic_record.topic_name.data.clone(), partition.partition_id)
-        {
-            for record in partition_metadata.record_batches {
-                records.push(record);
+/// A fetch as resolved against its session, which can be read again as
+/// long as it waits for data.
+pub struct PendingFetch {
+    header: RequestHeader,
+    metadata: MetadataFile,
+    max_bytes: i32,
+    min_bytes: i32,
+    isolation_level: i8,
+    context: FetchContext,
+    /// Index of the partition that is handed out its byte budget first.
+    start: usize,
+    deadline: Instant,
+}
+
+impl DelayedOperation for PendingFetch {
+    fn deadline(&self) -> Instant {
+        self.deadline
+    }
+
+    /// The response, if the fetch has min_bytes of data. Errors and
+    /// diverging epochs complete it right away.
+    fn try_complete(&mut self) -> Option<ResponseData> {
+        let (fetched, size) = self.read();
+        let failed = fetched
+            .iter()
+            .any(|x| !matches!(x.error_code, ErrorCode::NoError) || x.diverging_epoch.is_some());
+        (failed || size >= self.min_bytes.max(0) as usize).then(|| self.respond(fetched))
+    }
+
+    /// The response with whatever data there is, once max_wait_ms passed.
+    fn force_complete(&mut self) -> ResponseData {
+        let (fetched, _) = self.read();
+        self.respond(fetched)
+    }
+}
+
+impl PendingFetch {
+    /// Topic name and partition of every partition the fetch reads.
+    pub fn watched_partitions(&self) -> Vec<(String, i32)> {
+        self.context
+            .partitions
+            .iter()
+            .filter_map(|x| {
+                let topic = resolve_topic(&self.metadata, x).ok()?;
+                Some((topic.topic_name.data.clone(), x.request.partition_index))
+            })
+            .collect()
+    }
+
+    /// Reads every partition, in request order, and the bytes read.
+    fn read(&self) -> (Vec<FetchTopicPartition>, usize) {
+        let partitions = &self.context.partitions;
+        let mut budget = FetchBudget {
+            remaining: self.max_bytes.max(0) as usize,
+            fetched: 0,
+        };
+        let mut fetched: Vec<Option<FetchTopicPartition>> =
+            partitions.iter().map(|_| None).collect();
+        for k in 0..partitions.len() {
+            let idx = (self.start + k) % partitions.len();
+            fetched[idx] = Some(fetch_partition(
+                resolve_topic(&self.metadata, &partitions[idx]),
+                &self.metadata,
+                &partitions[idx].request,
+                self.header.request_api_version,
+                self.isolation_level,
+                &mut budget,
+            ));
+        }
+        (fetched.into_iter().flatten().collect(), budget.fetched)
+    }
+
+    fn respond(&self, fetched: Vec<FetchTopicPartition>) -> ResponseData {
+        let context = &self.context;
+        let partitions = &context.partitions;
+        // Incremental responses leave out partitions that did not change.
+        let returned: Vec<PartitionOffsets> = partitions
+            .iter()
+            .zip(&fetched)
+            .map(|(cached, x)| PartitionOffsets {
+                topic_id: cached.topic_id,
+                topic_name: cached.topic_name.clone(),
+                partition_index: x.partition_index,
+                high_watermark: x.high_watermark,
+                last_stable_offset: x.last_stable_offset,
+                log_start_offset: x.log_start_offset,
+                has_data: !x.records.is_empty()
+                    || !matches!(x.error_code, ErrorCode::NoError)
+                    || x.diverging_epoch.is_some(),
+            })
+            .collect();
+        let changed = fetch_session_cache().complete(context.session_id, &returned);
+        let mut responses: Vec<FetchTopicResponse> = vec![];
+        for ((cached, partition), changed) in partitions.iter().zip(fetched).zip(changed) {
+            if context.incremental && !changed {
+                continue;
+            }
+            match responses.last_mut() {
+                Some(topic)
+                    if topic.topic_id == cached.topic_id
+                        && topic.topic_name == cached.topic_name =>
+                {
+                    topic.partitions.push(partition)
+                }
+                _ => responses.push(FetchTopicResponse {
+                    topic_id: cached.topic_id,
+                    topic_name: cached.topic_name.clone(),
+                    partitions: vec![partition],
+                }),
             }
         }
-        partitions.push(FetchTopicPartition {
-            partition_index: partition.partition_id,
+        let mut response = ResponseBuilder::for_request(&self.header);
+        FetchResponseBody {
+            version: self.header.request_api_version,
+            flexible: self.header.is_flexible(),
             error_code: ErrorCode::NoError,
-            high_watermark: 0,
-            last_stable_offset: 0,
-            log_start_offset: 0,
-            aborted_transactions: CompactArray::new(vec![]),
-            preferred_read_replica: 0,
-            record_batches: records,
-        });
+            session_id: context.session_id,
+            responses,
+        }
+        .write(&mut response);
+        response.finish()
+    }
+}
+
+/// The id of a requested topic, looked up by name for requests before v13.
+/// Unknown names resolve to 0.
+fn resolve_topic_id(metadata: &MetadataFile, topic_id: i128, topic_name: &str) -> i128 {
+    if topic_name.is_empty() {
+        return topic_id;
+    }
+    metadata
+        .get_topic_by_name(topic_name)
+        .map_or(0, |topic| topic.uuid)
+}
+
+/// The topic a session partition reads from. Partitions named by a topic
+/// whose id changed since they were added fail.
+fn resolve_topic<'a>(
+    metadata: &'a MetadataFile,
+    cached: &CachedPartition,
+) -> Result<&'a TopicRecord, ErrorCode> {
+    if cached.topic_name.is_empty() {
+        return metadata
+            .get_topics()
+            .find(|topic| topic.uuid == cached.topic_id)
+            .ok_or(ErrorCode::UnknownTopic);
     }
-    FetchTopicResponse {
-        topic_id: topic_record.uuid,
-        partitions: CompactArray::new(partitions),
+    match metadata.get_topic_by_name(&cached.topic_name) {
+        None => Err(ErrorCode::UnknownTopicOrPartition),
+        Some(topic) if cached.topic_id != 0 && topic.uuid != cached.topic_id => {
+            Err(ErrorCode::InconsistentTopicId)
+        }
+        Some(topic) => Ok(topic),
     }
 }
 
-fn topic_not_found_response(topic: &FetchTopic) -> FetchTopicResponse {
-    let partitions = vec![FetchTopicPartition {
-        partition_index: 0,
-        error_code: ErrorCode::UnknownTopic,
-        high_watermark: 0,
-        last_stable_offset: 0,
-        log_start_offset: 0,
-        aborted_transactions: CompactArray::new(vec![]),
+/// Reads the batches of a partition from the fetch offset on, within both
+/// the partition's limit and what is left of the response's.
+fn fetch_partition(
+    topic_record: Result<&TopicRecord, ErrorCode>,
+    metadata: &MetadataFile,
+    request: &FetchTopicRequestPartition,
+    version: i16,
+    isolation_level: i8,
+    budget: &mut FetchBudget,
+) -> FetchTopicPartition {
+    let mut response = FetchTopicPartition {
+        partition_index: request.partition_index,
+        error_code: ErrorCode::NoError,
+        high_watermark: -1,
+        last_stable_offset: -1,
+        log_start_offset: -1,
+        aborted_transactions: vec![],
         preferred_read_replica: 0,
-        record_batches: vec![],
-    }];
-    FetchTopicResponse {
-        topic_id: topic.topic_id,
-        partitions: CompactArray::new(partitions),
+        records: FetchedRecords::Stored(vec![]),
+        diverging_epoch: None,
+    };
+    let topic_record = match topic_record {
+        Ok(topic_record) => topic_record,
+        Err(error_code) => {
+            response.error_code = error_code;
+            return response;
+        }
+    };
+    let Some(partition_record) =
+        metadata.get_partition(&topic_record.uuid, request.partition_index)
+    else {
+        response.error_code = ErrorCode::UnknownTopicOrPartition;
+        return response;
+    };
+    if let Err(error_code) =
+        check_leader_epoch(request.current_leader_epoch, partition_record.leader_epoch)
+    {
+        response.error_code = error_code;
+        return response;
+    }
+    let opened = PartitionLog::open(&topic_record.topic_name.data, request.partition_index)
+        .and_then(|log| Ok((log.state()?, log)));
+    let Ok((state, log)) = opened else {
+        response.error_code = ErrorCode::KafkaStorageError;
+        return response;
+    };
+    // A fetcher whose last fetched epoch ends before its fetch offset here,
+    // or that we never had, has to truncate before it fetches on.
+    if request.last_fetched_epoch != UNDEFINED_EPOCH {
+        let Ok(cache) = log.leader_epoch_cache() else {
+            response.error_code = ErrorCode::KafkaStorageError;
+            return response;
+        };
+        let (epoch, end_offset) =
+            cache.end_offset_for(request.last_fetched_epoch, state.log_end_offset);
+        // Without an epoch to truncate to, the fetcher has to reset its
+        // offset.
+        if epoch == UNDEFINED_EPOCH || end_offset == UNDEFINED_EPOCH_OFFSET {
+            response.error_code = ErrorCode::OffsetOutOfRange;
+            return response;
+        }
+        if epoch < request.last_fetched_epoch || end_offset < request.fetch_offset {
+            response.high_watermark = state.high_watermark;
+            response.last_stable_offset = state.last_stable_offset;
+            response.log_start_offset = state.log_start_offset;
+            response.diverging_epoch = Some(DivergingEpoch { epoch, end_offset });
+            return response;
+        }
+    }
+    // Fetching at the high watermark is valid and returns nothing.
+    if request.fetch_offset < state.log_start_offset || request.fetch_offset > state.high_watermark
+    {
+        response.error_code = ErrorCode::OffsetOutOfRange;
+        return response;
+    }
+    response.high_watermark = state.high_watermark;
+    response.last_stable_offset = state.last_stable_offset;
+    response.log_start_offset = state.log_start_offset;
+    let limit = budget
+        .remaining
+        .min(request.partition_max_bytes.max(0) as usize);
+    let Ok(batches) = log.locate_batches(request.fetch_offset, limit) else {
+        response.error_code = ErrorCode::KafkaStorageError;
+        return response;
+    };
+    let mut ranges: Vec<FileRange> = vec![];
+    let mut size = 0;
+    let mut upper_bound = None;
+    for (info, range) in batches {
+        // Committed readers stop at the first open transaction.
+        if isolation_level == READ_COMMITTED && info.base_offset >= state.last_stable_offset {
+            break;
+        }
+        let first_batch = budget.fetched == 0 && size == 0;
+        if size + info.size > limit && !first_batch {
+            break;
+        }
+        size += info.size;
+        upper_bound = Some(info.next_offset());
+        if !ranges.last_mut().is_some_and(|last| last.merge(&range)) {
+            ranges.push(range);
+        }
+    }
+    budget.remaining = budget.remaining.saturating_sub(size);
+    budget.fetched += size;
+    if let (READ_COMMITTED, Some(to)) = (isolation_level, upper_bound) {
+        response.aborted_transactions = log
+            .aborted_txns(request.fetch_offset, to)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|x| AbortedTransaction {
+                producer_id: x.producer_id,
+                first_offset: x.first_offset,
+            })
+            .collect();
+    }
+    if version >= RECORD_BATCH_VERSION {
+        response.records = FetchedRecords::Stored(ranges);
+        return response;
+    }
+    let magic = if version >= 2 { 1 } else { 0 };
+    match down_convert(&ranges, magic) {
+        Ok(messages) => response.records = FetchedRecords::Converted(messages),
+        Err(error_code) => response.error_code = error_code,
+    }
+    response
+}
+
+/// Converts batches to v0 or v1 messages, leaving out transaction markers.
+/// Compressed batches are not inflated, so conversion stops at the first.
+fn down_convert(ranges: &[FileRange], magic: i8) -> Result<Bytes, ErrorCode> {
+    let mut messages = BytesMut::new();
+    for range in ranges {
+        let data = range.read().map_err(|_| ErrorCode::KafkaStorageError)?;
+        for batch in parse_batches(&data) {
+            if batch.is_compressed() && messages.is_empty() {
+                return Err(ErrorCode::UnsupportedCompressionType);
+            }
+            if batch.is_compressed() {
+                return Ok(messages.freeze());
+            }
+            if !batch.is_control() {
+                let position = batch.position;
+                batch
+                    .write_messages(
+                        &data.slice(position..position + batch.size),
+                        magic,
+                        &mut messages,
+                    )
+                    .ok_or(ErrorCode::CorruptMessage)?;
+            }
+        }
     }
+    Ok(messages.freeze())
 }
@@ -0,0 +1,237 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, OnceLock};
+use std::thread;
+use std::time::Instant;
+
+use super::ResponseData;
+
+/// A topic name and partition index.
+type PartitionKey = (String, i32);
+
+/// Threads reading the fetches the purgatory thread hands out, so that a
+/// slow read does not hold up the other fetches.
+const READ_THREADS: usize = 4;
+
+/// A request waiting in the purgatory, like a fetch or a share fetch, which
+/// can be read again as long as it waits for data.
+pub trait DelayedOperation: Send {
+    /// When the request completes with whatever there is.
+    fn deadline(&self) -> Instant;
+
+    /// The response, if the request has enough data now.
+    fn try_complete(&mut self) -> Option<ResponseData>;
+
+    /// The response with whatever data there is, once the deadline passed.
+    fn force_complete(&mut self) -> ResponseData;
+}
+
+/// A fetch parked until data arrives on one of its partitions or its
+/// deadline passes, and where its response goes.
+struct DelayedFetch {
+    fetch: Box<dyn DelayedOperation>,
+    partitions: Vec<PartitionKey>,
+    respond: Sender<ResponseData>,
+}
+
+/// A read handed out to the read threads.
+enum ReadJob {
+    /// Read a woken fetch again, with the appends it has seen.
+    Retry(DelayedFetch, u64),
+    /// Complete a fetch whose deadline passed.
+    Expire(DelayedFetch),
+}
+
+#[derive(Default)]
+struct PurgatoryState {
+    next_id: u64,
+    fetches: HashMap<u64, DelayedFetch>,
+    /// Ids of the fetches waiting on each partition.
+    watchers: HashMap<PartitionKey, HashSet<u64>>,
+    /// Partitions appended to since the purgatory thread last looked.
+    appended: HashSet<PartitionKey>,
+    /// Appends to each partition since startup.
+    appends: HashMap<PartitionKey, u64>,
+}
+
+impl PurgatoryState {
+    fn appends_to(&self, partitions: &[PartitionKey]) -> u64 {
+        partitions.iter().filter_map(|x| self.appends.get(x)).sum()
+    }
+
+    /// Watches the partitions of a fetch that did not have enough data when
+    /// they had seen `seen` appends.
+    fn insert(&mut self, delayed: DelayedFetch, seen: u64) {
+        let id = self.next_id;
+        self.next_id += 1;
+        for key in &delayed.partitions {
+            self.watchers.entry(key.clone()).or_default().insert(id);
+        }
+        if self.appends_to(&delayed.partitions) != seen {
+            self.appended.extend(delayed.partitions.iter().cloned());
+        }
+        self.fetches.insert(id, delayed);
+    }
+
+    fn remove(&mut self, id: u64) -> Option<DelayedFetch> {
+        let delayed = self.fetches.remove(&id)?;
+        for key in &delayed.partitions {
+            if let Some(ids) = self.watchers.get_mut(key) {
+                ids.remove(&id);
+                if ids.is_empty() {
+                    self.watchers.remove(key);
+                }
+            }
+        }
+        Some(delayed)
+    }
+}
+
+/// Fetches and share fetches waiting for min_bytes of data. Appends mark their partition, and
+/// the purgatory's own thread hands the fetches watching it to the read
+/// threads to read again, or to complete with what there is once
+/// max_wait_ms passed. Handler threads never wait for data.
+#[derive(Default)]
+pub struct FetchPurgatory {
+    state: Mutex<PurgatoryState>,
+    changed: Condvar,
+}
+
+pub fn fetch_purgatory() -> &'static FetchPurgatory {
+    static PURGATORY: OnceLock<FetchPurgatory> = OnceLock::new();
+    PURGATORY.get_or_init(|| {
+        let (jobs, queue) = mpsc::channel();
+        let queue = Arc::new(Mutex::new(queue));
+        for _ in 0..READ_THREADS {
+            let queue = queue.clone();
+            thread::spawn(move || fetch_purgatory().read(&queue));
+        }
+        thread::spawn(move || fetch_purgatory().run(jobs));
+        FetchPurgatory::default()
+    })
+}
+
+impl FetchPurgatory {
+    fn lock(&self) -> MutexGuard<'_, PurgatoryState> {
+        self.state.lock().unwrap()
+    }
+
+    /// Appends to the partitions so far, read before a fetch's first attempt
+    /// so that one made before the fetch is parked is not missed.
+    pub fn appends_to(&self, partitions: &[PartitionKey]) -> u64 {
+        self.lock().appends_to(partitions)
+    }
+
+    /// Parks a fetch that did not have enough data after `seen` appends to
+    /// its partitions. Returns where its response arrives.
+    pub fn park(
+        &self,
+        fetch: impl DelayedOperation + 'static,
+        partitions: Vec<PartitionKey>,
+        seen: u64,
+    ) -> Receiver<ResponseData> {
+        let (respond, response) = mpsc::channel();
+        let delayed = DelayedFetch {
+            fetch: Box::new(fetch),
+            partitions,
+            respond,
+        };
+        self.lock().insert(delayed, seen);
+        self.changed.notify_one();
+        response
+    }
+
+    /// Wakes the fetches waiting on a partition that was appended to.
+    pub fn partition_appended(&self, topic: &str, partition: i32) {
+        let mut state = self.lock();
+        let key = (topic.to_owned(), partition);
+        *state.appends.entry(key.clone()).or_default() += 1;
+        if state.watchers.contains_key(&key) {
+            state.appended.insert(key);
+            self.changed.notify_one();
+        }
+    }
+
+    /// Hands out the fetches whose partitions were appended to and those
+    /// whose deadline passed to the read threads.
+    fn run(&self, jobs: Sender<ReadJob>) {
+        loop {
+            let (woken, expired) = self.next_batch();
+            for delayed in expired {
+                let _ = jobs.send(ReadJob::Expire(delayed));
+            }
+            for (delayed, seen) in woken {
+                let _ = jobs.send(ReadJob::Retry(delayed, seen));
+            }
+        }
+    }
+
+    /// Completes woken fetches if they have enough data now, parking them
+    /// again otherwise, and expired ones with whatever there is.
+    fn read(&self, queue: &Mutex<Receiver<ReadJob>>) {
+        loop {
+            let job = queue.lock().unwrap().recv();
+            match job {
+                Ok(ReadJob::Expire(mut delayed)) => {
+                    let _ = delayed.respond.send(delayed.fetch.force_complete());
+                }
+                Ok(ReadJob::Retry(mut delayed, seen)) => match delayed.fetch.try_complete() {
+                    Some(response) => {
+                        let _ = delayed.respond.send(response);
+                    }
+                    None => {
+                        self.lock().insert(delayed, seen);
+                        self.changed.notify_one();
+                    }
+                },
+                Err(_) => return,
+            }
+        }
+    }
+
+    /// Waits for appends to watched partitions or for a deadline, then takes
+    /// the fetches to try again, with the appends they see, and those that
+    /// expired out.
+    fn next_batch(&self) -> (Vec<(DelayedFetch, u64)>, Vec<DelayedFetch>) {
+        let mut state = self.lock();
+        loop {
+            let now = Instant::now();
+            let expired: Vec<u64> = state
+                .fetches
+                .iter()
+                .filter(|(_, x)| x.fetch.deadline() <= now)
+                .map(|(id, _)| *id)
+                .collect();
+            let appended = std::mem::take(&mut state.appended);
+            let woken: HashSet<u64> = appended
+                .iter()
+                .filter_map(|key| state.watchers.get(key))
+                .flatten()
+                .filter(|id| !expired.contains(id))
+                .copied()
+                .collect();
+            if !expired.is_empty() || !woken.is_empty() {
+                let expired = expired.into_iter().filter_map(|id| state.remove(id));
+                let expired = expired.collect();
+                let mut taken = vec![];
+                for id in woken {
+                    if let Some(delayed) = state.remove(id) {
+                        let seen = state.appends_to(&delayed.partitions);
+                        taken.push((delayed, seen));
+                    }
+                }
+                return (taken, expired);
+            }
+            let next_deadline = state.fetches.values().map(|x| x.fetch.deadline()).min();
+            state = match next_deadline {
+                Some(deadline) => {
+                    self.changed
+                        .wait_timeout(state, deadline.saturating_duration_since(now))
+                        .unwrap()
+                        .0
+                }
+                None => self.changed.wait(state).unwrap(),
+            };
+        }
+    }
+}
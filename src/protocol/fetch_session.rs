@@ -0,0 +1,434 @@
+use std::collections::HashMap;
+use std::sync::{Mutex, MutexGuard, OnceLock};
+use std::time::{Duration, Instant};
+
+use crate::metadata::random_uuid;
+
+use super::{ErrorCode, FetchTopicRequestPartition};
+
+/// Session epoch of a full fetch request opening a new session.
+pub const INITIAL_FETCH_SESSION_EPOCH: i32 = 0;
+/// Session epoch of a full fetch request that does not use a session.
+pub const FINAL_FETCH_SESSION_EPOCH: i32 = -1;
+/// Session id of requests and responses without a session.
+pub const NO_FETCH_SESSION_ID: i32 = 0;
+/// Sessions unused for this long make room for any new session.
+const SESSION_EVICTION_TIME: Duration = Duration::from_secs(120);
+
+/// A partition of a fetch session: what the client last asked for, and the
+/// offsets last returned for it.
+#[derive(Debug, Clone)]
+pub struct CachedPartition {
+    /// Requests before v13 name topics. Their partitions are keyed by name,
+    /// and keep the id the name had when they were added, or 0 if unknown.
+    pub topic_id: i128,
+    pub topic_name: String,
+    pub request: FetchTopicRequestPartition,
+    pub high_watermark: i64,
+    pub last_stable_offset: i64,
+    pub log_start_offset: i64,
+}
+
+impl CachedPartition {
+    fn new(topic_id: i128, topic_name: String, request: FetchTopicRequestPartition) -> Self {
+        Self {
+            topic_id,
+            topic_name,
+            request,
+            high_watermark: -1,
+            last_stable_offset: -1,
+            log_start_offset: -1,
+        }
+    }
+
+    fn key(&self) -> (i128, &str, i32) {
+        partition_key(
+            self.topic_id,
+            &self.topic_name,
+            self.request.partition_index,
+        )
+    }
+}
+
+/// The partitions a client fetches from, sent in full when the session opens
+/// and only as changes afterwards.
+#[derive(Debug)]
+struct FetchSession {
+    /// Epoch of the next incremental request.
+    epoch: i32,
+    partitions: Vec<CachedPartition>,
+    last_used: Instant,
+    /// Counts the session's requests, to rotate where each starts handing
+    /// out its byte budget.
+    rotation: usize,
+}
+
+/// What a fetch request reads, once its session is resolved.
+#[derive(Debug)]
+pub struct FetchContext {
+    pub session_id: i32,
+    /// Incremental responses only carry partitions that changed.
+    pub incremental: bool,
+    pub partitions: Vec<CachedPartition>,
+    /// Position of the request in its session.
+    pub rotation: usize,
+}
+
+/// Offsets returned for a session partition, and whether the response has
+/// to carry it whatever they are.
+#[derive(Debug)]
+pub struct PartitionOffsets {
+    pub topic_id: i128,
+    pub topic_name: String,
+    pub partition_index: i32,
+    pub high_watermark: i64,
+    pub last_stable_offset: i64,
+    pub log_start_offset: i64,
+    pub has_data: bool,
+}
+
+/// The incremental fetch sessions (KIP-227) this broker keeps, up to
+/// `max.incremental.fetch.session.cache.slots`.
+#[derive(Debug, Default)]
+pub struct FetchSessionCache {
+    sessions: Mutex<HashMap<i32, FetchSession>>,
+}
+
+pub fn fetch_session_cache() -> &'static FetchSessionCache {
+    static CACHE: OnceLock<FetchSessionCache> = OnceLock::new();
+    CACHE.get_or_init(FetchSessionCache::default)
+}
+
+fn next_epoch(epoch: i32) -> i32 {
+    epoch.checked_add(1).unwrap_or(1)
+}
+
+fn partition_key(topic_id: i128, topic_name: &str, partition_index: i32) -> (i128, &str, i32) {
+    if topic_name.is_empty() {
+        (topic_id, topic_name, partition_index)
+    } else {
+        (0, topic_name, partition_index)
+    }
+}
+
+/// A partition as a fetch request names it: by topic id, or by topic name
+/// and the id resolved from it.
+pub type RequestedPartition = (i128, String, FetchTopicRequestPartition);
+
+/// Adds a requested partition to `partitions`, or updates its entry.
+fn upsert(partitions: &mut Vec<CachedPartition>, requested: RequestedPartition) {
+    let (topic_id, topic_name, request) = requested;
+    let key = partition_key(topic_id, &topic_name, request.partition_index);
+    match partitions.iter_mut().find(|x| x.key() == key) {
+        Some(cached) => cached.request = request,
+        None => partitions.push(CachedPartition::new(topic_id, topic_name, request)),
+    }
+}
+
+impl FetchSessionCache {
+    fn lock(&self) -> MutexGuard<'_, HashMap<i32, FetchSession>> {
+        self.sessions.lock().unwrap()
+    }
+
+    /// Resolves the session of a fetch request and applies the changes of
+    /// incremental ones. Full requests close the session they name, and open
+    /// a new one if their epoch is the initial one and the cache has room.
+    pub fn begin(
+        &self,
+        session_id: i32,
+        epoch: i32,
+        requested: Vec<RequestedPartition>,
+        forgotten: &[(i128, String, i32)],
+        max_slots: usize,
+    ) -> Result<FetchContext, ErrorCode> {
+        let mut sessions = self.lock();
+        let now = Instant::now();
+        if epoch == INITIAL_FETCH_SESSION_EPOCH || epoch == FINAL_FETCH_SESSION_EPOCH {
+            sessions.remove(&session_id);
+            let mut partitions = vec![];
+            for requested in requested {
+                upsert(&mut partitions, requested);
+            }
+            let mut session_id = NO_FETCH_SESSION_ID;
+            if epoch == INITIAL_FETCH_SESSION_EPOCH
+                && Self::make_room(&mut sessions, partitions.len(), max_slots, now)
+            {
+                session_id = Self::new_session_id(&sessions);
+                sessions.insert(
+                    session_id,
+                    FetchSession {
+                        epoch: next_epoch(INITIAL_FETCH_SESSION_EPOCH),
+                        partitions: partitions.clone(),
+                        last_used: now,
+                        rotation: 1,
+                    },
+                );
+            }
+            return Ok(FetchContext {
+                session_id,
+                incremental: false,
+                partitions,
+                rotation: 0,
+            });
+        }
+        let Some(session) = sessions.get_mut(&session_id) else {
+            return Err(ErrorCode::FetchSessionIdNotFound);
+        };
+        if epoch != session.epoch {
+            return Err(ErrorCode::InvalidFetchSessionEpoch);
+        }
+        for requested in requested {
+            upsert(&mut session.partitions, requested);
+        }
+        session.partitions.retain(|x| {
+            !forgotten
+                .iter()
+                .any(|(id, name, index)| partition_key(*id, name, *index) == x.key())
+        });
+        session.epoch = next_epoch(session.epoch);
+        session.last_used = now;
+        let rotation = session.rotation;
+        session.rotation = rotation.wrapping_add(1);
+        Ok(FetchContext {
+            session_id,
+            incremental: true,
+            partitions: session.partitions.clone(),
+            rotation,
+        })
+    }
+
+    /// Remembers the offsets returned for the session's partitions. Returns,
+    /// for each, whether an incremental response has to carry it: when it has
+    /// records or an error, or its offsets changed since the last response.
+    pub fn complete(&self, session_id: i32, returned: &[PartitionOffsets]) -> Vec<bool> {
+        let mut sessions = self.lock();
+        let Some(session) = sessions.get_mut(&session_id) else {
+            return vec![true; returned.len()];
+        };
+        returned
+            .iter()
+            .map(|offsets| {
+                let key = partition_key(
+                    offsets.topic_id,
+                    &offsets.topic_name,
+                    offsets.partition_index,
+                );
+                let Some(cached) = session.partitions.iter_mut().find(|x| x.key() == key) else {
+                    return true;
+                };
+                let changed = cached.high_watermark != offsets.high_watermark
+                    || cached.last_stable_offset != offsets.last_stable_offset
+                    || cached.log_start_offset != offsets.log_start_offset;
+                cached.high_watermark = offsets.high_watermark;
+                cached.last_stable_offset = offsets.last_stable_offset;
+                cached.log_start_offset = offsets.log_start_offset;
+                changed || offsets.has_data
+            })
+            .collect()
+    }
+
+    /// Frees a slot for a session of `size` partitions if the cache is full:
+    /// the least recently used session goes if it is stale, or else the
+    /// smallest session, if smaller than the new one.
+    fn make_room(
+        sessions: &mut HashMap<i32, FetchSession>,
+        size: usize,
+        max_slots: usize,
+        now: Instant,
+    ) -> bool {
+        if sessions.len() < max_slots {
+            return true;
+        }
+        let stale = sessions
+            .iter()
+            .filter(|(_, x)| now.duration_since(x.last_used) >= SESSION_EVICTION_TIME)
+            .min_by_key(|(_, x)| x.last_used)
+            .map(|(id, _)| *id);
+        let evicted = stale.or_else(|| {
+            sessions
+                .iter()
+                .filter(|(_, x)| x.partitions.len() < size)
+                .min_by_key(|(_, x)| (x.partitions.len(), x.last_used))
+                .map(|(id, _)| *id)
+        });
+        match evicted {
+            Some(id) if sessions.len() <= max_slots => {
+                sessions.remove(&id);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn new_session_id(sessions: &HashMap<i32, FetchSession>) -> i32 {
+        loop {
+            let id = random_uuid() as i32 & i32::MAX;
+            if id != NO_FETCH_SESSION_ID && !sessions.contains_key(&id) {
+                return id;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TOPIC: i128 = 0x1234;
+
+    fn requested(partition_index: i32) -> RequestedPartition {
+        (
+            TOPIC,
+            String::new(),
+            FetchTopicRequestPartition::new(partition_index, 0),
+        )
+    }
+
+    fn indexes(context: &FetchContext) -> Vec<i32> {
+        context
+            .partitions
+            .iter()
+            .map(|x| x.request.partition_index)
+            .collect()
+    }
+
+    #[test]
+    fn begin_opens_a_session_with_the_initial_epoch() {
+        let cache = FetchSessionCache::default();
+        let context = cache
+            .begin(
+                NO_FETCH_SESSION_ID,
+                INITIAL_FETCH_SESSION_EPOCH,
+                vec![requested(0), requested(1)],
+                &[],
+                10,
+            )
+            .unwrap();
+        assert_ne!(context.session_id, NO_FETCH_SESSION_ID);
+        assert!(!context.incremental);
+        assert_eq!(indexes(&context), [0, 1]);
+    }
+
+    #[test]
+    fn begin_without_a_session() {
+        let cache = FetchSessionCache::default();
+        let context = cache
+            .begin(
+                NO_FETCH_SESSION_ID,
+                FINAL_FETCH_SESSION_EPOCH,
+                vec![requested(0)],
+                &[],
+                10,
+            )
+            .unwrap();
+        assert_eq!(context.session_id, NO_FETCH_SESSION_ID);
+        assert_eq!(indexes(&context), [0]);
+        // Without slots, initial requests fall back to sessionless fetches.
+        let context = cache
+            .begin(
+                NO_FETCH_SESSION_ID,
+                INITIAL_FETCH_SESSION_EPOCH,
+                vec![requested(0)],
+                &[],
+                0,
+            )
+            .unwrap();
+        assert_eq!(context.session_id, NO_FETCH_SESSION_ID);
+    }
+
+    #[test]
+    fn begin_applies_incremental_changes() {
+        let cache = FetchSessionCache::default();
+        let session_id = cache
+            .begin(
+                NO_FETCH_SESSION_ID,
+                INITIAL_FETCH_SESSION_EPOCH,
+                vec![requested(0), requested(1)],
+                &[],
+                10,
+            )
+            .unwrap()
+            .session_id;
+        let forgotten = [(TOPIC, String::new(), 0)];
+        let context = cache
+            .begin(session_id, 1, vec![requested(2)], &forgotten, 10)
+            .unwrap();
+        assert_eq!(context.session_id, session_id);
+        assert!(context.incremental);
+        assert_eq!(indexes(&context), [1, 2]);
+        let context = cache.begin(session_id, 2, vec![], &[], 10).unwrap();
+        assert_eq!(indexes(&context), [1, 2]);
+    }
+
+    #[test]
+    fn begin_rejects_unknown_sessions_and_stale_epochs() {
+        let cache = FetchSessionCache::default();
+        let session_id = cache
+            .begin(
+                NO_FETCH_SESSION_ID,
+                INITIAL_FETCH_SESSION_EPOCH,
+                vec![requested(0)],
+                &[],
+                10,
+            )
+            .unwrap()
+            .session_id;
+        assert!(matches!(
+            cache.begin(session_id.wrapping_add(1), 1, vec![], &[], 10),
+            Err(ErrorCode::FetchSessionIdNotFound)
+        ));
+        assert!(matches!(
+            cache.begin(session_id, 2, vec![], &[], 10),
+            Err(ErrorCode::InvalidFetchSessionEpoch)
+        ));
+        // A full request closes the session it names.
+        cache
+            .begin(session_id, FINAL_FETCH_SESSION_EPOCH, vec![], &[], 10)
+            .unwrap();
+        assert!(matches!(
+            cache.begin(session_id, 1, vec![], &[], 10),
+            Err(ErrorCode::FetchSessionIdNotFound)
+        ));
+    }
+
+    #[test]
+    fn begin_evicts_smaller_sessions_when_full() {
+        let cache = FetchSessionCache::default();
+        let small = cache
+            .begin(
+                NO_FETCH_SESSION_ID,
+                INITIAL_FETCH_SESSION_EPOCH,
+                vec![requested(0)],
+                &[],
+                1,
+            )
+            .unwrap()
+            .session_id;
+        let large = cache
+            .begin(
+                NO_FETCH_SESSION_ID,
+                INITIAL_FETCH_SESSION_EPOCH,
+                vec![requested(0), requested(1)],
+                &[],
+                1,
+            )
+            .unwrap()
+            .session_id;
+        assert_ne!(large, NO_FETCH_SESSION_ID);
+        assert!(matches!(
+            cache.begin(small, 1, vec![], &[], 1),
+            Err(ErrorCode::FetchSessionIdNotFound)
+        ));
+        // Sessions no larger than the cached one find no room.
+        let context = cache
+            .begin(
+                NO_FETCH_SESSION_ID,
+                INITIAL_FETCH_SESSION_EPOCH,
+                vec![requested(0)],
+                &[],
+                1,
+            )
+            .unwrap();
+        assert_eq!(context.session_id, NO_FETCH_SESSION_ID);
+    }
+}
@@ -0,0 +1,165 @@
+use anyhow::Result;
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+
+use crate::{
+    config::{advertised_endpoint, node_id},
+    coordinator::{
+        ensure_internal_topic, partition_for, GROUP_METADATA_TOPIC, SHARE_GROUP_STATE_TOPIC,
+        TRANSACTION_STATE_TOPIC,
+    },
+    protocol::{ErrorCode, Response},
+};
+
+use super::{
+    get_array, get_string, get_tag_buffer, put_array, put_nullable_string, put_string,
+    put_tag_buffer, RequestHeader, Serialize,
+};
+
+#[derive(Debug)]
+pub struct FindCoordinatorRequest {
+    key_type: i8,
+    coordinator_keys: Vec<String>,
+}
+
+impl FindCoordinatorRequest {
+    fn deserialize(bytes: &mut Bytes, version: i16, flexible: bool) -> Self {
+        let key = if version < 4 {
+            Some(get_string(bytes, flexible))
+        } else {
+            None
+        };
+        let key_type = if version >= 1 { bytes.get_i8() } else { 0 };
+        let coordinator_keys = match key {
+            Some(key) => vec![key],
+            None => get_array(bytes, flexible, |bytes| get_string(bytes, flexible)),
+        };
+        get_tag_buffer(bytes, flexible);
+        Self {
+            key_type,
+            coordinator_keys,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct FindCoordinatorResponse {
+    version: i16,
+    flexible: bool,
+    coordinators: Vec<Coordinator>,
+}
+
+#[derive(Debug)]
+pub struct Coordinator {
+    key: String,
+    node_id: i32,
+    host: String,
+    port: i32,
+    error_code: ErrorCode,
+    error_message: Option<String>,
+}
+
+impl Coordinator {
+    fn serialize_fields(&self, bytes: &mut BytesMut, flexible: bool) {
+        bytes.put_i32(self.node_id);
+        put_string(bytes, &self.host, flexible);
+        bytes.put_i32(self.port);
+    }
+}
+
+impl Serialize for FindCoordinatorResponse {
+    fn serialize(&self, bytes: &mut BytesMut) {
+        let (version, flexible) = (self.version, self.flexible);
+        if version >= 1 {
+            bytes.put_i32(0); // throttle time
+        }
+        if version >= 4 {
+            put_array(bytes, &self.coordinators, flexible, |coordinator, bytes| {
+                put_string(bytes, &coordinator.key, flexible);
+                coordinator.serialize_fields(bytes, flexible);
+                bytes.put_i16(coordinator.error_code.clone() as i16);
+                put_nullable_string(bytes, coordinator.error_message.as_deref(), flexible);
+                put_tag_buffer(bytes, flexible);
+            });
+        } else {
+            let coordinator = &self.coordinators[0];
+            bytes.put_i16(coordinator.error_code.clone() as i16);
+            if version >= 1 {
+                put_nullable_string(bytes, coordinator.error_message.as_deref(), flexible);
+            }
+            coordinator.serialize_fields(bytes, flexible);
+        }
+        put_tag_buffer(bytes, flexible);
+    }
+}
+
+pub fn find_coordinator_handler(bytes: &mut Bytes, header: RequestHeader) -> Result<Bytes> {
+    let flexible = header.is_flexible();
+    let req = FindCoordinatorRequest::deserialize(bytes, header.request_api_version, flexible);
+    let coordinators = req
+        .coordinator_keys
+        .iter()
+        .map(|key| {
+            let mut coordinator = Coordinator {
+                key: key.clone(),
+                node_id: -1,
+                host: String::new(),
+                port: -1,
+                error_code: ErrorCode::NoError,
+                error_message: None,
+            };
+            match find_coordinator(key, req.key_type) {
+                Ok((node_id, host, port)) => {
+                    coordinator.node_id = node_id;
+                    coordinator.host = host;
+                    coordinator.port = port;
+                }
+                Err((error_code, error_message)) => {
+                    coordinator.error_code = error_code;
+                    coordinator.error_message = Some(error_message);
+                }
+            }
+            coordinator
+        })
+        .collect();
+    let body = FindCoordinatorResponse {
+        version: header.request_api_version,
+        flexible,
+        coordinators,
+    };
+    Ok(Response::for_request(&header, body).into())
+}
+
+fn find_coordinator(key: &str, key_type: i8) -> Result<(i32, String, i32), (ErrorCode, String)> {
+    let topic_name = match key_type {
+        0 => GROUP_METADATA_TOPIC,
+        1 => TRANSACTION_STATE_TOPIC,
+        2 => SHARE_GROUP_STATE_TOPIC,
+        _ => {
+            return Err((
+                ErrorCode::InvalidRequest,
+                format!("Unsupported key type {}", key_type),
+            ))
+        }
+    };
+    if key.is_empty() {
+        return Err((
+            ErrorCode::InvalidRequest,
+            "Coordinator key must not be empty".to_owned(),
+        ));
+    }
+    let (metadata, partitions) = ensure_internal_topic(topic_name)
+        .map_err(|e| (ErrorCode::CoordinatorNotAvailable, e.to_string()))?;
+    let partition = partition_for(key, partitions);
+    let leader_id = metadata
+        .get_topic_by_name(topic_name)
+        .and_then(|x| metadata.get_partition(&x.uuid, partition))
+        .map(|x| x.leader_id);
+    if leader_id != Some(node_id()) {
+        return Err((
+            ErrorCode::CoordinatorNotAvailable,
+            format!("No live leader for {}-{}", topic_name, partition),
+        ));
+    }
+    let (host, port) = advertised_endpoint();
+    Ok((node_id(), host, port))
+}
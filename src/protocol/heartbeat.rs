@@ -0,0 +1,74 @@
+use anyhow::Result;
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+
+use crate::{
+    coordinator::group_coordinator,
+    protocol::{ErrorCode, Response},
+};
+
+use super::{
+    get_nullable_string, get_string, get_tag_buffer, put_tag_buffer, RequestHeader, Serialize,
+};
+
+#[derive(Debug)]
+pub struct HeartbeatRequest {
+    group_id: String,
+    generation_id: i32,
+    member_id: String,
+    group_instance_id: Option<String>,
+}
+
+impl HeartbeatRequest {
+    fn deserialize(bytes: &mut Bytes, version: i16, flexible: bool) -> Self {
+        let group_id = get_string(bytes, flexible);
+        let generation_id = bytes.get_i32();
+        let member_id = get_string(bytes, flexible);
+        let group_instance_id = if version >= 3 {
+            get_nullable_string(bytes, flexible)
+        } else {
+            None
+        };
+        get_tag_buffer(bytes, flexible);
+        Self {
+            group_id,
+            generation_id,
+            member_id,
+            group_instance_id,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct HeartbeatResponse {
+    version: i16,
+    flexible: bool,
+    error_code: ErrorCode,
+}
+
+impl Serialize for HeartbeatResponse {
+    fn serialize(&self, bytes: &mut BytesMut) {
+        if self.version >= 1 {
+            bytes.put_i32(0); // throttle time
+        }
+        bytes.put_i16(self.error_code.clone() as i16);
+        put_tag_buffer(bytes, self.flexible);
+    }
+}
+
+pub fn heartbeat_handler(bytes: &mut Bytes, header: RequestHeader) -> Result<Bytes> {
+    let version = header.request_api_version;
+    let flexible = header.is_flexible();
+    let req = HeartbeatRequest::deserialize(bytes, version, flexible);
+    let error_code = group_coordinator().heartbeat(
+        &req.group_id,
+        req.generation_id,
+        &req.member_id,
+        req.group_instance_id.as_deref(),
+    );
+    let body = HeartbeatResponse {
+        version,
+        flexible,
+        error_code,
+    };
+    Ok(Response::for_request(&header, body).into())
+}
@@ -0,0 +1,88 @@
+use anyhow::Result;
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+
+use crate::{
+    config::ConfigStore,
+    coordinator::{transaction_coordinator, NO_PRODUCER_EPOCH, NO_PRODUCER_ID},
+    metadata::read_cluster_metadata,
+    protocol::{ErrorCode, Response},
+};
+
+use super::{get_nullable_string, get_tag_buffer, put_tag_buffer, RequestHeader, Serialize};
+
+#[derive(Debug)]
+pub struct InitProducerIdRequest {
+    transactional_id: Option<String>,
+    transaction_timeout_ms: i32,
+    producer_id: i64,
+    producer_epoch: i16,
+}
+
+impl InitProducerIdRequest {
+    fn deserialize(bytes: &mut Bytes, version: i16, flexible: bool) -> Self {
+        let transactional_id = get_nullable_string(bytes, flexible);
+        let transaction_timeout_ms = bytes.get_i32();
+        let (producer_id, producer_epoch) = if version >= 3 {
+            (bytes.get_i64(), bytes.get_i16())
+        } else {
+            (NO_PRODUCER_ID, NO_PRODUCER_EPOCH)
+        };
+        get_tag_buffer(bytes, flexible);
+        Self {
+            transactional_id,
+            transaction_timeout_ms,
+            producer_id,
+            producer_epoch,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct InitProducerIdResponse {
+    flexible: bool,
+    error_code: ErrorCode,
+    producer_id: i64,
+    producer_epoch: i16,
+}
+
+impl Serialize for InitProducerIdResponse {
+    fn serialize(&self, bytes: &mut BytesMut) {
+        bytes.put_i32(0); // throttle time
+        bytes.put_i16(self.error_code.clone() as i16);
+        bytes.put_i64(self.producer_id);
+        bytes.put_i16(self.producer_epoch);
+        put_tag_buffer(bytes, self.flexible);
+    }
+}
+
+pub fn init_producer_id_handler(bytes: &mut Bytes, header: RequestHeader) -> Result<Bytes> {
+    let version = header.request_api_version;
+    let flexible = header.is_flexible();
+    let req = InitProducerIdRequest::deserialize(bytes, version, flexible);
+    let store = ConfigStore::load(&read_cluster_metadata()?);
+    let result = transaction_coordinator().init_producer_id(
+        req.transactional_id.as_deref(),
+        req.transaction_timeout_ms,
+        Some((req.producer_id, req.producer_epoch)),
+        &store,
+    );
+    let body = match result {
+        Ok((producer_id, producer_epoch)) => InitProducerIdResponse {
+            flexible,
+            error_code: ErrorCode::NoError,
+            producer_id,
+            producer_epoch,
+        },
+        Err(error_code) => InitProducerIdResponse {
+            flexible,
+            // PRODUCER_FENCED was introduced in v4.
+            error_code: match error_code {
+                ErrorCode::ProducerFenced if version < 4 => ErrorCode::InvalidProducerEpoch,
+                x => x,
+            },
+            producer_id: NO_PRODUCER_ID,
+            producer_epoch: NO_PRODUCER_EPOCH,
+        },
+    };
+    Ok(Response::for_request(&header, body).into())
+}
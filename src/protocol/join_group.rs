@@ -0,0 +1,148 @@
+use anyhow::Result;
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+
+use crate::{
+    config::ConfigStore,
+    coordinator::{
+        consumer_group_coordinator, group_coordinator, share_group_coordinator, GroupConfig,
+        JoinGroupParams, JoinResult,
+    },
+    metadata::read_cluster_metadata,
+    protocol::{ErrorCode, Response},
+    server::client_host,
+};
+
+use super::{
+    get_array, get_bytes, get_nullable_string, get_string, get_tag_buffer, put_array, put_bytes,
+    put_nullable_string, put_string, put_tag_buffer, RequestHeader, Serialize,
+};
+
+#[derive(Debug)]
+pub struct JoinGroupRequest {
+    group_id: String,
+    session_timeout_ms: i32,
+    rebalance_timeout_ms: i32,
+    member_id: String,
+    group_instance_id: Option<String>,
+    protocol_type: String,
+    protocols: Vec<(String, Bytes)>,
+    _reason: Option<String>,
+}
+
+impl JoinGroupRequest {
+    fn deserialize(bytes: &mut Bytes, version: i16, flexible: bool) -> Self {
+        let group_id = get_string(bytes, flexible);
+        let session_timeout_ms = bytes.get_i32();
+        // v0 rebalances within the session timeout.
+        let rebalance_timeout_ms = if version >= 1 {
+            bytes.get_i32()
+        } else {
+            session_timeout_ms
+        };
+        let member_id = get_string(bytes, flexible);
+        let group_instance_id = if version >= 5 {
+            get_nullable_string(bytes, flexible)
+        } else {
+            None
+        };
+        let protocol_type = get_string(bytes, flexible);
+        let protocols = get_array(bytes, flexible, |bytes| {
+            let name = get_string(bytes, flexible);
+            let metadata = get_bytes(bytes, flexible);
+            get_tag_buffer(bytes, flexible);
+            (name, metadata)
+        });
+        let reason = if version >= 8 {
+            get_nullable_string(bytes, flexible)
+        } else {
+            None
+        };
+        get_tag_buffer(bytes, flexible);
+        Self {
+            group_id,
+            session_timeout_ms,
+            rebalance_timeout_ms,
+            member_id,
+            group_instance_id,
+            protocol_type,
+            protocols,
+            _reason: reason,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct JoinGroupResponse {
+    version: i16,
+    flexible: bool,
+    result: JoinResult,
+}
+
+impl Serialize for JoinGroupResponse {
+    fn serialize(&self, bytes: &mut BytesMut) {
+        let (version, flexible, result) = (self.version, self.flexible, &self.result);
+        if version >= 2 {
+            bytes.put_i32(0); // throttle time
+        }
+        bytes.put_i16(result.error_code.clone() as i16);
+        bytes.put_i32(result.generation_id);
+        if version >= 7 {
+            put_nullable_string(bytes, result.protocol_type.as_deref(), flexible);
+            put_nullable_string(bytes, result.protocol_name.as_deref(), flexible);
+        } else {
+            put_string(
+                bytes,
+                result.protocol_name.as_deref().unwrap_or_default(),
+                flexible,
+            );
+        }
+        put_string(bytes, &result.leader_id, flexible);
+        if version >= 9 {
+            bytes.put_i8(result.skip_assignment as i8);
+        }
+        put_string(bytes, &result.member_id, flexible);
+        put_array(bytes, &result.members, flexible, |member, bytes| {
+            put_string(bytes, &member.member_id, flexible);
+            if version >= 5 {
+                put_nullable_string(bytes, member.group_instance_id.as_deref(), flexible);
+            }
+            put_bytes(bytes, &member.metadata, flexible);
+            put_tag_buffer(bytes, flexible);
+        });
+        put_tag_buffer(bytes, flexible);
+    }
+}
+
+pub fn join_group_handler(bytes: &mut Bytes, header: RequestHeader) -> Result<Bytes> {
+    let metadata = read_cluster_metadata()?;
+    let config = GroupConfig::load(&ConfigStore::load(&metadata));
+    let version = header.request_api_version;
+    let flexible = header.is_flexible();
+    let req = JoinGroupRequest::deserialize(bytes, version, flexible);
+    let params = JoinGroupParams {
+        group_id: req.group_id,
+        member_id: req.member_id,
+        group_instance_id: req.group_instance_id,
+        client_id: header.client_id.id().to_owned(),
+        client_host: client_host(),
+        session_timeout_ms: req.session_timeout_ms,
+        rebalance_timeout_ms: req.rebalance_timeout_ms,
+        protocol_type: req.protocol_type,
+        protocols: req.protocols,
+        require_known_member_id: version >= 4,
+        supports_skip_assignment: version >= 9,
+    };
+    let result = if consumer_group_coordinator().contains(&params.group_id)
+        || share_group_coordinator().contains(&params.group_id)
+    {
+        JoinResult::error(ErrorCode::InconsistentGroupProtocol, &params.member_id)
+    } else {
+        group_coordinator().join_group(params, &config)
+    };
+    let body = JoinGroupResponse {
+        version,
+        flexible,
+        result,
+    };
+    Ok(Response::for_request(&header, body).into())
+}
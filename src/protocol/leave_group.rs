@@ -0,0 +1,128 @@
+use anyhow::Result;
+use bytes::{BufMut, Bytes, BytesMut};
+
+use crate::{
+    coordinator::group_coordinator,
+    protocol::{ErrorCode, Response},
+};
+
+use super::{
+    get_array, get_nullable_string, get_string, get_tag_buffer, put_array, put_nullable_string,
+    put_string, put_tag_buffer, RequestHeader, Serialize,
+};
+
+#[derive(Debug)]
+pub struct LeaveGroupRequest {
+    group_id: String,
+    members: Vec<MemberIdentity>,
+}
+
+#[derive(Debug)]
+pub struct MemberIdentity {
+    member_id: String,
+    group_instance_id: Option<String>,
+    _reason: Option<String>,
+}
+
+impl LeaveGroupRequest {
+    fn deserialize(bytes: &mut Bytes, version: i16, flexible: bool) -> Self {
+        let group_id = get_string(bytes, flexible);
+        // v3 batched the single member id into a list of members.
+        let members = if version >= 3 {
+            get_array(bytes, flexible, |bytes| {
+                let member = MemberIdentity {
+                    member_id: get_string(bytes, flexible),
+                    group_instance_id: get_nullable_string(bytes, flexible),
+                    _reason: if version >= 5 {
+                        get_nullable_string(bytes, flexible)
+                    } else {
+                        None
+                    },
+                };
+                get_tag_buffer(bytes, flexible);
+                member
+            })
+        } else {
+            vec![MemberIdentity {
+                member_id: get_string(bytes, flexible),
+                group_instance_id: None,
+                _reason: None,
+            }]
+        };
+        get_tag_buffer(bytes, flexible);
+        Self { group_id, members }
+    }
+}
+
+#[derive(Debug)]
+pub struct LeaveGroupResponse {
+    version: i16,
+    flexible: bool,
+    error_code: ErrorCode,
+    members: Vec<MemberResponse>,
+}
+
+#[derive(Debug)]
+pub struct MemberResponse {
+    member_id: String,
+    group_instance_id: Option<String>,
+    error_code: ErrorCode,
+}
+
+impl Serialize for LeaveGroupResponse {
+    fn serialize(&self, bytes: &mut BytesMut) {
+        let (version, flexible) = (self.version, self.flexible);
+        if version >= 1 {
+            bytes.put_i32(0); // throttle time
+        }
+        bytes.put_i16(self.error_code.clone() as i16);
+        if version >= 3 {
+            put_array(bytes, &self.members, flexible, |member, bytes| {
+                put_string(bytes, &member.member_id, flexible);
+                put_nullable_string(bytes, member.group_instance_id.as_deref(), flexible);
+                bytes.put_i16(member.error_code.clone() as i16);
+                put_tag_buffer(bytes, flexible);
+            });
+        }
+        put_tag_buffer(bytes, flexible);
+    }
+}
+
+pub fn leave_group_handler(bytes: &mut Bytes, header: RequestHeader) -> Result<Bytes> {
+    let version = header.request_api_version;
+    let flexible = header.is_flexible();
+    let req = LeaveGroupRequest::deserialize(bytes, version, flexible);
+    let members: Vec<(String, Option<String>)> = req
+        .members
+        .iter()
+        .map(|x| (x.member_id.clone(), x.group_instance_id.clone()))
+        .collect();
+    let mut body = LeaveGroupResponse {
+        version,
+        flexible,
+        error_code: ErrorCode::NoError,
+        members: vec![],
+    };
+    match group_coordinator().leave_group(&req.group_id, &members) {
+        Ok(errors) => {
+            body.members = req
+                .members
+                .into_iter()
+                .zip(errors)
+                .map(|(member, error_code)| MemberResponse {
+                    member_id: member.member_id,
+                    group_instance_id: member.group_instance_id,
+                    error_code,
+                })
+                .collect();
+            // Before v3 the single member's error is the response's.
+            if version < 3 {
+                if let Some(member) = body.members.first() {
+                    body.error_code = member.error_code.clone();
+                }
+            }
+        }
+        Err(error_code) => body.error_code = error_code,
+    }
+    Ok(Response::for_request(&header, body).into())
+}
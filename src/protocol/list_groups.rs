@@ -0,0 +1,159 @@
+use anyhow::Result;
+use bytes::{BufMut, Bytes, BytesMut};
+
+use crate::{
+    config::ConfigStore,
+    coordinator::{
+        consumer_group_coordinator, group_coordinator, offset_store, share_group_coordinator,
+        GroupState, ShareGroupConfig, CONSUMER_PROTOCOL_TYPE,
+    },
+    metadata::read_cluster_metadata,
+    protocol::{ErrorCode, Response},
+};
+
+use super::{
+    get_array, get_string, get_tag_buffer, put_array, put_string, put_tag_buffer, RequestHeader,
+    Serialize,
+};
+
+const CLASSIC_GROUP_TYPE: &str = "classic";
+const CONSUMER_GROUP_TYPE: &str = "consumer";
+const SHARE_GROUP_TYPE: &str = "share";
+
+#[derive(Debug)]
+pub struct ListGroupsRequest {
+    states_filter: Vec<String>,
+    types_filter: Vec<String>,
+}
+
+impl ListGroupsRequest {
+    fn deserialize(bytes: &mut Bytes, version: i16, flexible: bool) -> Self {
+        let states_filter = if version >= 4 {
+            get_array(bytes, flexible, |bytes| get_string(bytes, flexible))
+        } else {
+            vec![]
+        };
+        let types_filter = if version >= 5 {
+            get_array(bytes, flexible, |bytes| get_string(bytes, flexible))
+        } else {
+            vec![]
+        };
+        get_tag_buffer(bytes, flexible);
+        Self {
+            states_filter,
+            types_filter,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ListGroupsResponse {
+    version: i16,
+    flexible: bool,
+    error_code: ErrorCode,
+    groups: Vec<ListedGroup>,
+}
+
+#[derive(Debug)]
+pub struct ListedGroup {
+    group_id: String,
+    protocol_type: String,
+    state: &'static str,
+    group_type: &'static str,
+}
+
+impl Serialize for ListGroupsResponse {
+    fn serialize(&self, bytes: &mut BytesMut) {
+        let (version, flexible) = (self.version, self.flexible);
+        if version >= 1 {
+            bytes.put_i32(0); // throttle time
+        }
+        bytes.put_i16(self.error_code.clone() as i16);
+        put_array(bytes, &self.groups, flexible, |group, bytes| {
+            put_string(bytes, &group.group_id, flexible);
+            put_string(bytes, &group.protocol_type, flexible);
+            if version >= 4 {
+                put_string(bytes, group.state, flexible);
+            }
+            if version >= 5 {
+                put_string(bytes, group.group_type, flexible);
+            }
+            put_tag_buffer(bytes, flexible);
+        });
+        put_tag_buffer(bytes, flexible);
+    }
+}
+
+pub fn list_groups_handler(bytes: &mut Bytes, header: RequestHeader) -> Result<Bytes> {
+    let metadata = read_cluster_metadata()?;
+    let version = header.request_api_version;
+    let flexible = header.is_flexible();
+    let req = ListGroupsRequest::deserialize(bytes, version, flexible);
+    let share_config = ShareGroupConfig::load(&ConfigStore::load(&metadata));
+
+    let mut groups: Vec<ListedGroup> = group_coordinator()
+        .list_groups()
+        .into_iter()
+        .map(|x| ListedGroup {
+            group_id: x.group_id,
+            protocol_type: x.protocol_type,
+            state: x.state.name(),
+            group_type: CLASSIC_GROUP_TYPE,
+        })
+        .chain(
+            consumer_group_coordinator()
+                .list_groups(&metadata)
+                .into_iter()
+                .map(|x| ListedGroup {
+                    state: x.state().name(),
+                    group_id: x.group_id,
+                    protocol_type: CONSUMER_PROTOCOL_TYPE.to_owned(),
+                    group_type: CONSUMER_GROUP_TYPE,
+                }),
+        )
+        .chain(
+            share_group_coordinator()
+                .list_groups(&share_config, &metadata)
+                .into_iter()
+                .map(|x| ListedGroup {
+                    state: x.state(),
+                    group_id: x.group_id,
+                    protocol_type: SHARE_GROUP_TYPE.to_owned(),
+                    group_type: SHARE_GROUP_TYPE,
+                }),
+        )
+        .collect();
+    // Groups only known from their committed offsets.
+    for group_id in offset_store().group_ids() {
+        if !groups.iter().any(|x| x.group_id == group_id) {
+            groups.push(ListedGroup {
+                group_id,
+                protocol_type: String::new(),
+                state: GroupState::Empty.name(),
+                group_type: CLASSIC_GROUP_TYPE,
+            });
+        }
+    }
+    groups.retain(|group| {
+        let state_matches = req.states_filter.is_empty()
+            || req
+                .states_filter
+                .iter()
+                .any(|x| x.eq_ignore_ascii_case(group.state));
+        let type_matches = req.types_filter.is_empty()
+            || req
+                .types_filter
+                .iter()
+                .any(|x| x.eq_ignore_ascii_case(group.group_type));
+        state_matches && type_matches
+    });
+    groups.sort_by(|a, b| a.group_id.cmp(&b.group_id));
+
+    let body = ListGroupsResponse {
+        version,
+        flexible,
+        error_code: ErrorCode::NoError,
+        groups,
+    };
+    Ok(Response::for_request(&header, body).into())
+}
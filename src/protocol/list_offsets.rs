@@ -0,0 +1,224 @@
+use anyhow::Result;
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+
+use crate::{
+    metadata::{read_cluster_metadata, MetadataFile},
+    protocol::{ErrorCode, Response, READ_COMMITTED},
+    storage::PartitionLog,
+};
+
+use super::{
+    get_array, get_string, get_tag_buffer, put_array, put_string, put_tag_buffer, RequestHeader,
+    Serialize,
+};
+
+const LATEST_TIMESTAMP: i64 = -1;
+const EARLIEST_TIMESTAMP: i64 = -2;
+const MAX_TIMESTAMP: i64 = -3;
+
+#[derive(Debug)]
+pub struct ListOffsetsRequest {
+    _replica_id: i32,
+    isolation_level: i8,
+    topics: Vec<ListOffsetsTopic>,
+}
+
+#[derive(Debug)]
+pub struct ListOffsetsTopic {
+    name: String,
+    partitions: Vec<ListOffsetsPartition>,
+}
+
+#[derive(Debug)]
+pub struct ListOffsetsPartition {
+    partition_index: i32,
+    _current_leader_epoch: i32,
+    timestamp: i64,
+}
+
+impl ListOffsetsRequest {
+    fn deserialize(bytes: &mut Bytes, version: i16, flexible: bool) -> Self {
+        let replica_id = bytes.get_i32();
+        let isolation_level = if version >= 2 { bytes.get_i8() } else { 0 };
+        let topics = get_array(bytes, flexible, |bytes| {
+            let name = get_string(bytes, flexible);
+            let partitions = get_array(bytes, flexible, |bytes| {
+                let partition = ListOffsetsPartition {
+                    partition_index: bytes.get_i32(),
+                    _current_leader_epoch: if version >= 4 { bytes.get_i32() } else { -1 },
+                    timestamp: bytes.get_i64(),
+                };
+                get_tag_buffer(bytes, flexible);
+                partition
+            });
+            get_tag_buffer(bytes, flexible);
+            ListOffsetsTopic { name, partitions }
+        });
+        get_tag_buffer(bytes, flexible);
+        Self {
+            _replica_id: replica_id,
+            isolation_level,
+            topics,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ListOffsetsResponse {
+    version: i16,
+    flexible: bool,
+    topics: Vec<ListOffsetsTopicResponse>,
+}
+
+#[derive(Debug)]
+pub struct ListOffsetsTopicResponse {
+    name: String,
+    partitions: Vec<ListOffsetsPartitionResponse>,
+}
+
+#[derive(Debug)]
+pub struct ListOffsetsPartitionResponse {
+    partition_index: i32,
+    error_code: ErrorCode,
+    timestamp: i64,
+    offset: i64,
+    leader_epoch: i32,
+}
+
+impl Serialize for ListOffsetsResponse {
+    fn serialize(&self, bytes: &mut BytesMut) {
+        let (version, flexible) = (self.version, self.flexible);
+        if version >= 2 {
+            bytes.put_i32(0); // throttle time
+        }
+        put_array(bytes, &self.topics, flexible, |topic, bytes| {
+            put_string(bytes, &topic.name, flexible);
+            put_array(bytes, &topic.partitions, flexible, |partition, bytes| {
+                bytes.put_i32(partition.partition_index);
+                bytes.put_i16(partition.error_code.clone() as i16);
+                bytes.put_i64(partition.timestamp);
+                bytes.put_i64(partition.offset);
+                if version >= 4 {
+                    bytes.put_i32(partition.leader_epoch);
+                }
+                put_tag_buffer(bytes, flexible);
+            });
+            put_tag_buffer(bytes, flexible);
+        });
+        put_tag_buffer(bytes, flexible);
+    }
+}
+
+pub fn list_offsets_handler(bytes: &mut Bytes, header: RequestHeader) -> Result<Bytes> {
+    let metadata = read_cluster_metadata()?;
+    let flexible = header.is_flexible();
+    let req = ListOffsetsRequest::deserialize(bytes, header.request_api_version, flexible);
+    let topics = req
+        .topics
+        .iter()
+        .map(|topic| ListOffsetsTopicResponse {
+            name: topic.name.clone(),
+            partitions: topic
+                .partitions
+                .iter()
+                .map(|partition| {
+                    partition_handler(&topic.name, partition, req.isolation_level, &metadata)
+                })
+                .collect(),
+        })
+        .collect();
+    let body = ListOffsetsResponse {
+        version: header.request_api_version,
+        flexible,
+        topics,
+    };
+    Ok(Response::for_request(&header, body).into())
+}
+
+fn partition_handler(
+    topic_name: &str,
+    partition: &ListOffsetsPartition,
+    isolation_level: i8,
+    metadata: &MetadataFile,
+) -> ListOffsetsPartitionResponse {
+    let mut response = ListOffsetsPartitionResponse {
+        partition_index: partition.partition_index,
+        error_code: ErrorCode::NoError,
+        timestamp: -1,
+        offset: -1,
+        leader_epoch: -1,
+    };
+    let known = metadata
+        .get_topic_by_name(topic_name)
+        .is_some_and(|x| metadata.has_partition(&x.uuid, partition.partition_index));
+    let log = match PartitionLog::open(topic_name, partition.partition_index) {
+        Ok(log) if known => log,
+        _ => {
+            response.error_code = ErrorCode::UnknownTopicOrPartition;
+            return response;
+        }
+    };
+    match lookup_offset(&log, partition.timestamp, isolation_level) {
+        Ok(Some((offset, timestamp, leader_epoch))) => {
+            response.offset = offset;
+            response.timestamp = timestamp;
+            response.leader_epoch = leader_epoch;
+        }
+        Ok(None) => (),
+        Err(_) => response.error_code = ErrorCode::UnknownServerError,
+    }
+    response
+}
+
+/// Resolves a ListOffsets timestamp to (offset, timestamp, leader epoch).
+/// Committed readers see the log up to the last stable offset.
+fn lookup_offset(
+    log: &PartitionLog,
+    timestamp: i64,
+    isolation_level: i8,
+) -> Result<Option<(i64, i64, i32)>> {
+    match timestamp {
+        EARLIEST_TIMESTAMP => {
+            let epoch = log.leader_epoch_cache()?.epoch_for(log.log_start_offset());
+            Ok(Some((log.log_start_offset(), -1, epoch.unwrap_or(-1))))
+        }
+        LATEST_TIMESTAMP => {
+            let epoch = log.leader_epoch_cache()?.latest_epoch();
+            let offset = match isolation_level {
+                READ_COMMITTED => log.last_stable_offset()?,
+                _ => log.high_watermark()?,
+            };
+            Ok(Some((offset, -1, epoch.unwrap_or(-1))))
+        }
+        MAX_TIMESTAMP => {
+            let mut found: Option<(i64, i64, i32)> = None;
+            for (batch, data) in log.read_batches(log.log_start_offset())? {
+                for (offset, record_timestamp) in batch.record_timestamps(&data) {
+                    if offset >= log.log_start_offset()
+                        && found.map_or(true, |(_, max, _)| record_timestamp > max)
+                    {
+                        found = Some((offset, record_timestamp, batch.partition_leader_epoch));
+                    }
+                }
+            }
+            Ok(found)
+        }
+        _ => {
+            for (batch, data) in log.read_batches(log.log_start_offset())? {
+                if batch.max_timestamp < timestamp {
+                    continue;
+                }
+                for (offset, record_timestamp) in batch.record_timestamps(&data) {
+                    if offset >= log.log_start_offset() && record_timestamp >= timestamp {
+                        return Ok(Some((
+                            offset,
+                            record_timestamp,
+                            batch.partition_leader_epoch,
+                        )));
+                    }
+                }
+            }
+            Ok(None)
+        }
+    }
+}
@@ -0,0 +1,100 @@
+use anyhow::Result;
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+
+use crate::{
+    coordinator::{transaction_coordinator, TransactionMetadata, TransactionState},
+    metadata::now_ms,
+    protocol::{ErrorCode, Response},
+};
+
+use super::{
+    get_array, get_string, get_tag_buffer, put_array, put_string, put_tag_buffer, RequestHeader,
+    Serialize,
+};
+
+#[derive(Debug)]
+pub struct ListTransactionsRequest {
+    state_filters: Vec<String>,
+    producer_id_filters: Vec<i64>,
+    /// Only transactions running for longer than this, -1 for all.
+    duration_filter_ms: i64,
+}
+
+impl ListTransactionsRequest {
+    fn deserialize(bytes: &mut Bytes, version: i16, flexible: bool) -> Self {
+        let state_filters = get_array(bytes, flexible, |bytes| get_string(bytes, flexible));
+        let producer_id_filters = get_array(bytes, flexible, |bytes| bytes.get_i64());
+        let duration_filter_ms = if version >= 1 { bytes.get_i64() } else { -1 };
+        get_tag_buffer(bytes, flexible);
+        Self {
+            state_filters,
+            producer_id_filters,
+            duration_filter_ms,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ListTransactionsResponse {
+    flexible: bool,
+    unknown_state_filters: Vec<String>,
+    transaction_states: Vec<TransactionMetadata>,
+}
+
+impl Serialize for ListTransactionsResponse {
+    fn serialize(&self, bytes: &mut BytesMut) {
+        let flexible = self.flexible;
+        bytes.put_i32(0); // throttle time
+        bytes.put_i16(ErrorCode::NoError as i16);
+        put_array(bytes, &self.unknown_state_filters, flexible, |x, bytes| {
+            put_string(bytes, x, flexible)
+        });
+        put_array(bytes, &self.transaction_states, flexible, |x, bytes| {
+            put_string(bytes, &x.transactional_id, flexible);
+            bytes.put_i64(x.producer_id);
+            put_string(bytes, x.state.name(), flexible);
+            put_tag_buffer(bytes, flexible);
+        });
+        put_tag_buffer(bytes, flexible);
+    }
+}
+
+const STATES: [TransactionState; 8] = [
+    TransactionState::Empty,
+    TransactionState::Ongoing,
+    TransactionState::PrepareCommit,
+    TransactionState::PrepareAbort,
+    TransactionState::CompleteCommit,
+    TransactionState::CompleteAbort,
+    TransactionState::Dead,
+    TransactionState::PrepareEpochFence,
+];
+
+pub fn list_transactions_handler(bytes: &mut Bytes, header: RequestHeader) -> Result<Bytes> {
+    let flexible = header.is_flexible();
+    let req = ListTransactionsRequest::deserialize(bytes, header.request_api_version, flexible);
+    let (states, unknown_state_filters): (Vec<String>, Vec<String>) = req
+        .state_filters
+        .into_iter()
+        .partition(|x| STATES.iter().any(|state| state.name() == x));
+    let now = now_ms();
+    let transaction_states = transaction_coordinator()
+        .transactions()
+        .into_iter()
+        .filter(|x| states.is_empty() || states.iter().any(|state| state == x.state.name()))
+        .filter(|x| {
+            req.producer_id_filters.is_empty() || req.producer_id_filters.contains(&x.producer_id)
+        })
+        .filter(|x| {
+            req.duration_filter_ms < 0
+                || x.state == TransactionState::Ongoing
+                    && now - x.start_timestamp > req.duration_filter_ms
+        })
+        .collect();
+    let body = ListTransactionsResponse {
+        flexible,
+        unknown_state_filters,
+        transaction_states,
+    };
+    Ok(Response::for_request(&header, body).into())
+}
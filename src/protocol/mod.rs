@@ -12,3 +12,75 @@ mod varint;
 pub use varint::*;
 mod fetch;
 pub use fetch::*;
+mod fetch_session;
+pub use fetch_session::*;
+mod fetch_purgatory;
+pub use fetch_purgatory::*;
+mod codec;
+pub use codec::*;
+mod list_offsets;
+pub use list_offsets::*;
+mod delete_records;
+pub use delete_records::*;
+mod describe_configs;
+pub use describe_configs::*;
+mod alter_configs;
+pub use alter_configs::*;
+mod describe_cluster;
+pub use describe_cluster::*;
+mod describe_log_dirs;
+pub use describe_log_dirs::*;
+mod offset_for_leader_epoch;
+pub use offset_for_leader_epoch::*;
+mod find_coordinator;
+pub use find_coordinator::*;
+mod join_group;
+pub use join_group::*;
+mod sync_group;
+pub use sync_group::*;
+mod heartbeat;
+pub use heartbeat::*;
+mod leave_group;
+pub use leave_group::*;
+mod offset_commit;
+pub use offset_commit::*;
+mod offset_fetch;
+pub use offset_fetch::*;
+mod describe_groups;
+pub use describe_groups::*;
+mod list_groups;
+pub use list_groups::*;
+mod delete_groups;
+pub use delete_groups::*;
+mod offset_delete;
+pub use offset_delete::*;
+mod consumer_group_heartbeat;
+pub use consumer_group_heartbeat::*;
+mod consumer_group_describe;
+pub use consumer_group_describe::*;
+mod share_group_heartbeat;
+pub use share_group_heartbeat::*;
+mod share_fetch;
+pub use share_fetch::*;
+mod share_acknowledge;
+pub use share_acknowledge::*;
+mod init_producer_id;
+pub use init_producer_id::*;
+mod produce;
+pub use produce::*;
+mod add_partitions_to_txn;
+pub use add_partitions_to_txn::*;
+mod add_offsets_to_txn;
+pub use add_offsets_to_txn::*;
+mod end_txn;
+pub use end_txn::*;
+mod txn_offset_commit;
+pub use txn_offset_commit::*;
+mod write_txn_markers;
+pub use write_txn_markers::*;
+mod describe_producers;
+pub use describe_producers::*;
+mod describe_transactions;
+pub use describe_transactions::*;
+mod list_transactions;
+pub use list_transactions::*;
@@ -0,0 +1,225 @@
+use anyhow::Result;
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+
+use crate::{
+    config::ConfigStore,
+    coordinator::{consumer_group_coordinator, group_coordinator, offset_store, CommittedOffset},
+    metadata::{now_ms, read_cluster_metadata, MetadataFile},
+    protocol::{ErrorCode, Response},
+};
+
+use super::{
+    get_array, get_nullable_string, get_string, get_tag_buffer, put_array, put_string,
+    put_tag_buffer, RequestHeader, Serialize,
+};
+
+#[derive(Debug)]
+pub struct OffsetCommitRequest {
+    group_id: String,
+    generation_id: i32,
+    member_id: String,
+    group_instance_id: Option<String>,
+    /// -1 keeps the broker's offsets.retention.minutes.
+    retention_time_ms: i64,
+    topics: Vec<OffsetCommitRequestTopic>,
+}
+
+#[derive(Debug)]
+pub struct OffsetCommitRequestTopic {
+    name: String,
+    partitions: Vec<OffsetCommitRequestPartition>,
+}
+
+#[derive(Debug)]
+pub struct OffsetCommitRequestPartition {
+    partition_index: i32,
+    committed_offset: i64,
+    committed_leader_epoch: i32,
+    commit_timestamp: i64,
+    committed_metadata: Option<String>,
+}
+
+impl OffsetCommitRequest {
+    fn deserialize(bytes: &mut Bytes, version: i16, flexible: bool) -> Self {
+        let group_id = get_string(bytes, flexible);
+        let (generation_id, member_id) = if version >= 1 {
+            (bytes.get_i32(), get_string(bytes, flexible))
+        } else {
+            (-1, String::new())
+        };
+        let group_instance_id = if version >= 7 {
+            get_nullable_string(bytes, flexible)
+        } else {
+            None
+        };
+        let retention_time_ms = if (2..=4).contains(&version) {
+            bytes.get_i64()
+        } else {
+            -1
+        };
+        let topics = get_array(bytes, flexible, |bytes| {
+            let name = get_string(bytes, flexible);
+            let partitions = get_array(bytes, flexible, |bytes| {
+                let partition = OffsetCommitRequestPartition {
+                    partition_index: bytes.get_i32(),
+                    committed_offset: bytes.get_i64(),
+                    committed_leader_epoch: if version >= 6 { bytes.get_i32() } else { -1 },
+                    commit_timestamp: if version == 1 { bytes.get_i64() } else { -1 },
+                    committed_metadata: get_nullable_string(bytes, flexible),
+                };
+                get_tag_buffer(bytes, flexible);
+                partition
+            });
+            get_tag_buffer(bytes, flexible);
+            OffsetCommitRequestTopic { name, partitions }
+        });
+        get_tag_buffer(bytes, flexible);
+        Self {
+            group_id,
+            generation_id,
+            member_id,
+            group_instance_id,
+            retention_time_ms,
+            topics,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct OffsetCommitResponse {
+    version: i16,
+    flexible: bool,
+    topics: Vec<OffsetCommitResponseTopic>,
+}
+
+#[derive(Debug)]
+pub struct OffsetCommitResponseTopic {
+    name: String,
+    partitions: Vec<(i32, ErrorCode)>,
+}
+
+impl Serialize for OffsetCommitResponse {
+    fn serialize(&self, bytes: &mut BytesMut) {
+        let (version, flexible) = (self.version, self.flexible);
+        if version >= 3 {
+            bytes.put_i32(0); // throttle time
+        }
+        put_array(bytes, &self.topics, flexible, |topic, bytes| {
+            put_string(bytes, &topic.name, flexible);
+            put_array(
+                bytes,
+                &topic.partitions,
+                flexible,
+                |(partition_index, error_code), bytes| {
+                    bytes.put_i32(*partition_index);
+                    bytes.put_i16(error_code.clone() as i16);
+                    put_tag_buffer(bytes, flexible);
+                },
+            );
+            put_tag_buffer(bytes, flexible);
+        });
+        put_tag_buffer(bytes, flexible);
+    }
+}
+
+pub fn offset_commit_handler(bytes: &mut Bytes, header: RequestHeader) -> Result<Bytes> {
+    let metadata = read_cluster_metadata()?;
+    let store = ConfigStore::load(&metadata);
+    let flexible = header.is_flexible();
+    let req = OffsetCommitRequest::deserialize(bytes, header.request_api_version, flexible);
+
+    let group_error = if req.group_id.is_empty() {
+        Err(ErrorCode::InvalidGroupId)
+    } else if consumer_group_coordinator().contains(&req.group_id) {
+        consumer_group_coordinator().validate_offset_commit(
+            &req.group_id,
+            req.generation_id,
+            &req.member_id,
+        )
+    } else {
+        group_coordinator().validate_offset_commit(
+            &req.group_id,
+            req.generation_id,
+            &req.member_id,
+            req.group_instance_id.as_deref(),
+        )
+    };
+    let max_metadata_size = store.broker_config_i64("offset.metadata.max.bytes");
+    let now = now_ms();
+
+    // Every partition gets an error up front; the valid ones are then
+    // written together and keep NoError if the write succeeds.
+    let mut topics: Vec<OffsetCommitResponseTopic> = vec![];
+    let mut entries = vec![];
+    for topic in &req.topics {
+        let mut partitions = vec![];
+        for partition in &topic.partitions {
+            let error_code = match &group_error {
+                Err(error_code) => error_code.clone(),
+                Ok(()) => validate_partition(
+                    &topic.name,
+                    partition.partition_index,
+                    partition.committed_metadata.as_deref(),
+                    &metadata,
+                    max_metadata_size,
+                ),
+            };
+            if matches!(error_code, ErrorCode::NoError) {
+                let commit_timestamp = if partition.commit_timestamp >= 0 {
+                    partition.commit_timestamp
+                } else {
+                    now
+                };
+                let value = CommittedOffset {
+                    offset: partition.committed_offset,
+                    leader_epoch: partition.committed_leader_epoch,
+                    metadata: partition.committed_metadata.clone().unwrap_or_default(),
+                    commit_timestamp,
+                    expire_timestamp: (req.retention_time_ms >= 0)
+                        .then(|| commit_timestamp.saturating_add(req.retention_time_ms)),
+                };
+                entries.push(((topic.name.clone(), partition.partition_index), Some(value)));
+            }
+            partitions.push((partition.partition_index, error_code));
+        }
+        topics.push(OffsetCommitResponseTopic {
+            name: topic.name.clone(),
+            partitions,
+        });
+    }
+    if offset_store().write(&req.group_id, entries).is_err() {
+        for (_, error_code) in topics.iter_mut().flat_map(|x| x.partitions.iter_mut()) {
+            if matches!(error_code, ErrorCode::NoError) {
+                *error_code = ErrorCode::CoordinatorNotAvailable;
+            }
+        }
+    }
+
+    let body = OffsetCommitResponse {
+        version: header.request_api_version,
+        flexible,
+        topics,
+    };
+    Ok(Response::for_request(&header, body).into())
+}
+
+/// Checks the partition of an offset commit exists and its metadata fits.
+pub fn validate_partition(
+    topic_name: &str,
+    partition_index: i32,
+    committed_metadata: Option<&str>,
+    metadata: &MetadataFile,
+    max_metadata_size: i64,
+) -> ErrorCode {
+    let exists = metadata
+        .get_topic_by_name(topic_name)
+        .is_some_and(|x| metadata.has_partition(&x.uuid, partition_index));
+    let metadata_size = committed_metadata.map_or(0, |x| x.len());
+    if !exists {
+        ErrorCode::UnknownTopicOrPartition
+    } else if metadata_size as i64 > max_metadata_size {
+        ErrorCode::OffsetMetadataTooLarge
+    } else {
+        ErrorCode::NoError
+    }
+}
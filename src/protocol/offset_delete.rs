@@ -0,0 +1,106 @@
+use anyhow::Result;
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+
+use crate::{
+    coordinator::{consumer_group_coordinator, group_coordinator, offset_store},
+    metadata::read_cluster_metadata,
+    protocol::{ErrorCode, Response},
+};
+
+use super::{get_array, get_string, put_array, put_string, RequestHeader, Serialize};
+
+#[derive(Debug)]
+pub struct OffsetDeleteRequest {
+    group_id: String,
+    topics: Vec<(String, Vec<i32>)>,
+}
+
+impl OffsetDeleteRequest {
+    fn deserialize(bytes: &mut Bytes) -> Self {
+        let group_id = get_string(bytes, false);
+        let topics = get_array(bytes, false, |bytes| {
+            let name = get_string(bytes, false);
+            let partitions = get_array(bytes, false, |bytes| bytes.get_i32());
+            (name, partitions)
+        });
+        Self { group_id, topics }
+    }
+}
+
+#[derive(Debug)]
+pub struct OffsetDeleteResponse {
+    error_code: ErrorCode,
+    topics: Vec<(String, Vec<(i32, ErrorCode)>)>,
+}
+
+impl Serialize for OffsetDeleteResponse {
+    fn serialize(&self, bytes: &mut BytesMut) {
+        bytes.put_i16(self.error_code.clone() as i16);
+        bytes.put_i32(0); // throttle time
+        put_array(bytes, &self.topics, false, |(name, partitions), bytes| {
+            put_string(bytes, name, false);
+            put_array(
+                bytes,
+                partitions,
+                false,
+                |(partition_index, error_code), bytes| {
+                    bytes.put_i32(*partition_index);
+                    bytes.put_i16(error_code.clone() as i16);
+                },
+            );
+        });
+    }
+}
+
+pub fn offset_delete_handler(bytes: &mut Bytes, header: RequestHeader) -> Result<Bytes> {
+    let metadata = read_cluster_metadata()?;
+    let req = OffsetDeleteRequest::deserialize(bytes);
+
+    let subscribed = if req.group_id.is_empty() {
+        Err(ErrorCode::InvalidGroupId)
+    } else if let Some(topics) = consumer_group_coordinator().subscribed_topics(&req.group_id) {
+        Ok(topics)
+    } else if !group_coordinator().contains(&req.group_id)
+        && !offset_store().has_group(&req.group_id)
+    {
+        Err(ErrorCode::GroupIdNotFound)
+    } else {
+        group_coordinator().subscribed_topics(&req.group_id)
+    };
+    let body = match subscribed {
+        Err(error_code) => OffsetDeleteResponse {
+            error_code,
+            topics: vec![],
+        },
+        Ok(subscribed) => {
+            let mut deleted = vec![];
+            let mut topics = vec![];
+            for (name, partitions) in &req.topics {
+                let topic = metadata.get_topic_by_name(name);
+                let partitions = partitions
+                    .iter()
+                    .map(|partition| {
+                        let error_code = if !topic
+                            .is_some_and(|x| metadata.has_partition(&x.uuid, *partition))
+                        {
+                            ErrorCode::UnknownTopicOrPartition
+                        } else if subscribed.contains(name) {
+                            ErrorCode::GroupSubscribedToTopic
+                        } else {
+                            deleted.push((name.clone(), *partition));
+                            ErrorCode::NoError
+                        };
+                        (*partition, error_code)
+                    })
+                    .collect();
+                topics.push((name.clone(), partitions));
+            }
+            let error_code = match offset_store().delete_offsets(&req.group_id, &deleted) {
+                Ok(()) => ErrorCode::NoError,
+                Err(_) => ErrorCode::CoordinatorNotAvailable,
+            };
+            OffsetDeleteResponse { error_code, topics }
+        }
+    };
+    Ok(Response::for_request(&header, body).into())
+}
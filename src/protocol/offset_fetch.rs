@@ -0,0 +1,185 @@
+use anyhow::Result;
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+
+use crate::{
+    coordinator::offset_store,
+    protocol::{ErrorCode, Response},
+};
+
+use super::{
+    get_array, get_nullable_array, get_string, get_tag_buffer, put_array, put_nullable_string,
+    put_string, put_tag_buffer, RequestHeader, Serialize,
+};
+
+#[derive(Debug)]
+pub struct OffsetFetchRequest {
+    groups: Vec<OffsetFetchRequestGroup>,
+    _require_stable: bool,
+}
+
+#[derive(Debug)]
+pub struct OffsetFetchRequestGroup {
+    group_id: String,
+    /// `None` fetches every committed offset of the group.
+    topics: Option<Vec<(String, Vec<i32>)>>,
+}
+
+impl OffsetFetchRequest {
+    fn deserialize(bytes: &mut Bytes, version: i16, flexible: bool) -> Self {
+        let get_topics = |bytes: &mut Bytes| {
+            get_nullable_array(bytes, flexible, |bytes| {
+                let name = get_string(bytes, flexible);
+                let partitions = get_array(bytes, flexible, |bytes| bytes.get_i32());
+                get_tag_buffer(bytes, flexible);
+                (name, partitions)
+            })
+        };
+        // v8 batched the single group into a list of groups.
+        let groups = if version >= 8 {
+            get_array(bytes, flexible, |bytes| {
+                let group = OffsetFetchRequestGroup {
+                    group_id: get_string(bytes, flexible),
+                    topics: get_topics(bytes),
+                };
+                get_tag_buffer(bytes, flexible);
+                group
+            })
+        } else {
+            vec![OffsetFetchRequestGroup {
+                group_id: get_string(bytes, flexible),
+                topics: get_topics(bytes),
+            }]
+        };
+        let require_stable = version >= 7 && bytes.get_i8() != 0;
+        get_tag_buffer(bytes, flexible);
+        Self {
+            groups,
+            _require_stable: require_stable,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct OffsetFetchResponse {
+    version: i16,
+    flexible: bool,
+    groups: Vec<OffsetFetchResponseGroup>,
+}
+
+#[derive(Debug)]
+pub struct OffsetFetchResponseGroup {
+    group_id: String,
+    topics: Vec<OffsetFetchResponseTopic>,
+    error_code: ErrorCode,
+}
+
+#[derive(Debug)]
+pub struct OffsetFetchResponseTopic {
+    name: String,
+    partitions: Vec<OffsetFetchResponsePartition>,
+}
+
+#[derive(Debug)]
+pub struct OffsetFetchResponsePartition {
+    partition_index: i32,
+    committed_offset: i64,
+    committed_leader_epoch: i32,
+    metadata: Option<String>,
+    error_code: ErrorCode,
+}
+
+impl OffsetFetchResponse {
+    fn put_topics(&self, bytes: &mut BytesMut, topics: &[OffsetFetchResponseTopic]) {
+        let (version, flexible) = (self.version, self.flexible);
+        put_array(bytes, topics, flexible, |topic, bytes| {
+            put_string(bytes, &topic.name, flexible);
+            put_array(bytes, &topic.partitions, flexible, |partition, bytes| {
+                bytes.put_i32(partition.partition_index);
+                bytes.put_i64(partition.committed_offset);
+                if version >= 5 {
+                    bytes.put_i32(partition.committed_leader_epoch);
+                }
+                put_nullable_string(bytes, partition.metadata.as_deref(), flexible);
+                bytes.put_i16(partition.error_code.clone() as i16);
+                put_tag_buffer(bytes, flexible);
+            });
+            put_tag_buffer(bytes, flexible);
+        });
+    }
+}
+
+impl Serialize for OffsetFetchResponse {
+    fn serialize(&self, bytes: &mut BytesMut) {
+        let (version, flexible) = (self.version, self.flexible);
+        if version >= 3 {
+            bytes.put_i32(0); // throttle time
+        }
+        if version >= 8 {
+            put_array(bytes, &self.groups, flexible, |group, bytes| {
+                put_string(bytes, &group.group_id, flexible);
+                self.put_topics(bytes, &group.topics);
+                bytes.put_i16(group.error_code.clone() as i16);
+                put_tag_buffer(bytes, flexible);
+            });
+        } else {
+            let group = &self.groups[0];
+            self.put_topics(bytes, &group.topics);
+            if version >= 2 {
+                bytes.put_i16(group.error_code.clone() as i16);
+            }
+        }
+        put_tag_buffer(bytes, flexible);
+    }
+}
+
+pub fn offset_fetch_handler(bytes: &mut Bytes, header: RequestHeader) -> Result<Bytes> {
+    let flexible = header.is_flexible();
+    let req = OffsetFetchRequest::deserialize(bytes, header.request_api_version, flexible);
+    let body = OffsetFetchResponse {
+        version: header.request_api_version,
+        flexible,
+        groups: req.groups.iter().map(group_handler).collect(),
+    };
+    Ok(Response::for_request(&header, body).into())
+}
+
+fn group_handler(group: &OffsetFetchRequestGroup) -> OffsetFetchResponseGroup {
+    let requested: Option<Vec<(String, i32)>> = group.topics.as_ref().map(|topics| {
+        topics
+            .iter()
+            .flat_map(|(name, partitions)| partitions.iter().map(|x| (name.clone(), *x)))
+            .collect()
+    });
+    let mut topics: Vec<OffsetFetchResponseTopic> = vec![];
+    for ((topic, partition), offset) in offset_store().fetch(&group.group_id, requested.as_deref())
+    {
+        let partition = match offset {
+            Some(offset) => OffsetFetchResponsePartition {
+                partition_index: partition,
+                committed_offset: offset.offset,
+                committed_leader_epoch: offset.leader_epoch,
+                metadata: Some(offset.metadata),
+                error_code: ErrorCode::NoError,
+            },
+            None => OffsetFetchResponsePartition {
+                partition_index: partition,
+                committed_offset: -1,
+                committed_leader_epoch: -1,
+                metadata: Some(String::new()),
+                error_code: ErrorCode::NoError,
+            },
+        };
+        match topics.last_mut() {
+            Some(last) if last.name == topic => last.partitions.push(partition),
+            _ => topics.push(OffsetFetchResponseTopic {
+                name: topic,
+                partitions: vec![partition],
+            }),
+        }
+    }
+    OffsetFetchResponseGroup {
+        group_id: group.group_id.clone(),
+        topics,
+        error_code: ErrorCode::NoError,
+    }
+}
@@ -0,0 +1,176 @@
+use anyhow::Result;
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+
+use crate::{
+    metadata::{read_cluster_metadata, MetadataFile},
+    protocol::{ErrorCode, Response},
+    storage::{PartitionLog, UNDEFINED_EPOCH, UNDEFINED_EPOCH_OFFSET},
+};
+
+use super::{
+    get_array, get_string, get_tag_buffer, put_array, put_string, put_tag_buffer, RequestHeader,
+    Serialize,
+};
+
+#[derive(Debug)]
+pub struct OffsetForLeaderEpochRequest {
+    _replica_id: i32,
+    topics: Vec<OffsetForLeaderTopic>,
+}
+
+#[derive(Debug)]
+pub struct OffsetForLeaderTopic {
+    topic: String,
+    partitions: Vec<OffsetForLeaderPartition>,
+}
+
+#[derive(Debug)]
+pub struct OffsetForLeaderPartition {
+    partition: i32,
+    current_leader_epoch: i32,
+    leader_epoch: i32,
+}
+
+impl OffsetForLeaderEpochRequest {
+    fn deserialize(bytes: &mut Bytes, version: i16, flexible: bool) -> Self {
+        let replica_id = if version >= 3 { bytes.get_i32() } else { -1 };
+        let topics = get_array(bytes, flexible, |bytes| {
+            let topic = get_string(bytes, flexible);
+            let partitions = get_array(bytes, flexible, |bytes| {
+                let partition = OffsetForLeaderPartition {
+                    partition: bytes.get_i32(),
+                    current_leader_epoch: if version >= 2 { bytes.get_i32() } else { -1 },
+                    leader_epoch: bytes.get_i32(),
+                };
+                get_tag_buffer(bytes, flexible);
+                partition
+            });
+            get_tag_buffer(bytes, flexible);
+            OffsetForLeaderTopic { topic, partitions }
+        });
+        get_tag_buffer(bytes, flexible);
+        Self {
+            _replica_id: replica_id,
+            topics,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct OffsetForLeaderEpochResponse {
+    version: i16,
+    flexible: bool,
+    topics: Vec<OffsetForLeaderTopicResult>,
+}
+
+#[derive(Debug)]
+pub struct OffsetForLeaderTopicResult {
+    topic: String,
+    partitions: Vec<EpochEndOffset>,
+}
+
+#[derive(Debug)]
+pub struct EpochEndOffset {
+    error_code: ErrorCode,
+    partition: i32,
+    leader_epoch: i32,
+    end_offset: i64,
+}
+
+impl Serialize for OffsetForLeaderEpochResponse {
+    fn serialize(&self, bytes: &mut BytesMut) {
+        let (version, flexible) = (self.version, self.flexible);
+        if version >= 2 {
+            bytes.put_i32(0); // throttle time
+        }
+        put_array(bytes, &self.topics, flexible, |topic, bytes| {
+            put_string(bytes, &topic.topic, flexible);
+            put_array(bytes, &topic.partitions, flexible, |partition, bytes| {
+                bytes.put_i16(partition.error_code.clone() as i16);
+                bytes.put_i32(partition.partition);
+                if version >= 1 {
+                    bytes.put_i32(partition.leader_epoch);
+                }
+                bytes.put_i64(partition.end_offset);
+                put_tag_buffer(bytes, flexible);
+            });
+            put_tag_buffer(bytes, flexible);
+        });
+        put_tag_buffer(bytes, flexible);
+    }
+}
+
+pub fn offset_for_leader_epoch_handler(bytes: &mut Bytes, header: RequestHeader) -> Result<Bytes> {
+    let metadata = read_cluster_metadata()?;
+    let flexible = header.is_flexible();
+    let req = OffsetForLeaderEpochRequest::deserialize(bytes, header.request_api_version, flexible);
+    let topics = req
+        .topics
+        .iter()
+        .map(|topic| OffsetForLeaderTopicResult {
+            topic: topic.topic.clone(),
+            partitions: topic
+                .partitions
+                .iter()
+                .map(|partition| partition_handler(&topic.topic, partition, &metadata))
+                .collect(),
+        })
+        .collect();
+    let body = OffsetForLeaderEpochResponse {
+        version: header.request_api_version,
+        flexible,
+        topics,
+    };
+    Ok(Response::for_request(&header, body).into())
+}
+
+fn partition_handler(
+    topic_name: &str,
+    partition: &OffsetForLeaderPartition,
+    metadata: &MetadataFile,
+) -> EpochEndOffset {
+    let mut response = EpochEndOffset {
+        error_code: ErrorCode::NoError,
+        partition: partition.partition,
+        leader_epoch: UNDEFINED_EPOCH,
+        end_offset: UNDEFINED_EPOCH_OFFSET,
+    };
+    let Some(partition_record) = metadata
+        .get_topic_by_name(topic_name)
+        .and_then(|x| metadata.get_partition(&x.uuid, partition.partition))
+    else {
+        response.error_code = ErrorCode::UnknownTopicOrPartition;
+        return response;
+    };
+    if let Err(error_code) = check_leader_epoch(
+        partition.current_leader_epoch,
+        partition_record.leader_epoch,
+    ) {
+        response.error_code = error_code;
+        return response;
+    }
+    let result = PartitionLog::open(topic_name, partition.partition).and_then(|log| {
+        let cache = log.leader_epoch_cache()?;
+        Ok(cache.end_offset_for(partition.leader_epoch, log.log_end_offset()?))
+    });
+    match result {
+        Ok((leader_epoch, end_offset)) => {
+            response.leader_epoch = leader_epoch;
+            response.end_offset = end_offset;
+        }
+        Err(_) => response.error_code = ErrorCode::KafkaStorageError,
+    }
+    response
+}
+
+/// Validates a client's view of the leader epoch against the partition's.
+/// -1 means the client did not send one.
+pub fn check_leader_epoch(requested: i32, current: i32) -> Result<(), ErrorCode> {
+    if requested == UNDEFINED_EPOCH || requested == current {
+        Ok(())
+    } else if requested < current {
+        Err(ErrorCode::FencedLeaderEpoch)
+    } else {
+        Err(ErrorCode::UnknownLeaderEpoch)
+    }
+}
@@ -0,0 +1,264 @@
+use anyhow::Result;
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+
+use crate::{
+    config::ConfigStore,
+    coordinator::{is_internal_topic, transaction_coordinator},
+    metadata::{now_ms, read_cluster_metadata, MetadataFile},
+    protocol::{ErrorCode, Response},
+    storage::{parse_batches, producer_state, BatchInfo, PartitionLog},
+};
+
+use super::{
+    get_array, get_nullable_bytes, get_nullable_string, get_string, get_tag_buffer, put_array,
+    put_nullable_string, put_string, put_tag_buffer, RequestHeader, Serialize,
+};
+
+#[derive(Debug)]
+pub struct ProducePartition {
+    index: i32,
+    records: Option<Bytes>,
+}
+
+#[derive(Debug)]
+pub struct ProduceRequest {
+    transactional_id: Option<String>,
+    acks: i16,
+    _timeout_ms: i32,
+    topic_data: Vec<(String, Vec<ProducePartition>)>,
+}
+
+impl ProduceRequest {
+    fn deserialize(bytes: &mut Bytes, flexible: bool) -> Self {
+        let req = Self {
+            transactional_id: get_nullable_string(bytes, flexible),
+            acks: bytes.get_i16(),
+            _timeout_ms: bytes.get_i32(),
+            topic_data: get_array(bytes, flexible, |bytes| {
+                let name = get_string(bytes, flexible);
+                let partitions = get_array(bytes, flexible, |bytes| {
+                    let partition = ProducePartition {
+                        index: bytes.get_i32(),
+                        records: get_nullable_bytes(bytes, flexible),
+                    };
+                    get_tag_buffer(bytes, flexible);
+                    partition
+                });
+                get_tag_buffer(bytes, flexible);
+                (name, partitions)
+            }),
+        };
+        get_tag_buffer(bytes, flexible);
+        req
+    }
+}
+
+#[derive(Debug)]
+pub struct ProducePartitionResponse {
+    index: i32,
+    error_code: ErrorCode,
+    base_offset: i64,
+    log_start_offset: i64,
+    error_message: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct ProduceResponse {
+    version: i16,
+    flexible: bool,
+    responses: Vec<(String, Vec<ProducePartitionResponse>)>,
+}
+
+impl Serialize for ProduceResponse {
+    fn serialize(&self, bytes: &mut BytesMut) {
+        let (version, flexible) = (self.version, self.flexible);
+        put_array(
+            bytes,
+            &self.responses,
+            flexible,
+            |(name, partitions), bytes| {
+                put_string(bytes, name, flexible);
+                put_array(bytes, partitions, flexible, |x, bytes| {
+                    bytes.put_i32(x.index);
+                    bytes.put_i16(x.error_code.clone() as i16);
+                    bytes.put_i64(x.base_offset);
+                    bytes.put_i64(-1); // log append time, timestamps are CreateTime
+                    if version >= 5 {
+                        bytes.put_i64(x.log_start_offset);
+                    }
+                    if version >= 8 {
+                        put_array::<i32>(bytes, &[], flexible, |_, _| {}); // record errors
+                        put_nullable_string(bytes, x.error_message.as_deref(), flexible);
+                    }
+                    put_tag_buffer(bytes, flexible);
+                });
+                put_tag_buffer(bytes, flexible);
+            },
+        );
+        bytes.put_i32(0); // throttle time
+        put_tag_buffer(bytes, flexible);
+    }
+}
+
+impl ProducePartitionResponse {
+    fn error(index: i32, error_code: ErrorCode) -> Self {
+        Self {
+            index,
+            error_code,
+            base_offset: -1,
+            log_start_offset: -1,
+            error_message: None,
+        }
+    }
+}
+
+/// Appends the single batch of a partition, checking its producer sequence
+/// first. Retries of a batch already in the log get its original offset.
+fn append_partition(
+    transactional_id: Option<&str>,
+    topic: &str,
+    partition: &ProducePartition,
+    metadata: &MetadataFile,
+    store: &ConfigStore,
+) -> ProducePartitionResponse {
+    let index = partition.index;
+    let Some(leader) = metadata
+        .get_topic_by_name(topic)
+        .and_then(|x| metadata.get_partition(&x.uuid, index))
+    else {
+        return ProducePartitionResponse::error(index, ErrorCode::UnknownTopicOrPartition);
+    };
+    if is_internal_topic(topic) {
+        return ProducePartitionResponse::error(index, ErrorCode::InvalidTopicException);
+    }
+    let records = partition.records.clone().unwrap_or_default();
+    let batches = parse_batches(&records);
+    let [batch] = batches.as_slice() else {
+        let mut response = ProducePartitionResponse::error(index, ErrorCode::InvalidRecord);
+        response.error_message =
+            Some("Produce requests must have exactly one record batch per partition.".to_owned());
+        return response;
+    };
+    if batch.size != records.len() || !batch.is_valid(&records) {
+        return ProducePartitionResponse::error(index, ErrorCode::CorruptMessage);
+    }
+    if batch.is_control() {
+        let mut response = ProducePartitionResponse::error(index, ErrorCode::InvalidRecord);
+        response.error_message = Some("Clients cannot write control batches.".to_owned());
+        return response;
+    }
+    if batch.is_transactional() {
+        // The partition must have been added to the producer's transaction.
+        let Some(transactional_id) = transactional_id else {
+            return ProducePartitionResponse::error(index, ErrorCode::InvalidRecord);
+        };
+        let verified = transaction_coordinator().verify_partition(
+            transactional_id,
+            batch.producer_id,
+            batch.producer_epoch,
+            &(topic.to_owned(), index),
+        );
+        if let Err(error_code) = verified {
+            let error_code = match error_code {
+                ErrorCode::ProducerFenced => ErrorCode::InvalidProducerEpoch,
+                x => x,
+            };
+            return ProducePartitionResponse::error(index, error_code);
+        }
+    }
+
+    match append_batch(topic, index, batch, &records, leader.leader_epoch, store) {
+        Ok((base_offset, log_start_offset)) => ProducePartitionResponse {
+            index,
+            error_code: ErrorCode::NoError,
+            base_offset,
+            log_start_offset,
+            error_message: None,
+        },
+        Err(error_code) => ProducePartitionResponse::error(index, error_code),
+    }
+}
+
+fn storage_error(_: anyhow::Error) -> ErrorCode {
+    ErrorCode::KafkaStorageError
+}
+
+/// Returns the base offset of the batch and the log start offset.
+fn append_batch(
+    topic: &str,
+    partition: i32,
+    batch: &BatchInfo,
+    records: &Bytes,
+    leader_epoch: i32,
+    store: &ConfigStore,
+) -> Result<(i64, i64), ErrorCode> {
+    let state = producer_state(topic, partition);
+    let mut state = state.lock().unwrap();
+    let mut log = PartitionLog::open(topic, partition).map_err(storage_error)?;
+    if let Some(segment_bytes) = store
+        .topic_config(topic, "segment.bytes")
+        .and_then(|x| x.parse().ok())
+    {
+        log.set_segment_bytes(segment_bytes);
+    }
+    state.sync(&log).map_err(storage_error)?;
+    state.expire(
+        now_ms(),
+        store.broker_config_i64("producer.id.expiration.ms"),
+    );
+    if let Some(duplicate) = state.check(batch)? {
+        return Ok((duplicate.first_offset, log.log_start_offset()));
+    }
+    let base_offset = log
+        .append_records(BytesMut::from(&records[..]), leader_epoch)
+        .map_err(storage_error)?;
+    let mut appended = batch.clone();
+    appended.base_offset = base_offset;
+    appended.last_offset = batch.last_offset - batch.base_offset + base_offset;
+    state.update(&appended);
+    state
+        .maybe_take_snapshot(log.dir())
+        .map_err(storage_error)?;
+    Ok((base_offset, log.log_start_offset()))
+}
+
+pub fn produce_handler(bytes: &mut Bytes, header: RequestHeader) -> Result<Bytes> {
+    let version = header.request_api_version;
+    let flexible = header.is_flexible();
+    let req = ProduceRequest::deserialize(bytes, flexible);
+    let metadata = read_cluster_metadata()?;
+    let store = ConfigStore::load(&metadata);
+    let responses = req
+        .topic_data
+        .iter()
+        .map(|(topic, partitions)| {
+            let partitions = partitions
+                .iter()
+                .map(|partition| match req.acks {
+                    -1..=1 => append_partition(
+                        req.transactional_id.as_deref(),
+                        topic,
+                        partition,
+                        &metadata,
+                        &store,
+                    ),
+                    _ => ProducePartitionResponse::error(
+                        partition.index,
+                        ErrorCode::InvalidRequiredAcks,
+                    ),
+                })
+                .collect();
+            (topic.clone(), partitions)
+        })
+        .collect();
+    // Producers sending acks=0 do not read responses.
+    if req.acks == 0 {
+        return Ok(Bytes::new());
+    }
+    let body = ProduceResponse {
+        version,
+        flexible,
+        responses,
+    };
+    Ok(Response::for_request(&header, body).into())
+}
@@ -1,7 +1,7 @@
 use crate::protocol::Deserialize;
 use bytes::{Buf, Bytes};
 
-use super::ClientId;
+use super::{get_tag_buffer, Api, ClientId};
 
 pub struct Request<T: Deserialize> {
     pub header: RequestHeader,
@@ -23,6 +23,12 @@ impl Deserialize for EmptyRequestBody {
     }
 }
 
+impl RequestHeader {
+    pub fn is_flexible(&self) -> bool {
+        Api::from(self.request_api_key).is_flexible(self.request_api_version)
+    }
+}
+
 impl From<&mut Bytes> for RequestHeader {
     fn from(bytes: &mut Bytes) -> Self {
         let header = Self {
@@ -32,7 +38,7 @@ impl From<&mut Bytes> for RequestHeader {
             correlation_id: bytes.get_i32(),
             client_id: ClientId::deserialize(bytes),
         };
-        bytes.advance(1); // tag buffer
+        get_tag_buffer(bytes, header.is_flexible());
         header
     }
 }
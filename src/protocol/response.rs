@@ -1,6 +1,8 @@
 use std::fmt::Debug;
+use std::sync::mpsc::Receiver;
 
-use super::{ErrorCode, Serialize};
+use super::{ErrorCode, RequestHeader, Serialize};
+use crate::storage::FileRange;
 use bytes::{BufMut, Bytes, BytesMut};
 
 #[derive(Debug)]
@@ -25,6 +27,14 @@ impl<T: Serialize> Response<T> {
             skip_tag_buffer: true,
         }
     }
+    /// Picks the response header version matching the request's encoding.
+    pub fn for_request(header: &RequestHeader, body: T) -> Self {
+        if header.is_flexible() {
+            Self::new(header.correlation_id, body)
+        } else {
+            Self::new_v0(header.correlation_id, body)
+        }
+    }
 }
 
 impl<T: Serialize + Debug> From<Response<T>> for Bytes {
@@ -46,6 +56,104 @@ impl<T: Serialize + Debug> From<Response<T>> for Bytes {
     }
 }
 
+/// Part of an encoded response: bytes, or records sent from a segment file
+/// without being copied.
+#[derive(Debug, Clone)]
+pub enum ResponsePart {
+    Bytes(Bytes),
+    File(FileRange),
+}
+
+/// An encoded response, size prefix included, ready to be written.
+#[derive(Debug, Default)]
+pub struct ResponseData {
+    pub parts: Vec<ResponsePart>,
+}
+
+impl ResponseData {
+    /// Empty for requests that expect no response.
+    pub fn is_empty(&self) -> bool {
+        self.parts.is_empty()
+    }
+}
+
+impl From<Bytes> for ResponseData {
+    fn from(bytes: Bytes) -> Self {
+        let parts = if bytes.is_empty() {
+            vec![]
+        } else {
+            vec![ResponsePart::Bytes(bytes)]
+        };
+        Self { parts }
+    }
+}
+
+/// What a handler answers: a response, or one that is completed later on
+/// another thread, like a fetch waiting for data. The connection writes
+/// both in request order.
+#[derive(Debug)]
+pub enum Reply {
+    Ready(ResponseData),
+    Delayed(Receiver<ResponseData>),
+}
+
+impl From<ResponseData> for Reply {
+    fn from(response: ResponseData) -> Self {
+        Self::Ready(response)
+    }
+}
+
+/// Encodes a response whose body holds ranges of segment files.
+#[derive(Debug)]
+pub struct ResponseBuilder {
+    parts: Vec<ResponsePart>,
+    bytes: BytesMut,
+    size: usize,
+}
+
+impl ResponseBuilder {
+    /// Starts a response with the header matching the request's encoding.
+    pub fn for_request(header: &RequestHeader) -> Self {
+        let mut bytes = BytesMut::new();
+        bytes.put_bytes(0, 4); // reserve 4 bytes for size
+        bytes.put_i32(header.correlation_id);
+        if header.is_flexible() {
+            bytes.put_i8(0); // tag buffer
+        }
+        Self {
+            parts: vec![],
+            bytes,
+            size: 0,
+        }
+    }
+
+    /// Where the encoded fields go.
+    pub fn bytes(&mut self) -> &mut BytesMut {
+        &mut self.bytes
+    }
+
+    pub fn put_file(&mut self, range: FileRange) {
+        let bytes = self.bytes.split().freeze();
+        self.size += bytes.len() + range.size as usize;
+        self.parts.push(ResponsePart::Bytes(bytes));
+        self.parts.push(ResponsePart::File(range));
+    }
+
+    pub fn finish(mut self) -> ResponseData {
+        let bytes = self.bytes.split().freeze();
+        self.size += bytes.len();
+        self.parts.push(ResponsePart::Bytes(bytes));
+        let size = (self.size - 4) as i32; // size of message after first 4 bytes
+        let ResponsePart::Bytes(first) = &self.parts[0] else {
+            unreachable!("responses start with their header");
+        };
+        let mut first = BytesMut::from(&first[..]);
+        first[0..4].copy_from_slice(&size.to_be_bytes());
+        self.parts[0] = ResponsePart::Bytes(first.freeze());
+        ResponseData { parts: self.parts }
+    }
+}
+
 #[derive(Debug)]
 pub struct EmptyResponseBody {
     pub error_code: ErrorCode,
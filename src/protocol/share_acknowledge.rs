@@ -0,0 +1,180 @@
+use anyhow::Result;
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+
+use crate::{
+    config::ConfigStore,
+    coordinator::{
+        share_partition_manager, AcknowledgementBatch, ShareGroupConfig, SharePartitionKey,
+        FINAL_SHARE_SESSION_EPOCH, INITIAL_SHARE_SESSION_EPOCH,
+    },
+    metadata::read_cluster_metadata,
+    protocol::{ErrorCode, Response},
+};
+
+use super::{
+    get_acknowledgement_batches, get_array, get_nullable_string, get_tag_buffer, put_array,
+    put_nullable_string, put_tag_buffer, share_member, RequestHeader, Serialize,
+};
+
+#[derive(Debug)]
+pub struct ShareAcknowledgePartition {
+    partition_index: i32,
+    acknowledgement_batches: Vec<AcknowledgementBatch>,
+}
+
+#[derive(Debug)]
+pub struct ShareAcknowledgeRequest {
+    group_id: Option<String>,
+    member_id: Option<String>,
+    share_session_epoch: i32,
+    topics: Vec<(i128, Vec<ShareAcknowledgePartition>)>,
+}
+
+impl ShareAcknowledgeRequest {
+    fn deserialize(bytes: &mut Bytes) -> Self {
+        let req = Self {
+            group_id: get_nullable_string(bytes, true),
+            member_id: get_nullable_string(bytes, true),
+            share_session_epoch: bytes.get_i32(),
+            topics: get_array(bytes, true, |bytes| {
+                let topic_id = bytes.get_i128();
+                let partitions = get_array(bytes, true, |bytes| {
+                    let partition = ShareAcknowledgePartition {
+                        partition_index: bytes.get_i32(),
+                        acknowledgement_batches: get_acknowledgement_batches(bytes),
+                    };
+                    get_tag_buffer(bytes, true);
+                    partition
+                });
+                get_tag_buffer(bytes, true);
+                (topic_id, partitions)
+            }),
+        };
+        get_tag_buffer(bytes, true);
+        req
+    }
+}
+
+#[derive(Debug)]
+pub struct ShareAcknowledgePartitionResponse {
+    partition_index: i32,
+    error_code: ErrorCode,
+    leader_id: i32,
+    leader_epoch: i32,
+}
+
+#[derive(Debug)]
+pub struct ShareAcknowledgeResponse {
+    error_code: ErrorCode,
+    error_message: Option<String>,
+    responses: Vec<(i128, Vec<ShareAcknowledgePartitionResponse>)>,
+}
+
+impl Serialize for ShareAcknowledgeResponse {
+    fn serialize(&self, bytes: &mut BytesMut) {
+        bytes.put_i32(0); // throttle time
+        bytes.put_i16(self.error_code.clone() as i16);
+        put_nullable_string(bytes, self.error_message.as_deref(), true);
+        put_array(
+            bytes,
+            &self.responses,
+            true,
+            |(topic_id, partitions), bytes| {
+                bytes.put_i128(*topic_id);
+                put_array(bytes, partitions, true, |x, bytes| {
+                    bytes.put_i32(x.partition_index);
+                    bytes.put_i16(x.error_code.clone() as i16);
+                    put_nullable_string(bytes, None, true);
+                    bytes.put_i32(x.leader_id);
+                    bytes.put_i32(x.leader_epoch);
+                    put_tag_buffer(bytes, true);
+                    put_tag_buffer(bytes, true);
+                });
+                put_tag_buffer(bytes, true);
+            },
+        );
+        put_array::<i32>(bytes, &[], true, |_, _| {}); // node endpoints
+        put_tag_buffer(bytes, true);
+    }
+}
+
+pub fn share_acknowledge_handler(bytes: &mut Bytes, header: RequestHeader) -> Result<Bytes> {
+    let metadata = read_cluster_metadata()?;
+    let config = ShareGroupConfig::load(&ConfigStore::load(&metadata));
+    let req = ShareAcknowledgeRequest::deserialize(bytes);
+    let result = share_member(req.group_id.as_deref(), req.member_id.as_deref()).and_then(
+        |(group_id, member_id)| {
+            if req.share_session_epoch == INITIAL_SHARE_SESSION_EPOCH {
+                return Err((
+                    ErrorCode::InvalidShareSessionEpoch,
+                    "A share session can only be opened by ShareFetch.".to_owned(),
+                ));
+            }
+            share_partition_manager().update_session(
+                &group_id,
+                &member_id,
+                req.share_session_epoch,
+                &[],
+                &[],
+                &config.limits,
+            )?;
+            Ok((group_id, member_id))
+        },
+    );
+    let (group_id, member_id) = match result {
+        Ok(x) => x,
+        Err((error_code, error_message)) => {
+            let body = ShareAcknowledgeResponse {
+                error_code,
+                error_message: Some(error_message),
+                responses: vec![],
+            };
+            return Ok(Response::for_request(&header, body).into());
+        }
+    };
+
+    let responses = req
+        .topics
+        .iter()
+        .map(|(topic_id, partitions)| {
+            let partitions = partitions
+                .iter()
+                .map(|partition| {
+                    let partition_index = partition.partition_index;
+                    let leader = metadata.get_partition(topic_id, partition_index);
+                    let key = SharePartitionKey {
+                        group_id: group_id.clone(),
+                        topic_id: *topic_id,
+                        partition: partition_index,
+                    };
+                    let error_code = match share_partition_manager().acknowledge(
+                        &key,
+                        &member_id,
+                        &partition.acknowledgement_batches,
+                        &config.limits,
+                        &metadata,
+                    ) {
+                        Ok(()) => ErrorCode::NoError,
+                        Err(error_code) => error_code,
+                    };
+                    ShareAcknowledgePartitionResponse {
+                        partition_index,
+                        error_code,
+                        leader_id: leader.map_or(-1, |x| x.leader_id),
+                        leader_epoch: leader.map_or(-1, |x| x.leader_epoch),
+                    }
+                })
+                .collect();
+            (*topic_id, partitions)
+        })
+        .collect();
+    if req.share_session_epoch == FINAL_SHARE_SESSION_EPOCH {
+        share_partition_manager().close_session(&group_id, &member_id, &config.limits);
+    }
+    let body = ShareAcknowledgeResponse {
+        error_code: ErrorCode::NoError,
+        error_message: None,
+        responses,
+    };
+    Ok(Response::for_request(&header, body).into())
+}
@@ -0,0 +1,455 @@
+use anyhow::Result;
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+use crate::{
+    config::ConfigStore,
+    coordinator::{
+        share_group_coordinator, share_partition_manager, AcknowledgementBatch, AcquiredRecords,
+        ShareGroupConfig, SharePartitionKey, SharePartitionLimits, FINAL_SHARE_SESSION_EPOCH,
+        INITIAL_SHARE_SESSION_EPOCH,
+    },
+    metadata::{read_cluster_metadata, MetadataFile},
+    protocol::{ErrorCode, Reply, Response, ResponseData},
+};
+
+use super::{
+    fetch_purgatory, get_array, get_nullable_string, get_tag_buffer, put_array, put_nullable_bytes,
+    put_nullable_string, put_tag_buffer, DelayedOperation, RequestHeader, Serialize,
+};
+
+#[derive(Debug)]
+pub struct ShareFetchPartition {
+    partition_index: i32,
+    acknowledgement_batches: Vec<AcknowledgementBatch>,
+}
+
+#[derive(Debug)]
+pub struct ShareFetchRequest {
+    group_id: Option<String>,
+    member_id: Option<String>,
+    share_session_epoch: i32,
+    max_wait_ms: i32,
+    min_bytes: i32,
+    max_bytes: i32,
+    max_records: i32,
+    _batch_size: i32,
+    topics: Vec<(i128, Vec<ShareFetchPartition>)>,
+    forgotten_topics: Vec<(i128, Vec<i32>)>,
+}
+
+/// Reads the acknowledgement batches of a partition, shared with
+/// ShareAcknowledge.
+pub fn get_acknowledgement_batches(bytes: &mut Bytes) -> Vec<AcknowledgementBatch> {
+    get_array(bytes, true, |bytes| {
+        let batch = AcknowledgementBatch {
+            first_offset: bytes.get_i64(),
+            last_offset: bytes.get_i64(),
+            acknowledge_types: get_array(bytes, true, |bytes| bytes.get_i8()),
+        };
+        get_tag_buffer(bytes, true);
+        batch
+    })
+}
+
+impl ShareFetchRequest {
+    fn deserialize(bytes: &mut Bytes) -> Self {
+        let req = Self {
+            group_id: get_nullable_string(bytes, true),
+            member_id: get_nullable_string(bytes, true),
+            share_session_epoch: bytes.get_i32(),
+            max_wait_ms: bytes.get_i32(),
+            min_bytes: bytes.get_i32(),
+            max_bytes: bytes.get_i32(),
+            max_records: bytes.get_i32(),
+            _batch_size: bytes.get_i32(),
+            topics: get_array(bytes, true, |bytes| {
+                let topic_id = bytes.get_i128();
+                let partitions = get_array(bytes, true, |bytes| {
+                    let partition = ShareFetchPartition {
+                        partition_index: bytes.get_i32(),
+                        acknowledgement_batches: get_acknowledgement_batches(bytes),
+                    };
+                    get_tag_buffer(bytes, true);
+                    partition
+                });
+                get_tag_buffer(bytes, true);
+                (topic_id, partitions)
+            }),
+            forgotten_topics: get_array(bytes, true, |bytes| {
+                let topic_id = bytes.get_i128();
+                let partitions = get_array(bytes, true, |bytes| bytes.get_i32());
+                get_tag_buffer(bytes, true);
+                (topic_id, partitions)
+            }),
+        };
+        get_tag_buffer(bytes, true);
+        req
+    }
+}
+
+#[derive(Debug)]
+pub struct ShareFetchPartitionResponse {
+    partition_index: i32,
+    error_code: ErrorCode,
+    acknowledge_error_code: ErrorCode,
+    leader_id: i32,
+    leader_epoch: i32,
+    records: Vec<Bytes>,
+    acquired_records: Vec<AcquiredRecords>,
+}
+
+impl ShareFetchPartitionResponse {
+    fn new(partition_index: i32, metadata: &MetadataFile, topic_id: i128) -> Self {
+        let leader = metadata.get_partition(&topic_id, partition_index);
+        Self {
+            partition_index,
+            error_code: ErrorCode::NoError,
+            acknowledge_error_code: ErrorCode::NoError,
+            leader_id: leader.map_or(-1, |x| x.leader_id),
+            leader_epoch: leader.map_or(-1, |x| x.leader_epoch),
+            records: vec![],
+            acquired_records: vec![],
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ShareFetchResponse {
+    error_code: ErrorCode,
+    error_message: Option<String>,
+    acquisition_lock_timeout_ms: i32,
+    responses: Vec<(i128, Vec<ShareFetchPartitionResponse>)>,
+}
+
+impl Serialize for ShareFetchResponse {
+    fn serialize(&self, bytes: &mut BytesMut) {
+        bytes.put_i32(0); // throttle time
+        bytes.put_i16(self.error_code.clone() as i16);
+        put_nullable_string(bytes, self.error_message.as_deref(), true);
+        bytes.put_i32(self.acquisition_lock_timeout_ms);
+        put_array(
+            bytes,
+            &self.responses,
+            true,
+            |(topic_id, partitions), bytes| {
+                bytes.put_i128(*topic_id);
+                put_array(bytes, partitions, true, |x, bytes| {
+                    bytes.put_i32(x.partition_index);
+                    bytes.put_i16(x.error_code.clone() as i16);
+                    put_nullable_string(bytes, None, true);
+                    bytes.put_i16(x.acknowledge_error_code.clone() as i16);
+                    put_nullable_string(bytes, None, true);
+                    bytes.put_i32(x.leader_id);
+                    bytes.put_i32(x.leader_epoch);
+                    put_tag_buffer(bytes, true);
+                    put_nullable_bytes(bytes, Some(&x.records.concat()), true);
+                    put_array(bytes, &x.acquired_records, true, |x, bytes| {
+                        bytes.put_i64(x.first_offset);
+                        bytes.put_i64(x.last_offset);
+                        bytes.put_i16(x.delivery_count);
+                        put_tag_buffer(bytes, true);
+                    });
+                    put_tag_buffer(bytes, true);
+                });
+                put_tag_buffer(bytes, true);
+            },
+        );
+        put_array::<i32>(bytes, &[], true, |_, _| {}); // node endpoints
+        put_tag_buffer(bytes, true);
+    }
+}
+
+type PartitionResponses = BTreeMap<i128, BTreeMap<i32, ShareFetchPartitionResponse>>;
+
+fn partition_response<'a>(
+    responses: &'a mut PartitionResponses,
+    topic_id: i128,
+    partition: i32,
+    metadata: &MetadataFile,
+) -> &'a mut ShareFetchPartitionResponse {
+    responses
+        .entry(topic_id)
+        .or_default()
+        .entry(partition)
+        .or_insert_with(|| ShareFetchPartitionResponse::new(partition, metadata, topic_id))
+}
+
+/// Validates the group and member of a share fetch or acknowledge request.
+pub fn share_member(
+    group_id: Option<&str>,
+    member_id: Option<&str>,
+) -> Result<(String, String), (ErrorCode, String)> {
+    let (Some(group_id), Some(member_id)) = (group_id, member_id) else {
+        return Err((
+            ErrorCode::InvalidRequest,
+            "GroupId and MemberId must be set.".to_owned(),
+        ));
+    };
+    if !share_group_coordinator().is_member(group_id, member_id) {
+        return Err((
+            ErrorCode::UnknownMemberId,
+            format!(
+                "Member {} is not a member of share group {}.",
+                member_id, group_id
+            ),
+        ));
+    }
+    Ok((group_id.to_owned(), member_id.to_owned()))
+}
+
+pub fn share_fetch_handler(bytes: &mut Bytes, header: RequestHeader) -> Result<Reply> {
+    let metadata = read_cluster_metadata()?;
+    let config = ShareGroupConfig::load(&ConfigStore::load(&metadata));
+    let req = ShareFetchRequest::deserialize(bytes);
+    let deadline = Instant::now() + Duration::from_millis(req.max_wait_ms.max(0) as u64);
+    let mut body = ShareFetchResponse {
+        error_code: ErrorCode::NoError,
+        error_message: None,
+        acquisition_lock_timeout_ms: config.limits.record_lock_duration.as_millis() as i32,
+        responses: vec![],
+    };
+    let result =
+        share_member(req.group_id.as_deref(), req.member_id.as_deref()).and_then(|member| {
+            let has_acknowledgements = req
+                .topics
+                .iter()
+                .flat_map(|(_, partitions)| partitions)
+                .any(|x| !x.acknowledgement_batches.is_empty());
+            if req.share_session_epoch == INITIAL_SHARE_SESSION_EPOCH && has_acknowledgements {
+                return Err((
+                    ErrorCode::InvalidRequest,
+                    "Acknowledgements are not allowed when opening a share session.".to_owned(),
+                ));
+            }
+            let added: Vec<(i128, i32)> = req
+                .topics
+                .iter()
+                .flat_map(|(topic_id, partitions)| {
+                    partitions.iter().map(|x| (*topic_id, x.partition_index))
+                })
+                .collect();
+            let forgotten: Vec<(i128, i32)> = req
+                .forgotten_topics
+                .iter()
+                .flat_map(|(topic_id, partitions)| partitions.iter().map(|x| (*topic_id, *x)))
+                .collect();
+            share_partition_manager()
+                .update_session(
+                    &member.0,
+                    &member.1,
+                    req.share_session_epoch,
+                    &added,
+                    &forgotten,
+                    &config.limits,
+                )
+                .map(|session| (member, session))
+        });
+    let ((group_id, member_id), session) = match result {
+        Ok(x) => x,
+        Err((error_code, error_message)) => {
+            body.error_code = error_code;
+            body.error_message = Some(error_message);
+            return Ok(reply(&header, body));
+        }
+    };
+
+    let mut responses = PartitionResponses::new();
+
+    for (topic_id, partitions) in &req.topics {
+        for partition in partitions {
+            let x = partition_response(
+                &mut responses,
+                *topic_id,
+                partition.partition_index,
+                &metadata,
+            );
+            if partition.acknowledgement_batches.is_empty() {
+                continue;
+            }
+            let key = SharePartitionKey {
+                group_id: group_id.clone(),
+                topic_id: *topic_id,
+                partition: partition.partition_index,
+            };
+            if let Err(error_code) = share_partition_manager().acknowledge(
+                &key,
+                &member_id,
+                &partition.acknowledgement_batches,
+                &config.limits,
+                &metadata,
+            ) {
+                x.acknowledge_error_code = error_code;
+            }
+        }
+    }
+
+    if req.share_session_epoch == FINAL_SHARE_SESSION_EPOCH {
+        share_partition_manager().close_session(&group_id, &member_id, &config.limits);
+        body.responses = topic_responses(responses);
+        return Ok(reply(&header, body));
+    }
+    let mut fetch = PendingShareFetch {
+        header,
+        metadata,
+        responses,
+        group_id,
+        member_id,
+        session,
+        limits: config.limits,
+        max_bytes: req.max_bytes,
+        min_bytes: req.min_bytes,
+        records_left: req.max_records as i64,
+        bytes_left: req.max_bytes as i64,
+        deadline,
+    };
+
+    // Share fetches without min_bytes of records yet wait in the purgatory
+    // for more, until max_wait_ms passes, like fetches do.
+    let partitions = fetch.watched_partitions();
+    let seen = fetch_purgatory().appends_to(&partitions);
+    match fetch.try_complete() {
+        Some(response) => Ok(response.into()),
+        None if Instant::now() >= deadline => Ok(fetch.force_complete().into()),
+        None => Ok(Reply::Delayed(
+            fetch_purgatory().park(fetch, partitions, seen),
+        )),
+    }
+}
+
+fn reply(header: &RequestHeader, body: ShareFetchResponse) -> Reply {
+    ResponseData::from(Bytes::from(Response::for_request(header, body))).into()
+}
+
+fn topic_responses(responses: PartitionResponses) -> Vec<(i128, Vec<ShareFetchPartitionResponse>)> {
+    responses
+        .into_iter()
+        .map(|(topic_id, partitions)| (topic_id, partitions.into_values().collect()))
+        .collect()
+}
+
+/// A share fetch past its acknowledgements, acquiring records of the
+/// session's partitions as long as it waits for them.
+struct PendingShareFetch {
+    header: RequestHeader,
+    metadata: MetadataFile,
+    responses: PartitionResponses,
+    group_id: String,
+    member_id: String,
+    session: Vec<(i128, i32)>,
+    limits: SharePartitionLimits,
+    max_bytes: i32,
+    min_bytes: i32,
+    records_left: i64,
+    bytes_left: i64,
+    deadline: Instant,
+}
+
+impl DelayedOperation for PendingShareFetch {
+    fn deadline(&self) -> Instant {
+        self.deadline
+    }
+
+    /// The response, once min_bytes of records were acquired. Errors
+    /// complete it right away.
+    fn try_complete(&mut self) -> Option<ResponseData> {
+        let failed = self.acquire();
+        let acquired = self.max_bytes as i64 - self.bytes_left;
+        (failed || acquired >= self.min_bytes.max(0) as i64).then(|| self.respond())
+    }
+
+    /// The response with whatever records there are, once max_wait_ms
+    /// passed.
+    fn force_complete(&mut self) -> ResponseData {
+        self.acquire();
+        self.respond()
+    }
+}
+
+impl PendingShareFetch {
+    /// Topic name and partition of every partition of the session.
+    fn watched_partitions(&self) -> Vec<(String, i32)> {
+        self.session
+            .iter()
+            .filter_map(|(topic_id, partition)| {
+                let topic = self.metadata.get_topics().find(|x| x.uuid == *topic_id)?;
+                Some((topic.topic_name.data.clone(), *partition))
+            })
+            .collect()
+    }
+
+    /// Acquires records of the session's partitions on top of those earlier
+    /// attempts acquired. Returns whether a partition failed.
+    fn acquire(&mut self) -> bool {
+        let mut failed = false;
+        for &(topic_id, partition) in &self.session {
+            if self.records_left <= 0 || self.bytes_left <= 0 {
+                break;
+            }
+            let metadata = &self.metadata;
+            let Some(topic) = metadata.get_topics().find(|x| x.uuid == topic_id) else {
+                partition_response(&mut self.responses, topic_id, partition, metadata).error_code =
+                    ErrorCode::UnknownTopic;
+                failed = true;
+                continue;
+            };
+            if metadata.get_partition(&topic_id, partition).is_none() {
+                partition_response(&mut self.responses, topic_id, partition, metadata).error_code =
+                    ErrorCode::UnknownTopicOrPartition;
+                failed = true;
+                continue;
+            }
+            let key = SharePartitionKey {
+                group_id: self.group_id.clone(),
+                topic_id,
+                partition,
+            };
+            match share_partition_manager().acquire(
+                &key,
+                &topic.topic_name.data,
+                &self.member_id,
+                self.records_left,
+                &self.limits,
+                metadata,
+            ) {
+                Ok(data) if data.acquired.is_empty() => {}
+                Ok(data) => {
+                    self.records_left -= data
+                        .acquired
+                        .iter()
+                        .map(|x| x.last_offset - x.first_offset + 1)
+                        .sum::<i64>();
+                    let x = partition_response(&mut self.responses, topic_id, partition, metadata);
+                    // Later attempts may acquire released records before
+                    // those acquired earlier, or more of the same batches.
+                    x.acquired_records.extend(data.acquired);
+                    x.acquired_records.sort_by_key(|x| x.first_offset);
+                    for batch in data.records {
+                        if !x.records.contains(&batch) {
+                            self.bytes_left -= batch.len() as i64;
+                            x.records.push(batch);
+                        }
+                    }
+                    x.records.sort_by_key(|x| x.clone().get_i64());
+                }
+                Err(error_code) => {
+                    partition_response(&mut self.responses, topic_id, partition, metadata)
+                        .error_code = error_code;
+                    failed = true;
+                }
+            }
+        }
+        failed
+    }
+
+    fn respond(&mut self) -> ResponseData {
+        let body = ShareFetchResponse {
+            error_code: ErrorCode::NoError,
+            error_message: None,
+            acquisition_lock_timeout_ms: self.limits.record_lock_duration.as_millis() as i32,
+            responses: topic_responses(std::mem::take(&mut self.responses)),
+        };
+        Bytes::from(Response::for_request(&self.header, body)).into()
+    }
+}
@@ -0,0 +1,153 @@
+use anyhow::Result;
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+
+use crate::{
+    config::ConfigStore,
+    coordinator::{
+        consumer_group_coordinator, group_coordinator, share_group_coordinator, Assignment,
+        ShareGroupConfig, ShareGroupHeartbeatParams, ShareGroupHeartbeatResult,
+        LEAVE_GROUP_MEMBER_EPOCH,
+    },
+    metadata::read_cluster_metadata,
+    protocol::{ErrorCode, Response},
+    server::client_host,
+};
+
+use super::{
+    get_nullable_array, get_nullable_string, get_string, get_tag_buffer, put_array,
+    put_nullable_string, put_tag_buffer, RequestHeader, Serialize,
+};
+
+#[derive(Debug)]
+pub struct ShareGroupHeartbeatRequest {
+    group_id: String,
+    member_id: String,
+    member_epoch: i32,
+    rack_id: Option<String>,
+    subscribed_topic_names: Option<Vec<String>>,
+}
+
+impl ShareGroupHeartbeatRequest {
+    fn deserialize(bytes: &mut Bytes) -> Self {
+        let req = Self {
+            group_id: get_string(bytes, true),
+            member_id: get_string(bytes, true),
+            member_epoch: bytes.get_i32(),
+            rack_id: get_nullable_string(bytes, true),
+            subscribed_topic_names: get_nullable_array(bytes, true, |bytes| {
+                get_string(bytes, true)
+            }),
+        };
+        get_tag_buffer(bytes, true);
+        req
+    }
+
+    fn validate(&self) -> Result<(), (ErrorCode, String)> {
+        let invalid = |message: &str| Err((ErrorCode::InvalidRequest, message.to_owned()));
+        if self.group_id.is_empty() {
+            return invalid("GroupId can't be empty.");
+        }
+        if self.member_id.is_empty() {
+            return invalid("MemberId can't be empty.");
+        }
+        if self.rack_id.as_ref().is_some_and(String::is_empty) {
+            return invalid("RackId can't be empty.");
+        }
+        if self.member_epoch < LEAVE_GROUP_MEMBER_EPOCH {
+            return invalid("MemberEpoch is invalid.");
+        }
+        if self.member_epoch == 0
+            && self
+                .subscribed_topic_names
+                .as_ref()
+                .map_or(true, Vec::is_empty)
+        {
+            return invalid("SubscribedTopicNames must be set in first request.");
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct ShareGroupHeartbeatResponse {
+    error_code: ErrorCode,
+    error_message: Option<String>,
+    member_id: Option<String>,
+    member_epoch: i32,
+    heartbeat_interval_ms: i32,
+    assignment: Option<Assignment>,
+}
+
+impl Serialize for ShareGroupHeartbeatResponse {
+    fn serialize(&self, bytes: &mut BytesMut) {
+        bytes.put_i32(0); // throttle time
+        bytes.put_i16(self.error_code.clone() as i16);
+        put_nullable_string(bytes, self.error_message.as_deref(), true);
+        put_nullable_string(bytes, self.member_id.as_deref(), true);
+        bytes.put_i32(self.member_epoch);
+        bytes.put_i32(self.heartbeat_interval_ms);
+        match &self.assignment {
+            None => bytes.put_i8(-1),
+            Some(assignment) => {
+                bytes.put_i8(1);
+                let topics: Vec<_> = assignment.iter().collect();
+                put_array(bytes, &topics, true, |(topic_id, partitions), bytes| {
+                    bytes.put_i128(**topic_id);
+                    let partitions: Vec<i32> = partitions.iter().copied().collect();
+                    put_array(bytes, &partitions, true, |x, bytes| bytes.put_i32(*x));
+                    put_tag_buffer(bytes, true);
+                });
+                put_tag_buffer(bytes, true);
+            }
+        }
+        put_tag_buffer(bytes, true);
+    }
+}
+
+pub fn share_group_heartbeat_handler(bytes: &mut Bytes, header: RequestHeader) -> Result<Bytes> {
+    let metadata = read_cluster_metadata()?;
+    let config = ShareGroupConfig::load(&ConfigStore::load(&metadata));
+    let req = ShareGroupHeartbeatRequest::deserialize(bytes);
+    let result = req.validate().and_then(|()| {
+        if group_coordinator().has_members(&req.group_id)
+            || consumer_group_coordinator().contains(&req.group_id)
+        {
+            return Err((
+                ErrorCode::GroupIdNotFound,
+                format!("Group {} is not a share group.", req.group_id),
+            ));
+        }
+        let params = ShareGroupHeartbeatParams {
+            group_id: req.group_id.clone(),
+            member_id: req.member_id.clone(),
+            member_epoch: req.member_epoch,
+            rack_id: req.rack_id.clone(),
+            subscribed_topic_names: req.subscribed_topic_names.clone(),
+            client_id: header.client_id.id().to_owned(),
+            client_host: client_host(),
+        };
+        share_group_coordinator().heartbeat(params, &config, &metadata)
+    });
+    let body = match result {
+        Ok(ShareGroupHeartbeatResult {
+            member_epoch,
+            assignment,
+        }) => ShareGroupHeartbeatResponse {
+            error_code: ErrorCode::NoError,
+            error_message: None,
+            member_id: Some(req.member_id),
+            member_epoch,
+            heartbeat_interval_ms: config.heartbeat_interval_ms,
+            assignment,
+        },
+        Err((error_code, error_message)) => ShareGroupHeartbeatResponse {
+            error_code,
+            error_message: Some(error_message),
+            member_id: None,
+            member_epoch: -1,
+            heartbeat_interval_ms: 0,
+            assignment: None,
+        },
+    };
+    Ok(Response::for_request(&header, body).into())
+}
@@ -0,0 +1,105 @@
+use anyhow::Result;
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+
+use crate::{
+    coordinator::{group_coordinator, SyncGroupParams, SyncResult},
+    protocol::Response,
+};
+
+use super::{
+    get_array, get_bytes, get_nullable_string, get_string, get_tag_buffer, put_bytes,
+    put_nullable_string, put_tag_buffer, RequestHeader, Serialize,
+};
+
+#[derive(Debug)]
+pub struct SyncGroupRequest {
+    group_id: String,
+    generation_id: i32,
+    member_id: String,
+    group_instance_id: Option<String>,
+    protocol_type: Option<String>,
+    protocol_name: Option<String>,
+    /// Only sent by the leader.
+    assignments: Vec<(String, Bytes)>,
+}
+
+impl SyncGroupRequest {
+    fn deserialize(bytes: &mut Bytes, version: i16, flexible: bool) -> Self {
+        let group_id = get_string(bytes, flexible);
+        let generation_id = bytes.get_i32();
+        let member_id = get_string(bytes, flexible);
+        let group_instance_id = if version >= 3 {
+            get_nullable_string(bytes, flexible)
+        } else {
+            None
+        };
+        let (protocol_type, protocol_name) = if version >= 5 {
+            (
+                get_nullable_string(bytes, flexible),
+                get_nullable_string(bytes, flexible),
+            )
+        } else {
+            (None, None)
+        };
+        let assignments = get_array(bytes, flexible, |bytes| {
+            let member_id = get_string(bytes, flexible);
+            let assignment = get_bytes(bytes, flexible);
+            get_tag_buffer(bytes, flexible);
+            (member_id, assignment)
+        });
+        get_tag_buffer(bytes, flexible);
+        Self {
+            group_id,
+            generation_id,
+            member_id,
+            group_instance_id,
+            protocol_type,
+            protocol_name,
+            assignments,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct SyncGroupResponse {
+    version: i16,
+    flexible: bool,
+    result: SyncResult,
+}
+
+impl Serialize for SyncGroupResponse {
+    fn serialize(&self, bytes: &mut BytesMut) {
+        let (version, flexible, result) = (self.version, self.flexible, &self.result);
+        if version >= 1 {
+            bytes.put_i32(0); // throttle time
+        }
+        bytes.put_i16(result.error_code.clone() as i16);
+        if version >= 5 {
+            put_nullable_string(bytes, result.protocol_type.as_deref(), flexible);
+            put_nullable_string(bytes, result.protocol_name.as_deref(), flexible);
+        }
+        put_bytes(bytes, &result.assignment, flexible);
+        put_tag_buffer(bytes, flexible);
+    }
+}
+
+pub fn sync_group_handler(bytes: &mut Bytes, header: RequestHeader) -> Result<Bytes> {
+    let version = header.request_api_version;
+    let flexible = header.is_flexible();
+    let req = SyncGroupRequest::deserialize(bytes, version, flexible);
+    let result = group_coordinator().sync_group(SyncGroupParams {
+        group_id: req.group_id,
+        generation_id: req.generation_id,
+        member_id: req.member_id,
+        group_instance_id: req.group_instance_id,
+        protocol_type: req.protocol_type,
+        protocol_name: req.protocol_name,
+        assignments: req.assignments,
+    });
+    let body = SyncGroupResponse {
+        version,
+        flexible,
+        result,
+    };
+    Ok(Response::for_request(&header, body).into())
+}
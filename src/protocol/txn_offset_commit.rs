@@ -0,0 +1,204 @@
+use anyhow::Result;
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+
+use crate::{
+    config::ConfigStore,
+    coordinator::{
+        consumer_group_coordinator, group_coordinator, transaction_coordinator, CommittedOffset,
+    },
+    metadata::{now_ms, read_cluster_metadata},
+    protocol::{ErrorCode, Response},
+};
+
+use super::{
+    get_array, get_nullable_string, get_string, get_tag_buffer, put_array, put_string,
+    put_tag_buffer, validate_partition, RequestHeader, Serialize,
+};
+
+#[derive(Debug)]
+pub struct TxnOffsetCommitRequest {
+    transactional_id: String,
+    group_id: String,
+    producer_id: i64,
+    producer_epoch: i16,
+    generation_id: i32,
+    member_id: String,
+    group_instance_id: Option<String>,
+    topics: Vec<(String, Vec<TxnOffsetCommitRequestPartition>)>,
+}
+
+#[derive(Debug)]
+pub struct TxnOffsetCommitRequestPartition {
+    partition_index: i32,
+    committed_offset: i64,
+    committed_leader_epoch: i32,
+    committed_metadata: Option<String>,
+}
+
+impl TxnOffsetCommitRequest {
+    fn deserialize(bytes: &mut Bytes, version: i16, flexible: bool) -> Self {
+        let transactional_id = get_string(bytes, flexible);
+        let group_id = get_string(bytes, flexible);
+        let producer_id = bytes.get_i64();
+        let producer_epoch = bytes.get_i16();
+        let (generation_id, member_id, group_instance_id) = if version >= 3 {
+            (
+                bytes.get_i32(),
+                get_string(bytes, flexible),
+                get_nullable_string(bytes, flexible),
+            )
+        } else {
+            (-1, String::new(), None)
+        };
+        let topics = get_array(bytes, flexible, |bytes| {
+            let name = get_string(bytes, flexible);
+            let partitions = get_array(bytes, flexible, |bytes| {
+                let partition = TxnOffsetCommitRequestPartition {
+                    partition_index: bytes.get_i32(),
+                    committed_offset: bytes.get_i64(),
+                    committed_leader_epoch: if version >= 2 { bytes.get_i32() } else { -1 },
+                    committed_metadata: get_nullable_string(bytes, flexible),
+                };
+                get_tag_buffer(bytes, flexible);
+                partition
+            });
+            get_tag_buffer(bytes, flexible);
+            (name, partitions)
+        });
+        get_tag_buffer(bytes, flexible);
+        Self {
+            transactional_id,
+            group_id,
+            producer_id,
+            producer_epoch,
+            generation_id,
+            member_id,
+            group_instance_id,
+            topics,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct TxnOffsetCommitResponse {
+    flexible: bool,
+    topics: Vec<(String, Vec<(i32, ErrorCode)>)>,
+}
+
+impl Serialize for TxnOffsetCommitResponse {
+    fn serialize(&self, bytes: &mut BytesMut) {
+        let flexible = self.flexible;
+        bytes.put_i32(0); // throttle time
+        put_array(
+            bytes,
+            &self.topics,
+            flexible,
+            |(name, partitions), bytes| {
+                put_string(bytes, name, flexible);
+                put_array(
+                    bytes,
+                    partitions,
+                    flexible,
+                    |(partition_index, error_code), bytes| {
+                        bytes.put_i32(*partition_index);
+                        bytes.put_i16(error_code.clone() as i16);
+                        put_tag_buffer(bytes, flexible);
+                    },
+                );
+                put_tag_buffer(bytes, flexible);
+            },
+        );
+        put_tag_buffer(bytes, flexible);
+    }
+}
+
+/// Checks the member against the group, unless the producer committed
+/// without one, as producers before v3 did.
+fn validate_group(req: &TxnOffsetCommitRequest) -> Result<(), ErrorCode> {
+    if req.group_id.is_empty() {
+        Err(ErrorCode::InvalidGroupId)
+    } else if req.generation_id < 0 && req.member_id.is_empty() {
+        Ok(())
+    } else if consumer_group_coordinator().contains(&req.group_id) {
+        consumer_group_coordinator().validate_offset_commit(
+            &req.group_id,
+            req.generation_id,
+            &req.member_id,
+        )
+    } else {
+        group_coordinator().validate_offset_commit(
+            &req.group_id,
+            req.generation_id,
+            &req.member_id,
+            req.group_instance_id.as_deref(),
+        )
+    }
+}
+
+pub fn txn_offset_commit_handler(bytes: &mut Bytes, header: RequestHeader) -> Result<Bytes> {
+    let version = header.request_api_version;
+    let flexible = header.is_flexible();
+    let req = TxnOffsetCommitRequest::deserialize(bytes, version, flexible);
+    let metadata = read_cluster_metadata()?;
+    let store = ConfigStore::load(&metadata);
+    transaction_coordinator().abort_timed_out_transactions(&store);
+    let max_metadata_size = store.broker_config_i64("offset.metadata.max.bytes");
+    let group_error = validate_group(&req);
+    let now = now_ms();
+
+    // As in OffsetCommit, the valid partitions are committed together and
+    // keep NoError if that succeeds.
+    let mut topics: Vec<(String, Vec<(i32, ErrorCode)>)> = vec![];
+    let mut entries = vec![];
+    for (name, partitions) in &req.topics {
+        let mut results = vec![];
+        for partition in partitions {
+            let error_code = match &group_error {
+                Err(error_code) => error_code.clone(),
+                Ok(()) => validate_partition(
+                    name,
+                    partition.partition_index,
+                    partition.committed_metadata.as_deref(),
+                    &metadata,
+                    max_metadata_size,
+                ),
+            };
+            if matches!(error_code, ErrorCode::NoError) {
+                let value = CommittedOffset {
+                    offset: partition.committed_offset,
+                    leader_epoch: partition.committed_leader_epoch,
+                    metadata: partition.committed_metadata.clone().unwrap_or_default(),
+                    commit_timestamp: now,
+                    expire_timestamp: None,
+                };
+                entries.push(((name.clone(), partition.partition_index), value));
+            }
+            results.push((partition.partition_index, error_code));
+        }
+        topics.push((name.clone(), results));
+    }
+    if !entries.is_empty() {
+        let result = transaction_coordinator().txn_offset_commit(
+            &req.transactional_id,
+            req.producer_id,
+            req.producer_epoch,
+            &req.group_id,
+            entries,
+        );
+        if let Err(error_code) = result {
+            let error_code = match error_code {
+                // PRODUCER_FENCED was introduced in v3.
+                ErrorCode::ProducerFenced if version < 3 => ErrorCode::InvalidProducerEpoch,
+                x => x,
+            };
+            for (_, x) in topics.iter_mut().flat_map(|x| x.1.iter_mut()) {
+                if matches!(x, ErrorCode::NoError) {
+                    *x = error_code.clone();
+                }
+            }
+        }
+    }
+
+    let body = TxnOffsetCommitResponse { flexible, topics };
+    Ok(Response::for_request(&header, body).into())
+}
@@ -0,0 +1,121 @@
+use anyhow::Result;
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+
+use crate::{
+    coordinator::write_txn_marker,
+    protocol::{ErrorCode, Response},
+    storage::TxnMarker,
+};
+
+use super::{
+    get_array, get_string, get_tag_buffer, put_array, put_string, put_tag_buffer, RequestHeader,
+    Serialize,
+};
+
+#[derive(Debug)]
+pub struct WritableTxnMarker {
+    marker: TxnMarker,
+    topics: Vec<(String, Vec<i32>)>,
+}
+
+#[derive(Debug)]
+pub struct WriteTxnMarkersRequest {
+    markers: Vec<WritableTxnMarker>,
+}
+
+impl WriteTxnMarkersRequest {
+    fn deserialize(bytes: &mut Bytes, flexible: bool) -> Self {
+        let markers = get_array(bytes, flexible, |bytes| {
+            let producer_id = bytes.get_i64();
+            let producer_epoch = bytes.get_i16();
+            let commit = bytes.get_u8() != 0;
+            let topics = get_array(bytes, flexible, |bytes| {
+                let name = get_string(bytes, flexible);
+                let partitions = get_array(bytes, flexible, |bytes| bytes.get_i32());
+                get_tag_buffer(bytes, flexible);
+                (name, partitions)
+            });
+            let coordinator_epoch = bytes.get_i32();
+            get_tag_buffer(bytes, flexible);
+            WritableTxnMarker {
+                marker: TxnMarker {
+                    producer_id,
+                    producer_epoch,
+                    commit,
+                    coordinator_epoch,
+                },
+                topics,
+            }
+        });
+        get_tag_buffer(bytes, flexible);
+        Self { markers }
+    }
+}
+
+type TopicResults = Vec<(String, Vec<(i32, ErrorCode)>)>;
+
+#[derive(Debug)]
+pub struct WriteTxnMarkersResponse {
+    flexible: bool,
+    markers: Vec<(i64, TopicResults)>,
+}
+
+impl Serialize for WriteTxnMarkersResponse {
+    fn serialize(&self, bytes: &mut BytesMut) {
+        let flexible = self.flexible;
+        put_array(
+            bytes,
+            &self.markers,
+            flexible,
+            |(producer_id, topics), bytes| {
+                bytes.put_i64(*producer_id);
+                put_array(bytes, topics, flexible, |(name, partitions), bytes| {
+                    put_string(bytes, name, flexible);
+                    put_array(
+                        bytes,
+                        partitions,
+                        flexible,
+                        |(partition_index, error_code), bytes| {
+                            bytes.put_i32(*partition_index);
+                            bytes.put_i16(error_code.clone() as i16);
+                            put_tag_buffer(bytes, flexible);
+                        },
+                    );
+                    put_tag_buffer(bytes, flexible);
+                });
+                put_tag_buffer(bytes, flexible);
+            },
+        );
+        put_tag_buffer(bytes, flexible);
+    }
+}
+
+pub fn write_txn_markers_handler(bytes: &mut Bytes, header: RequestHeader) -> Result<Bytes> {
+    let flexible = header.is_flexible();
+    let req = WriteTxnMarkersRequest::deserialize(bytes, flexible);
+    let markers = req
+        .markers
+        .iter()
+        .map(|x| {
+            let topics = x
+                .topics
+                .iter()
+                .map(|(name, partitions)| {
+                    let partitions = partitions
+                        .iter()
+                        .map(|partition| {
+                            let error_code = write_txn_marker(name, *partition, &x.marker)
+                                .err()
+                                .unwrap_or(ErrorCode::NoError);
+                            (*partition, error_code)
+                        })
+                        .collect();
+                    (name.clone(), partitions)
+                })
+                .collect();
+            (x.marker.producer_id, topics)
+        })
+        .collect();
+    let body = WriteTxnMarkersResponse { flexible, markers };
+    Ok(Response::for_request(&header, body).into())
+}
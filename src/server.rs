@@ -1,16 +1,35 @@
 pub struct Server;
 use anyhow::{Context, Result};
 use bytes::Bytes;
-use std::io::{Read, Write};
+use std::cell::RefCell;
+use std::io::{self, Read, Write};
 use std::net::{TcpListener, TcpStream};
+#[cfg(not(target_os = "linux"))]
+use std::os::unix::fs::FileExt;
+#[cfg(target_os = "linux")]
+use std::os::unix::io::AsRawFd;
+use std::sync::mpsc::{self, Receiver};
 use std::thread;
-use std::time::Duration;
 
+use crate::coordinator::{spawn_offset_expiration, spawn_transaction_expiration};
 use crate::handler::process_request;
+use crate::protocol::{Reply, ResponseData, ResponsePart};
+use crate::storage::FileRange;
+
+thread_local! {
+    static CLIENT_HOST: RefCell<String> = const { RefCell::new(String::new()) };
+}
+
+/// Address of the client whose request is being handled on this thread.
+pub fn client_host() -> String {
+    CLIENT_HOST.with(|x| x.borrow().clone())
+}
 
 impl Server {
     pub fn run(self) -> Result<()> {
         let listener = TcpListener::bind("127.0.0.1:9092").unwrap();
+        spawn_transaction_expiration();
+        spawn_offset_expiration();
 
         for stream in listener.incoming() {
             match stream {
@@ -30,18 +49,101 @@ impl Server {
 
 fn handle_connection(mut stream: TcpStream) -> Result<()> {
     println!("accepted new connection");
-    let mut buffer = [0u8; 1024];
-    while let Ok(length) = stream.read(&mut buffer) {
-        let request_bytes = Bytes::copy_from_slice(&buffer[..length]);
-        if request_bytes.is_empty() {
+    // Responses are written in parts, which must not wait for each other.
+    stream.set_nodelay(true)?;
+    if let Ok(addr) = stream.peer_addr() {
+        CLIENT_HOST.with(|x| *x.borrow_mut() = format!("/{}", addr.ip()));
+    }
+    // Responses are written by their own thread, so that requests are not
+    // held up by the ones answered later, like fetches waiting for data.
+    let (replies, pending) = mpsc::channel();
+    let writer = {
+        let stream = stream.try_clone()?;
+        thread::spawn(move || write_replies(stream, pending))
+    };
+    while let Some(request_bytes) = read_request(&mut stream)? {
+        let reply = process_request(request_bytes)?;
+        if replies.send(reply).is_err() {
             break;
         }
-        let response = process_request(request_bytes)?;
-        stream
-            .write_all(&response)
-            .context("Failed to write response")?;
+    }
+    drop(replies);
+    let _ = writer.join();
+    Ok(())
+}
+
+/// Writes the replies of a connection in request order, waiting for delayed
+/// ones to be completed.
+fn write_replies(mut stream: TcpStream, replies: Receiver<Reply>) -> Result<()> {
+    for reply in replies {
+        let response = match reply {
+            Reply::Ready(response) => response,
+            Reply::Delayed(response) => response.recv().context("Delayed response dropped")?,
+        };
+        // Empty for requests that expect no response, like acks=0 produces.
+        if response.is_empty() {
+            continue;
+        }
+        write_response(&mut stream, &response).context("Failed to write response")?;
         stream.flush()?;
-        thread::sleep(Duration::from_millis(1000));
     }
     Ok(())
 }
+
+fn write_response(stream: &mut TcpStream, response: &ResponseData) -> io::Result<()> {
+    for part in &response.parts {
+        match part {
+            ResponsePart::Bytes(bytes) => stream.write_all(bytes)?,
+            ResponsePart::File(range) => send_file(stream, range)?,
+        }
+    }
+    Ok(())
+}
+
+/// Copies a range of a segment file to the socket within the kernel.
+#[cfg(target_os = "linux")]
+fn send_file(stream: &mut TcpStream, range: &FileRange) -> io::Result<()> {
+    let mut offset = range.position as libc::off_t;
+    let end = offset + range.size as libc::off_t;
+    while offset < end {
+        let sent = unsafe {
+            libc::sendfile(
+                stream.as_raw_fd(),
+                range.file.as_raw_fd(),
+                &mut offset,
+                (end - offset) as usize,
+            )
+        };
+        if sent < 0 {
+            let error = io::Error::last_os_error();
+            if error.kind() != io::ErrorKind::Interrupted {
+                return Err(error);
+            }
+        } else if sent == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn send_file(stream: &mut TcpStream, range: &FileRange) -> io::Result<()> {
+    let mut bytes = vec![0u8; range.size as usize];
+    range.file.read_exact_at(&mut bytes, range.position)?;
+    stream.write_all(&bytes)
+}
+
+/// Reads one size-prefixed request, keeping the size in front of it.
+fn read_request(stream: &mut TcpStream) -> Result<Option<Bytes>> {
+    let mut size = [0u8; 4];
+    if stream.read_exact(&mut size).is_err() {
+        return Ok(None);
+    }
+    let length = i32::from_be_bytes(size).max(0) as usize;
+    let mut buffer = vec![0u8; 4 + length];
+    buffer[..4].copy_from_slice(&size);
+    stream
+        .read_exact(&mut buffer[4..])
+        .context("Failed to read request")?;
+    Ok(Some(Bytes::from(buffer)))
+}
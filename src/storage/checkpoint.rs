@@ -0,0 +1,87 @@
+use anyhow::{bail, Context, Result};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use super::LOG_DIR;
+
+const CHECKPOINT_VERSION: &str = "0";
+pub const LOG_START_OFFSET_CHECKPOINT: &str = "log-start-offset-checkpoint";
+
+static LOG_START_OFFSET_LOCK: Mutex<()> = Mutex::new(());
+
+/// Kafka's plain text checkpoint format: a version line, an entry count line,
+/// then one whitespace separated entry per line.
+#[derive(Debug)]
+pub struct CheckpointFile {
+    path: PathBuf,
+}
+
+impl CheckpointFile {
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+        }
+    }
+
+    pub fn read(&self) -> Result<Vec<Vec<String>>> {
+        if !self.path.exists() {
+            return Ok(vec![]);
+        }
+        let content = fs::read_to_string(&self.path)
+            .with_context(|| format!("Failed to read {}", self.path.display()))?;
+        let mut lines = content.lines();
+        if lines.next() != Some(CHECKPOINT_VERSION) {
+            bail!("Unsupported checkpoint version in {}", self.path.display());
+        }
+        let count: usize = lines.next().unwrap_or("0").trim().parse()?;
+        let entries: Vec<Vec<String>> = lines
+            .take(count)
+            .map(|line| line.split_whitespace().map(str::to_owned).collect())
+            .collect();
+        if entries.len() != count {
+            bail!("Truncated checkpoint {}", self.path.display());
+        }
+        Ok(entries)
+    }
+
+    /// Writes to a temporary file first so a crash never leaves a torn checkpoint.
+    pub fn write(&self, entries: &[String]) -> Result<()> {
+        let mut content = format!("{}\n{}\n", CHECKPOINT_VERSION, entries.len());
+        for entry in entries {
+            content.push_str(entry);
+            content.push('\n');
+        }
+        let tmp = self.path.with_extension("tmp");
+        fs::write(&tmp, content)?;
+        fs::rename(&tmp, &self.path)?;
+        Ok(())
+    }
+}
+
+fn log_start_offset_checkpoint() -> CheckpointFile {
+    CheckpointFile::new(Path::new(LOG_DIR).join(LOG_START_OFFSET_CHECKPOINT))
+}
+
+pub fn read_log_start_offsets() -> Result<HashMap<(String, i32), i64>> {
+    let mut offsets = HashMap::new();
+    for entry in log_start_offset_checkpoint().read()? {
+        if let [topic, partition, offset] = entry.as_slice() {
+            offsets.insert((topic.clone(), partition.parse()?), offset.parse()?);
+        }
+    }
+    Ok(offsets)
+}
+
+pub fn write_log_start_offset(topic: &str, partition: i32, offset: i64) -> Result<()> {
+    let _guard = LOG_START_OFFSET_LOCK.lock().unwrap();
+    let mut offsets = read_log_start_offsets()?;
+    offsets.insert((topic.to_owned(), partition), offset);
+    let mut entries: Vec<String> = offsets
+        .iter()
+        .map(|((topic, partition), offset)| format!("{} {} {}", topic, partition, offset))
+        .collect();
+    entries.sort();
+    log_start_offset_checkpoint().write(&entries)
+}
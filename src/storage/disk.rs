@@ -0,0 +1,24 @@
+use anyhow::{bail, Result};
+use std::ffi::CString;
+use std::mem::MaybeUninit;
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
+
+/// Total and usable bytes of the filesystem holding `path`.
+pub fn disk_usage(path: &Path) -> Result<(i64, i64)> {
+    let c_path = CString::new(path.as_os_str().as_bytes())?;
+    let mut stat = MaybeUninit::<libc::statvfs>::uninit();
+    // SAFETY: `c_path` is a valid NUL terminated string and `stat` is only
+    // read after statvfs reported success.
+    let stat = unsafe {
+        if libc::statvfs(c_path.as_ptr(), stat.as_mut_ptr()) != 0 {
+            bail!("statvfs failed for {}", path.display());
+        }
+        stat.assume_init()
+    };
+    let block_size = stat.f_frsize as i64;
+    Ok((
+        stat.f_blocks as i64 * block_size,
+        stat.f_bavail as i64 * block_size,
+    ))
+}
@@ -0,0 +1,185 @@
+use anyhow::Result;
+use std::path::Path;
+
+use super::{BatchInfo, CheckpointFile};
+
+pub const LEADER_EPOCH_CHECKPOINT: &str = "leader-epoch-checkpoint";
+pub const UNDEFINED_EPOCH: i32 = -1;
+pub const UNDEFINED_EPOCH_OFFSET: i64 = -1;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EpochEntry {
+    pub epoch: i32,
+    pub start_offset: i64,
+}
+
+/// Maps each leader epoch to the first offset written in it, backed by the
+/// partition's `leader-epoch-checkpoint` file.
+#[derive(Debug)]
+pub struct LeaderEpochCache {
+    checkpoint: CheckpointFile,
+    entries: Vec<EpochEntry>,
+}
+
+impl LeaderEpochCache {
+    /// Loads the checkpoint, rebuilding it from `batches` when it is missing.
+    pub fn load(dir: &Path, batches: impl FnOnce() -> Result<Vec<BatchInfo>>) -> Result<Self> {
+        let path = dir.join(LEADER_EPOCH_CHECKPOINT);
+        let checkpoint = CheckpointFile::new(&path);
+        if path.exists() {
+            let entries = checkpoint
+                .read()?
+                .iter()
+                .filter_map(|entry| match entry.as_slice() {
+                    [epoch, start_offset] => Some(EpochEntry {
+                        epoch: epoch.parse().ok()?,
+                        start_offset: start_offset.parse().ok()?,
+                    }),
+                    _ => None,
+                })
+                .collect();
+            return Ok(Self {
+                checkpoint,
+                entries,
+            });
+        }
+        let mut cache = Self {
+            checkpoint,
+            entries: vec![],
+        };
+        for batch in batches()? {
+            cache.push(batch.partition_leader_epoch, batch.base_offset);
+        }
+        if dir.exists() {
+            cache.flush()?;
+        }
+        Ok(cache)
+    }
+
+    pub fn entries(&self) -> &[EpochEntry] {
+        &self.entries
+    }
+
+    pub fn latest_epoch(&self) -> Option<i32> {
+        self.entries.last().map(|x| x.epoch)
+    }
+
+    /// The epoch `offset` was written in.
+    pub fn epoch_for(&self, offset: i64) -> Option<i32> {
+        self.entries
+            .iter()
+            .rev()
+            .find(|x| x.start_offset <= offset)
+            .map(|x| x.epoch)
+    }
+
+    fn push(&mut self, epoch: i32, start_offset: i64) -> bool {
+        if epoch < 0 || self.latest_epoch().is_some_and(|x| x >= epoch) {
+            return false;
+        }
+        self.entries.push(EpochEntry {
+            epoch,
+            start_offset,
+        });
+        true
+    }
+
+    /// Records that `epoch` started at `start_offset` if it is newer than
+    /// every cached epoch.
+    pub fn assign(&mut self, epoch: i32, start_offset: i64) -> Result<()> {
+        if self.push(epoch, start_offset) {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Drops epochs that ended before the new log start offset. The epoch
+    /// covering it is kept and moved to start there.
+    pub fn truncate_from_start(&mut self, log_start_offset: i64) -> Result<()> {
+        let covering = self
+            .entries
+            .iter()
+            .rposition(|x| x.start_offset <= log_start_offset);
+        if let Some(idx) = covering {
+            self.entries.drain(..idx);
+            self.entries[0].start_offset = log_start_offset;
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    /// The largest epoch not above `requested` and the offset where the next
+    /// epoch starts, or the log end offset if `requested` is the latest.
+    pub fn end_offset_for(&self, requested: i32, log_end_offset: i64) -> (i32, i64) {
+        if requested == UNDEFINED_EPOCH {
+            return (UNDEFINED_EPOCH, UNDEFINED_EPOCH_OFFSET);
+        }
+        if self.latest_epoch() == Some(requested) {
+            return (requested, log_end_offset);
+        }
+        let Some(higher) = self.entries.iter().find(|x| x.epoch > requested) else {
+            return (UNDEFINED_EPOCH, UNDEFINED_EPOCH_OFFSET);
+        };
+        match self.entries.iter().rev().find(|x| x.epoch <= requested) {
+            Some(floor) => (floor.epoch, higher.start_offset),
+            None => (requested, higher.start_offset),
+        }
+    }
+
+    fn flush(&self) -> Result<()> {
+        let entries: Vec<String> = self
+            .entries
+            .iter()
+            .map(|x| format!("{} {}", x.epoch, x.start_offset))
+            .collect();
+        self.checkpoint.write(&entries)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cache(entries: &[(i32, i64)]) -> LeaderEpochCache {
+        LeaderEpochCache {
+            checkpoint: CheckpointFile::new(Path::new(LEADER_EPOCH_CHECKPOINT)),
+            entries: entries
+                .iter()
+                .map(|&(epoch, start_offset)| EpochEntry {
+                    epoch,
+                    start_offset,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn end_offset_for_latest_epoch_is_log_end() {
+        let cache = cache(&[(0, 0), (2, 10), (5, 30)]);
+        assert_eq!(cache.end_offset_for(5, 42), (5, 42));
+    }
+
+    #[test]
+    fn end_offset_for_older_epoch_is_next_epoch_start() {
+        let cache = cache(&[(0, 0), (2, 10), (5, 30)]);
+        assert_eq!(cache.end_offset_for(0, 42), (0, 10));
+        assert_eq!(cache.end_offset_for(2, 42), (2, 30));
+        // Epochs without entries map to the largest epoch below them.
+        assert_eq!(cache.end_offset_for(3, 42), (2, 30));
+    }
+
+    #[test]
+    fn end_offset_for_epoch_before_first_entry() {
+        let cache = cache(&[(2, 10), (5, 30)]);
+        assert_eq!(cache.end_offset_for(1, 42), (1, 10));
+    }
+
+    #[test]
+    fn end_offset_for_unknown_epochs_is_undefined() {
+        let epochs = cache(&[(0, 0), (2, 10)]);
+        let undefined = (UNDEFINED_EPOCH, UNDEFINED_EPOCH_OFFSET);
+        assert_eq!(epochs.end_offset_for(UNDEFINED_EPOCH, 42), undefined);
+        assert_eq!(epochs.end_offset_for(3, 42), undefined);
+        assert_eq!(cache(&[]).end_offset_for(0, 42), undefined);
+    }
+}
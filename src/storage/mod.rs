@@ -0,0 +1,22 @@
+mod checkpoint;
+pub use checkpoint::*;
+mod segment;
+pub use segment::*;
+mod disk;
+pub use disk::*;
+mod leader_epoch;
+pub use leader_epoch::*;
+mod offset_index;
+pub use offset_index::*;
+mod partition_log;
+pub use partition_log::*;
+mod partition_state;
+pub use partition_state::*;
+mod producer_state;
+pub use producer_state::*;
+mod transaction_index;
+pub use transaction_index::*;
+mod transaction_marker;
+pub use transaction_marker::*;
+
+pub const LOG_DIR: &str = "/tmp/kraft-combined-logs";
@@ -0,0 +1,111 @@
+use anyhow::Result;
+use bytes::{BufMut, BytesMut};
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::os::unix::fs::FileExt;
+use std::path::{Path, PathBuf};
+
+const INDEX_ENTRY_SIZE: u64 = 8;
+/// Bytes of batches between two index entries, Kafka's default
+/// `log.index.interval.bytes`.
+const INDEX_INTERVAL_BYTES: u64 = 4096;
+
+/// The base offset of a batch and its position in the segment file.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct IndexEntry {
+    pub offset: i64,
+    pub position: u64,
+}
+
+/// The `.index` file of a segment, in Kafka's format: the offset, relative
+/// to the segment's base offset, and the position of a batch every
+/// `INDEX_INTERVAL_BYTES` of the segment.
+#[derive(Debug)]
+pub struct OffsetIndex {
+    path: PathBuf,
+    base_offset: i64,
+}
+
+impl OffsetIndex {
+    pub fn new(path: &Path, base_offset: i64) -> Self {
+        Self {
+            path: path.with_extension("index"),
+            base_offset,
+        }
+    }
+
+    /// Creates the index empty, for a segment that was just rolled.
+    pub fn create(&self) -> Result<()> {
+        File::create(&self.path)?;
+        Ok(())
+    }
+
+    /// Indexes the batch written at `position` when it starts far enough
+    /// past the last indexed batch.
+    pub fn maybe_append(&self, offset: i64, position: u64) -> Result<()> {
+        let last_position = self.last_entry()?.map_or(0, |x| x.position);
+        if position < last_position + INDEX_INTERVAL_BYTES {
+            return Ok(());
+        }
+        let mut bytes = BytesMut::with_capacity(INDEX_ENTRY_SIZE as usize);
+        bytes.put_i32((offset - self.base_offset) as i32);
+        bytes.put_i32(position as i32);
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        file.write_all(&bytes)?;
+        Ok(())
+    }
+
+    /// Where to start reading batch headers to find `offset`: the position
+    /// of the last indexed batch starting at or before it, or the start of
+    /// the segment.
+    pub fn lookup(&self, offset: i64) -> Result<u64> {
+        let Some((file, count)) = self.open()? else {
+            return Ok(0);
+        };
+        // The first entry past `offset`, found by binary search.
+        let (mut low, mut high) = (0, count);
+        while low < high {
+            let mid = (low + high) / 2;
+            if self.entry_at(&file, mid)?.offset <= offset {
+                low = mid + 1;
+            } else {
+                high = mid;
+            }
+        }
+        match low {
+            0 => Ok(0),
+            idx => Ok(self.entry_at(&file, idx - 1)?.position),
+        }
+    }
+
+    fn last_entry(&self) -> Result<Option<IndexEntry>> {
+        match self.open()? {
+            Some((file, count)) if count > 0 => Ok(Some(self.entry_at(&file, count - 1)?)),
+            _ => Ok(None),
+        }
+    }
+
+    /// The index file and its number of complete entries.
+    fn open(&self) -> Result<Option<(File, u64)>> {
+        if !self.path.exists() {
+            return Ok(None);
+        }
+        let file = File::open(&self.path)?;
+        let count = file.metadata()?.len() / INDEX_ENTRY_SIZE;
+        Ok(Some((file, count)))
+    }
+
+    fn entry_at(&self, file: &File, idx: u64) -> Result<IndexEntry> {
+        let mut entry = [0u8; INDEX_ENTRY_SIZE as usize];
+        file.read_exact_at(&mut entry, idx * INDEX_ENTRY_SIZE)?;
+        let relative_offset = i32::from_be_bytes(entry[..4].try_into()?);
+        let position = u32::from_be_bytes(entry[4..].try_into()?);
+        Ok(IndexEntry {
+            offset: self.base_offset + relative_offset as i64,
+            position: position as u64,
+        })
+    }
+}
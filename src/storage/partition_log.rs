@@ -0,0 +1,349 @@
+use anyhow::{bail, Result};
+use bytes::{Bytes, BytesMut};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::{
+    metadata::RecordBatch,
+    protocol::{fetch_purgatory, Serialize},
+};
+
+use super::{
+    partition_lock, partition_state, read_batch_headers, read_log_start_offsets,
+    update_partition_state, write_log_start_offset, AbortedTxn, BatchInfo, FileRange,
+    LeaderEpochCache, PartitionState, Segment, LOG_DIR, UNDEFINED_EPOCH,
+};
+
+/// On-disk view of a single topic partition: its segments and the offset
+/// below which records are no longer visible to clients.
+#[derive(Debug)]
+pub struct PartitionLog {
+    pub topic: String,
+    pub partition: i32,
+    dir: PathBuf,
+    segments: Vec<Segment>,
+    log_start_offset: i64,
+    /// Size past which appends roll a new segment.
+    segment_bytes: u64,
+}
+
+impl PartitionLog {
+    pub fn open(topic: &str, partition: i32) -> Result<Self> {
+        Self::open_in(Path::new(LOG_DIR), topic, partition)
+    }
+
+    pub fn open_in(log_dir: &Path, topic: &str, partition: i32) -> Result<Self> {
+        let dir = log_dir.join(format!("{}-{}", topic, partition));
+        let segments = list_segments(&dir)?;
+        let mut log = Self {
+            topic: topic.to_owned(),
+            partition,
+            dir,
+            segments,
+            log_start_offset: 0,
+            segment_bytes: u64::MAX,
+        };
+        log.log_start_offset = log.state()?.log_start_offset;
+        Ok(log)
+    }
+
+    /// The partition's offsets, read from disk the first time it is opened.
+    pub fn state(&self) -> Result<PartitionState> {
+        partition_state(&self.dir, || {
+            let checkpointed = read_log_start_offsets()?
+                .get(&(self.topic.clone(), self.partition))
+                .copied()
+                .unwrap_or(0);
+            let first_segment = self.segments.first().map(|x| x.base_offset).unwrap_or(0);
+            let log_start_offset = checkpointed.max(first_segment);
+            let mut log_end_offset = self
+                .segments
+                .last()
+                .map(|x| x.base_offset)
+                .unwrap_or(log_start_offset);
+            // Transactions may have been left open anywhere in the log, so
+            // every batch header is read to find them.
+            let mut state = PartitionState::default();
+            for segment in &self.segments {
+                for batch in segment.batches()? {
+                    state.apply_txn(&batch);
+                    log_end_offset = log_end_offset.max(batch.next_offset());
+                }
+            }
+            state.log_start_offset = log_start_offset;
+            state.log_end_offset = log_end_offset.max(log_start_offset);
+            state.high_watermark = state.log_end_offset;
+            state.update_last_stable_offset();
+            state.leader_epoch = self
+                .leader_epoch_cache()?
+                .latest_epoch()
+                .unwrap_or(UNDEFINED_EPOCH);
+            Ok(state)
+        })
+    }
+
+    /// Rolls a new segment once the active one would grow past
+    /// `segment_bytes`. Logs roll no segments unless this is set.
+    pub fn set_segment_bytes(&mut self, segment_bytes: i64) {
+        self.segment_bytes = segment_bytes.max(1) as u64;
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn segments(&self) -> &[Segment] {
+        &self.segments
+    }
+
+    pub fn exists(&self) -> bool {
+        self.dir.exists()
+    }
+
+    /// Bytes used by the partition's segments.
+    pub fn size(&self) -> i64 {
+        self.segments.iter().map(|x| x.size() as i64).sum()
+    }
+
+    pub fn log_start_offset(&self) -> i64 {
+        self.log_start_offset
+    }
+
+    pub fn log_end_offset(&self) -> Result<i64> {
+        Ok(self.state()?.log_end_offset)
+    }
+
+    pub fn high_watermark(&self) -> Result<i64> {
+        Ok(self.state()?.high_watermark)
+    }
+
+    pub fn last_stable_offset(&self) -> Result<i64> {
+        Ok(self.state()?.last_stable_offset)
+    }
+
+    pub fn leader_epoch_cache(&self) -> Result<LeaderEpochCache> {
+        LeaderEpochCache::load(&self.dir, || self.batches())
+    }
+
+    /// Headers of all batches that still hold visible records.
+    pub fn batches(&self) -> Result<Vec<BatchInfo>> {
+        Ok(self
+            .locate_batches(self.log_start_offset, usize::MAX)?
+            .into_iter()
+            .map(|(info, _)| info)
+            .collect())
+    }
+
+    /// Raw batches containing offsets at or after `from_offset`.
+    pub fn read_batches(&self, from_offset: i64) -> Result<Vec<(BatchInfo, Bytes)>> {
+        let from_offset = from_offset.max(self.log_start_offset);
+        let mut batches = vec![];
+        for (idx, segment) in self.segments.iter().enumerate() {
+            let next_base = self.segments.get(idx + 1).map(|x| x.base_offset);
+            if next_base.is_some_and(|x| x <= from_offset) {
+                continue;
+            }
+            let bytes = segment.read()?;
+            for batch in segment.batches()? {
+                if batch.last_offset < from_offset {
+                    continue;
+                }
+                let data = bytes.slice(batch.position..batch.position + batch.size);
+                batches.push((batch, data));
+            }
+        }
+        Ok(batches)
+    }
+
+    /// Headers of the batches containing offsets at or after `from_offset`,
+    /// each with where it is stored, read without loading any records. Stops
+    /// at the batch that brings their size to `max_bytes`.
+    pub fn locate_batches(
+        &self,
+        from_offset: i64,
+        max_bytes: usize,
+    ) -> Result<Vec<(BatchInfo, FileRange)>> {
+        let from_offset = from_offset.max(self.log_start_offset);
+        let mut batches = vec![];
+        let mut size = 0;
+        for (idx, segment) in self.segments.iter().enumerate() {
+            let next_base = self.segments.get(idx + 1).map(|x| x.base_offset);
+            if next_base.is_some_and(|x| x <= from_offset) {
+                continue;
+            }
+            let file = segment.open()?;
+            let start = segment.offset_index().lookup(from_offset)?;
+            for batch in read_batch_headers(&file, start)? {
+                let batch = batch?;
+                if batch.last_offset < from_offset {
+                    continue;
+                }
+                let range = FileRange {
+                    file: file.clone(),
+                    position: batch.position as u64,
+                    size: batch.size as u64,
+                };
+                size += batch.size;
+                batches.push((batch, range));
+                if size >= max_bytes {
+                    return Ok(batches);
+                }
+            }
+        }
+        Ok(batches)
+    }
+
+    /// Picks up segments rolled or deleted through other handles on the
+    /// partition since this one was opened. Called with the partition lock
+    /// held.
+    fn reload(&mut self) -> Result<()> {
+        self.segments = list_segments(&self.dir)?;
+        self.log_start_offset = self.state()?.log_start_offset;
+        Ok(())
+    }
+
+    /// Assigns the next offsets to `batch` and writes it to the active
+    /// segment. Returns the batch's base offset.
+    pub fn append(&mut self, batch: &mut RecordBatch) -> Result<i64> {
+        let lock = partition_lock(&self.dir);
+        let _guard = lock.lock().unwrap();
+        self.reload()?;
+        let base_offset = self.log_end_offset()?;
+        batch.set_base_offset(base_offset);
+        let mut bytes = BytesMut::new();
+        batch.serialize(&mut bytes);
+        self.write(&bytes, batch.partition_leader_epoch(), base_offset)?;
+        Ok(base_offset)
+    }
+
+    /// Appends an encoded batch as sent by a client, setting its base offset
+    /// and leader epoch. Both are outside the checksummed part of the batch.
+    pub fn append_records(&mut self, mut batch: BytesMut, leader_epoch: i32) -> Result<i64> {
+        let lock = partition_lock(&self.dir);
+        let _guard = lock.lock().unwrap();
+        self.reload()?;
+        let base_offset = self.log_end_offset()?;
+        batch[0..8].copy_from_slice(&base_offset.to_be_bytes());
+        batch[12..16].copy_from_slice(&leader_epoch.to_be_bytes());
+        self.write(&batch, leader_epoch, base_offset)?;
+        Ok(base_offset)
+    }
+
+    fn write(&mut self, bytes: &[u8], leader_epoch: i32, base_offset: i64) -> Result<()> {
+        // A batch larger than the segment size still goes into a segment of
+        // its own.
+        let roll = self.segments.last().map_or(true, |active| {
+            let size = active.size();
+            size > 0 && size + bytes.len() as u64 > self.segment_bytes
+        });
+        if roll {
+            fs::create_dir_all(&self.dir)?;
+            let segment = Segment::new(&self.dir, base_offset);
+            segment.create()?;
+            self.segments.push(segment);
+        }
+        let active = self.segments.last().unwrap();
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&active.path)?;
+        let position = file.metadata()?.len();
+        file.write_all(bytes)?;
+        active.offset_index().maybe_append(base_offset, position)?;
+        if leader_epoch > self.state()?.leader_epoch {
+            self.leader_epoch_cache()?
+                .assign(leader_epoch, base_offset)?;
+        }
+        let Some(batch) = BatchInfo::parse_header(bytes, 0, bytes.len()) else {
+            bail!("Truncated batch appended to {}", self.dir.display());
+        };
+        update_partition_state(&self.dir, |x| {
+            x.apply_txn(&batch);
+            x.leader_epoch = x.leader_epoch.max(leader_epoch);
+            x.log_end_offset = batch.next_offset();
+            x.high_watermark = batch.next_offset();
+            x.update_last_stable_offset();
+        });
+        fetch_purgatory().partition_appended(&self.topic, self.partition);
+        Ok(())
+    }
+
+    /// Records an aborted transaction in the index of the active segment,
+    /// where its abort marker was written.
+    pub fn append_aborted_txn(&self, txn: &AbortedTxn) -> Result<()> {
+        match self.segments.last() {
+            Some(active) => active.transaction_index().append(txn),
+            None => Ok(()),
+        }
+    }
+
+    /// Aborted transactions with records in `[from_offset, to_offset)`.
+    pub fn aborted_txns(&self, from_offset: i64, to_offset: i64) -> Result<Vec<AbortedTxn>> {
+        let mut txns = vec![];
+        for segment in &self.segments {
+            for txn in segment.transaction_index().read()? {
+                if txn.last_offset >= from_offset && txn.first_offset < to_offset {
+                    txns.push(txn);
+                }
+            }
+        }
+        Ok(txns)
+    }
+
+    /// Moves the log start offset forward and drops segments that only hold
+    /// records below it. Returns the resulting low watermark.
+    pub fn delete_records_before(&mut self, offset: i64) -> Result<i64> {
+        // Appends must not race the checkpoint or the segments going away.
+        let lock = partition_lock(&self.dir);
+        let _guard = lock.lock().unwrap();
+        self.reload()?;
+        if offset <= self.log_start_offset {
+            return Ok(self.log_start_offset);
+        }
+        write_log_start_offset(&self.topic, self.partition, offset)?;
+        self.log_start_offset = offset;
+        update_partition_state(&self.dir, |x| {
+            x.log_start_offset = offset;
+            x.log_end_offset = x.log_end_offset.max(offset);
+            x.high_watermark = x.high_watermark.max(offset);
+            x.update_last_stable_offset();
+        });
+        self.leader_epoch_cache()?.truncate_from_start(offset)?;
+
+        // The active segment is never removed, even when it is fully deleted.
+        let next_bases: Vec<Option<i64>> = (0..self.segments.len())
+            .map(|idx| self.segments.get(idx + 1).map(|x| x.base_offset))
+            .collect();
+        let segments = std::mem::take(&mut self.segments);
+        for (segment, next_base) in segments.into_iter().zip(next_bases) {
+            if next_base.is_some_and(|x| x <= offset) {
+                segment.delete()?;
+            } else {
+                self.segments.push(segment);
+            }
+        }
+        Ok(self.log_start_offset)
+    }
+}
+
+/// The segments stored in `dir`, by base offset.
+fn list_segments(dir: &Path) -> Result<Vec<Segment>> {
+    let mut segments = vec![];
+    if dir.exists() {
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|x| x == "log") {
+                if let Some(base_offset) = path
+                    .file_stem()
+                    .and_then(|x| x.to_str())
+                    .and_then(|x| x.parse::<i64>().ok())
+                {
+                    segments.push(Segment::new(dir, base_offset));
+                }
+            }
+        }
+    }
+    segments.sort_by_key(|x| x.base_offset);
+    Ok(segments)
+}
@@ -0,0 +1,99 @@
+use anyhow::Result;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+
+use super::BatchInfo;
+
+/// Offsets of a partition as clients see them. Loaded from disk the first
+/// time the partition is opened, then kept up to date by the log as it
+/// appends and deletes records, so reading them does not scan segments.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PartitionState {
+    pub log_start_offset: i64,
+    pub log_end_offset: i64,
+    /// With a single replica every appended record is committed, so this
+    /// follows the log end offset.
+    pub high_watermark: i64,
+    /// The start of the oldest open transaction, or the high watermark if
+    /// every transaction is complete.
+    pub last_stable_offset: i64,
+    /// Base offset of the first batch of every open transaction, by
+    /// producer id.
+    pub open_txns: HashMap<i64, i64>,
+    /// The latest epoch of the leader epoch cache, so that appends only
+    /// write its checkpoint when a new epoch starts.
+    pub leader_epoch: i32,
+}
+
+impl PartitionState {
+    /// Applies an appended batch to the open transactions: a transactional
+    /// batch opens one unless its producer has one open already, and a
+    /// marker completes it.
+    pub fn apply_txn(&mut self, batch: &BatchInfo) {
+        if batch.is_control() {
+            self.open_txns.remove(&batch.producer_id);
+        } else if batch.is_transactional() {
+            self.open_txns
+                .entry(batch.producer_id)
+                .or_insert(batch.base_offset);
+        }
+    }
+
+    /// Moves the last stable offset to the oldest open transaction, or to
+    /// the high watermark once every transaction is complete.
+    pub fn update_last_stable_offset(&mut self) {
+        self.last_stable_offset = self
+            .open_txns
+            .values()
+            .min()
+            .map_or(self.high_watermark, |x| {
+                (*x).clamp(self.log_start_offset, self.high_watermark)
+            });
+    }
+}
+
+type PartitionStates = Mutex<HashMap<PathBuf, PartitionState>>;
+
+fn partition_states() -> &'static PartitionStates {
+    static STATES: OnceLock<PartitionStates> = OnceLock::new();
+    STATES.get_or_init(Mutex::default)
+}
+
+/// The state of the partition stored in `dir`, loaded with `load` if the
+/// partition was not opened before.
+pub fn partition_state(
+    dir: &Path,
+    load: impl FnOnce() -> Result<PartitionState>,
+) -> Result<PartitionState> {
+    let mut states = partition_states().lock().unwrap();
+    if let Some(state) = states.get(dir) {
+        return Ok(state.clone());
+    }
+    let state = load()?;
+    states.insert(dir.to_owned(), state.clone());
+    Ok(state)
+}
+
+/// Applies a change made to the log to the partition's state. States not
+/// loaded yet pick the change up from disk when they are.
+pub fn update_partition_state(dir: &Path, f: impl FnOnce(&mut PartitionState)) {
+    if let Some(state) = partition_states().lock().unwrap().get_mut(dir) {
+        f(state);
+    }
+}
+
+type PartitionLocks = Mutex<HashMap<PathBuf, Arc<Mutex<()>>>>;
+
+/// The lock that appends to, and deletions from, the partition stored in
+/// `dir` hold, so that they see each other's offsets and segments.
+pub fn partition_lock(dir: &Path) -> Arc<Mutex<()>> {
+    static LOCKS: OnceLock<PartitionLocks> = OnceLock::new();
+    LOCKS
+        .get_or_init(Mutex::default)
+        .lock()
+        .unwrap()
+        .entry(dir.to_owned())
+        .or_default()
+        .clone()
+}
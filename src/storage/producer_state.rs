@@ -0,0 +1,465 @@
+use anyhow::{bail, Result};
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use std::collections::{HashMap, VecDeque};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+
+use crate::protocol::ErrorCode;
+
+use super::{BatchInfo, PartitionLog};
+
+pub const PRODUCER_SNAPSHOT_SUFFIX: &str = "snapshot";
+const SNAPSHOT_VERSION: i16 = 1;
+/// Size of a producer's entry in a snapshot.
+const SNAPSHOT_ENTRY_SIZE: usize = 46;
+/// Batches remembered per producer to recognise retried ones.
+pub const NUM_BATCHES_TO_RETAIN: usize = 5;
+pub const NO_SEQUENCE: i32 = -1;
+/// Offsets written between snapshots. Loading the state replays the batches
+/// after the latest snapshot, so this bounds the replay.
+const SNAPSHOT_INTERVAL: i64 = 1000;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BatchMetadata {
+    pub first_seq: i32,
+    pub last_seq: i32,
+    pub first_offset: i64,
+    pub last_offset: i64,
+    pub timestamp: i64,
+}
+
+/// What the partition remembers of a producer: its epoch and its last
+/// batches, newest last.
+#[derive(Debug, Clone)]
+pub struct ProducerStateEntry {
+    pub producer_id: i64,
+    pub producer_epoch: i16,
+    pub batches: VecDeque<BatchMetadata>,
+    pub last_timestamp: i64,
+    pub coordinator_epoch: i32,
+    pub current_txn_first_offset: Option<i64>,
+}
+
+impl ProducerStateEntry {
+    pub fn last_seq(&self) -> i32 {
+        self.batches.back().map_or(NO_SEQUENCE, |x| x.last_seq)
+    }
+
+    pub fn last_offset(&self) -> i64 {
+        self.batches.back().map_or(-1, |x| x.last_offset)
+    }
+}
+
+fn in_sequence(last_seq: i32, next_seq: i32) -> bool {
+    next_seq == last_seq.wrapping_add(1) || (next_seq == 0 && last_seq == i32::MAX)
+}
+
+/// Producer state of one partition: the producers that wrote to it, used to
+/// drop retried batches and fence zombie producers. Persisted in `.snapshot`
+/// files named after the offset the state is valid up to.
+#[derive(Debug, Default)]
+pub struct ProducerStateManager {
+    producers: HashMap<i64, ProducerStateEntry>,
+    /// Offset of the first batch not applied to the state yet.
+    map_end_offset: i64,
+    /// Offset of the latest snapshot, or where the replay started.
+    snapshot_offset: i64,
+    loaded: bool,
+}
+
+type SharedProducerState = Arc<Mutex<ProducerStateManager>>;
+
+/// The producer state of a partition, shared by everything writing to it.
+/// Call [`ProducerStateManager::sync`] once locked.
+pub fn producer_state(topic: &str, partition: i32) -> SharedProducerState {
+    static STATES: OnceLock<Mutex<HashMap<(String, i32), SharedProducerState>>> = OnceLock::new();
+    STATES
+        .get_or_init(Mutex::default)
+        .lock()
+        .unwrap()
+        .entry((topic.to_owned(), partition))
+        .or_default()
+        .clone()
+}
+
+fn snapshot_path(dir: &Path, offset: i64) -> PathBuf {
+    dir.join(format!("{:020}.{}", offset, PRODUCER_SNAPSHOT_SUFFIX))
+}
+
+/// Offsets of the snapshots in the partition directory, oldest first.
+fn snapshot_offsets(dir: &Path) -> Result<Vec<i64>> {
+    let mut offsets = vec![];
+    if !dir.exists() {
+        return Ok(offsets);
+    }
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path
+            .extension()
+            .is_some_and(|x| x == PRODUCER_SNAPSHOT_SUFFIX)
+        {
+            if let Some(offset) = path
+                .file_stem()
+                .and_then(|x| x.to_str())
+                .and_then(|x| x.parse::<i64>().ok())
+            {
+                offsets.push(offset);
+            }
+        }
+    }
+    offsets.sort();
+    Ok(offsets)
+}
+
+impl ProducerStateManager {
+    pub fn producers(&self) -> &HashMap<i64, ProducerStateEntry> {
+        &self.producers
+    }
+
+    /// Brings the state up to date with the log: loads the latest usable
+    /// snapshot on first use, then replays the batches written since.
+    pub fn sync(&mut self, log: &PartitionLog) -> Result<()> {
+        let log_end_offset = log.log_end_offset()?;
+        if !self.loaded || log_end_offset < self.map_end_offset {
+            self.load(log, log_end_offset)?;
+        }
+        if self.map_end_offset < log_end_offset {
+            for (batch, _) in log.read_batches(self.map_end_offset)? {
+                if batch.base_offset >= self.map_end_offset {
+                    self.update(&batch);
+                }
+            }
+            self.map_end_offset = log_end_offset;
+        }
+        Ok(())
+    }
+
+    fn load(&mut self, log: &PartitionLog, log_end_offset: i64) -> Result<()> {
+        self.producers.clear();
+        self.map_end_offset = log.log_start_offset();
+        self.snapshot_offset = self.map_end_offset;
+        for offset in snapshot_offsets(log.dir())?.into_iter().rev() {
+            if offset > log_end_offset || offset < log.log_start_offset() {
+                continue;
+            }
+            if let Ok(producers) = read_snapshot(&snapshot_path(log.dir(), offset)) {
+                self.producers = producers;
+                self.map_end_offset = offset;
+                self.snapshot_offset = offset;
+                break;
+            }
+        }
+        self.loaded = true;
+        Ok(())
+    }
+
+    /// Checks a batch against the producer's epoch and sequence. Returns the
+    /// original batch when this one is a retry of it.
+    pub fn check(&self, batch: &BatchInfo) -> Result<Option<BatchMetadata>, ErrorCode> {
+        if batch.producer_id < 0 {
+            return Ok(None);
+        }
+        let Some(entry) = self.producers.get(&batch.producer_id) else {
+            // Unknown producers, e.g. ones that expired, may start anywhere.
+            return Ok(None);
+        };
+        if batch.producer_epoch < entry.producer_epoch {
+            return Err(ErrorCode::InvalidProducerEpoch);
+        }
+        let last_seq = batch.base_sequence.wrapping_add(batch.record_count - 1);
+        if batch.producer_epoch != entry.producer_epoch {
+            if batch.base_sequence != 0 {
+                return Err(ErrorCode::OutOfOrderSequenceNumber);
+            }
+            return Ok(None);
+        }
+        if let Some(duplicate) = entry
+            .batches
+            .iter()
+            .find(|x| x.first_seq == batch.base_sequence && x.last_seq == last_seq)
+        {
+            return Ok(Some(duplicate.clone()));
+        }
+        let current = entry.last_seq();
+        if current == NO_SEQUENCE && batch.base_sequence != 0
+            || current != NO_SEQUENCE && !in_sequence(current, batch.base_sequence)
+        {
+            return Err(ErrorCode::OutOfOrderSequenceNumber);
+        }
+        Ok(None)
+    }
+
+    /// Records a batch written to the log at the offsets in `batch`.
+    pub fn update(&mut self, batch: &BatchInfo) {
+        self.map_end_offset = self.map_end_offset.max(batch.next_offset());
+        if batch.producer_id < 0 {
+            return;
+        }
+        let entry = self
+            .producers
+            .entry(batch.producer_id)
+            .or_insert_with(|| ProducerStateEntry {
+                producer_id: batch.producer_id,
+                producer_epoch: batch.producer_epoch,
+                batches: VecDeque::new(),
+                last_timestamp: batch.max_timestamp,
+                coordinator_epoch: -1,
+                current_txn_first_offset: None,
+            });
+        if batch.producer_epoch != entry.producer_epoch {
+            entry.producer_epoch = batch.producer_epoch;
+            entry.batches.clear();
+        }
+        entry.last_timestamp = batch.max_timestamp;
+        if batch.is_control() {
+            // A marker ends the producer's transaction.
+            entry.current_txn_first_offset = None;
+            return;
+        }
+        if batch.is_transactional() && entry.current_txn_first_offset.is_none() {
+            entry.current_txn_first_offset = Some(batch.base_offset);
+        }
+        if batch.base_sequence != NO_SEQUENCE {
+            entry.batches.push_back(BatchMetadata {
+                first_seq: batch.base_sequence,
+                last_seq: batch.base_sequence.wrapping_add(batch.record_count - 1),
+                first_offset: batch.base_offset,
+                last_offset: batch.last_offset,
+                timestamp: batch.max_timestamp,
+            });
+            if entry.batches.len() > NUM_BATCHES_TO_RETAIN {
+                entry.batches.pop_front();
+            }
+        }
+    }
+
+    /// First offset of the oldest transaction still open on the partition.
+    pub fn first_unstable_offset(&self) -> Option<i64> {
+        self.producers
+            .values()
+            .filter_map(|x| x.current_txn_first_offset)
+            .min()
+    }
+
+    /// Forgets producers that have not written for `expiration_ms`.
+    pub fn expire(&mut self, now: i64, expiration_ms: i64) {
+        self.producers.retain(|_, x| {
+            x.current_txn_first_offset.is_some()
+                || now.saturating_sub(x.last_timestamp) < expiration_ms
+        });
+    }
+
+    /// Takes a snapshot once the state is [`SNAPSHOT_INTERVAL`] offsets past
+    /// the latest one, rather than on every write.
+    pub fn maybe_take_snapshot(&mut self, dir: &Path) -> Result<()> {
+        if self.map_end_offset - self.snapshot_offset >= SNAPSHOT_INTERVAL {
+            self.take_snapshot(dir)?;
+        }
+        Ok(())
+    }
+
+    /// Writes the state at the current offset and removes older snapshots.
+    fn take_snapshot(&mut self, dir: &Path) -> Result<()> {
+        let path = snapshot_path(dir, self.map_end_offset);
+        write_snapshot(&path, &self.producers)?;
+        self.snapshot_offset = self.map_end_offset;
+        for offset in snapshot_offsets(dir)? {
+            if offset < self.map_end_offset {
+                let _ = fs::remove_file(snapshot_path(dir, offset));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Kafka's snapshot format: a version, a CRC32C of the rest of the file and
+/// the last batch of every producer.
+fn write_snapshot(path: &Path, producers: &HashMap<i64, ProducerStateEntry>) -> Result<()> {
+    let mut entries = BytesMut::new();
+    let mut producers: Vec<&ProducerStateEntry> = producers.values().collect();
+    producers.sort_by_key(|x| x.producer_id);
+    entries.put_i32(producers.len() as i32);
+    for x in producers {
+        let last = x.batches.back();
+        entries.put_i64(x.producer_id);
+        entries.put_i16(x.producer_epoch);
+        entries.put_i32(x.last_seq());
+        entries.put_i64(x.last_offset());
+        entries.put_i32(last.map_or(0, |x| (x.last_offset - x.first_offset) as i32));
+        entries.put_i64(x.last_timestamp);
+        entries.put_i32(x.coordinator_epoch);
+        entries.put_i64(x.current_txn_first_offset.unwrap_or(-1));
+    }
+    let mut bytes = BytesMut::new();
+    bytes.put_i16(SNAPSHOT_VERSION);
+    bytes.put_u32(crc32c::crc32c(&entries));
+    bytes.put_slice(&entries);
+    let tmp = path.with_extension("tmp");
+    fs::write(&tmp, bytes)?;
+    fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+fn read_snapshot(path: &Path) -> Result<HashMap<i64, ProducerStateEntry>> {
+    let mut bytes = Bytes::from(fs::read(path)?);
+    if bytes.len() < 10 || bytes.get_i16() != SNAPSHOT_VERSION {
+        bail!("Unsupported producer snapshot {}", path.display());
+    }
+    let crc = bytes.get_u32();
+    if crc32c::crc32c(&bytes) != crc {
+        bail!("Corrupt producer snapshot {}", path.display());
+    }
+    let count = bytes.get_i32();
+    let mut producers = HashMap::new();
+    for _ in 0..count {
+        if bytes.remaining() < SNAPSHOT_ENTRY_SIZE {
+            bail!("Truncated producer snapshot {}", path.display());
+        }
+        let producer_id = bytes.get_i64();
+        let producer_epoch = bytes.get_i16();
+        let last_seq = bytes.get_i32();
+        let last_offset = bytes.get_i64();
+        let offset_delta = bytes.get_i32();
+        let timestamp = bytes.get_i64();
+        let coordinator_epoch = bytes.get_i32();
+        let current_txn_first_offset = Some(bytes.get_i64()).filter(|x| *x >= 0);
+        let mut batches = VecDeque::new();
+        if last_seq != NO_SEQUENCE {
+            batches.push_back(BatchMetadata {
+                first_seq: (last_seq - offset_delta).max(0),
+                last_seq,
+                first_offset: last_offset - offset_delta as i64,
+                last_offset,
+                timestamp,
+            });
+        }
+        let entry = ProducerStateEntry {
+            producer_id,
+            producer_epoch,
+            batches,
+            last_timestamp: timestamp,
+            coordinator_epoch,
+            current_txn_first_offset,
+        };
+        producers.insert(producer_id, entry);
+    }
+    Ok(producers)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn batch(base_offset: i64, producer_epoch: i16, base_sequence: i32, count: i32) -> BatchInfo {
+        BatchInfo {
+            base_offset,
+            last_offset: base_offset + count as i64 - 1,
+            position: 0,
+            size: 0,
+            partition_leader_epoch: 0,
+            magic: 2,
+            crc: 0,
+            attributes: 0,
+            base_timestamp: 1000,
+            max_timestamp: 1000,
+            producer_id: 7,
+            producer_epoch,
+            base_sequence,
+            record_count: count,
+        }
+    }
+
+    #[test]
+    fn check_accepts_next_sequence_and_returns_duplicates() {
+        let mut state = ProducerStateManager::default();
+        let first = batch(0, 0, 0, 3);
+        assert!(matches!(state.check(&first), Ok(None)));
+        state.update(&first);
+
+        assert!(matches!(state.check(&batch(3, 0, 3, 2)), Ok(None)));
+        let duplicate = state.check(&batch(10, 0, 0, 3)).unwrap().unwrap();
+        assert_eq!((duplicate.first_offset, duplicate.last_offset), (0, 2));
+        assert!(matches!(
+            state.check(&batch(3, 0, 5, 1)),
+            Err(ErrorCode::OutOfOrderSequenceNumber)
+        ));
+    }
+
+    #[test]
+    fn check_fences_older_epochs() {
+        let mut state = ProducerStateManager::default();
+        state.update(&batch(0, 1, 0, 1));
+        assert!(matches!(
+            state.check(&batch(1, 0, 1, 1)),
+            Err(ErrorCode::InvalidProducerEpoch)
+        ));
+        // A new epoch starts its sequence over.
+        assert!(matches!(state.check(&batch(1, 2, 0, 1)), Ok(None)));
+        assert!(matches!(
+            state.check(&batch(1, 2, 1, 1)),
+            Err(ErrorCode::OutOfOrderSequenceNumber)
+        ));
+    }
+
+    #[test]
+    fn update_retains_the_last_batches() {
+        let mut state = ProducerStateManager::default();
+        for seq in 0..NUM_BATCHES_TO_RETAIN as i32 + 2 {
+            state.update(&batch(seq as i64, 0, seq, 1));
+        }
+        let entry = &state.producers()[&7];
+        assert_eq!(entry.batches.len(), NUM_BATCHES_TO_RETAIN);
+        assert_eq!(entry.last_seq(), NUM_BATCHES_TO_RETAIN as i32 + 1);
+        // Batches that fell out of the window are no longer duplicates.
+        assert!(matches!(
+            state.check(&batch(10, 0, 0, 1)),
+            Err(ErrorCode::OutOfOrderSequenceNumber)
+        ));
+    }
+
+    #[test]
+    fn snapshot_round_trip() {
+        let dir = std::env::temp_dir().join(format!("producer-state-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let mut state = ProducerStateManager::default();
+        state.update(&batch(0, 3, 0, 2));
+        state.update(&batch(2, 3, 2, 4));
+        let path = snapshot_path(&dir, 6);
+        write_snapshot(&path, state.producers()).unwrap();
+
+        let producers = read_snapshot(&path).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        let entry = &producers[&7];
+        assert_eq!(entry.producer_epoch, 3);
+        assert_eq!(entry.last_timestamp, 1000);
+        assert_eq!(entry.current_txn_first_offset, None);
+        assert_eq!(
+            entry.batches.back(),
+            Some(&BatchMetadata {
+                first_seq: 2,
+                last_seq: 5,
+                first_offset: 2,
+                last_offset: 5,
+                timestamp: 1000,
+            })
+        );
+    }
+
+    #[test]
+    fn read_snapshot_rejects_corrupt_files() {
+        let dir = std::env::temp_dir().join(format!("producer-corrupt-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let mut state = ProducerStateManager::default();
+        state.update(&batch(0, 0, 0, 1));
+        let path = snapshot_path(&dir, 1);
+        write_snapshot(&path, state.producers()).unwrap();
+        let mut bytes = fs::read(&path).unwrap();
+        *bytes.last_mut().unwrap() ^= 1;
+        fs::write(&path, &bytes).unwrap();
+
+        let result = read_snapshot(&path);
+        fs::remove_dir_all(&dir).unwrap();
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,350 @@
+use anyhow::Result;
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+
+use super::{OffsetIndex, TransactionIndex};
+use std::fs::{self, File};
+use std::iter;
+use std::os::unix::fs::FileExt;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+// Offsets into the v2 record batch header.
+const LOG_OVERHEAD: usize = 12; // base offset + batch length
+const BATCH_HEADER_SIZE: usize = 61;
+const CRC_END: usize = 21;
+
+pub const SEGMENT_SUFFIXES: [&str; 4] = ["log", "index", "timeindex", "txnindex"];
+
+#[derive(Debug, Clone)]
+pub struct BatchInfo {
+    pub base_offset: i64,
+    pub last_offset: i64,
+    pub position: usize,
+    pub size: usize,
+    pub partition_leader_epoch: i32,
+    pub magic: i8,
+    pub crc: u32,
+    pub attributes: i16,
+    pub base_timestamp: i64,
+    pub max_timestamp: i64,
+    pub producer_id: i64,
+    pub producer_epoch: i16,
+    pub base_sequence: i32,
+    pub record_count: i32,
+}
+
+impl BatchInfo {
+    /// Reads the header of the batch at the front of `bytes` without
+    /// decoding its records.
+    fn parse(bytes: &Bytes, position: usize) -> Option<Self> {
+        if bytes.len() < BATCH_HEADER_SIZE {
+            return None;
+        }
+        Self::parse_header(&bytes[..BATCH_HEADER_SIZE], position, bytes.len())
+    }
+
+    /// Reads a batch header, given the bytes available from its start.
+    /// Returns `None` if the batch is incomplete.
+    pub fn parse_header(mut header: &[u8], position: usize, available: usize) -> Option<Self> {
+        let base_offset = header.get_i64();
+        let batch_length = header.get_i32();
+        if batch_length < 0 || LOG_OVERHEAD + batch_length as usize > available {
+            return None;
+        }
+        let size = LOG_OVERHEAD + batch_length as usize;
+        let partition_leader_epoch = header.get_i32();
+        let magic = header.get_i8();
+        let crc = header.get_u32();
+        let attributes = header.get_i16();
+        let last_offset_delta = header.get_i32();
+        Some(Self {
+            base_offset,
+            last_offset: base_offset + last_offset_delta as i64,
+            position,
+            size,
+            partition_leader_epoch,
+            magic,
+            crc,
+            attributes,
+            base_timestamp: header.get_i64(),
+            max_timestamp: header.get_i64(),
+            producer_id: header.get_i64(),
+            producer_epoch: header.get_i16(),
+            base_sequence: header.get_i32(),
+            record_count: header.get_i32(),
+        })
+    }
+
+    pub fn next_offset(&self) -> i64 {
+        self.last_offset + 1
+    }
+
+    pub fn is_compressed(&self) -> bool {
+        self.attributes & 0x07 != 0
+    }
+
+    /// Whether the batch is a v2 batch whose checksum matches its content.
+    pub fn is_valid(&self, batch: &Bytes) -> bool {
+        self.magic == 2 && crc32c::crc32c(&batch[CRC_END..]) == self.crc
+    }
+
+    pub fn is_transactional(&self) -> bool {
+        self.attributes & 0x10 != 0
+    }
+
+    /// Whether the batch holds transaction markers rather than data.
+    pub fn is_control(&self) -> bool {
+        self.attributes & 0x20 != 0
+    }
+
+    /// Offset and timestamp of every record in `batch`. Compressed batches are
+    /// not inflated, they report their last offset and max timestamp instead.
+    /// Malformed batches yield nothing.
+    pub fn record_timestamps(&self, batch: &Bytes) -> Vec<(i64, i64)> {
+        if self.is_compressed() {
+            return vec![(self.last_offset, self.max_timestamp)];
+        }
+        self.records(batch)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|x| (x.offset, x.timestamp))
+            .collect()
+    }
+
+    /// Decodes the records of an uncompressed batch. Compressed batches
+    /// yield nothing. Returns `None` if a record runs past the end of the
+    /// batch.
+    pub fn records(&self, batch: &Bytes) -> Option<Vec<RecordInfo>> {
+        if self.is_compressed() {
+            return Some(vec![]);
+        }
+        if batch.len() < BATCH_HEADER_SIZE {
+            return None;
+        }
+        let mut records = batch.slice(BATCH_HEADER_SIZE..);
+        let mut result = vec![];
+        for _ in 0..self.record_count {
+            let length = usize::try_from(get_varint(&mut records)?).ok()?;
+            if records.len() < length {
+                return None;
+            }
+            let mut record = records.split_to(length);
+            if !record.has_remaining() {
+                return None;
+            }
+            let _attributes = record.get_i8();
+            let timestamp_delta = get_varint(&mut record)?;
+            let offset_delta = get_varint(&mut record)?;
+            let key = get_varint_bytes(&mut record)?;
+            let value = get_varint_bytes(&mut record)?;
+            result.push(RecordInfo {
+                offset: self.base_offset + offset_delta,
+                timestamp: self.base_timestamp + timestamp_delta,
+                key,
+                value,
+            });
+        }
+        Some(result)
+    }
+
+    /// Appends the records of an uncompressed batch to `message_set` as v0
+    /// or v1 messages, the format of clients that predate v2 batches.
+    /// Returns `None`, having written nothing, if the batch is malformed.
+    pub fn write_messages(
+        &self,
+        batch: &Bytes,
+        magic: i8,
+        message_set: &mut BytesMut,
+    ) -> Option<()> {
+        let log_append_time = self.attributes & 0x08 != 0;
+        for record in self.records(batch)? {
+            let mut message = BytesMut::new();
+            message.put_i8(magic);
+            if magic == 0 {
+                message.put_i8(0);
+            } else {
+                message.put_i8(if log_append_time { 0x08 } else { 0 });
+                message.put_i64(if log_append_time {
+                    self.max_timestamp
+                } else {
+                    record.timestamp
+                });
+            }
+            for field in [&record.key, &record.value] {
+                match field {
+                    Some(x) => {
+                        message.put_i32(x.len() as i32);
+                        message.put_slice(x);
+                    }
+                    None => message.put_i32(-1),
+                }
+            }
+            message_set.put_i64(record.offset);
+            message_set.put_i32(message.len() as i32 + 4);
+            // v0 and v1 messages are checksummed with CRC-32 (IEEE).
+            message_set.put_u32(crc32fast::hash(&message));
+            message_set.put_slice(&message);
+        }
+        Some(())
+    }
+}
+
+/// A record decoded from a batch, with its absolute offset and timestamp.
+#[derive(Debug, Clone)]
+pub struct RecordInfo {
+    pub offset: i64,
+    pub timestamp: i64,
+    pub key: Option<Bytes>,
+    /// `None` for tombstones.
+    pub value: Option<Bytes>,
+}
+
+/// A zigzag encoded varint, or `None` if `bytes` ends before it does.
+fn get_varint(bytes: &mut Bytes) -> Option<i64> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        if !bytes.has_remaining() {
+            return None;
+        }
+        let byte = bytes.get_u8();
+        value |= ((byte & 0x7F) as u64) << shift;
+        if byte & 0x80 == 0 {
+            return Some((value >> 1) as i64 ^ -((value & 1) as i64));
+        }
+    }
+    None
+}
+
+/// Bytes prefixed with their varint length, `Some(None)` for null, or `None`
+/// if `bytes` ends before they do.
+fn get_varint_bytes(bytes: &mut Bytes) -> Option<Option<Bytes>> {
+    let length = get_varint(bytes)?;
+    if length < 0 {
+        return Some(None);
+    }
+    let length = usize::try_from(length).ok()?;
+    if bytes.len() < length {
+        return None;
+    }
+    Some(Some(bytes.split_to(length)))
+}
+
+/// Headers of the complete batches at the front of `bytes`.
+pub fn parse_batches(bytes: &Bytes) -> Vec<BatchInfo> {
+    let mut batches = vec![];
+    let mut position = 0;
+    while let Some(batch) = BatchInfo::parse(&bytes.slice(position..), position) {
+        position += batch.size;
+        batches.push(batch);
+    }
+    batches
+}
+
+/// Headers of the complete batches in a segment file, from the batch at
+/// `position` on. Each header is read as the iterator gets to it.
+pub fn read_batch_headers(
+    file: &File,
+    position: u64,
+) -> Result<impl Iterator<Item = Result<BatchInfo>> + '_> {
+    let length = file.metadata()?.len() as usize;
+    let mut position = position as usize;
+    Ok(iter::from_fn(move || {
+        if position + BATCH_HEADER_SIZE > length {
+            return None;
+        }
+        let mut header = [0u8; BATCH_HEADER_SIZE];
+        if let Err(error) = file.read_exact_at(&mut header, position as u64) {
+            position = length;
+            return Some(Err(error.into()));
+        }
+        let batch = BatchInfo::parse_header(&header, position, length - position)?;
+        position += batch.size;
+        Some(Ok(batch))
+    }))
+}
+
+/// A range of a segment file, sent to clients as it is stored.
+#[derive(Debug, Clone)]
+pub struct FileRange {
+    pub file: Arc<File>,
+    pub position: u64,
+    pub size: u64,
+}
+
+impl FileRange {
+    /// Extends the range with `other` if it directly follows in the same file.
+    pub fn merge(&mut self, other: &FileRange) -> bool {
+        if !Arc::ptr_eq(&self.file, &other.file) || self.position + self.size != other.position {
+            return false;
+        }
+        self.size += other.size;
+        true
+    }
+
+    /// Copies the range into memory, for records that must be converted.
+    pub fn read(&self) -> Result<Bytes> {
+        let mut bytes = vec![0u8; self.size as usize];
+        self.file.read_exact_at(&mut bytes, self.position)?;
+        Ok(Bytes::from(bytes))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Segment {
+    pub base_offset: i64,
+    pub path: PathBuf,
+}
+
+impl Segment {
+    pub fn new(dir: &Path, base_offset: i64) -> Self {
+        Self {
+            base_offset,
+            path: dir.join(format!("{:020}.log", base_offset)),
+        }
+    }
+
+    /// Creates the segment's log and offset index empty.
+    pub fn create(&self) -> Result<()> {
+        File::create(&self.path)?;
+        self.offset_index().create()
+    }
+
+    pub fn read(&self) -> Result<Bytes> {
+        Ok(Bytes::from(fs::read(&self.path)?))
+    }
+
+    pub fn size(&self) -> u64 {
+        fs::metadata(&self.path).map(|x| x.len()).unwrap_or(0)
+    }
+
+    pub fn open(&self) -> Result<Arc<File>> {
+        Ok(Arc::new(File::open(&self.path)?))
+    }
+
+    /// Headers of the complete batches in the segment, read without loading
+    /// their records.
+    pub fn batches(&self) -> Result<Vec<BatchInfo>> {
+        let file = self.open()?;
+        let batches = read_batch_headers(&file, 0)?.collect();
+        batches
+    }
+
+    pub fn offset_index(&self) -> OffsetIndex {
+        OffsetIndex::new(&self.path, self.base_offset)
+    }
+
+    pub fn transaction_index(&self) -> TransactionIndex {
+        TransactionIndex::new(&self.path)
+    }
+
+    /// Removes the segment together with its index files.
+    pub fn delete(&self) -> Result<()> {
+        for suffix in SEGMENT_SUFFIXES {
+            let path = self.path.with_extension(suffix);
+            if path.exists() {
+                fs::remove_file(path)?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,69 @@
+use anyhow::Result;
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+const ABORTED_TXN_VERSION: i16 = 0;
+const ABORTED_TXN_SIZE: usize = 34;
+
+/// A transaction aborted in the partition: the offsets of its first record
+/// and of its abort marker, and the last stable offset once it completed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AbortedTxn {
+    pub producer_id: i64,
+    pub first_offset: i64,
+    pub last_offset: i64,
+    pub last_stable_offset: i64,
+}
+
+/// The `.txnindex` file of a segment, listing the transactions whose abort
+/// marker was written to the segment, in Kafka's format.
+#[derive(Debug)]
+pub struct TransactionIndex {
+    path: PathBuf,
+}
+
+impl TransactionIndex {
+    pub fn new(path: &Path) -> Self {
+        Self {
+            path: path.with_extension("txnindex"),
+        }
+    }
+
+    pub fn append(&self, txn: &AbortedTxn) -> Result<()> {
+        let mut bytes = BytesMut::with_capacity(ABORTED_TXN_SIZE);
+        bytes.put_i16(ABORTED_TXN_VERSION);
+        bytes.put_i64(txn.producer_id);
+        bytes.put_i64(txn.first_offset);
+        bytes.put_i64(txn.last_offset);
+        bytes.put_i64(txn.last_stable_offset);
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        file.write_all(&bytes)?;
+        Ok(())
+    }
+
+    /// Every entry of the index. A partially written last entry is ignored.
+    pub fn read(&self) -> Result<Vec<AbortedTxn>> {
+        if !self.path.exists() {
+            return Ok(vec![]);
+        }
+        let mut bytes = Bytes::from(fs::read(&self.path)?);
+        let mut txns = vec![];
+        while bytes.remaining() >= ABORTED_TXN_SIZE {
+            if bytes.get_i16() != ABORTED_TXN_VERSION {
+                break;
+            }
+            txns.push(AbortedTxn {
+                producer_id: bytes.get_i64(),
+                first_offset: bytes.get_i64(),
+                last_offset: bytes.get_i64(),
+                last_stable_offset: bytes.get_i64(),
+            });
+        }
+        Ok(txns)
+    }
+}
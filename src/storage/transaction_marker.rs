@@ -0,0 +1,91 @@
+use bytes::{BufMut, Bytes, BytesMut};
+
+use crate::{
+    metadata::{Record, RecordBatch},
+    protocol::ErrorCode,
+};
+
+use super::{producer_state, AbortedTxn, PartitionLog};
+
+const CONTROL_RECORD_VERSION: i16 = 0;
+const ABORT_MARKER: i16 = 0;
+const COMMIT_MARKER: i16 = 1;
+
+/// A COMMIT or ABORT marker ending a producer's transaction on a partition.
+#[derive(Debug, Clone)]
+pub struct TxnMarker {
+    pub producer_id: i64,
+    pub producer_epoch: i16,
+    pub commit: bool,
+    pub coordinator_epoch: i32,
+}
+
+impl TxnMarker {
+    /// The control record: its key holds the marker type, its value the
+    /// coordinator epoch.
+    fn record(&self) -> Record {
+        let mut key = BytesMut::new();
+        key.put_i16(CONTROL_RECORD_VERSION);
+        key.put_i16(if self.commit {
+            COMMIT_MARKER
+        } else {
+            ABORT_MARKER
+        });
+        let mut value = BytesMut::new();
+        value.put_i16(CONTROL_RECORD_VERSION);
+        value.put_i32(self.coordinator_epoch);
+        Record::new_keyed(0, key.freeze(), Some(value.freeze()))
+    }
+
+    /// Whether the key of a control record is a COMMIT marker.
+    pub fn is_commit(key: &Bytes) -> bool {
+        key.len() >= 4 && i16::from_be_bytes([key[2], key[3]]) == COMMIT_MARKER
+    }
+}
+
+fn storage_error(_: anyhow::Error) -> ErrorCode {
+    ErrorCode::KafkaStorageError
+}
+
+/// Appends the marker to the partition, rejecting markers from older
+/// producer epochs, and indexes the transaction if it was aborted. Returns
+/// the offset of the marker.
+pub fn append_txn_marker(
+    topic: &str,
+    partition: i32,
+    leader_epoch: i32,
+    marker: &TxnMarker,
+) -> Result<i64, ErrorCode> {
+    let state = producer_state(topic, partition);
+    let mut state = state.lock().unwrap();
+    let mut log = PartitionLog::open(topic, partition).map_err(storage_error)?;
+    state.sync(&log).map_err(storage_error)?;
+    let first_offset = match state.producers().get(&marker.producer_id) {
+        Some(x) if marker.producer_epoch < x.producer_epoch => {
+            return Err(ErrorCode::InvalidProducerEpoch)
+        }
+        Some(x) => x.current_txn_first_offset,
+        None => None,
+    };
+    let mut batch = RecordBatch::new_control(
+        leader_epoch,
+        marker.producer_id,
+        marker.producer_epoch,
+        marker.record(),
+    );
+    let offset = log.append(&mut batch).map_err(storage_error)?;
+    state.sync(&log).map_err(storage_error)?;
+    if let (false, Some(first_offset)) = (marker.commit, first_offset) {
+        let txn = AbortedTxn {
+            producer_id: marker.producer_id,
+            first_offset,
+            last_offset: offset,
+            last_stable_offset: state.first_unstable_offset().unwrap_or(offset + 1),
+        };
+        log.append_aborted_txn(&txn).map_err(storage_error)?;
+    }
+    state
+        .maybe_take_snapshot(log.dir())
+        .map_err(storage_error)?;
+    Ok(offset)
+}